@@ -1,6 +1,46 @@
+use std::fmt;
+
+// ISO 7816-4 5.4.1: bit 5 of CLA marks a command as "not the last command of a chain".
+pub const CLA_CHAINING: u8 = 0x10;
+
+// ISO 7816-4 5.1.3: SW1 of 0x61 means SW2 more response bytes are available via GET RESPONSE.
+pub const SW1_BYTES_REMAINING: u8 = 0x61;
+
+// The largest Lc/Le values that can be encoded in short and extended form.
+pub const MAX_SHORT_LC: usize = 255;
+pub const MAX_SHORT_LE: usize = 256;
+pub const MAX_EXTENDED_LC: usize = 65535;
+pub const MAX_EXTENDED_LE: usize = 65536;
+
+// The largest command APDU which only uses short coding: header, Lc, 255 bytes of data and Le.
+pub const MAX_SHORT_APDU_SIZE: usize = HEADER_SIZE + 1 + MAX_SHORT_LC + 1;
+
+const HEADER_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    DataTooLong(usize),
+    InvalidLe(usize),
+    ApduSizeTooSmall(usize),
+    InvalidLength(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DataTooLong(len) => write!(f, "command data too long: {len} bytes"),
+            Error::InvalidLe(le) => write!(f, "invalid expected response length: {le}"),
+            Error::ApduSizeTooSmall(size) => write!(f, "APDU size too small: {size} bytes"),
+            Error::InvalidLength(len) => write!(f, "malformed command: {len} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 // T4T 1.1 5.1.2 Format of Command-APDU
-// Lc is encoded when serialized.
-// WCA doesn't use Le, and it's optional, so it is unimplemented.
+// Lc and Le are encoded when serialized. Short coding is used unless either of them needs the
+// extended form, in which case both use it (ISO 7816-4 5.1).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Command {
     pub cla: u8,
@@ -8,6 +48,7 @@ pub struct Command {
     pub p1: u8,
     pub p2: u8,
     pub data: Option<Vec<u8>>,
+    pub le: Option<usize>,
 }
 
 // T4T 1.1 5.1.3 Format of Response-APDU
@@ -27,6 +68,7 @@ impl Command {
             p1,
             p2,
             data: Some(data),
+            le: None,
         }
     }
 
@@ -37,36 +79,162 @@ impl Command {
             p1,
             p2,
             data: None,
+            le: None,
         }
     }
 
-    pub fn serialize(self) -> Vec<u8> {
+    /// Set the maximum number of response bytes expected (Le), from 1 to 65536.
+    pub fn with_le(mut self, le: usize) -> Self {
+        self.le = Some(le);
+        self
+    }
+
+    pub fn serialize(self) -> Result<Vec<u8>, Error> {
+        let lc = self.data.as_ref().map_or(0, |d| d.len());
+        if lc > MAX_EXTENDED_LC {
+            return Err(Error::DataTooLong(lc));
+        }
+        if let Some(le) = self.le {
+            if le == 0 || le > MAX_EXTENDED_LE {
+                return Err(Error::InvalidLe(le));
+            }
+        }
+        let extended = lc > MAX_SHORT_LC || self.le.is_some_and(|le| le > MAX_SHORT_LE);
+
         let mut out = vec![self.cla, self.ins, self.p1, self.p2];
 
         // Data length (Lc) and data, if present
         if let Some(d) = self.data {
             // T4T 1.1 Table 19: Coding of Lc field
-            let lc = d.len() as u16;
-            match lc {
-                1..=255 => {
-                    // Short coding
-                    out.push(lc as u8);
-                }
-                _ => {
-                    // Extended; 0 is prefixed by big-endian Lc
+            if extended || lc == 0 {
+                // Extended; 0 is prefixed by big-endian Lc
+                out.push(0);
+                out.extend((lc as u16).to_be_bytes());
+            } else {
+                // Short coding
+                out.push(lc as u8);
+            }
+            out.extend(d);
+        }
+
+        // Expected response length (Le), if present. The maximum is encoded as zero.
+        if let Some(le) = self.le {
+            if extended {
+                // Extended Le is prefixed by 0 only when there's no extended Lc before it
+                if out.len() == HEADER_SIZE {
                     out.push(0);
-                    out.extend(lc.to_be_bytes());
                 }
+                out.extend(((le % MAX_EXTENDED_LE) as u16).to_be_bytes());
+            } else {
+                out.push((le % MAX_SHORT_LE) as u8);
             }
-            out.extend(d);
         }
 
-        out
+        Ok(out)
+    }
+
+    /// Split the command data into fragments such that each serialized command fits in
+    /// `max_apdu_size` bytes. Every fragment keeps the original header; only the last keeps Le.
+    /// A command without data is returned as-is.
+    pub fn fragment(self, max_apdu_size: usize) -> Result<Vec<Command>, Error> {
+        let capacity = data_capacity(max_apdu_size, self.le)?;
+        let data = match self.data {
+            Some(ref d) if d.len() > capacity => d,
+            _ => return Ok(vec![self]),
+        };
+
+        let mut fragments: Vec<Command> = data
+            .chunks(capacity)
+            .map(|chunk| Command::new(self.cla, self.ins, self.p1, self.p2, chunk.to_vec()))
+            .collect();
+        if let Some(last) = fragments.last_mut() {
+            last.le = self.le;
+        }
+
+        Ok(fragments)
+    }
+
+    /// ISO 7816-4 command chaining: fragment the command, and set the chaining bit in CLA on every
+    /// command except the last.
+    pub fn chain(self, max_apdu_size: usize) -> Result<Vec<Command>, Error> {
+        let mut commands = self.fragment(max_apdu_size)?;
+        let last = commands.len() - 1;
+        for command in &mut commands[..last] {
+            command.cla |= CLA_CHAINING;
+        }
+        Ok(commands)
+    }
+
+    /// Parse a serialized command, the inverse of `serialize` (ISO 7816-3 12.1, cases 1 to 4 in
+    /// short and extended form).
+    pub fn deserialize(buffer: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::InvalidLength(buffer.len());
+        if buffer.len() < HEADER_SIZE {
+            return Err(invalid());
+        }
+        let (header, body) = buffer.split_at(HEADER_SIZE);
+        let mut command = Command::new_header(header[0], header[1], header[2], header[3]);
+
+        let (lc, lc_size, le_size) = match body {
+            [] => return Ok(command),
+            // Case 2S: short Le only
+            [le] => return Ok(command.with_le(short_le(*le))),
+            // Case 2E: extended Le only
+            [0, le @ ..] if le.len() == 2 => return Ok(command.with_le(extended_le(le))),
+            [0, lc_hi, lc_lo, ..] => (u16::from_be_bytes([*lc_hi, *lc_lo]) as usize, 3, 2),
+            [lc, ..] => (*lc as usize, 1, 1),
+        };
+
+        if body.len() < lc_size + lc {
+            return Err(invalid());
+        }
+        let (data, le) = body[lc_size..].split_at(lc);
+        command.data = Some(data.to_vec());
+        match le.len() {
+            0 => {}
+            1 if le_size == 1 => command.le = Some(short_le(le[0])),
+            2 if le_size == 2 => command.le = Some(extended_le(le)),
+            _ => return Err(invalid()),
+        }
+
+        Ok(command)
+    }
+}
+
+// Le is encoded modulo its maximum, so zero stands for the largest value.
+fn short_le(le: u8) -> usize {
+    match le {
+        0 => MAX_SHORT_LE,
+        le => le as usize,
     }
 }
 
-impl From<Command> for Vec<u8> {
-    fn from(command: Command) -> Self {
+fn extended_le(le: &[u8]) -> usize {
+    match u16::from_be_bytes([le[0], le[1]]) {
+        0 => MAX_EXTENDED_LE,
+        le => le as usize,
+    }
+}
+
+// The number of data bytes that fit in a command APDU of `max_apdu_size` bytes. Sizes that only
+// allow short APDUs use short coding, and anything larger is assumed to support extended coding.
+fn data_capacity(max_apdu_size: usize, le: Option<usize>) -> Result<usize, Error> {
+    let (lc_size, le_size, max_lc) = match max_apdu_size {
+        0..=MAX_SHORT_APDU_SIZE => (1, 1, MAX_SHORT_LC),
+        _ => (3, 2, MAX_EXTENDED_LC),
+    };
+    let overhead = HEADER_SIZE + lc_size + le.map_or(0, |_| le_size);
+
+    match max_apdu_size.checked_sub(overhead) {
+        Some(capacity) if capacity > 0 => Ok(capacity.min(max_lc)),
+        _ => Err(Error::ApduSizeTooSmall(max_apdu_size)),
+    }
+}
+
+impl TryFrom<Command> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(command: Command) -> Result<Self, Self::Error> {
         command.serialize()
     }
 }
@@ -75,6 +243,18 @@ impl Response {
     pub fn is_ok(&self) -> bool {
         (self.sw1 == 0x90 || self.sw1 == 0x91) && (self.sw2 == 0x00)
     }
+
+    /// Whether more response data is waiting to be fetched with GET RESPONSE.
+    pub fn has_more(&self) -> bool {
+        self.sw1 == SW1_BYTES_REMAINING
+    }
+
+    /// Append a response fetched with GET RESPONSE, taking on its status words.
+    pub fn extend(&mut self, next: Response) {
+        self.data.extend(next.data);
+        self.sw1 = next.sw1;
+        self.sw2 = next.sw2;
+    }
 }
 
 impl From<Vec<u8>> for Response {
@@ -106,7 +286,7 @@ mod tests {
     fn header_only() {
         assert_eq!(
             vec![1, 2, 3, 4],
-            Command::new_header(1, 2, 3, 4).serialize(),
+            Command::new_header(1, 2, 3, 4).serialize().unwrap(),
         );
     }
 
@@ -114,7 +294,9 @@ mod tests {
     fn header_and_data() {
         assert_eq!(
             vec![0xaa, 0xbb, 0xcc, 0xdd, 2, 0xff, 0xff],
-            Command::new(0xaa, 0xbb, 0xcc, 0xdd, vec![0xff, 0xff]).serialize()
+            Command::new(0xaa, 0xbb, 0xcc, 0xdd, vec![0xff, 0xff])
+                .serialize()
+                .unwrap()
         );
     }
 
@@ -122,7 +304,7 @@ mod tests {
     fn extended_lc() {
         let len = 512;
         let cmd = Command::new(1, 2, 3, 4, vec![0xaf; len]);
-        let ser = cmd.serialize();
+        let ser = cmd.serialize().unwrap();
         // Header
         assert_eq!(vec![1, 2, 3, 4], ser[0..4]);
         // Lc
@@ -131,6 +313,194 @@ mod tests {
         assert_eq!(vec![0xaf; len], ser[7..]);
     }
 
+    #[test]
+    fn data_too_long() {
+        let cmd = Command::new(1, 2, 3, 4, vec![0; MAX_EXTENDED_LC + 1]);
+        assert_eq!(
+            Err(Error::DataTooLong(MAX_EXTENDED_LC + 1)),
+            cmd.serialize()
+        );
+    }
+
+    #[test]
+    fn short_le() {
+        assert_eq!(
+            vec![1, 2, 3, 4, 0x10],
+            Command::new_header(1, 2, 3, 4)
+                .with_le(0x10)
+                .serialize()
+                .unwrap()
+        );
+        assert_eq!(
+            vec![1, 2, 3, 4, 1, 0xff, 0],
+            Command::new(1, 2, 3, 4, vec![0xff])
+                .with_le(256)
+                .serialize()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn extended_le() {
+        assert_eq!(
+            vec![1, 2, 3, 4, 0, 0x01, 0x01],
+            Command::new_header(1, 2, 3, 4)
+                .with_le(257)
+                .serialize()
+                .unwrap()
+        );
+        assert_eq!(
+            vec![1, 2, 3, 4, 0, 0, 0],
+            Command::new_header(1, 2, 3, 4)
+                .with_le(MAX_EXTENDED_LE)
+                .serialize()
+                .unwrap()
+        );
+        // Short data is promoted to extended Lc when Le needs extended coding
+        assert_eq!(
+            vec![1, 2, 3, 4, 0, 0, 1, 0xff, 0x02, 0x00],
+            Command::new(1, 2, 3, 4, vec![0xff])
+                .with_le(512)
+                .serialize()
+                .unwrap()
+        );
+        // Extended data forces an extended Le
+        let ser = Command::new(1, 2, 3, 4, vec![0xaf; 300])
+            .with_le(1)
+            .serialize()
+            .unwrap();
+        assert_eq!(vec![0, 1, 44], ser[4..7]);
+        assert_eq!(vec![0, 1], ser[ser.len() - 2..]);
+    }
+
+    #[test]
+    fn invalid_le() {
+        let cmd = Command::new_header(1, 2, 3, 4);
+        assert_eq!(Err(Error::InvalidLe(0)), cmd.clone().with_le(0).serialize());
+        assert_eq!(
+            Err(Error::InvalidLe(MAX_EXTENDED_LE + 1)),
+            cmd.with_le(MAX_EXTENDED_LE + 1).serialize()
+        );
+    }
+
+    #[test]
+    fn fragment_fits() {
+        let cmd = Command::new(1, 2, 3, 4, vec![0xaf; 505]);
+        assert_eq!(vec![cmd.clone()], cmd.fragment(512).unwrap());
+
+        let header = Command::new_header(1, 2, 3, 4);
+        assert_eq!(vec![header.clone()], header.fragment(8).unwrap());
+    }
+
+    #[test]
+    fn fragment_extended() {
+        let data: Vec<u8> = (0..1200).map(|i| i as u8).collect();
+        let fragments = Command::new(1, 2, 3, 4, data.clone())
+            .with_le(1000)
+            .fragment(512)
+            .unwrap();
+
+        // 512 - header (4) - extended Lc (3) - extended Le (2)
+        assert_eq!(3, fragments.len());
+        assert_eq!(Some(503), fragments[0].data.as_ref().map(Vec::len));
+        assert_eq!(None, fragments[0].le);
+        assert_eq!(Some(1000), fragments[2].le);
+        assert!(fragments
+            .iter()
+            .all(|f| (f.cla, f.ins, f.p1, f.p2) == (1, 2, 3, 4)));
+        assert!(fragments
+            .iter()
+            .all(|f| f.clone().serialize().unwrap().len() <= 512));
+        assert_eq!(
+            data,
+            fragments
+                .into_iter()
+                .flat_map(|f| f.data.unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn fragment_short() {
+        let fragments = Command::new(1, 2, 3, 4, vec![0xaf; 600])
+            .fragment(MAX_SHORT_APDU_SIZE)
+            .unwrap();
+        assert_eq!(3, fragments.len());
+        assert_eq!(
+            vec![1, 2, 3, 4, 0xff],
+            fragments[0].clone().serialize().unwrap()[..5]
+        );
+        assert_eq!(Some(90), fragments[2].data.as_ref().map(Vec::len));
+    }
+
+    #[test]
+    fn fragment_too_small() {
+        let cmd = Command::new(1, 2, 3, 4, vec![0xaf; 16]);
+        assert_eq!(Err(Error::ApduSizeTooSmall(5)), cmd.fragment(5));
+    }
+
+    #[test]
+    fn chain() {
+        let commands = Command::new(0x80, 2, 3, 4, vec![0xaf; 600])
+            .chain(MAX_SHORT_APDU_SIZE)
+            .unwrap();
+        assert_eq!(
+            vec![0x90, 0x90, 0x80],
+            commands.iter().map(|c| c.cla).collect::<Vec<_>>()
+        );
+
+        let single = Command::new(0x80, 2, 3, 4, vec![0xaf; 16]);
+        assert_eq!(vec![single.clone()], single.chain(512).unwrap());
+    }
+
+    #[test]
+    fn deserialize_round_trip() {
+        let commands = [
+            Command::new_header(1, 2, 3, 4),
+            Command::new_header(1, 2, 3, 4).with_le(256),
+            Command::new_header(1, 2, 3, 4).with_le(MAX_EXTENDED_LE),
+            Command::new(1, 2, 3, 4, vec![0xaf; 16]),
+            Command::new(1, 2, 3, 4, vec![0xaf; 16]).with_le(16),
+            Command::new(1, 2, 3, 4, vec![0xaf; 600]),
+            Command::new(1, 2, 3, 4, vec![0xaf; 600]).with_le(1000),
+            Command::new(1, 2, 3, 4, vec![0xff]).with_le(512),
+        ];
+        for command in commands {
+            let ser = command.clone().serialize().unwrap();
+            assert_eq!(Ok(command), Command::deserialize(&ser));
+        }
+    }
+
+    #[test]
+    fn deserialize_invalid() {
+        assert_eq!(
+            Err(Error::InvalidLength(3)),
+            Command::deserialize(&[1, 2, 3])
+        );
+        // Lc claims more data than is present
+        assert_eq!(
+            Err(Error::InvalidLength(7)),
+            Command::deserialize(&[1, 2, 3, 4, 4, 0xaf, 0xaf])
+        );
+        // Too many trailing bytes for Le
+        assert_eq!(
+            Err(Error::InvalidLength(9)),
+            Command::deserialize(&[1, 2, 3, 4, 1, 0xaf, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn response_get_response() {
+        let mut rsp = Response::from(vec![0xaa, 0xbb, 0x61, 0x02]);
+        assert!(rsp.has_more());
+        assert!(!rsp.is_ok());
+
+        rsp.extend(Response::from(vec![0xcc, 0xdd, 0x90, 0x00]));
+        assert!(!rsp.has_more());
+        assert!(rsp.is_ok());
+        assert_eq!(vec![0xaa, 0xbb, 0xcc, 0xdd], rsp.data);
+    }
+
     #[test]
     fn response_ok() {
         let buf = vec![0xaa, 0xbb, 0xcc, 0xdd, 0x90, 0x00];
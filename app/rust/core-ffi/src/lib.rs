@@ -4,16 +4,20 @@ use bitcoin::secp256k1::ecdsa::Signature;
 use crypto::chacha20poly1305::{ChaCha20Poly1305Error, XChaCha20Poly1305};
 use crypto::crypto_box::{CryptoBox, CryptoBoxError, CryptoBoxKeyPair};
 use crypto::ecdh::Secp256k1SharedSecret;
-use crypto::frost::FrostShare;
+use crypto::frost::SecretShare;
 use crypto::hkdf::{Hkdf, HkdfError};
 use crypto::keys::{PublicKey, SecretKey, SecretKeyError};
 use crypto::noise::{
     DhError, HardwareBackedDh, HardwareBackedKeyPair, NoiseContext, NoiseRole, NoiseWrapperError,
     PrivateKey,
 };
+use crypto::secret::SecretBytes;
 use crypto::signature_verifier::{SignatureVerifier, SignatureVerifierError};
 use crypto::spake2::{Spake2Context, Spake2Error, Spake2Keys, Spake2Role};
-use frost::{KeyCommitments, KeygenError, ShareDetails, ShareGenerator, SharePackage};
+use frost::{
+    FrostSigner, KeyCommitments, KeygenError, ShareDetails, ShareGenerator, SharePackage,
+    ShareRefresher, SigningError,
+};
 use lightning_support::invoice::{Invoice, InvoiceError, Sha256};
 use wsm_integrity::{WsmContext, WsmIntegrityVerifier, WsmIntegrityVerifierError};
 
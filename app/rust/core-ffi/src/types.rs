@@ -2,7 +2,8 @@ use std::{fmt::Display, str::FromStr};
 
 use crate::UniffiCustomTypeConverter;
 use bitcoin::secp256k1::ecdsa::Signature;
-use crypto::frost::FrostShare;
+use crypto::frost::{FrostShare, SecretShare};
+use crypto::secret::SecretBytes;
 
 trait Stringable: Display + FromStr {}
 impl Stringable for lightning_support::invoice::Sha256 {}
@@ -25,15 +26,28 @@ where
     }
 }
 
-impl UniffiCustomTypeConverter for FrostShare {
+impl UniffiCustomTypeConverter for SecretShare {
     type Builtin = Vec<u8>;
 
     fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
         // TODO [W-9921] Impl std::error:Error for FrostError
-        Ok(FrostShare::from_slice(&val).unwrap())
+        Ok(FrostShare::from_slice(&val).unwrap().into())
     }
 
     fn from_custom(obj: Self) -> Self::Builtin {
-        obj.serialize().to_vec()
+        obj.expose_secret().serialize().to_vec()
+    }
+}
+
+// The foreign side gets its own copy of the bytes; the Rust side's copy is wiped when dropped.
+impl UniffiCustomTypeConverter for SecretBytes {
+    type Builtin = Vec<u8>;
+
+    fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+        Ok(SecretBytes::new(val))
+    }
+
+    fn from_custom(obj: Self) -> Self::Builtin {
+        obj.expose_secret().to_vec()
     }
 }
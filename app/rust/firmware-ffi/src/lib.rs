@@ -1,14 +1,19 @@
 mod csek;
+mod summary;
 mod types;
 
 use crate::csek::{SealKey, UnsealKey};
+use crate::summary::{
+    Anomaly, OutputKind, OutputSummary, SignTransactionWithSummary, SignedTransaction,
+    TransactionSummary,
+};
 use bitcoin::{bip32::Fingerprint, secp256k1::PublicKey};
 use teltra::{TelemetryIdentifiers, Teltra, TeltraError};
 use wca::attestation::{Attestation, AttestationError};
 use wca::command_interface::{Command, State};
 use wca::commands::{
     BioMatchStats, BtcNetwork, CancelFingerprintEnrollment, CoredumpFragment, DeleteFingerprint,
-    DescriptorPublicKey, DeviceIdentifiers, DeviceInfo, EnrolledFingerprints,
+    Descriptor, DescriptorPublicKey, DeviceIdentifiers, DeviceInfo, EnrolledFingerprints,
     EnrollmentDiagnostics, EventFragment, FingerprintEnrollmentResult, FingerprintEnrollmentStatus,
     FirmwareFeatureFlag, FirmwareFeatureFlagCfg, FirmwareMetadata, FirmwareSlot, FwupFinish,
     FwupFinishRspStatus, FwupMode, FwupStart, FwupTransfer, GetAuthenticationKey,
@@ -27,6 +32,7 @@ use wca::fwpb::FingerprintHandle;
 use wca::log_buffer::{
     disable_proto_exchange_logging, enable_proto_exchange_logging, get_proto_exchange_logs,
 };
+use wca::ApduSession;
 use wca::{EllipticCurve, KeyEncoding, PublicKeyHandle, PublicKeyMetadata, SignatureContext};
 
 type BooleanState = State<bool>;
@@ -49,5 +55,8 @@ type PublicKeyHandleState = State<PublicKeyHandle>;
 type SignatureContextState = State<SignatureContext>;
 type EnrolledFingerprintsState = State<EnrolledFingerprints>;
 type UnlockInfoState = State<UnlockInfo>;
+type SignedTransactionState = State<SignedTransaction>;
+
+type WalletDescriptor = Descriptor<DescriptorPublicKey>;
 
 uniffi::include_scaffolding!("firmware");
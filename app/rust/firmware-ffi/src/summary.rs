@@ -0,0 +1,145 @@
+use bitcoin::{bip32::Fingerprint, Amount, Network};
+use wca::{
+    command_interface::{Command, State},
+    commands::{BtcNetwork, PartiallySignedTransaction},
+    errors::CommandError,
+    signing::summary::{self, TransactionSummaryBuilder},
+};
+
+use crate::{SignedTransactionState, WalletDescriptor};
+
+pub struct SignedTransaction {
+    pub psbt: PartiallySignedTransaction,
+    pub summary: TransactionSummary,
+}
+
+pub struct TransactionSummary {
+    pub outputs: Vec<OutputSummary>,
+    pub input_value: u64,
+    pub fee: u64,
+    pub fee_rate: f64,
+    pub anomalies: Vec<Anomaly>,
+}
+
+pub struct OutputSummary {
+    pub index: u32,
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+    pub address: Option<String>,
+    pub kind: OutputKind,
+}
+
+pub enum OutputKind {
+    External,
+    Change { path: String },
+}
+
+pub enum Anomaly {
+    LargeFee { fee: u64 },
+    HighFeeRate { fee_rate: f64 },
+    UnexpectedChangePath { output_index: u32, path: String },
+    MixedNetworks,
+    UnverifiedInputValue { input_index: u32 },
+    UnverifiedChange { output_index: u32 },
+}
+
+pub struct SignTransactionWithSummary(wca::commands::SignTransactionWithSummary);
+
+impl SignTransactionWithSummary {
+    pub fn new(
+        serialized_psbt: PartiallySignedTransaction,
+        origin_fingerprint: Fingerprint,
+        descriptors: Vec<WalletDescriptor>,
+        network: BtcNetwork,
+        max_fee: Option<u64>,
+        max_fee_rate: Option<f64>,
+        async_sign: bool,
+    ) -> Self {
+        let mut summary =
+            TransactionSummaryBuilder::new(origin_fingerprint).network(match network {
+                BtcNetwork::Bitcoin => Network::Bitcoin,
+                BtcNetwork::Testnet => Network::Testnet,
+                BtcNetwork::Signet => Network::Signet,
+                BtcNetwork::Regtest => Network::Regtest,
+            });
+        for descriptor in descriptors {
+            summary = summary.descriptor(descriptor);
+        }
+        if let Some(max_fee) = max_fee {
+            summary = summary.max_fee(Amount::from_sat(max_fee));
+        }
+        if let Some(max_fee_rate) = max_fee_rate {
+            summary = summary.max_fee_rate(max_fee_rate);
+        }
+
+        Self(wca::commands::SignTransactionWithSummary::new(
+            serialized_psbt,
+            summary,
+            async_sign,
+        ))
+    }
+
+    pub fn next(&self, response: Vec<u8>) -> Result<SignedTransactionState, CommandError> {
+        let state = match self.0.next(response)? {
+            State::Data { response } => SignedTransactionState::Data { response },
+            State::Result { value } => SignedTransactionState::Result {
+                value: SignedTransaction {
+                    psbt: value.psbt,
+                    summary: value.summary.into(),
+                },
+            },
+        };
+        Ok(state)
+    }
+}
+
+impl From<summary::TransactionSummary> for TransactionSummary {
+    fn from(summary: summary::TransactionSummary) -> Self {
+        Self {
+            outputs: summary.outputs.into_iter().map(Into::into).collect(),
+            input_value: summary.input_value.to_sat(),
+            fee: summary.fee.to_sat(),
+            fee_rate: summary.fee_rate,
+            anomalies: summary.anomalies.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<summary::OutputSummary> for OutputSummary {
+    fn from(output: summary::OutputSummary) -> Self {
+        Self {
+            index: output.index as u32,
+            value: output.value.to_sat(),
+            script_pubkey: output.script_pubkey.into_bytes(),
+            address: output.address.map(|address| address.to_string()),
+            kind: match output.kind {
+                summary::OutputKind::External => OutputKind::External,
+                summary::OutputKind::Change { path } => OutputKind::Change {
+                    path: path.to_string(),
+                },
+            },
+        }
+    }
+}
+
+impl From<summary::Anomaly> for Anomaly {
+    fn from(anomaly: summary::Anomaly) -> Self {
+        match anomaly {
+            summary::Anomaly::LargeFee { fee } => Self::LargeFee { fee: fee.to_sat() },
+            summary::Anomaly::HighFeeRate { fee_rate } => Self::HighFeeRate { fee_rate },
+            summary::Anomaly::UnexpectedChangePath { output_index, path } => {
+                Self::UnexpectedChangePath {
+                    output_index: output_index as u32,
+                    path: path.to_string(),
+                }
+            }
+            summary::Anomaly::MixedNetworks => Self::MixedNetworks,
+            summary::Anomaly::UnverifiedInputValue { input_index } => Self::UnverifiedInputValue {
+                input_index: input_index as u32,
+            },
+            summary::Anomaly::UnverifiedChange { output_index } => Self::UnverifiedChange {
+                output_index: output_index as u32,
+            },
+        }
+    }
+}
@@ -9,6 +9,7 @@ impl Stringable for bitcoin::secp256k1::PublicKey {}
 impl Stringable for wca::commands::PartiallySignedTransaction {}
 impl Stringable for bitcoin::bip32::Fingerprint {}
 impl Stringable for wca::commands::DescriptorPublicKey {}
+impl Stringable for crate::WalletDescriptor {}
 impl Stringable for Signature {}
 
 impl<T> UniffiCustomTypeConverter for T
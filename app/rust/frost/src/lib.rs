@@ -1,14 +1,21 @@
 // Re-export from crypto crate
 pub use crypto::frost::{
     dkg::{KeygenError, SharePackage},
+    signing::SigningError,
     KeyCommitments, ShareDetails,
 };
 use serde::{Deserialize, Serialize};
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use bitcoin::{hashes::Hash, sighash::TapSighash};
 use std::sync::Mutex;
 
 use crypto::frost::dkg::{aggregate_shares, equality_check, generate_share_packages};
+use crypto::frost::refresh::{aggregate_refresh_shares, generate_refresh_packages, RefreshPackage};
+use crypto::frost::signing::{
+    aggregate, generate_nonce_pair, partial_sign, verify_partial_signature, FrostSecNonce,
+    PartialSignature, SigningCommitment, SigningPackage,
+};
 use crypto::frost::Participant;
 
 #[derive(Serialize)]
@@ -127,15 +134,268 @@ impl ShareGeneratorState {
     }
 }
 
+#[derive(Serialize)]
+pub struct InitiateShareRefreshAppRequest {
+    pub app_refresh_package: RefreshPackage,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InitiateShareRefreshServerResponse {
+    pub server_refresh_package: RefreshPackage,
+    pub server_key_commitments: KeyCommitments,
+}
+
+/// Re-randomizes the App's share of an existing distributed key with the Server. The refreshed
+/// `ShareDetails` have the same aggregate public key as the ones passed in.
+pub struct ShareRefresher {
+    inner: Mutex<ShareRefresherState>,
+}
+
+impl ShareRefresher {
+    pub fn new(share_details: ShareDetails) -> Self {
+        Self {
+            inner: Mutex::new(ShareRefresherState::new(share_details)),
+        }
+    }
+
+    pub fn generate(&self) -> Result<String, KeygenError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let refresh_package = inner.generate()?;
+
+        // TODO: shouldn't be unwrapping here, but error type is from lower-level crate.
+        Ok(BASE64.encode(
+            serde_json::to_vec(&InitiateShareRefreshAppRequest {
+                app_refresh_package: refresh_package,
+            })
+            .unwrap(),
+        ))
+    }
+
+    pub fn aggregate(&self, sealed_response: String) -> Result<ShareDetails, KeygenError> {
+        let inner = self.inner.lock().unwrap();
+
+        let unsealed_response: InitiateShareRefreshServerResponse = BASE64
+            .decode(sealed_response)
+            .ok()
+            .and_then(|response| serde_json::from_slice(&response).ok())
+            .ok_or(KeygenError::InvalidServerResponse)?;
+
+        inner.aggregate(
+            &unsealed_response.server_refresh_package,
+            &unsealed_response.server_key_commitments,
+        )
+    }
+
+    pub fn encode_complete_refresh_request(
+        &self,
+        share_details: ShareDetails,
+    ) -> Result<String, KeygenError> {
+        Ok(BASE64.encode(
+            serde_json::to_vec(&CompleteDistributedKeygenAppRequest {
+                app_key_commitments: share_details.key_commitments,
+            })
+            .unwrap(),
+        ))
+    }
+}
+
+struct ShareRefresherState {
+    share_details: ShareDetails,
+    app_refresh_package: Option<RefreshPackage>,
+}
+
+impl ShareRefresherState {
+    fn new(share_details: ShareDetails) -> Self {
+        Self {
+            share_details,
+            app_refresh_package: None,
+        }
+    }
+
+    /// Returns a RefreshPackage to send the Server.
+    fn generate(&mut self) -> Result<RefreshPackage, KeygenError> {
+        let mut refresh_packages = generate_refresh_packages(Participant::App)?;
+
+        // We always can expect the right refresh packages here given the above call.
+        let server_refresh_package = refresh_packages
+            .pop()
+            .expect("Missing server refresh package.");
+        self.app_refresh_package = Some(
+            refresh_packages
+                .pop()
+                .expect("Missing app refresh package."),
+        );
+
+        Ok(server_refresh_package)
+    }
+
+    /// Aggregates the peer's refresh package into our share.
+    /// MUST be run after `generate`.
+    fn aggregate(
+        &self,
+        peer_refresh_package: &RefreshPackage,
+        peer_key_commitments: &KeyCommitments,
+    ) -> Result<ShareDetails, KeygenError> {
+        let app_refresh_package = if let Some(package) = &self.app_refresh_package {
+            package
+        } else {
+            return Err(KeygenError::MissingSharePackage);
+        };
+
+        let share_details = aggregate_refresh_shares(
+            Participant::App,
+            &self.share_details,
+            &[peer_refresh_package, app_refresh_package],
+        )?;
+
+        equality_check(peer_key_commitments, share_details)
+    }
+}
+
+#[derive(Serialize)]
+pub struct InitiateSigningAppRequest {
+    pub sighash: TapSighash,
+    pub app_commitment: SigningCommitment,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InitiateSigningServerResponse {
+    pub server_commitment: SigningCommitment,
+    pub server_partial_signature: PartialSignature,
+}
+
+/// Drives the App side of a FROST signing session with the Server.
+///
+/// `generate` produces the App's nonce commitment for the server, and `sign` consumes the
+/// server's commitment and partial signature to produce the final BIP340 signature.
+pub struct FrostSigner {
+    inner: Mutex<FrostSignerState>,
+}
+
+impl FrostSigner {
+    pub fn new(share_details: ShareDetails) -> Self {
+        Self {
+            inner: Mutex::new(FrostSignerState::new(share_details)),
+        }
+    }
+
+    pub fn generate(&self, sighash: Vec<u8>) -> Result<String, SigningError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let sighash = TapSighash::from_slice(&sighash).map_err(|_| SigningError::InvalidSighash)?;
+        let app_commitment = inner.generate(sighash)?;
+
+        // TODO: shouldn't be unwrapping here, but error type is from lower-level crate.
+        Ok(BASE64.encode(
+            serde_json::to_vec(&InitiateSigningAppRequest {
+                sighash,
+                app_commitment,
+            })
+            .unwrap(),
+        ))
+    }
+
+    /// Returns the 64-byte BIP340 signature.
+    pub fn sign(&self, sealed_response: String) -> Result<Vec<u8>, SigningError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let unsealed_response: InitiateSigningServerResponse = BASE64
+            .decode(sealed_response)
+            .ok()
+            .and_then(|response| serde_json::from_slice(&response).ok())
+            .ok_or(SigningError::InvalidServerResponse)?;
+
+        inner.sign(
+            unsealed_response.server_commitment,
+            &unsealed_response.server_partial_signature,
+        )
+    }
+}
+
+struct FrostSignerState {
+    share_details: ShareDetails,
+    pending: Option<PendingSignature>,
+}
+
+struct PendingSignature {
+    sighash: TapSighash,
+    secret_nonce: FrostSecNonce,
+    commitment: SigningCommitment,
+}
+
+impl FrostSignerState {
+    fn new(share_details: ShareDetails) -> Self {
+        Self {
+            share_details,
+            pending: None,
+        }
+    }
+
+    /// Returns the SigningCommitment to send the Server. Any previously generated nonce is
+    /// discarded.
+    fn generate(&mut self, sighash: TapSighash) -> Result<SigningCommitment, SigningError> {
+        let (secret_nonce, commitment) =
+            generate_nonce_pair(Participant::App, &self.share_details, sighash, None)?;
+        self.pending = Some(PendingSignature {
+            sighash,
+            secret_nonce,
+            commitment: commitment.clone(),
+        });
+
+        Ok(commitment)
+    }
+
+    /// Verifies the peer's partial signature, signs and aggregates.
+    /// MUST be run after `generate`. The nonce is consumed even if signing fails.
+    fn sign(
+        &mut self,
+        peer_commitment: SigningCommitment,
+        peer_partial_signature: &PartialSignature,
+    ) -> Result<Vec<u8>, SigningError> {
+        let pending = self.pending.take().ok_or(SigningError::MissingNonce)?;
+
+        let signing_package = SigningPackage::new(
+            pending.sighash,
+            None,
+            vec![pending.commitment, peer_commitment],
+        )?;
+        verify_partial_signature(
+            &self.share_details.key_commitments,
+            &signing_package,
+            peer_partial_signature,
+        )?;
+        let partial_signature = partial_sign(
+            Participant::App,
+            &self.share_details,
+            &signing_package,
+            pending.secret_nonce,
+        )?;
+
+        let signature = aggregate(
+            &self.share_details.key_commitments,
+            &signing_package,
+            &[&partial_signature, peer_partial_signature],
+        )?;
+
+        Ok(signature[..].to_vec())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+    use bitcoin::{hashes::Hash, sighash::TapSighash};
     use crypto::frost::{
-        dkg::{aggregate_shares, generate_share_packages, KeygenError},
+        dkg::{self, aggregate_shares, generate_share_packages, KeygenError},
+        signing::{self, generate_nonce_pair},
         Participant,
     };
 
-    use crate::{InitiateDistributedKeygenServerResponse, ShareGenerator};
+    use crate::{
+        FrostSigner, InitiateDistributedKeygenServerResponse, InitiateSigningServerResponse,
+        ShareGenerator, SigningError,
+    };
 
     #[test]
     fn test_aggregate_without_calling_generate_first() {
@@ -176,4 +436,65 @@ mod tests {
             Err(KeygenError::MissingSharePackage)
         )
     }
+
+    #[test]
+    fn test_sign_without_calling_generate_first() {
+        let app_initiate_result = dkg::app::initiate_dkg().unwrap();
+        let server_initiate_result =
+            dkg::server::initiate_dkg(&app_initiate_result.share_package_for_peer).unwrap();
+        let app_share_details = dkg::app::continue_dkg(
+            &app_initiate_result.share_package,
+            &server_initiate_result.share_package,
+            &server_initiate_result.share_details.key_commitments,
+        )
+        .unwrap();
+
+        let sighash = TapSighash::from_byte_array([1u8; 32]);
+        let (_, app_commitment) =
+            generate_nonce_pair(Participant::App, &app_share_details, sighash, None).unwrap();
+        let server_result = signing::server::sign(
+            &server_initiate_result.share_details,
+            sighash,
+            None,
+            app_commitment,
+        )
+        .unwrap();
+
+        let server_response = InitiateSigningServerResponse {
+            server_commitment: server_result.commitment,
+            server_partial_signature: server_result.partial_signature,
+        };
+
+        // Attempt to sign with a FrostSigner that never generated a nonce.
+        assert_eq!(
+            FrostSigner::new(app_share_details)
+                .sign(BASE64.encode(serde_json::to_vec(&server_response).unwrap())),
+            Err(SigningError::MissingNonce)
+        )
+    }
+
+    #[test]
+    fn test_sign_with_malformed_response() {
+        let app_initiate_result = dkg::app::initiate_dkg().unwrap();
+        let server_initiate_result =
+            dkg::server::initiate_dkg(&app_initiate_result.share_package_for_peer).unwrap();
+        let app_share_details = dkg::app::continue_dkg(
+            &app_initiate_result.share_package,
+            &server_initiate_result.share_package,
+            &server_initiate_result.share_details.key_commitments,
+        )
+        .unwrap();
+
+        let signer = FrostSigner::new(app_share_details);
+        signer.generate(vec![1u8; 32]).unwrap();
+
+        assert_eq!(
+            signer.sign("not base64!".to_string()),
+            Err(SigningError::InvalidServerResponse)
+        );
+        assert_eq!(
+            signer.sign(BASE64.encode(b"{}")),
+            Err(SigningError::InvalidServerResponse)
+        );
+    }
 }
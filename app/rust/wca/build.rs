@@ -15,4 +15,18 @@ fn main() {
 
     let protos = ["wallet.proto"];
     prost_build::compile_protos(&protos, &[proto_include_dir, proto_source_dir]).unwrap();
+
+    // The production firmware signing root is a picocert certificate issued by the release
+    // pipeline, which points FWUP_SIGNING_ROOT at it. Builds without one embed nothing, and can
+    // only verify bundles against a root given at runtime.
+    println!("cargo:rerun-if-env-changed=FWUP_SIGNING_ROOT");
+    let signing_root = match std::env::var_os("FWUP_SIGNING_ROOT") {
+        Some(path) => {
+            println!("cargo:rerun-if-changed={}", path.to_string_lossy());
+            std::fs::read(path).unwrap()
+        }
+        None => Vec::new(),
+    };
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("fwup-signing-root.cert"), signing_root).unwrap();
 }
@@ -16,7 +16,11 @@ use crate::fwpb::{
     DerivePublicKeyAndSignCmd, DerivePublicKeyAndSignRsp, DerivePublicKeyCmd, DerivePublicKeyRsp,
     DeriveRsp, GetUnlockMethodCmd, GetUnlockMethodRsp, LockDeviceCmd, LockDeviceRsp,
 };
-use crate::{command, errors::CommandError, wca};
+use crate::{
+    command,
+    errors::CommandError,
+    wca::{self, transceive},
+};
 
 pub struct UnlockInfo {
     pub method: UnlockMethod,
@@ -45,8 +49,7 @@ fn get_authentication_key_v2() -> Result<PublicKeyHandle, CommandError> {
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -74,8 +77,7 @@ fn sign_challenge_v2(challenge: Vec<u8>) -> Result<SignatureContext, CommandErro
         hash,
     }
     .try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -104,8 +106,7 @@ fn get_authentication_key() -> Result<PublicKey, CommandError> {
         ..Default::default()
     }
     .try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -150,8 +151,7 @@ fn sign_challenge(challenge: Vec<u8>, async_sign: bool) -> Result<Signature, Com
 fn lock_device() -> Result<bool, CommandError> {
     let apdu: apdu::Command = LockDeviceCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -167,8 +167,7 @@ fn lock_device() -> Result<bool, CommandError> {
 fn get_unlock_method() -> Result<UnlockInfo, CommandError> {
     let apdu: apdu::Command = GetUnlockMethodCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -6,7 +6,8 @@ use crate::{
         coredump_get_cmd::CoredumpGetType, coredump_get_rsp::CoredumpGetRspStatus, wallet_rsp::Msg,
         CoredumpGetCmd, CoredumpGetRsp,
     },
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 use crate::command_interface::command;
@@ -26,8 +27,7 @@ fn get_coredump_fragment(offset: u32) -> Result<CoredumpFragment, CommandError>
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -69,8 +69,7 @@ fn get_coredump_count() -> Result<u16, CommandError> {
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -11,7 +11,8 @@ use crate::{
         wallet_rsp::Msg, DeviceIdCmd, DeviceIdRsp, DeviceInfoCmd, DeviceInfoRsp, TelemetryIdGetCmd,
         TelemetryIdGetRsp,
     },
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 use crate::command_interface::command;
@@ -57,8 +58,7 @@ pub struct DeviceInfo {
 fn device_id() -> Result<DeviceIdentifiers, CommandError> {
     let apdu: apdu::Command = DeviceIdCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -87,8 +87,7 @@ fn device_id() -> Result<DeviceIdentifiers, CommandError> {
 fn telemetry_id() -> Result<TelemetryIdentifiers, CommandError> {
     let apdu: apdu::Command = TelemetryIdGetCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -132,8 +131,7 @@ fn telemetry_id() -> Result<TelemetryIdentifiers, CommandError> {
 fn device_info() -> Result<DeviceInfo, CommandError> {
     let apdu: apdu::Command = DeviceInfoCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -8,7 +8,8 @@ use crate::{
         FeatureFlagCfg, FeatureFlagsGetCmd, FeatureFlagsGetRsp, FeatureFlagsSetCmd,
         FeatureFlagsSetRsp,
     },
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 use crate::command_interface::command;
@@ -73,8 +74,7 @@ pub struct FirmwareFeatureFlagCfg {
 fn feature_flags_get() -> Result<Vec<FirmwareFeatureFlagCfg>, CommandError> {
     let apdu: apdu::Command = FeatureFlagsGetCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -118,8 +118,7 @@ fn feature_flags_set(flags: Vec<FirmwareFeatureFlag>, enabled: bool) -> Result<b
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
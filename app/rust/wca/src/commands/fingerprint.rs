@@ -15,7 +15,8 @@ use crate::{
         GetFingerprintEnrollmentStatusRsp, SetFingerprintLabelCmd, StartFingerprintEnrollmentCmd,
         StartFingerprintEnrollmentRsp, WalletRsp,
     },
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 pub struct EnrolledFingerprints {
@@ -55,8 +56,7 @@ fn get_fingerprint_enrollment_status(
         app_knows_about_this_field: is_enrollment_context_aware,
     }
     .try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = WalletRsp::decode(std::io::Cursor::new(response.data))?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -107,8 +107,7 @@ fn start_fingerprint_enrollment(index: u32, label: String) -> Result<bool, Comma
         handle: Some(FingerprintHandle { index, label }),
     }
     .try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -142,8 +141,7 @@ fn set_fingerprint_label(index: u32, label: String) -> Result<bool, CommandError
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
 
     wca::decode_and_check(response).map(|_| true)
 }
@@ -152,8 +150,7 @@ fn set_fingerprint_label(index: u32, label: String) -> Result<bool, CommandError
 fn get_enrolled_fingerprints() -> Result<EnrolledFingerprints, CommandError> {
     let apdu: apdu::Command = GetEnrolledFingerprintsCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -172,8 +169,7 @@ fn get_enrolled_fingerprints() -> Result<EnrolledFingerprints, CommandError> {
 #[generator(yield(Vec<u8>), resume(Vec<u8>))]
 fn delete_fingerprint(index: u32) -> Result<bool, CommandError> {
     let apdu: apdu::Command = DeleteFingerprintCmd { index }.try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
 
     let result = wca::decode_and_check(response);
     match result {
@@ -187,8 +183,7 @@ fn delete_fingerprint(index: u32) -> Result<bool, CommandError> {
 #[generator(yield(Vec<u8>), resume(Vec<u8>))]
 fn cancel_fingerprint_enrollment() -> Result<bool, CommandError> {
     let apdu: apdu::Command = CancelFingerprintEnrollmentCmd {}.try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     wca::decode_and_check(response).map(|_| true)
 }
 
@@ -8,7 +8,8 @@ use crate::{
         fwup_transfer_rsp::FwupTransferRspStatus, wallet_rsp::Msg, FwupFinishCmd, FwupFinishRsp,
         FwupStartCmd, FwupStartRsp, FwupTransferCmd, FwupTransferRsp,
     },
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 use crate::command_interface::command;
@@ -36,8 +37,7 @@ fn fwup_start(patch_size: Option<u32>, fwup_mode: FwupMode) -> Result<bool, Comm
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -71,8 +71,7 @@ fn fwup_transfer(
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -105,8 +104,7 @@ fn fwup_finish(
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -5,7 +5,7 @@ use next_gen::generator;
 use crate::fwpb::derive_rsp::DeriveRspStatus;
 use crate::fwpb::wallet_rsp::Msg;
 use crate::fwpb::{BtcNetwork, DeriveKeyDescriptorCmd, DeriveRsp};
-use crate::wca;
+use crate::wca::{self, transceive};
 use crate::yield_from_;
 use crate::{errors::CommandError, fwpb};
 
@@ -21,8 +21,7 @@ pub(crate) fn derive(
         derivation_path: Some(derivation_path.into()),
     }
     .try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
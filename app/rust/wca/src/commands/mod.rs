@@ -61,6 +61,7 @@ pub use query_authentication::QueryAuthentication;
 pub use seal_key::SealKey;
 pub use sign_sighash::SignedSighash;
 pub use sign_transaction::SignTransaction;
+pub use sign_transaction::{SignTransactionWithSummary, SignedTransaction};
 pub use telemetry::EventFragment;
 pub use telemetry::GetEvents;
 pub use unseal_key::UnsealKey;
@@ -71,4 +72,4 @@ pub type SealedKey = Vec<u8>;
 pub type UnsealedKey = [u8; 32];
 pub type Signature = bitcoin::secp256k1::ecdsa::Signature;
 pub use bitcoin::psbt::PartiallySignedTransaction;
-pub use miniscript::DescriptorPublicKey;
+pub use miniscript::{Descriptor, DescriptorPublicKey};
@@ -6,7 +6,8 @@ use crate::{
         query_authentication_rsp::QueryAuthenticationRspStatus, wallet_rsp::Msg,
         QueryAuthenticationCmd, QueryAuthenticationRsp,
     },
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 use crate::command_interface::command;
@@ -14,8 +15,7 @@ use crate::command_interface::command;
 #[generator(yield(Vec<u8>), resume(Vec<u8>))]
 fn query_authentication() -> Result<bool, CommandError> {
     let apdu: apdu::Command = QueryAuthenticationCmd {}.try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -7,7 +7,12 @@ use crate::{
     command_interface::command,
     commands::SignedSighash,
     errors::CommandError,
-    signing::{derived::DerivedKeySigner, sign, Signer},
+    signing::{
+        derived::DerivedKeySigner,
+        sign,
+        summary::{TransactionSummary, TransactionSummaryBuilder},
+        Signer,
+    },
     yield_from_,
 };
 
@@ -39,8 +44,32 @@ fn sign_transaction(
     Ok(psbt)
 }
 
+pub struct SignedTransaction {
+    pub psbt: PartiallySignedTransaction,
+    pub summary: TransactionSummary,
+}
+
+// Summarise the transaction before signing it, so the summary describes exactly what was approved.
+#[generator(yield(Vec<u8>), resume(Vec<u8>))]
+fn sign_transaction_with_summary(
+    psbt: PartiallySignedTransaction,
+    summary: TransactionSummaryBuilder,
+    async_sign: bool,
+) -> Result<SignedTransaction, CommandError> {
+    let origin_fingerprint = summary.origin_fingerprint();
+    let summary = summary.build(&psbt)?;
+    let psbt = yield_from_!(sign_transaction(psbt, origin_fingerprint, async_sign))?;
+
+    Ok(SignedTransaction { psbt, summary })
+}
+
 command!(SignTransaction = sign_transaction -> PartiallySignedTransaction,
     psbt: PartiallySignedTransaction,
     origin_fingerprint: Fingerprint,
     async_sign: bool
 );
+command!(SignTransactionWithSummary = sign_transaction_with_summary -> SignedTransaction,
+    psbt: PartiallySignedTransaction,
+    summary: TransactionSummaryBuilder,
+    async_sign: bool
+);
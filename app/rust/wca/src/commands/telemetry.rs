@@ -3,7 +3,8 @@ use next_gen::generator;
 use crate::{
     errors::CommandError,
     fwpb::{events_get_rsp::EventsGetRspStatus, wallet_rsp::Msg, EventsGetCmd, EventsGetRsp},
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 use crate::command_interface::command;
@@ -17,8 +18,7 @@ pub struct EventFragment {
 fn get_events() -> Result<EventFragment, CommandError> {
     let apdu: apdu::Command = EventsGetCmd {}.try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
@@ -7,7 +7,8 @@ use crate::{
         unseal_csek_rsp::UnsealCsekRspStatus, wallet_rsp::Msg, SealedData, UnsealCsekCmd,
         UnsealCsekRsp,
     },
-    wca,
+    wca::{self, transceive},
+    yield_from_,
 };
 
 use super::{SealedKey, UnsealedKey};
@@ -21,8 +22,7 @@ fn unseal_key(sealed_key: SealedKey) -> Result<UnsealedKey, CommandError> {
     }
     .try_into()?;
 
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = wca::decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
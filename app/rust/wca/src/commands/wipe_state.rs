@@ -3,7 +3,8 @@ use next_gen::generator;
 use crate::{
     errors::CommandError,
     fwpb::{wallet_rsp::Msg, wipe_state_rsp::WipeStateRspStatus, WipeStateCmd, WipeStateRsp},
-    wca::decode_and_check,
+    wca::{decode_and_check, transceive},
+    yield_from_,
 };
 
 use crate::command_interface::command;
@@ -11,8 +12,7 @@ use crate::command_interface::command;
 #[generator(yield(Vec<u8>), resume(Vec<u8>))]
 fn wipe_state() -> Result<bool, CommandError> {
     let apdu: apdu::Command = WipeStateCmd {}.try_into()?;
-    let data = yield_!(apdu.into());
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu))?;
     let message = decode_and_check(response)?
         .msg
         .ok_or(CommandError::MissingMessage)?;
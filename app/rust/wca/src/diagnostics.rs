@@ -0,0 +1,272 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use teltra::{TelemetryIdentifiers, Teltra};
+use thiserror::Error;
+
+#[cfg(feature = "pcsc")]
+use crate::{
+    commands::{GetCoredumpCount, GetCoredumpFragment, GetDeviceInfo, GetEvents},
+    pcsc::{Performer, Transactor, TransactorError},
+};
+
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum DiagnosticsError {
+    #[error("could not read or write diagnostics bundle")]
+    Io(#[from] std::io::Error),
+    #[error("invalid diagnostics bundle")]
+    Bundle(#[from] serde_json::Error),
+    #[error("unsupported diagnostics bundle version: {0}")]
+    BundleVersion(u32),
+    #[error("coredump fragment at offset {actual}, expected {expected}")]
+    FragmentOffset { expected: usize, actual: usize },
+    #[error("coredump fragment is empty but incomplete")]
+    EmptyFragment,
+    #[cfg(feature = "pcsc")]
+    #[error(transparent)]
+    Transactor(#[from] TransactorError),
+}
+
+/// Everything collected from the hardware in one session, for uploading later.
+///
+/// Collecting drains coredumps and events from the hardware, so the raw bitlogs are kept alongside
+/// their Memfault translation: if teltra can't translate them now, they aren't lost.
+/// `incomplete` bundles hold what was drained before collection failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsBundle {
+    pub version: u32,
+    pub collected_at: u64,
+    pub device: DeviceIdentifiers,
+    #[serde(with = "hex_bytes_list")]
+    pub coredumps: Vec<Vec<u8>>,
+    #[serde(with = "hex_bytes")]
+    pub bitlogs: Vec<u8>,
+    #[serde(with = "hex_bytes_list")]
+    pub events: Vec<Vec<u8>>,
+    pub translation_error: Option<String>,
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdentifiers {
+    pub serial: String,
+    pub version: String,
+    pub sw_type: String,
+    pub hw_revision: String,
+}
+
+impl From<&DeviceIdentifiers> for TelemetryIdentifiers {
+    fn from(device: &DeviceIdentifiers) -> Self {
+        Self {
+            serial: device.serial.clone(),
+            version: device.version.clone(),
+            sw_type: device.sw_type.clone(),
+            hw_revision: device.hw_revision.clone(),
+        }
+    }
+}
+
+impl DiagnosticsBundle {
+    pub fn new(
+        device: DeviceIdentifiers,
+        collected_at: u64,
+        coredumps: Vec<Vec<u8>>,
+        bitlogs: Vec<u8>,
+    ) -> Self {
+        let mut bundle = Self {
+            version: BUNDLE_VERSION,
+            collected_at,
+            device,
+            coredumps,
+            bitlogs,
+            events: vec![],
+            translation_error: None,
+            incomplete: false,
+        };
+        bundle.translate();
+        bundle
+    }
+
+    // Translate the bitlogs into Memfault events, replacing any earlier translation.
+    fn translate(&mut self) {
+        (self.events, self.translation_error) = if self.bitlogs.is_empty() {
+            (vec![], None)
+        } else {
+            match Teltra::new().translate_bitlogs(self.bitlogs.clone(), (&self.device).into()) {
+                Ok(events) => (events, None),
+                Err(err) => (vec![], Some(err.to_string())),
+            }
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coredumps.is_empty() && self.bitlogs.is_empty()
+    }
+
+    /// Write the bundle to `path`, replacing it atomically so that a failed write never loses an
+    /// earlier bundle.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<(), DiagnosticsError> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        std::fs::rename(temp, path)?;
+        Ok(())
+    }
+
+    pub fn read_from(path: impl AsRef<Path>) -> Result<Self, DiagnosticsError> {
+        let reader = BufReader::new(File::open(path)?);
+        let bundle: Self = serde_json::from_reader(reader)?;
+        if bundle.version != BUNDLE_VERSION {
+            return Err(DiagnosticsError::BundleVersion(bundle.version));
+        }
+        Ok(bundle)
+    }
+}
+
+/// Drains coredumps and telemetry from the hardware into a `DiagnosticsBundle` on disk.
+#[cfg(feature = "pcsc")]
+pub struct DiagnosticsCollector<'a, T: Transactor + ?Sized> {
+    transactor: &'a T,
+}
+
+#[cfg(feature = "pcsc")]
+impl<'a, T: Transactor + ?Sized> DiagnosticsCollector<'a, T> {
+    pub fn new(transactor: &'a T) -> Self {
+        Self { transactor }
+    }
+
+    /// Collect a bundle from the hardware, writing it to `path`. The hardware deletes what's been
+    /// read, so the bundle is rewritten as each coredump and telemetry fragment is read: if
+    /// collection fails part way, `path` holds everything drained so far, marked incomplete.
+    pub fn collect_to(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<DiagnosticsBundle, DiagnosticsError> {
+        let path = path.as_ref();
+        let device_info = self.transactor.perform(GetDeviceInfo::new())?;
+        let device = DeviceIdentifiers {
+            serial: device_info.serial,
+            version: device_info.version,
+            sw_type: device_info.sw_type,
+            hw_revision: device_info.hw_revision,
+        };
+        let collected_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let mut bundle = DiagnosticsBundle::new(device, collected_at, vec![], vec![]);
+        bundle.incomplete = true;
+        let result = self
+            .coredumps(&mut bundle, path)
+            .and_then(|_| self.bitlogs(&mut bundle, path));
+
+        bundle.translate();
+        bundle.incomplete = result.is_err();
+        if result.is_ok() || !bundle.is_empty() {
+            bundle.write_to(path)?;
+        }
+        result.map(|_| bundle)
+    }
+
+    // Read every coredump off the hardware into the bundle, writing it out after each one. The
+    // hardware deletes each coredump once its last fragment has been read.
+    fn coredumps(
+        &self,
+        bundle: &mut DiagnosticsBundle,
+        path: &Path,
+    ) -> Result<(), DiagnosticsError> {
+        if self.transactor.perform(GetCoredumpCount::new())? == 0 {
+            return Ok(());
+        }
+
+        let mut coredump = vec![];
+        loop {
+            let fragment = self
+                .transactor
+                .perform(GetCoredumpFragment::new(coredump.len() as u32))?;
+            if !fragment.complete && fragment.data.is_empty() {
+                return Err(DiagnosticsError::EmptyFragment);
+            }
+
+            // The offset the hardware returns is where the next fragment starts.
+            coredump.extend(fragment.data);
+            if fragment.offset as usize != coredump.len() {
+                return Err(DiagnosticsError::FragmentOffset {
+                    expected: coredump.len(),
+                    actual: fragment.offset as usize,
+                });
+            }
+
+            if fragment.complete {
+                bundle.coredumps.push(std::mem::take(&mut coredump));
+                bundle.write_to(path)?;
+                if fragment.coredumps_remaining <= 0 {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Read the hardware's buffered bitlog telemetry into the bundle, writing it out after each
+    // fragment. The hardware discards each fragment once read.
+    fn bitlogs(&self, bundle: &mut DiagnosticsBundle, path: &Path) -> Result<(), DiagnosticsError> {
+        loop {
+            let events = self.transactor.perform(GetEvents::new())?;
+            let empty = events.fragment.is_empty();
+            if !empty {
+                bundle.bitlogs.extend(events.fragment);
+                bundle.write_to(path)?;
+            }
+            if empty || events.remaining_size <= 0 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        hex::decode(String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+mod hex_bytes_list {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(list: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(list.iter().map(hex::encode))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|s| hex::decode(s).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
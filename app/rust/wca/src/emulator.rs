@@ -0,0 +1,1278 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use bitcoin::{
+    bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey},
+    hashes::{sha256, Hash},
+    secp256k1::{All, Message as EcdsaMessage, Secp256k1},
+    Network,
+};
+use pcsc::MAX_BUFFER_SIZE_EXTENDED;
+use prost::Message;
+use rand_core::{OsRng, RngCore};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    hmac,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+
+use crate::{
+    fwpb::{
+        self,
+        cert_get_rsp::CertGetRspStatus,
+        coredump_get_cmd::CoredumpGetType,
+        coredump_get_rsp::CoredumpGetRspStatus,
+        derive_and_sign_rsp::DeriveAndSignRspStatus,
+        derive_rsp::DeriveRspStatus,
+        device_info_rsp::DeviceInfoRspStatus,
+        events_get_rsp::EventsGetRspStatus,
+        feature_flags_get_rsp::FeatureFlagsGetRspStatus,
+        feature_flags_set_rsp::FeatureFlagsSetRspStatus,
+        fwup_finish_rsp::FwupFinishRspStatus,
+        fwup_start_rsp::FwupStartRspStatus,
+        fwup_transfer_rsp::FwupTransferRspStatus,
+        get_fingerprint_enrollment_status_rsp::{
+            FingerprintEnrollmentStatus, GetFingerprintEnrollmentStatusRspStatus,
+        },
+        get_unlock_method_rsp::UnlockMethod,
+        meta_rsp::MetaRspStatus,
+        query_authentication_rsp::QueryAuthenticationRspStatus,
+        seal_csek_rsp::SealCsekRspStatus,
+        start_fingerprint_enrollment_rsp::StartFingerprintEnrollmentRspStatus,
+        telemetry_id_get_rsp::TelemetryIdGetRspStatus,
+        unseal_csek_rsp::UnsealCsekRspStatus,
+        wallet_cmd::Msg as Cmd,
+        wallet_rsp::Msg as Rsp,
+        wipe_state_rsp::WipeStateRspStatus,
+        Status,
+    },
+    pcsc::Transactor,
+    wca::{
+        ApduSession, WCA_CLA, WCA_INS_GET_RESPONSE, WCA_INS_PROTO, WCA_INS_PROTO_CONTINUATION,
+        WCA_INS_VERSION,
+    },
+};
+
+// An in-process stand-in for the hardware wallet, reachable through the same
+// `Transactor` interface as a real card reader. APDUs are unwrapped exactly as
+// the firmware's WCA layer does (see firmware/lib/wca), the `fwpb` protobufs are
+// decoded and answered from emulated device state, and responses are chunked
+// back with `61xx` status words when they don't fit in a single R-APDU.
+//
+// The emulator is meant for tests and for `bk --fake`; it holds its seed in
+// plain memory and makes no attempt to resist anything.
+
+const WCA_VERSION: u16 = 1;
+
+const SW_OK: [u8; 2] = [0x90, 0x00];
+const SW_BYTES_REMAINING: u8 = 0x61;
+const SW_UNSUPPORTED_INS: [u8; 2] = [0x68, 0x00];
+const SW_GENERIC_FAILURE: [u8; 2] = [0x6f, 0x00];
+const SW_SIZE: usize = 2;
+
+// Mirrors the nanopb size limits in wallet.proto.
+const MAX_FINGERPRINTS: u32 = 3;
+const MAX_COREDUMPS: usize = 6;
+const COREDUMP_FRAGMENT_SIZE: usize = 452;
+const EVENT_FRAGMENT_SIZE: usize = 451;
+const FWUP_CHUNK_SIZE: u32 = 452;
+const FWUP_SIGNATURE_SIZE: usize = 64;
+const APP_SLOT_SIZE: usize = 632 * 1024;
+const FEATURE_FLAG_COUNT: usize = 8;
+const EVENTS_VERSION: u32 = 1;
+
+// Successful samples needed before an enrollment completes. Each status poll
+// counts as one touch of the sensor.
+const ENROLLMENT_SAMPLES: u32 = 3;
+
+const HW_REVISION: &str = "w1a-dvt";
+const SERIAL: &str = "EMULATED00000000";
+const ED25519_KEY_LABEL_PREFIX: &[u8] = b"emulator-ed25519/";
+const CSEK_KEY_LABEL: &[u8] = b"emulator-csek";
+
+/// A software hardware wallet that answers WCA APDUs from in-memory state.
+pub struct EmulatedTransactor {
+    device: Mutex<EmulatedDevice>,
+    session: Mutex<ApduSession>,
+}
+
+impl EmulatedTransactor {
+    /// A factory-fresh device with a random seed and no enrolled fingerprints.
+    pub fn new() -> Self {
+        Self::from_seed(random_seed())
+    }
+
+    /// A factory-fresh device whose keys are derived from `seed`.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self {
+            device: Mutex::new(EmulatedDevice::new(seed)),
+            session: Mutex::new(ApduSession::default()),
+        }
+    }
+
+    /// A device derived from `seed` that has fingerprint 0 enrolled and is
+    /// already unlocked, as if the user had just touched the sensor.
+    pub fn onboarded(seed: [u8; 32]) -> Self {
+        let emulator = Self::from_seed(seed);
+        {
+            let mut device = emulator.device();
+            device.fingerprints.insert(0, Template::default());
+            device.unlock(0);
+        }
+        emulator
+    }
+
+    /// Simulate a touch of the sensor with the finger enrolled at `index`.
+    /// Returns whether the finger matched and the device is now unlocked.
+    pub fn unlock(&self, index: u32) -> bool {
+        self.device().unlock(index)
+    }
+
+    pub fn lock(&self) {
+        self.device().authenticated = false;
+    }
+
+    /// Simulate the hardware resetting, e.g. on losing power: it locks, and
+    /// any firmware update in progress is lost.
+    pub fn power_cycle(&self) {
+        let mut device = self.device();
+        device.authenticated = false;
+        device.fwup = None;
+        device.transport = Transport::default();
+    }
+
+    /// Store a coredump, evicting the oldest once the device is full.
+    pub fn push_coredump(&self, coredump: Vec<u8>) {
+        let mut device = self.device();
+        if device.coredumps.len() >= MAX_COREDUMPS {
+            device.coredumps.remove(0);
+        }
+        device.coredumps.push(coredump);
+    }
+
+    /// Append serialized telemetry events to the device's event buffer.
+    pub fn push_events(&self, events: &[u8]) {
+        self.device().events.extend_from_slice(events);
+    }
+
+    /// Limit the size of each R-APDU, including status words, so that larger
+    /// responses must be fetched with GET RESPONSE.
+    pub fn set_max_response_size(&self, size: usize) {
+        assert!(size > SW_SIZE, "response size must leave room for data");
+        self.device().max_response_size = size;
+    }
+
+    /// Limit the size of each C-APDU, as a phone's NFC transceiver would, so
+    /// that larger commands are split across PROTO_CONT commands. Returns the
+    /// negotiated size.
+    pub fn negotiate_max_apdu_size(&self, transceive_length: u32) -> u32 {
+        let session = ApduSession::new(transceive_length);
+        *self.session.lock().unwrap() = session;
+        session.max_apdu_size()
+    }
+
+    fn device(&self) -> std::sync::MutexGuard<'_, EmulatedDevice> {
+        self.device.lock().unwrap()
+    }
+}
+
+impl Default for EmulatedTransactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transactor for EmulatedTransactor {
+    fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        Ok(self.device().transmit(buffer))
+    }
+
+    fn reset(&mut self) -> Result<(), pcsc::Error> {
+        // Lifting the phone off the device drops anything in flight, but
+        // leaves the unlock state alone.
+        self.device().transport = Transport::default();
+        Ok(())
+    }
+
+    fn session(&self) -> ApduSession {
+        *self.session.lock().unwrap()
+    }
+}
+
+#[derive(Default)]
+struct Transport {
+    command: Vec<u8>,
+    command_size: usize,
+    response: Vec<u8>,
+    response_offset: usize,
+}
+
+#[derive(Default)]
+struct Template {
+    label: String,
+    pass_count: u32,
+}
+
+struct Enrollment {
+    index: u32,
+    label: String,
+    samples: u32,
+    complete: bool,
+}
+
+struct FirmwareImage {
+    version: fwpb::Semver,
+    hash: Vec<u8>,
+    timestamp: u64,
+}
+
+impl FirmwareImage {
+    fn new(version: fwpb::Semver, image: &[u8]) -> Self {
+        Self {
+            version,
+            hash: sha256::Hash::hash(image).to_byte_array().to_vec(),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    fn metadata(&self) -> fwpb::FirmwareMetadata {
+        fwpb::FirmwareMetadata {
+            valid: true,
+            git_id: format!(
+                "emulator-{}.{}.{}",
+                self.version.major, self.version.minor, self.version.patch
+            ),
+            git_branch: "main".into(),
+            version: Some(self.version.clone()),
+            build: "dev".into(),
+            timestamp: self.timestamp,
+            hash: self.hash.clone(),
+            hw_revision: HW_REVISION.into(),
+        }
+    }
+}
+
+enum AuthPolicy {
+    Never,
+    AfterOnboarding,
+    Always,
+}
+
+struct EmulatedDevice {
+    secp: Secp256k1<All>,
+    seed: [u8; 32],
+    authenticated: bool,
+    fingerprints: BTreeMap<u32, Template>,
+    unlocked_with: u32,
+    fail_count: u32,
+    enrollment: Option<Enrollment>,
+    pending_signature: Option<fwpb::DeriveKeyDescriptorAndSignCmd>,
+    feature_flags: [bool; FEATURE_FLAG_COUNT],
+    bootloader: FirmwareImage,
+    slot_a: FirmwareImage,
+    slot_b: FirmwareImage,
+    active_slot: fwpb::FirmwareSlot,
+    fwup: Option<Vec<u8>>,
+    coredumps: Vec<Vec<u8>>,
+    events: Vec<u8>,
+    transport: Transport,
+    max_response_size: usize,
+}
+
+impl EmulatedDevice {
+    fn new(seed: [u8; 32]) -> Self {
+        let mut feature_flags = [true; FEATURE_FLAG_COUNT];
+        feature_flags[fwpb::FeatureFlag::Unlock as usize] = false;
+
+        let semver = |patch| fwpb::Semver {
+            major: 1,
+            minor: 0,
+            patch,
+        };
+
+        Self {
+            secp: Secp256k1::new(),
+            seed,
+            authenticated: false,
+            fingerprints: BTreeMap::new(),
+            unlocked_with: 0,
+            fail_count: 0,
+            enrollment: None,
+            pending_signature: None,
+            feature_flags,
+            bootloader: FirmwareImage::new(semver(0), b"bl"),
+            slot_a: FirmwareImage::new(semver(1), b"app-a"),
+            slot_b: FirmwareImage::new(semver(0), b"app-b"),
+            active_slot: fwpb::FirmwareSlot::SlotA,
+            fwup: None,
+            coredumps: vec![],
+            events: vec![],
+            transport: Transport::default(),
+            max_response_size: MAX_BUFFER_SIZE_EXTENDED,
+        }
+    }
+
+    fn unlock(&mut self, index: u32) -> bool {
+        match self.fingerprints.get_mut(&index) {
+            Some(template) => {
+                template.pass_count += 1;
+                self.unlocked_with = index;
+                self.authenticated = true;
+                true
+            }
+            None => {
+                self.fail_count += 1;
+                false
+            }
+        }
+    }
+
+    fn is_onboarded(&self) -> bool {
+        !self.fingerprints.is_empty()
+    }
+
+    fn active_image(&self) -> &FirmwareImage {
+        match self.active_slot {
+            fwpb::FirmwareSlot::SlotB => &self.slot_b,
+            _ => &self.slot_a,
+        }
+    }
+
+    fn sw_type(&self) -> &'static str {
+        match self.active_slot {
+            fwpb::FirmwareSlot::SlotB => "app-b-dev",
+            _ => "app-a-dev",
+        }
+    }
+
+    // Transport
+
+    fn transmit(&mut self, apdu: &[u8]) -> Vec<u8> {
+        if apdu.len() < 4 || apdu[0] != WCA_CLA {
+            return SW_UNSUPPORTED_INS.to_vec();
+        }
+
+        match apdu[1] {
+            WCA_INS_VERSION => [WCA_VERSION.to_be_bytes(), SW_OK].concat(),
+            WCA_INS_PROTO => self.proto(apdu),
+            WCA_INS_PROTO_CONTINUATION => self.proto_continuation(apdu),
+            WCA_INS_GET_RESPONSE => self.drain_response(),
+            _ => SW_UNSUPPORTED_INS.to_vec(),
+        }
+    }
+
+    fn proto(&mut self, apdu: &[u8]) -> Vec<u8> {
+        // P1P2 carries the size of the whole protobuf; the rest follows in
+        // continuation APDUs.
+        let command_size = u16::from_be_bytes([apdu[2], apdu[3]]) as usize;
+        let Some(data) = command_data(apdu) else {
+            return SW_GENERIC_FAILURE.to_vec();
+        };
+        if data.len() > command_size {
+            return SW_GENERIC_FAILURE.to_vec();
+        }
+
+        self.transport.command = data.to_vec();
+        self.transport.command_size = command_size;
+        self.exchange_if_complete()
+    }
+
+    fn proto_continuation(&mut self, apdu: &[u8]) -> Vec<u8> {
+        let Some(data) = command_data(apdu) else {
+            return SW_GENERIC_FAILURE.to_vec();
+        };
+        let remaining = self
+            .transport
+            .command_size
+            .saturating_sub(self.transport.command.len());
+        if remaining == 0 {
+            return SW_GENERIC_FAILURE.to_vec();
+        }
+
+        let data = &data[..data.len().min(remaining)];
+        self.transport.command.extend_from_slice(data);
+        self.exchange_if_complete()
+    }
+
+    fn exchange_if_complete(&mut self) -> Vec<u8> {
+        if self.transport.command.len() < self.transport.command_size {
+            return SW_OK.to_vec();
+        }
+
+        let command = std::mem::take(&mut self.transport.command);
+        self.transport.command_size = 0;
+        match fwpb::WalletCmd::decode(command.as_slice()) {
+            Ok(command) => {
+                self.transport.response = self.handle(command).encode_to_vec();
+                self.transport.response_offset = 0;
+                self.drain_response()
+            }
+            Err(_) => SW_GENERIC_FAILURE.to_vec(),
+        }
+    }
+
+    fn drain_response(&mut self) -> Vec<u8> {
+        let transport = &mut self.transport;
+        let pending = &transport.response[transport.response_offset..];
+        let written = pending.len().min(self.max_response_size - SW_SIZE);
+
+        let mut rsp = pending[..written].to_vec();
+        transport.response_offset += written;
+
+        let remaining = transport.response.len() - transport.response_offset;
+        if remaining > 0 {
+            rsp.extend([SW_BYTES_REMAINING, remaining.min(u8::MAX as usize) as u8]);
+        } else {
+            transport.response.clear();
+            transport.response_offset = 0;
+            rsp.extend(SW_OK);
+        }
+        rsp
+    }
+
+    // Dispatch
+
+    fn handle(&mut self, command: fwpb::WalletCmd) -> fwpb::WalletRsp {
+        let Some(msg) = command.msg else {
+            return status(Status::UnknownMessage);
+        };
+
+        let requires_authentication = match auth_policy(&msg) {
+            AuthPolicy::Never => false,
+            AuthPolicy::AfterOnboarding => self.is_onboarded(),
+            AuthPolicy::Always => true,
+        };
+        if requires_authentication && !self.authenticated {
+            return status(Status::Unauthenticated);
+        }
+
+        self.dispatch(msg)
+    }
+
+    #[allow(deprecated)]
+    fn dispatch(&mut self, msg: Cmd) -> fwpb::WalletRsp {
+        match msg {
+            Cmd::MetaCmd(_) => self.meta(),
+            Cmd::DeviceIdCmd(_) => ok(Rsp::DeviceIdRsp(fwpb::DeviceIdRsp {
+                mlb_serial: SERIAL.into(),
+                mlb_serial_valid: true,
+                assy_serial: SERIAL.into(),
+                assy_serial_valid: true,
+            })),
+            Cmd::DeviceInfoCmd(_) => self.device_info(),
+            Cmd::TelemetryIdGetCmd(_) => ok(Rsp::TelemetryIdGetRsp(fwpb::TelemetryIdGetRsp {
+                rsp_status: TelemetryIdGetRspStatus::Success as i32,
+                serial: SERIAL.into(),
+                version: Some(self.active_image().version.clone()),
+                sw_type: self.sw_type().into(),
+                hw_revision: HW_REVISION.into(),
+            })),
+            Cmd::FeatureFlagsGetCmd(_) => self.feature_flags_get(),
+            Cmd::FeatureFlagsSetCmd(cmd) => self.feature_flags_set(cmd),
+            Cmd::CertGetCmd(_) => ok(Rsp::CertGetRsp(fwpb::CertGetRsp {
+                rsp_status: CertGetRspStatus::Unimplemented as i32,
+                cert: vec![],
+            })),
+            Cmd::HardwareAttestationCmd(_) => status(Status::FeatureNotSupported),
+
+            Cmd::QueryAuthenticationCmd(_) => {
+                ok(Rsp::QueryAuthenticationRsp(fwpb::QueryAuthenticationRsp {
+                    rsp_status: match self.authenticated {
+                        true => QueryAuthenticationRspStatus::Authenticated,
+                        false => QueryAuthenticationRspStatus::Unauthenticated,
+                    } as i32,
+                }))
+            }
+            Cmd::LockDeviceCmd(_) => {
+                self.authenticated = false;
+                ok(Rsp::LockDeviceRsp(fwpb::LockDeviceRsp {}))
+            }
+            Cmd::GetUnlockMethodCmd(_) => ok(Rsp::GetUnlockMethodRsp(fwpb::GetUnlockMethodRsp {
+                method: UnlockMethod::Biometrics as i32,
+                fingerprint_index: self.unlocked_with,
+            })),
+            Cmd::WipeStateCmd(_) => self.wipe_state(),
+
+            Cmd::StartFingerprintEnrollmentCmd(cmd) => self.start_fingerprint_enrollment(cmd),
+            Cmd::GetFingerprintEnrollmentStatusCmd(cmd) => {
+                self.get_fingerprint_enrollment_status(cmd)
+            }
+            Cmd::CancelFingerprintEnrollmentCmd(_) => self.cancel_fingerprint_enrollment(),
+            Cmd::GetEnrolledFingerprintsCmd(_) => ok(Rsp::GetEnrolledFingerprintsRsp(
+                fwpb::GetEnrolledFingerprintsRsp {
+                    max_count: MAX_FINGERPRINTS,
+                    handles: self
+                        .fingerprints
+                        .iter()
+                        .map(|(index, template)| fwpb::FingerprintHandle {
+                            index: *index,
+                            label: template.label.clone(),
+                        })
+                        .collect(),
+                },
+            )),
+            Cmd::SetFingerprintLabelCmd(cmd) => self.set_fingerprint_label(cmd),
+            Cmd::DeleteFingerprintCmd(cmd) => self.delete_fingerprint(cmd),
+
+            Cmd::DeriveKeyDescriptorCmd(cmd) => self.derive_key_descriptor(cmd),
+            Cmd::DeriveKeyDescriptorAndSignCmd(cmd) => self.derive_key_descriptor_and_sign(cmd),
+            Cmd::DerivePublicKeyCmd(cmd) => match self.ed25519_key(cmd.curve, &cmd.label) {
+                Ok(key) => ok(Rsp::DerivePublicKeyRsp(fwpb::DerivePublicKeyRsp {
+                    pubkey: key.public_key().as_ref().to_vec(),
+                })),
+                Err(s) => status(s),
+            },
+            Cmd::DerivePublicKeyAndSignCmd(cmd) => self.derive_public_key_and_sign(cmd),
+            Cmd::SealCsekCmd(cmd) => self.seal_csek(cmd),
+            Cmd::UnsealCsekCmd(cmd) => self.unseal_csek(cmd),
+
+            Cmd::FwupStartCmd(cmd) => self.fwup_start(cmd),
+            Cmd::FwupTransferCmd(cmd) => self.fwup_transfer(cmd),
+            Cmd::FwupFinishCmd(cmd) => self.fwup_finish(cmd),
+
+            Cmd::CoredumpGetCmd(cmd) => self.coredump_get(cmd),
+            Cmd::EventsGetCmd(_) => self.events_get(),
+
+            _ => status(Status::UnknownMessage),
+        }
+    }
+
+    // Device information
+
+    fn meta(&self) -> fwpb::WalletRsp {
+        ok(Rsp::MetaRsp(fwpb::MetaRsp {
+            rsp_status: MetaRspStatus::Success as i32,
+            meta_bl: Some(self.bootloader.metadata()),
+            meta_slot_a: Some(self.slot_a.metadata()),
+            meta_slot_b: Some(self.slot_b.metadata()),
+            active_slot: self.active_slot as i32,
+        }))
+    }
+
+    fn device_info(&self) -> fwpb::WalletRsp {
+        let version = self.active_image().version.clone();
+        ok(Rsp::DeviceInfoRsp(fwpb::DeviceInfoRsp {
+            rsp_status: DeviceInfoRspStatus::Success as i32,
+            version: Some(version.clone()),
+            serial: SERIAL.into(),
+            sw_type: self.sw_type().into(),
+            hw_revision: HW_REVISION.into(),
+            active_slot: self.active_slot as i32,
+            battery_charge: 85_000,
+            vcell: 4_100,
+            avg_current_ma: -2,
+            battery_cycles: 1,
+            secure_boot_config: fwpb::SecureBootConfig::Dev as i32,
+            bio_match_stats: Some(fwpb::BioMatchStats {
+                pass_counts: self
+                    .fingerprints
+                    .values()
+                    .map(|template| fwpb::TemplateMatchStats {
+                        pass_count: template.pass_count,
+                        firmware_version: Some(version.clone()),
+                    })
+                    .collect(),
+                fail_count: self.fail_count,
+            }),
+        }))
+    }
+
+    fn feature_flags_get(&self) -> fwpb::WalletRsp {
+        ok(Rsp::FeatureFlagsGetRsp(fwpb::FeatureFlagsGetRsp {
+            rsp_status: FeatureFlagsGetRspStatus::Success as i32,
+            flags: self
+                .feature_flags
+                .iter()
+                .enumerate()
+                .map(|(flag, enabled)| fwpb::FeatureFlagCfg {
+                    flag: flag as i32,
+                    enabled: *enabled,
+                })
+                .collect(),
+        }))
+    }
+
+    fn feature_flags_set(&mut self, cmd: fwpb::FeatureFlagsSetCmd) -> fwpb::WalletRsp {
+        let mut rsp_status = FeatureFlagsSetRspStatus::Success;
+        for cfg in cmd.flags {
+            match usize::try_from(cfg.flag)
+                .ok()
+                .and_then(|flag| self.feature_flags.get_mut(flag))
+            {
+                Some(enabled) => *enabled = cfg.enabled,
+                None => rsp_status = FeatureFlagsSetRspStatus::Error,
+            }
+        }
+
+        ok(Rsp::FeatureFlagsSetRsp(fwpb::FeatureFlagsSetRsp {
+            rsp_status: rsp_status as i32,
+        }))
+    }
+
+    fn wipe_state(&mut self) -> fwpb::WalletRsp {
+        self.seed = random_seed();
+        self.authenticated = false;
+        self.fingerprints.clear();
+        self.unlocked_with = 0;
+        self.enrollment = None;
+        self.pending_signature = None;
+
+        ok(Rsp::WipeStateRsp(fwpb::WipeStateRsp {
+            rsp_status: WipeStateRspStatus::Success as i32,
+        }))
+    }
+
+    // Fingerprints
+
+    fn start_fingerprint_enrollment(
+        &mut self,
+        cmd: fwpb::StartFingerprintEnrollmentCmd,
+    ) -> fwpb::WalletRsp {
+        let rsp_status = match cmd.handle {
+            Some(handle) if handle.index < MAX_FINGERPRINTS => {
+                self.enrollment = Some(Enrollment {
+                    index: handle.index,
+                    label: handle.label,
+                    samples: 0,
+                    complete: false,
+                });
+                StartFingerprintEnrollmentRspStatus::Success
+            }
+            _ => StartFingerprintEnrollmentRspStatus::Error,
+        };
+
+        ok(Rsp::StartFingerprintEnrollmentRsp(
+            fwpb::StartFingerprintEnrollmentRsp {
+                rsp_status: rsp_status as i32,
+            },
+        ))
+    }
+
+    fn get_fingerprint_enrollment_status(
+        &mut self,
+        cmd: fwpb::GetFingerprintEnrollmentStatusCmd,
+    ) -> fwpb::WalletRsp {
+        let mut completed = None;
+        if let Some(enrollment) = self.enrollment.as_mut().filter(|e| !e.complete) {
+            enrollment.samples += 1;
+            if enrollment.samples >= ENROLLMENT_SAMPLES {
+                enrollment.complete = true;
+                completed = Some((enrollment.index, enrollment.label.clone()));
+            }
+        }
+        if let Some((index, label)) = completed {
+            self.fingerprints.insert(
+                index,
+                Template {
+                    label,
+                    pass_count: 0,
+                },
+            );
+            self.unlocked_with = index;
+            self.authenticated = true;
+        }
+
+        let in_progress = self.enrollment.as_ref().map_or(false, |e| !e.complete);
+        let complete = match cmd.app_knows_about_this_field {
+            true => self.enrollment.as_ref().map_or(false, |e| e.complete),
+            // Older apps only learn whether any finger is enrolled at all.
+            false => self.is_onboarded(),
+        };
+        let fingerprint_status = if complete {
+            FingerprintEnrollmentStatus::Complete
+        } else if in_progress {
+            FingerprintEnrollmentStatus::Incomplete
+        } else {
+            FingerprintEnrollmentStatus::NotInProgress
+        };
+
+        ok(Rsp::GetFingerprintEnrollmentStatusRsp(
+            fwpb::GetFingerprintEnrollmentStatusRsp {
+                rsp_status: GetFingerprintEnrollmentStatusRspStatus::Success as i32,
+                fingerprint_status: fingerprint_status as i32,
+                pass_count: self.enrollment.as_ref().map_or(0, |e| e.samples),
+                fail_count: 0,
+                diagnostics: None,
+            },
+        ))
+    }
+
+    fn cancel_fingerprint_enrollment(&mut self) -> fwpb::WalletRsp {
+        match self.enrollment.take() {
+            Some(enrollment) if !enrollment.complete => ok(Rsp::CancelFingerprintEnrollmentRsp(
+                fwpb::CancelFingerprintEnrollmentRsp {},
+            )),
+            _ => status(Status::InvalidState),
+        }
+    }
+
+    fn set_fingerprint_label(&mut self, cmd: fwpb::SetFingerprintLabelCmd) -> fwpb::WalletRsp {
+        let Some(handle) = cmd.handle else {
+            return status(Status::InvalidArgument);
+        };
+        match self.fingerprints.get_mut(&handle.index) {
+            Some(template) => {
+                template.label = handle.label;
+                ok(Rsp::SetFingerprintLabelRsp(fwpb::SetFingerprintLabelRsp {}))
+            }
+            None => status(Status::FileNotFound),
+        }
+    }
+
+    fn delete_fingerprint(&mut self, cmd: fwpb::DeleteFingerprintCmd) -> fwpb::WalletRsp {
+        if !self.fingerprints.contains_key(&cmd.index) {
+            return status(Status::FileNotFound);
+        }
+        // The device refuses to delete the last finger; it would brick itself.
+        if self.fingerprints.len() == 1 {
+            return status(Status::InvalidState);
+        }
+
+        self.fingerprints.remove(&cmd.index);
+        ok(Rsp::DeleteFingerprintRsp(fwpb::DeleteFingerprintRsp {}))
+    }
+
+    // Keys
+
+    fn derive(
+        &self,
+        network: Network,
+        path: &fwpb::DerivationPath,
+    ) -> Result<(ExtendedPrivKey, ExtendedPrivKey), bitcoin::bip32::Error> {
+        let master = ExtendedPrivKey::new_master(network, &self.seed)?;
+        let path: DerivationPath = path
+            .child
+            .iter()
+            .copied()
+            .map(ChildNumber::from)
+            .collect::<Vec<_>>()
+            .into();
+        let key = master.derive_priv(&self.secp, &path)?;
+        Ok((master, key))
+    }
+
+    fn derive_key_descriptor(&self, cmd: fwpb::DeriveKeyDescriptorCmd) -> fwpb::WalletRsp {
+        let network = fwpb::BtcNetwork::from_i32(cmd.network)
+            .map(Network::from)
+            .unwrap_or(Network::Bitcoin);
+        let origin_path = cmd.derivation_path.unwrap_or_default();
+
+        let rsp = match self.derive(network, &origin_path) {
+            Ok((master, key)) => fwpb::DeriveRsp {
+                status: DeriveRspStatus::Success as i32,
+                descriptor: Some(fwpb::KeyDescriptor {
+                    origin_fingerprint: master.fingerprint(&self.secp).as_bytes().to_vec(),
+                    origin_path: Some(origin_path),
+                    bare_bip32_key: ExtendedPubKey::from_priv(&self.secp, &key)
+                        .encode()
+                        .to_vec(),
+                    xpub_path: None,
+                    wildcard: fwpb::Wildcard::None as i32,
+                }),
+            },
+            Err(_) => fwpb::DeriveRsp {
+                status: DeriveRspStatus::DerivationFailed as i32,
+                descriptor: None,
+            },
+        };
+        ok(Rsp::DeriveRsp(rsp))
+    }
+
+    fn derive_key_descriptor_and_sign(
+        &mut self,
+        cmd: fwpb::DeriveKeyDescriptorAndSignCmd,
+    ) -> fwpb::WalletRsp {
+        if !cmd.async_sign {
+            let rsp = match self.sign_hash(&cmd) {
+                Ok(signature) => fwpb::DeriveAndSignRsp {
+                    status: DeriveAndSignRspStatus::Success as i32,
+                    signature,
+                },
+                Err(rsp_status) => fwpb::DeriveAndSignRsp {
+                    status: rsp_status as i32,
+                    signature: vec![],
+                },
+            };
+            return ok(Rsp::DeriveAndSignRsp(rsp));
+        }
+
+        // Async signing answers IN_PROGRESS to the first request and hands out
+        // the signature when the identical command is repeated.
+        if self.pending_signature.as_ref() != Some(&cmd) {
+            self.pending_signature = Some(cmd);
+            return fwpb::WalletRsp {
+                status: Status::InProgress as i32,
+                ..ok(Rsp::DeriveAndSignRsp(fwpb::DeriveAndSignRsp::default()))
+            };
+        }
+
+        self.pending_signature = None;
+        match self.sign_hash(&cmd) {
+            Ok(signature) => ok(Rsp::DeriveAndSignRsp(fwpb::DeriveAndSignRsp {
+                status: DeriveAndSignRspStatus::Unspecified as i32,
+                signature,
+            })),
+            Err(DeriveAndSignRspStatus::DerivationFailed) => status(Status::KeyDerivationFailed),
+            Err(_) => status(Status::SigningFailed),
+        }
+    }
+
+    fn sign_hash(
+        &self,
+        cmd: &fwpb::DeriveKeyDescriptorAndSignCmd,
+    ) -> Result<Vec<u8>, DeriveAndSignRspStatus> {
+        let path = cmd.derivation_path.clone().unwrap_or_default();
+        let (_, key) = self
+            .derive(Network::Bitcoin, &path)
+            .map_err(|_| DeriveAndSignRspStatus::DerivationFailed)?;
+        let message =
+            EcdsaMessage::from_slice(&cmd.hash).map_err(|_| DeriveAndSignRspStatus::Error)?;
+
+        Ok(self
+            .secp
+            .sign_ecdsa(&message, &key.private_key)
+            .serialize_compact()
+            .to_vec())
+    }
+
+    fn ed25519_key(&self, curve: i32, label: &[u8]) -> Result<Ed25519KeyPair, Status> {
+        match fwpb::Curve::from_i32(curve) {
+            Some(fwpb::Curve::Ed25519) => {
+                let seed = self.seed_hmac(&[ED25519_KEY_LABEL_PREFIX, label].concat());
+                Ed25519KeyPair::from_seed_unchecked(seed.as_ref()).map_err(|_| Status::Error)
+            }
+            _ => Err(Status::FeatureNotSupported),
+        }
+    }
+
+    fn derive_public_key_and_sign(&self, cmd: fwpb::DerivePublicKeyAndSignCmd) -> fwpb::WalletRsp {
+        if cmd.hash.len() != 32 {
+            return status(Status::InvalidArgument);
+        }
+        match self.ed25519_key(cmd.curve, &cmd.label) {
+            Ok(key) => ok(Rsp::DerivePublicKeyAndSignRsp(
+                fwpb::DerivePublicKeyAndSignRsp {
+                    pubkey: key.public_key().as_ref().to_vec(),
+                    signature: key.sign(&cmd.hash).as_ref().to_vec(),
+                },
+            )),
+            Err(s) => status(s),
+        }
+    }
+
+    fn seed_hmac(&self, label: &[u8]) -> hmac::Tag {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &self.seed), label)
+    }
+
+    fn csek_key(&self) -> LessSafeKey {
+        let key = self.seed_hmac(CSEK_KEY_LABEL);
+        LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, key.as_ref())
+                .expect("HMAC-SHA256 output is a valid AES-256 key"),
+        )
+    }
+
+    fn seal_csek(&self, cmd: fwpb::SealCsekCmd) -> fwpb::WalletRsp {
+        let mut data = cmd.unsealed_csek;
+        if data.len() != 32 {
+            return ok(Rsp::SealCsekRsp(fwpb::SealCsekRsp {
+                rsp_status: SealCsekRspStatus::Error as i32,
+                sealed_csek: None,
+            }));
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let rsp = match self.csek_key().seal_in_place_separate_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut data,
+        ) {
+            Ok(tag) => fwpb::SealCsekRsp {
+                rsp_status: SealCsekRspStatus::Success as i32,
+                sealed_csek: Some(fwpb::SealedData {
+                    data,
+                    nonce: nonce.to_vec(),
+                    tag: tag.as_ref().to_vec(),
+                }),
+            },
+            Err(_) => fwpb::SealCsekRsp {
+                rsp_status: SealCsekRspStatus::SealError as i32,
+                sealed_csek: None,
+            },
+        };
+        ok(Rsp::SealCsekRsp(rsp))
+    }
+
+    fn unseal_csek(&self, cmd: fwpb::UnsealCsekCmd) -> fwpb::WalletRsp {
+        let unsealed = cmd.sealed_csek.and_then(|sealed| {
+            let nonce: [u8; NONCE_LEN] = sealed.nonce.as_slice().try_into().ok()?;
+            let mut in_out = [sealed.data, sealed.tag].concat();
+            self.csek_key()
+                .open_in_place(
+                    Nonce::assume_unique_for_key(nonce),
+                    Aad::empty(),
+                    &mut in_out,
+                )
+                .map(|plaintext| plaintext.to_vec())
+                .ok()
+        });
+
+        let rsp = match unsealed {
+            Some(unsealed_csek) => fwpb::UnsealCsekRsp {
+                rsp_status: UnsealCsekRspStatus::Success as i32,
+                unsealed_csek,
+            },
+            None => fwpb::UnsealCsekRsp {
+                rsp_status: UnsealCsekRspStatus::UnsealError as i32,
+                unsealed_csek: vec![],
+            },
+        };
+        ok(Rsp::UnsealCsekRsp(rsp))
+    }
+
+    // Firmware update
+
+    fn fwup_start(&mut self, cmd: fwpb::FwupStartCmd) -> fwpb::WalletRsp {
+        // Delta updates need the patch applied on-device, which the emulator
+        // doesn't attempt.
+        let rsp_status = match fwpb::FwupMode::from_i32(cmd.mode) {
+            Some(fwpb::FwupMode::Normal) => {
+                self.fwup = Some(vec![0xff; APP_SLOT_SIZE]);
+                FwupStartRspStatus::Success
+            }
+            _ => FwupStartRspStatus::Error,
+        };
+
+        ok(Rsp::FwupStartRsp(fwpb::FwupStartRsp {
+            rsp_status: rsp_status as i32,
+        }))
+    }
+
+    fn fwup_transfer(&mut self, cmd: fwpb::FwupTransferCmd) -> fwpb::WalletRsp {
+        let start = cmd
+            .sequence_id
+            .checked_mul(FWUP_CHUNK_SIZE)
+            .and_then(|address| address.checked_add(cmd.offset))
+            .map(|address| address as usize);
+
+        let rsp_status = match (self.fwup.as_mut(), start) {
+            (Some(image), Some(start)) if start + cmd.fwup_data.len() <= image.len() => {
+                image[start..start + cmd.fwup_data.len()].copy_from_slice(&cmd.fwup_data);
+                FwupTransferRspStatus::Success
+            }
+            _ => FwupTransferRspStatus::Error,
+        };
+
+        ok(Rsp::FwupTransferRsp(fwpb::FwupTransferRsp {
+            rsp_status: rsp_status as i32,
+        }))
+    }
+
+    fn fwup_finish(&mut self, cmd: fwpb::FwupFinishCmd) -> fwpb::WalletRsp {
+        let rsp_status = match self.fwup.take() {
+            _ if cmd.bl_upgrade => FwupFinishRspStatus::Error,
+            Some(image) => self.verify_and_apply(image, &cmd),
+            None => FwupFinishRspStatus::Error,
+        };
+
+        ok(Rsp::FwupFinishRsp(fwpb::FwupFinishRsp {
+            rsp_status: rsp_status as i32,
+        }))
+    }
+
+    fn verify_and_apply(
+        &mut self,
+        image: Vec<u8>,
+        cmd: &fwpb::FwupFinishCmd,
+    ) -> FwupFinishRspStatus {
+        let signature_offset = cmd.signature_offset as usize;
+        if cmd.app_properties_offset as usize >= image.len()
+            || signature_offset + FWUP_SIGNATURE_SIZE > image.len()
+        {
+            return FwupFinishRspStatus::Error;
+        }
+        // There's no real signature check, but an image that never had its
+        // signature written is rejected the same way the device would.
+        let signature = &image[signature_offset..signature_offset + FWUP_SIGNATURE_SIZE];
+        if signature.iter().all(|b| *b == 0xff) {
+            return FwupFinishRspStatus::SignatureInvalid;
+        }
+
+        let current = self.active_image().version.clone();
+        let updated = FirmwareImage::new(
+            fwpb::Semver {
+                patch: current.patch + 1,
+                ..current
+            },
+            &image,
+        );
+        match self.active_slot {
+            fwpb::FirmwareSlot::SlotB => {
+                self.slot_a = updated;
+                self.active_slot = fwpb::FirmwareSlot::SlotA;
+            }
+            _ => {
+                self.slot_b = updated;
+                self.active_slot = fwpb::FirmwareSlot::SlotB;
+            }
+        }
+        FwupFinishRspStatus::Success
+    }
+
+    // Diagnostics
+
+    fn coredump_get(&mut self, cmd: fwpb::CoredumpGetCmd) -> fwpb::WalletRsp {
+        let error = fwpb::CoredumpGetRsp {
+            rsp_status: CoredumpGetRspStatus::Error as i32,
+            coredump_fragment: None,
+            coredump_count: self.coredumps.len() as u32,
+        };
+
+        let rsp = match CoredumpGetType::from_i32(cmd.r#type) {
+            Some(CoredumpGetType::Count) => fwpb::CoredumpGetRsp {
+                rsp_status: CoredumpGetRspStatus::Success as i32,
+                ..error
+            },
+            Some(CoredumpGetType::Coredump) => match self.coredumps.last() {
+                Some(coredump) => {
+                    let start = (cmd.offset as usize).min(coredump.len());
+                    let end = (start + COREDUMP_FRAGMENT_SIZE).min(coredump.len());
+                    let data = coredump[start..end].to_vec();
+                    let complete = end == coredump.len();
+                    if complete {
+                        self.coredumps.pop();
+                    }
+
+                    fwpb::CoredumpGetRsp {
+                        rsp_status: CoredumpGetRspStatus::Success as i32,
+                        coredump_fragment: Some(fwpb::CoredumpFragment {
+                            data,
+                            offset: end as i32,
+                            complete,
+                            coredumps_remaining: self.coredumps.len() as i32,
+                        }),
+                        coredump_count: self.coredumps.len() as u32,
+                    }
+                }
+                None => error,
+            },
+            _ => error,
+        };
+        ok(Rsp::CoredumpGetRsp(rsp))
+    }
+
+    fn events_get(&mut self) -> fwpb::WalletRsp {
+        let size = self.events.len().min(EVENT_FRAGMENT_SIZE);
+        let data: Vec<u8> = self.events.drain(..size).collect();
+
+        ok(Rsp::EventsGetRsp(fwpb::EventsGetRsp {
+            rsp_status: EventsGetRspStatus::Success as i32,
+            version: EVENTS_VERSION,
+            fragment: Some(fwpb::EventFragment {
+                data,
+                remaining_size: self.events.len() as i32,
+            }),
+        }))
+    }
+}
+
+#[allow(deprecated)]
+fn auth_policy(msg: &Cmd) -> AuthPolicy {
+    // Mirrors the `auth` annotations in the firmware's IPC definitions. Until
+    // a finger is enrolled anyone may manage the device; afterwards, only
+    // the owner can.
+    match msg {
+        Cmd::WipeStateCmd(_)
+        | Cmd::CoredumpGetCmd(_)
+        | Cmd::EventsGetCmd(_)
+        | Cmd::FeatureFlagsSetCmd(_)
+        | Cmd::FingerprintSettingsGetCmd(_)
+        | Cmd::StartFingerprintEnrollmentCmd(_)
+        | Cmd::ProvisionUnlockSecretCmd(_)
+        | Cmd::ConfigureUnlockLimitResponseCmd(_)
+        | Cmd::DeleteFingerprintCmd(_)
+        | Cmd::GetEnrolledFingerprintsCmd(_)
+        | Cmd::SetFingerprintLabelCmd(_)
+        | Cmd::FwupStartCmd(_)
+        | Cmd::FwupTransferCmd(_)
+        | Cmd::FwupFinishCmd(_) => AuthPolicy::AfterOnboarding,
+        Cmd::GetUnlockMethodCmd(_)
+        | Cmd::SealCsekCmd(_)
+        | Cmd::UnsealCsekCmd(_)
+        | Cmd::SignTxnCmd(_)
+        | Cmd::DeriveKeyDescriptorCmd(_)
+        | Cmd::DeriveKeyDescriptorAndSignCmd(_)
+        | Cmd::DerivePublicKeyCmd(_)
+        | Cmd::DerivePublicKeyAndSignCmd(_) => AuthPolicy::Always,
+        _ => AuthPolicy::Never,
+    }
+}
+
+// Returns the command data following Lc, or None if Lc overruns the APDU.
+fn command_data(apdu: &[u8]) -> Option<&[u8]> {
+    let (size, offset) = match apdu.get(4) {
+        None => return Some(&[]),
+        Some(0) => (
+            u16::from_be_bytes([*apdu.get(5)?, *apdu.get(6)?]) as usize,
+            7,
+        ),
+        Some(lc) => (*lc as usize, 5),
+    };
+    apdu.get(offset..offset + size)
+}
+
+fn ok(msg: Rsp) -> fwpb::WalletRsp {
+    fwpb::WalletRsp {
+        msg: Some(msg),
+        status: Status::Success as i32,
+        ..Default::default()
+    }
+}
+
+fn status(status: Status) -> fwpb::WalletRsp {
+    fwpb::WalletRsp {
+        status: status as i32,
+        ..Default::default()
+    }
+}
+
+fn random_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use crate::{
+        fwpb::{self, wallet_cmd::Msg as Cmd, wallet_rsp::Msg as Rsp, Status},
+        pcsc::Transactor,
+    };
+
+    use super::EmulatedTransactor;
+
+    fn exchange(emulator: &EmulatedTransactor, msg: Cmd) -> (fwpb::WalletRsp, usize) {
+        let command = fwpb::WalletCmd {
+            msg: Some(msg),
+            timestamp: 0,
+        };
+        let apdu: apdu::Command = crate::wca::WCA::Proto(command.encode_to_vec())
+            .try_into()
+            .unwrap();
+
+        let mut exchanges = 1;
+        let mut response =
+            apdu::Response::from(emulator.transmit(&apdu.serialize().unwrap()).unwrap());
+        let mut data = response.data.clone();
+        while response.sw1 == 0x61 {
+            let get_response: apdu::Command = crate::wca::WCA::GetResponse.try_into().unwrap();
+            response = apdu::Response::from(
+                emulator
+                    .transmit(&get_response.serialize().unwrap())
+                    .unwrap(),
+            );
+            data.extend(&response.data);
+            exchanges += 1;
+        }
+        assert!(response.is_ok());
+
+        (fwpb::WalletRsp::decode(data.as_slice()).unwrap(), exchanges)
+    }
+
+    #[test]
+    fn version() {
+        let emulator = EmulatedTransactor::new();
+        assert_eq!(
+            emulator.transmit(&[0x87, 0x74, 0x00, 0x00]).unwrap(),
+            vec![0x00, 0x01, 0x90, 0x00]
+        );
+        assert_eq!(
+            emulator.transmit(&[0x80, 0x74, 0x00, 0x00]).unwrap(),
+            vec![0x68, 0x00]
+        );
+    }
+
+    #[test]
+    fn chunks_large_responses() {
+        let emulator = EmulatedTransactor::onboarded([7; 32]);
+        let (full, exchanges) = exchange(&emulator, Cmd::MetaCmd(fwpb::MetaCmd {}));
+        assert_eq!(exchanges, 1);
+
+        emulator.set_max_response_size(32);
+        let (chunked, exchanges) = exchange(&emulator, Cmd::MetaCmd(fwpb::MetaCmd {}));
+        assert!(exchanges > 1);
+        assert_eq!(full, chunked);
+    }
+
+    #[test]
+    fn reassembles_continuations() {
+        let emulator = EmulatedTransactor::onboarded([7; 32]);
+        let command = fwpb::WalletCmd {
+            msg: Some(Cmd::QueryAuthenticationCmd(fwpb::QueryAuthenticationCmd {})),
+            timestamp: 1234567890,
+        }
+        .encode_to_vec();
+        let (first, rest) = command.split_at(3);
+        let size = (command.len() as u16).to_be_bytes();
+
+        let mut apdu = vec![0x87, 0x75, size[0], size[1], first.len() as u8];
+        apdu.extend(first);
+        assert_eq!(emulator.transmit(&apdu).unwrap(), vec![0x90, 0x00]);
+
+        let mut apdu = vec![0x87, 0x77, 0x00, rest.len() as u8, rest.len() as u8];
+        apdu.extend(rest);
+        let response = apdu::Response::from(emulator.transmit(&apdu).unwrap());
+        assert!(response.is_ok());
+        assert!(matches!(
+            fwpb::WalletRsp::decode(response.data.as_slice())
+                .unwrap()
+                .msg,
+            Some(Rsp::QueryAuthenticationRsp(_))
+        ));
+    }
+
+    #[test]
+    fn enforces_auth_policy() {
+        let emulator = EmulatedTransactor::from_seed([7; 32]);
+        let derive = || {
+            Cmd::DeriveKeyDescriptorCmd(fwpb::DeriveKeyDescriptorCmd {
+                network: fwpb::BtcNetwork::Signet as i32,
+                derivation_path: None,
+            })
+        };
+
+        // Keys are never available to a locked device.
+        let (rsp, _) = exchange(&emulator, derive());
+        assert_eq!(rsp.status, Status::Unauthenticated as i32);
+
+        // Management commands are open until a finger is enrolled...
+        let (rsp, _) = exchange(
+            &emulator,
+            Cmd::GetEnrolledFingerprintsCmd(fwpb::GetEnrolledFingerprintsCmd {}),
+        );
+        assert_eq!(rsp.status, Status::Success as i32);
+
+        // ...and closed again once the owner has locked the device.
+        let emulator = EmulatedTransactor::onboarded([7; 32]);
+        emulator.lock();
+        let (rsp, _) = exchange(
+            &emulator,
+            Cmd::GetEnrolledFingerprintsCmd(fwpb::GetEnrolledFingerprintsCmd {}),
+        );
+        assert_eq!(rsp.status, Status::Unauthenticated as i32);
+
+        assert!(!emulator.unlock(1));
+        assert!(emulator.unlock(0));
+        let (rsp, _) = exchange(&emulator, derive());
+        assert_eq!(rsp.status, Status::Success as i32);
+    }
+
+    #[test]
+    fn unknown_messages() {
+        let emulator = EmulatedTransactor::onboarded([7; 32]);
+        let (rsp, _) = exchange(&emulator, Cmd::ResetCmd(fwpb::ResetCmd {}));
+        assert_eq!(rsp.status, Status::UnknownMessage as i32);
+    }
+}
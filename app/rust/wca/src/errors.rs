@@ -52,6 +52,8 @@ pub enum CommandError {
     KeyGenerationFailed,
     #[error(transparent)]
     PSBTSigningError(#[from] crate::signing::Error),
+    #[error(transparent)]
+    TransactionSummaryError(#[from] crate::signing::summary::SummaryError),
     #[error("failed to get metadata")]
     MetadataError,
     #[error("failed to get battery charge")]
@@ -90,6 +92,8 @@ pub enum CommandError {
     Timeout,
     #[error("bad status: {0}")]
     BadStatus(i32),
+    #[error("unexpected status words: {0:#06x}")]
+    UnexpectedStatusWords(u16),
 }
 
 impl<T> From<PoisonError<T>> for CommandError {
@@ -98,6 +102,12 @@ impl<T> From<PoisonError<T>> for CommandError {
     }
 }
 
+impl From<apdu::Error> for CommandError {
+    fn from(err: apdu::Error) -> Self {
+        CommandError::EncodeError(err.into())
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum DecodeError {
     #[error("no key bundle")]
@@ -122,4 +132,6 @@ pub enum EncodeError {
     TruncatedProto,
     #[error("oversize encoded proto")]
     OversizeProto(#[from] TryFromIntError),
+    #[error(transparent)]
+    Apdu(#[from] apdu::Error),
 }
@@ -0,0 +1,537 @@
+use std::io::{Read, Seek};
+
+use picocert::{Certificate, KeyUsage};
+use ring::digest::{digest, SHA256};
+use serde::Deserialize;
+use thiserror::Error;
+use zip::{result::ZipError, ZipArchive};
+
+use crate::commands::{FirmwareMetadata, FirmwareSlot, FwupFinishRspStatus, FwupMode};
+#[cfg(feature = "pcsc")]
+use crate::{
+    commands::{FwupFinish, FwupStart, FwupTransfer, GetFirmwareMetadata},
+    errors::CommandError,
+    pcsc::{Performer, Transactor, TransactorError},
+};
+
+pub const MANIFEST_FILE: &str = "fwup-manifest.json";
+// Detached picocert signature over the manifest, by the leaf of the manifest's signing chain.
+pub const MANIFEST_SIGNATURE_FILE: &str = "fwup-manifest.sig";
+
+const MANIFEST_VERSION: &str = "0.0.1";
+
+// The production firmware signing root, embedded at build time from FWUP_SIGNING_ROOT (see
+// build.rs). Empty if the build wasn't given one.
+const PRODUCTION_SIGNING_ROOT: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/fwup-signing-root.cert"));
+
+/// The roots production firmware bundles are signed under. Empty if this build has none embedded.
+pub fn production_roots() -> Result<Vec<Certificate>, FirmwareUpdateError> {
+    match PRODUCTION_SIGNING_ROOT {
+        [] => Ok(vec![]),
+        root => Ok(vec![Certificate::from_bytes(root)?]),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FirmwareUpdateError {
+    #[error("could not read firmware bundle")]
+    Read(#[from] std::io::Error),
+    #[error("invalid firmware bundle")]
+    Bundle(#[from] ZipError),
+    #[error("invalid firmware manifest")]
+    Manifest(#[from] serde_json::Error),
+    #[error("unsupported manifest version: {0}")]
+    ManifestVersion(String),
+    #[error("invalid firmware update parameters")]
+    InvalidParameters,
+    #[error("firmware bundle is not signed")]
+    Unsigned,
+    #[error("firmware bundle is not signed by a trusted root")]
+    UntrustedRoot,
+    #[error("firmware bundle signature is invalid: {0:?}")]
+    Certificate(picocert::Error),
+    #[error("firmware bundle has no digest for {0}")]
+    MissingDigest(String),
+    #[error("firmware bundle digest mismatch for {0}")]
+    DigestMismatch(String),
+    #[error("delta update from {from} does not apply to firmware {current}")]
+    DeltaMismatch { from: String, current: String },
+    #[error("could not start firmware update")]
+    Start,
+    #[error("firmware update failed: {0:?}")]
+    Finish(FwupFinishRspStatus),
+    #[cfg(feature = "pcsc")]
+    #[error(transparent)]
+    Transactor(#[from] TransactorError),
+}
+
+impl From<picocert::Error> for FirmwareUpdateError {
+    fn from(err: picocert::Error) -> Self {
+        FirmwareUpdateError::Certificate(err)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    manifest_version: String,
+    fwup_bundle: BundleManifest,
+}
+
+// Normal and delta manifests are told apart by their fields, as the app does.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BundleManifest {
+    Normal {
+        product: Product,
+        version: String,
+        assets: NormalAssets,
+        parameters: Parameters,
+        #[serde(default)]
+        signing_chain: Vec<FileReference>,
+    },
+    Delta {
+        product: Product,
+        from_version: String,
+        to_version: String,
+        assets: DeltaAssets,
+        parameters: Parameters,
+        #[serde(default)]
+        signing_chain: Vec<FileReference>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Product {
+    W1A,
+}
+
+#[derive(Debug, Deserialize)]
+struct NormalAssets {
+    application_a: AssetReference,
+    application_b: AssetReference,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeltaAssets {
+    a2b_patch: AssetReference,
+    b2a_patch: AssetReference,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetReference {
+    image: FileReference,
+    signature: FileReference,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileReference {
+    name: String,
+    // Hex-encoded SHA-256 of the file, which signed manifests must pin.
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Parameters {
+    wca_chunk_size: usize,
+    signature_offset: u32,
+    app_properties_offset: u32,
+}
+
+struct BundleFile {
+    name: String,
+    sha256: Option<String>,
+    data: Vec<u8>,
+}
+
+struct Asset {
+    image: BundleFile,
+    signature: BundleFile,
+}
+
+enum Assets {
+    Normal {
+        application_a: Asset,
+        application_b: Asset,
+    },
+    Delta {
+        from_version: String,
+        a2b_patch: Asset,
+        b2a_patch: Asset,
+    },
+}
+
+/// A firmware update bundle, as published for the hardware: a zip of the images for each slot (or
+/// the patches between them), their detached signatures, and a manifest describing them.
+///
+/// The bundle is built by `firmware/python/bitkey/fwup_bundler.py`, and contains:
+///
+/// - `fwup-manifest.json`: the manifest. Each asset's `image` and `signature` name a file in the
+///   bundle, along with its hex-encoded `sha256`. `signing_chain` names the picocert certificates
+///   the manifest is signed under, from the signer (first) to the root (last).
+/// - `fwup-manifest.sig`: a picocert signature over the exact bytes of `fwup-manifest.json`, by
+///   the first certificate of `signing_chain`, which must be allowed to sign firmware.
+/// - the files named by the manifest.
+///
+/// Verifying the manifest's signature, and then each file against its pinned digest, covers
+/// everything sent to the hardware. Unsigned bundles can still be parsed, but not verified.
+pub struct FirmwareBundle {
+    version: String,
+    assets: Assets,
+    parameters: Parameters,
+    manifest: Vec<u8>,
+    manifest_signature: Option<Vec<u8>>,
+    signing_chain: Vec<Certificate>,
+}
+
+impl FirmwareBundle {
+    pub fn from_zip<R: Read + Seek>(reader: R) -> Result<Self, FirmwareUpdateError> {
+        let mut zip = ZipArchive::new(reader)?;
+
+        let manifest_bytes = read_file(&mut zip, MANIFEST_FILE)?;
+        let manifest: Manifest = serde_json::from_slice(&manifest_bytes)?;
+        if manifest.manifest_version != MANIFEST_VERSION {
+            return Err(FirmwareUpdateError::ManifestVersion(
+                manifest.manifest_version,
+            ));
+        }
+
+        let manifest_signature = match zip.by_name(MANIFEST_SIGNATURE_FILE) {
+            Ok(mut file) => {
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf)?;
+                Some(buf)
+            }
+            Err(ZipError::FileNotFound) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let (version, assets, parameters, signing_chain) = match manifest.fwup_bundle {
+            BundleManifest::Normal {
+                product: Product::W1A,
+                version,
+                assets,
+                parameters,
+                signing_chain,
+            } => (
+                version,
+                Assets::Normal {
+                    application_a: read_asset(&mut zip, assets.application_a)?,
+                    application_b: read_asset(&mut zip, assets.application_b)?,
+                },
+                parameters,
+                signing_chain,
+            ),
+            BundleManifest::Delta {
+                product: Product::W1A,
+                from_version,
+                to_version,
+                assets,
+                parameters,
+                signing_chain,
+            } => (
+                to_version,
+                Assets::Delta {
+                    from_version,
+                    a2b_patch: read_asset(&mut zip, assets.a2b_patch)?,
+                    b2a_patch: read_asset(&mut zip, assets.b2a_patch)?,
+                },
+                parameters,
+                signing_chain,
+            ),
+        };
+
+        if parameters.wca_chunk_size == 0 {
+            return Err(FirmwareUpdateError::InvalidParameters);
+        }
+
+        let signing_chain = signing_chain
+            .into_iter()
+            .map(|reference| {
+                Ok(Certificate::from_bytes(&read_file(
+                    &mut zip,
+                    &reference.name,
+                )?)?)
+            })
+            .collect::<Result<Vec<_>, FirmwareUpdateError>>()?;
+
+        Ok(Self {
+            version,
+            assets,
+            parameters,
+            manifest: manifest_bytes,
+            manifest_signature,
+            signing_chain,
+        })
+    }
+
+    /// The firmware version this bundle updates to.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Check that the manifest is signed by a firmware signing certificate which chains up to one
+    /// of `trusted_roots`, and that every file in the bundle matches the digest it pins.
+    pub fn verify(&self, trusted_roots: &[Certificate]) -> Result<(), FirmwareUpdateError> {
+        let (root, signature) = match (self.signing_chain.last(), &self.manifest_signature) {
+            (Some(root), Some(signature)) => (root, signature),
+            _ => return Err(FirmwareUpdateError::Unsigned),
+        };
+        if !trusted_roots.contains(root) {
+            return Err(FirmwareUpdateError::UntrustedRoot);
+        }
+
+        picocert::verify_and_validate_chain_for(
+            &self.signing_chain,
+            &self.manifest,
+            signature,
+            KeyUsage::FIRMWARE_SIGNING,
+            &[],
+        )?;
+
+        for file in self.files() {
+            let expected = file
+                .sha256
+                .as_ref()
+                .ok_or_else(|| FirmwareUpdateError::MissingDigest(file.name.clone()))?;
+            let actual = hex::encode(digest(&SHA256, &file.data));
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(FirmwareUpdateError::DigestMismatch(file.name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The update mode and asset for the hardware, given its current firmware. The target slot is
+    // the opposite of the active one.
+    fn plan(&self, metadata: &FirmwareMetadata) -> Result<(FwupMode, &Asset), FirmwareUpdateError> {
+        match &self.assets {
+            Assets::Normal {
+                application_a,
+                application_b,
+            } => match metadata.active_slot {
+                FirmwareSlot::A => Ok((FwupMode::Normal, application_b)),
+                FirmwareSlot::B => Ok((FwupMode::Normal, application_a)),
+            },
+            Assets::Delta {
+                from_version,
+                a2b_patch,
+                b2a_patch,
+            } => {
+                if *from_version != metadata.version {
+                    return Err(FirmwareUpdateError::DeltaMismatch {
+                        from: from_version.clone(),
+                        current: metadata.version.clone(),
+                    });
+                }
+                match metadata.active_slot {
+                    FirmwareSlot::A => Ok((FwupMode::Delta, a2b_patch)),
+                    FirmwareSlot::B => Ok((FwupMode::Delta, b2a_patch)),
+                }
+            }
+        }
+    }
+
+    fn files(&self) -> Vec<&BundleFile> {
+        let (first, second) = match &self.assets {
+            Assets::Normal {
+                application_a,
+                application_b,
+            } => (application_a, application_b),
+            Assets::Delta {
+                a2b_patch,
+                b2a_patch,
+                ..
+            } => (a2b_patch, b2a_patch),
+        };
+        vec![
+            &first.image,
+            &first.signature,
+            &second.image,
+            &second.signature,
+        ]
+    }
+}
+
+fn read_file<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Vec<u8>, FirmwareUpdateError> {
+    let mut buf = Vec::new();
+    zip.by_name(name)?.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_asset<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    reference: AssetReference,
+) -> Result<Asset, FirmwareUpdateError> {
+    let mut read = |reference: FileReference| -> Result<BundleFile, FirmwareUpdateError> {
+        Ok(BundleFile {
+            data: read_file(zip, &reference.name)?,
+            name: reference.name,
+            sha256: reference.sha256,
+        })
+    };
+
+    Ok(Asset {
+        image: read(reference.image)?,
+        signature: read(reference.signature)?,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FwupProgress {
+    pub chunks_sent: u32,
+    pub total_chunks: u32,
+}
+
+/// Drives a firmware update from a bundle: start, transfer every chunk of the image and its
+/// signature, then finish.
+///
+/// If an update is interrupted (the hardware is pulled away, or locks), calling `update` again
+/// resumes from the last chunk the hardware acknowledged, rather than starting over.
+pub struct FirmwareUpdater {
+    bundle: FirmwareBundle,
+    // The next chunk to transfer, once the update has been started on the hardware.
+    next_sequence_id: Option<u32>,
+}
+
+impl FirmwareUpdater {
+    /// Verify the bundle against `trusted_roots` before anything is sent to the hardware.
+    pub fn new(
+        bundle: FirmwareBundle,
+        trusted_roots: &[Certificate],
+    ) -> Result<Self, FirmwareUpdateError> {
+        bundle.verify(trusted_roots)?;
+        Ok(Self::new_insecure_skip_verify(bundle))
+    }
+
+    /// Skip verifying the bundle, and rely solely on the hardware's own signature check. Only for
+    /// development bundles, which aren't signed under a root the client trusts.
+    pub fn new_insecure_skip_verify(bundle: FirmwareBundle) -> Self {
+        Self {
+            bundle,
+            next_sequence_id: None,
+        }
+    }
+
+    pub fn bundle(&self) -> &FirmwareBundle {
+        &self.bundle
+    }
+
+    /// The next chunk to transfer, if an update is in progress. Persist this to resume an update
+    /// across sessions with `resume_from`.
+    pub fn next_sequence_id(&self) -> Option<u32> {
+        self.next_sequence_id
+    }
+
+    /// Continue an update that the hardware has already started, from `sequence_id`. If the
+    /// hardware no longer has the update in progress, it's started over.
+    pub fn resume_from(&mut self, sequence_id: u32) {
+        self.next_sequence_id = Some(sequence_id);
+    }
+
+    #[cfg(feature = "pcsc")]
+    pub fn update<T: Transactor + ?Sized>(
+        &mut self,
+        transactor: &T,
+        mut progress: impl FnMut(FwupProgress),
+    ) -> Result<FwupFinishRspStatus, FirmwareUpdateError> {
+        // The active slot only changes once the update finishes, so the plan is the same for
+        // every attempt of an update.
+        let metadata = transactor.perform(GetFirmwareMetadata::new())?;
+        let (mode, asset) = self.bundle.plan(&metadata)?;
+        let parameters = self.bundle.parameters.clone();
+
+        let chunks = asset
+            .image
+            .data
+            .chunks(parameters.wca_chunk_size)
+            .collect::<Vec<_>>();
+        let total_chunks = chunks.len() as u32;
+        let transfer = |sequence_id: u32| {
+            transactor.perform(FwupTransfer::new(
+                sequence_id,
+                chunks[sequence_id as usize].to_vec(),
+                0,
+                mode.clone(),
+            ))
+        };
+
+        let mut next_sequence_id = match self.next_sequence_id {
+            // The hardware only keeps an update in progress until it resets, and rejects
+            // transfers once it has none. Don't trust our own record of how far the update got:
+            // send the next chunk, and start over if it's rejected.
+            Some(sequence_id) if sequence_id < total_chunks => match transfer(sequence_id) {
+                Ok(_) => {
+                    progress(FwupProgress {
+                        chunks_sent: sequence_id + 1,
+                        total_chunks,
+                    });
+                    sequence_id + 1
+                }
+                Err(TransactorError::CommandError(CommandError::GeneralCommandError)) => {
+                    start(transactor, &mode, asset)?;
+                    0
+                }
+                Err(err) => return Err(err.into()),
+            },
+            Some(sequence_id) => sequence_id,
+            None => {
+                start(transactor, &mode, asset)?;
+                0
+            }
+        };
+        self.next_sequence_id = Some(next_sequence_id);
+
+        while next_sequence_id < total_chunks {
+            transfer(next_sequence_id)?;
+            next_sequence_id += 1;
+            self.next_sequence_id = Some(next_sequence_id);
+            progress(FwupProgress {
+                chunks_sent: next_sequence_id,
+                total_chunks,
+            });
+        }
+
+        // Delta or not, the signature is always a normal transfer to its fixed offset.
+        transactor.perform(FwupTransfer::new(
+            0,
+            asset.signature.data.clone(),
+            parameters.signature_offset,
+            FwupMode::Normal,
+        ))?;
+
+        let status = transactor.perform(FwupFinish::new(
+            parameters.app_properties_offset,
+            parameters.signature_offset,
+            mode,
+        ))?;
+        self.next_sequence_id = None;
+
+        match status {
+            FwupFinishRspStatus::Success | FwupFinishRspStatus::WillApplyPatch => Ok(status),
+            status => Err(FirmwareUpdateError::Finish(status)),
+        }
+    }
+}
+
+#[cfg(feature = "pcsc")]
+fn start<T: Transactor + ?Sized>(
+    transactor: &T,
+    mode: &FwupMode,
+    asset: &Asset,
+) -> Result<(), FirmwareUpdateError> {
+    let patch_size = match mode {
+        FwupMode::Normal => None,
+        FwupMode::Delta => Some(asset.image.data.len() as u32),
+    };
+    match transactor.perform(FwupStart::new(patch_size, mode.clone()))? {
+        true => Ok(()),
+        false => Err(FirmwareUpdateError::Start),
+    }
+}
@@ -1,7 +1,11 @@
 pub mod attestation;
 pub mod command_interface;
 pub mod commands;
+pub mod diagnostics;
+#[cfg(feature = "pcsc")]
+pub mod emulator;
 pub mod errors;
+pub mod firmware_update;
 pub mod log_buffer;
 
 #[cfg(feature = "pcsc")]
@@ -9,6 +13,8 @@ pub mod pcsc;
 pub mod signing;
 mod wca;
 
+pub use crate::wca::ApduSession;
+
 use std::{
     fmt::{Display, Write},
     str::FromStr,
@@ -1,12 +1,18 @@
 use crate::{
     command_interface::{Command, State},
     errors::CommandError,
+    wca::ApduSession,
 };
 use pcsc::{Card, Context, Protocols, Scope, ShareMode, MAX_BUFFER_SIZE_EXTENDED};
 
 pub trait Transactor: Send + Sync {
     fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error>;
     fn reset(&mut self) -> Result<(), pcsc::Error>;
+
+    /// The session used to fragment commands for this transactor.
+    fn session(&self) -> ApduSession {
+        ApduSession::default()
+    }
 }
 
 pub trait Performer<T: Transactor + ?Sized> {
@@ -20,10 +26,15 @@ impl<T: Transactor + ?Sized> Performer<T> for T {
     where
         TransactorError: From<E>,
     {
+        let session = self.session();
         let mut response = vec![];
         loop {
             response = match command.next(response)? {
-                State::Data { response: cmd } => self.transmit(&cmd)?, // cmd is what is sent to the firmware
+                // cmd is what is sent to the firmware
+                State::Data { response: cmd } => session
+                    .transmit(&cmd, |apdu| -> Result<_, TransactorError> {
+                        Ok(self.transmit(apdu)?)
+                    })?,
                 State::Result { value } => break Ok(value),
             }
         }
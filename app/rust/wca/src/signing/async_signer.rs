@@ -1,7 +1,8 @@
 use bitcoin::secp256k1::ecdsa::Signature;
 use next_gen::generator;
 
-use crate::wca;
+use crate::wca::{self, transceive};
+use crate::yield_from_;
 use crate::{
     errors::CommandError,
     fwpb::{self, derive_and_sign_rsp::DeriveAndSignRspStatus, DeriveKeyDescriptorAndSignCmd},
@@ -19,9 +20,7 @@ pub(crate) fn derive_and_sign(
         async_sign,
     }
     .try_into()?;
-    let data = yield_!(apdu.clone().into());
-
-    let response = apdu::Response::from(data);
+    let response = yield_from_!(transceive(apdu.clone()))?;
     let message_outer = wca::decode_and_check(response)?;
     let message = message_outer.msg.ok_or(CommandError::MissingMessage)?;
 
@@ -57,8 +56,7 @@ pub(crate) fn derive_and_sign(
     for _ in 0..10 {
         std::thread::sleep(std::time::Duration::from_millis(50));
 
-        let data = yield_!(apdu.clone().into()); // The firmware will return InProgress until signing completes.
-        let response = apdu::Response::from(data);
+        let response = yield_from_!(transceive(apdu.clone()))?; // The firmware will return InProgress until signing completes.
         let message_outer = wca::decode_and_check(response)?;
         let message = message_outer.msg.ok_or(CommandError::MissingMessage)?;
 
@@ -1,5 +1,6 @@
 pub(crate) mod async_signer;
 pub(crate) mod derived;
+pub mod summary;
 
 use bitcoin::sighash::{LegacySighash, SegwitV0Sighash};
 use bitcoin::{
@@ -0,0 +1,499 @@
+use bitcoin::{
+    bip32::{ChildNumber, DerivationPath, Fingerprint},
+    psbt::PartiallySignedTransaction,
+    Address, Amount, Network, ScriptBuf,
+};
+use miniscript::{Descriptor, DescriptorPublicKey, ForEachKey};
+
+// BIP-44 style purposes, whose second (hardened) child is the coin type.
+const PURPOSES: [u32; 4] = [44, 49, 84, 86];
+// Anything above this is almost certainly a mistake, even in a fee spike.
+const DEFAULT_MAX_FEE_RATE: f64 = 1000.0;
+// Segwit marker and flag bytes, which the unsigned transaction's weight doesn't count.
+const SEGWIT_MARKER_WEIGHT: u64 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SummaryError {
+    #[error("input {0} is missing its previous output")]
+    MissingUtxo(usize),
+    #[error("input {0}'s previous transaction doesn't match its outpoint")]
+    UtxoMismatch(usize),
+    #[error("outputs are worth more than inputs")]
+    NegativeFee,
+    #[error("could not estimate transaction weight")]
+    Weight(#[from] miniscript::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputKind {
+    /// Leaves the wallet.
+    External,
+    /// Returns to the wallet, at `path` under the signer's fingerprint.
+    Change { path: DerivationPath },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSummary {
+    pub index: usize,
+    pub value: Amount,
+    pub script_pubkey: ScriptBuf,
+    /// Only known if the summary was built for a network.
+    pub address: Option<Address>,
+    pub kind: OutputKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    /// The fee is more than the configured maximum.
+    LargeFee { fee: Amount },
+    /// The fee rate (in sat/vB) is more than the configured maximum.
+    HighFeeRate { fee_rate: f64 },
+    /// An output claims to be the signer's, but isn't what the known descriptors derive at its
+    /// path. It's treated as external.
+    UnexpectedChangePath {
+        output_index: usize,
+        path: DerivationPath,
+    },
+    /// Keys or derivation paths in the transaction disagree about mainnet vs. test networks.
+    MixedNetworks,
+    /// The input only has a witness UTXO, whose value the host could have lied about: segwit v0
+    /// signatures don't commit to the values of the other inputs, so the fee may be larger than
+    /// summarised.
+    UnverifiedInputValue { input_index: usize },
+    /// An output derives from the signer's fingerprint, but there's no descriptor to check that it
+    /// really is change. It's treated as change.
+    UnverifiedChange { output_index: usize },
+}
+
+/// What a transaction does, from the point of view of the signer: where the money goes, and what
+/// it costs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionSummary {
+    pub outputs: Vec<OutputSummary>,
+    pub input_value: Amount,
+    pub fee: Amount,
+    /// Estimated from the weight of the fully signed transaction, in sat/vB.
+    pub fee_rate: f64,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl TransactionSummary {
+    /// The total leaving the wallet, not including the fee.
+    pub fn spend_amount(&self) -> Amount {
+        self.sum_outputs(|kind| *kind == OutputKind::External)
+    }
+
+    pub fn change_amount(&self) -> Amount {
+        self.sum_outputs(|kind| matches!(kind, OutputKind::Change { .. }))
+    }
+
+    fn sum_outputs(&self, filter: impl Fn(&OutputKind) -> bool) -> Amount {
+        self.outputs
+            .iter()
+            .filter(|output| filter(&output.kind))
+            .map(|output| output.value)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransactionSummaryBuilder {
+    origin_fingerprint: Fingerprint,
+    descriptors: Vec<Descriptor<DescriptorPublicKey>>,
+    network: Option<Network>,
+    max_fee: Option<Amount>,
+    max_fee_rate: f64,
+}
+
+impl TransactionSummaryBuilder {
+    pub fn new(origin_fingerprint: Fingerprint) -> Self {
+        Self {
+            origin_fingerprint,
+            descriptors: vec![],
+            network: None,
+            max_fee: None,
+            max_fee_rate: DEFAULT_MAX_FEE_RATE,
+        }
+    }
+
+    /// A descriptor of the wallet, e.g. its change descriptor, to check change outputs against.
+    /// Without any, an output is change if it derives from the signer's fingerprint, and is flagged
+    /// as unverified.
+    pub fn descriptor(mut self, descriptor: Descriptor<DescriptorPublicKey>) -> Self {
+        self.descriptors.push(descriptor);
+        self
+    }
+
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    pub fn max_fee(mut self, max_fee: Amount) -> Self {
+        self.max_fee = Some(max_fee);
+        self
+    }
+
+    pub fn max_fee_rate(mut self, max_fee_rate: f64) -> Self {
+        self.max_fee_rate = max_fee_rate;
+        self
+    }
+
+    pub fn origin_fingerprint(&self) -> Fingerprint {
+        self.origin_fingerprint
+    }
+
+    pub fn build(
+        &self,
+        psbt: &PartiallySignedTransaction,
+    ) -> Result<TransactionSummary, SummaryError> {
+        let mut anomalies = vec![];
+
+        let input_value = self.input_value(psbt, &mut anomalies)?;
+        let output_value: Amount = psbt
+            .unsigned_tx
+            .output
+            .iter()
+            .map(|o| Amount::from_sat(o.value))
+            .sum();
+        let fee = input_value
+            .checked_sub(output_value)
+            .ok_or(SummaryError::NegativeFee)?;
+
+        let vsize = (self.estimated_weight(psbt)? + 3) / 4;
+        let fee_rate = fee.to_sat() as f64 / vsize as f64;
+        if self.max_fee.is_some_and(|max_fee| fee > max_fee) {
+            anomalies.push(Anomaly::LargeFee { fee });
+        }
+        if fee_rate > self.max_fee_rate {
+            anomalies.push(Anomaly::HighFeeRate { fee_rate });
+        }
+
+        let mut outputs = vec![];
+        for (index, (txout, output)) in psbt
+            .unsigned_tx
+            .output
+            .iter()
+            .zip(&psbt.outputs)
+            .enumerate()
+        {
+            let kind = match self.own_path(&output.bip32_derivation) {
+                Some(path) if self.descriptors.is_empty() => {
+                    anomalies.push(Anomaly::UnverifiedChange {
+                        output_index: index,
+                    });
+                    OutputKind::Change { path: path.clone() }
+                }
+                Some(path) if self.is_expected_change(&txout.script_pubkey, path) => {
+                    OutputKind::Change { path: path.clone() }
+                }
+                Some(path) => {
+                    anomalies.push(Anomaly::UnexpectedChangePath {
+                        output_index: index,
+                        path: path.clone(),
+                    });
+                    OutputKind::External
+                }
+                None => OutputKind::External,
+            };
+
+            outputs.push(OutputSummary {
+                index,
+                value: Amount::from_sat(txout.value),
+                script_pubkey: txout.script_pubkey.clone(),
+                address: self
+                    .network
+                    .and_then(|network| Address::from_script(&txout.script_pubkey, network).ok()),
+                kind,
+            });
+        }
+
+        if self.has_mixed_networks(psbt) {
+            anomalies.push(Anomaly::MixedNetworks);
+        }
+
+        Ok(TransactionSummary {
+            outputs,
+            input_value,
+            fee,
+            fee_rate,
+            anomalies,
+        })
+    }
+
+    // Prefer the value from the full previous transaction, which can be checked against the
+    // outpoint, to the witness UTXO, which can't.
+    fn input_value(
+        &self,
+        psbt: &PartiallySignedTransaction,
+        anomalies: &mut Vec<Anomaly>,
+    ) -> Result<Amount, SummaryError> {
+        let mut total = Amount::ZERO;
+        for (index, (input, txin)) in psbt.inputs.iter().zip(&psbt.unsigned_tx.input).enumerate() {
+            let value = match (&input.non_witness_utxo, &input.witness_utxo) {
+                (Some(tx), witness_utxo) => {
+                    let outpoint = txin.previous_output;
+                    let utxo = tx
+                        .output
+                        .get(outpoint.vout as usize)
+                        .filter(|_| tx.txid() == outpoint.txid)
+                        .ok_or(SummaryError::UtxoMismatch(index))?;
+                    if witness_utxo
+                        .as_ref()
+                        .is_some_and(|witness_utxo| witness_utxo != utxo)
+                    {
+                        return Err(SummaryError::UtxoMismatch(index));
+                    }
+                    utxo.value
+                }
+                (None, Some(utxo)) => {
+                    anomalies.push(Anomaly::UnverifiedInputValue { input_index: index });
+                    utxo.value
+                }
+                (None, None) => return Err(SummaryError::MissingUtxo(index)),
+            };
+            total += Amount::from_sat(value);
+        }
+        Ok(total)
+    }
+
+    // The weight the transaction will have once every input is satisfied. Without a descriptor to
+    // size the witnesses, this is the (smaller) unsigned weight, so the fee rate errs high.
+    fn estimated_weight(&self, psbt: &PartiallySignedTransaction) -> Result<u64, SummaryError> {
+        let mut weight = psbt.unsigned_tx.weight().to_wu();
+        if let Some(descriptor) = self.descriptors.first() {
+            let satisfaction = descriptor.max_weight_to_satisfy()? as u64;
+            weight += satisfaction * psbt.inputs.len() as u64;
+            if psbt.inputs.iter().any(|input| input.witness_utxo.is_some()) {
+                weight += SEGWIT_MARKER_WEIGHT;
+            }
+        }
+        Ok(weight)
+    }
+
+    fn own_path<'a, K>(
+        &self,
+        derivation: &'a std::collections::BTreeMap<K, (Fingerprint, DerivationPath)>,
+    ) -> Option<&'a DerivationPath> {
+        derivation
+            .values()
+            .find(|(fingerprint, _)| *fingerprint == self.origin_fingerprint)
+            .map(|(_, path)| path)
+    }
+
+    fn is_expected_change(&self, script_pubkey: &ScriptBuf, path: &DerivationPath) -> bool {
+        let index = match path.as_ref().last() {
+            Some(ChildNumber::Normal { index }) => *index,
+            _ => return false,
+        };
+        self.descriptors.iter().any(|descriptor| {
+            descriptor
+                .at_derivation_index(index)
+                .is_ok_and(|derived| derived.script_pubkey() == *script_pubkey)
+        })
+    }
+
+    fn has_mixed_networks(&self, psbt: &PartiallySignedTransaction) -> bool {
+        let mut mainnet = vec![];
+        if let Some(network) = self.network {
+            mainnet.push(network == Network::Bitcoin);
+        }
+        for descriptor in &self.descriptors {
+            descriptor.for_each_key(|key| {
+                match key {
+                    DescriptorPublicKey::XPub(xpub) => {
+                        mainnet.push(xpub.xkey.network == Network::Bitcoin)
+                    }
+                    DescriptorPublicKey::MultiXPub(xpub) => {
+                        mainnet.push(xpub.xkey.network == Network::Bitcoin)
+                    }
+                    DescriptorPublicKey::Single(_) => {}
+                }
+                true
+            });
+        }
+        for xpub in psbt.xpub.keys() {
+            mainnet.push(xpub.network == Network::Bitcoin);
+        }
+
+        let paths = psbt
+            .inputs
+            .iter()
+            .filter_map(|input| self.own_path(&input.bip32_derivation))
+            .chain(
+                psbt.outputs
+                    .iter()
+                    .filter_map(|output| self.own_path(&output.bip32_derivation)),
+            );
+        for path in paths {
+            if let [ChildNumber::Hardened { index: purpose }, ChildNumber::Hardened { index: coin_type }, ..] =
+                path.as_ref()
+            {
+                if PURPOSES.contains(purpose) {
+                    mainnet.push(*coin_type == 0);
+                }
+            }
+        }
+
+        mainnet.windows(2).any(|pair| pair[0] != pair[1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bdk::wallet::{get_funded_wallet, AddressIndex};
+    use bitcoin::{
+        bip32::{ChildNumber, Fingerprint},
+        hashes::Hash,
+        psbt::PartiallySignedTransaction,
+        Address, Amount, Network, Txid,
+    };
+    use miniscript::{Descriptor, DescriptorPublicKey};
+
+    use super::{Anomaly, OutputKind, SummaryError, TransactionSummaryBuilder};
+
+    const DESCRIPTOR: &str = "wpkh([96ae1927/84'/1'/0']tpubDDTqca3h8xPvEas4gMwWuqVhnaPyfBQapLj3jkr7j7M9WVBDx6PiVec5XJBbWgP4UmuLSYW9pr36Lc2iyCLJZ2KQD2ggAX2dyRcVbcM9Ygn/*)";
+    const RECIPIENT: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn descriptor() -> Descriptor<DescriptorPublicKey> {
+        Descriptor::from_str(DESCRIPTOR).unwrap()
+    }
+
+    fn builder() -> TransactionSummaryBuilder {
+        TransactionSummaryBuilder::new(Fingerprint::from_str("96ae1927").unwrap())
+            .descriptor(descriptor())
+            .network(Network::Testnet)
+    }
+
+    fn get_spend_psbt() -> PartiallySignedTransaction {
+        let (wallet, _, _) = get_funded_wallet(DESCRIPTOR);
+        let recipient = Address::from_str(RECIPIENT)
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(recipient, 25_000);
+        let (psbt, _) = builder.finish().unwrap();
+        psbt
+    }
+
+    #[test]
+    fn test_summary() {
+        let psbt = get_spend_psbt();
+        let summary = builder().build(&psbt).unwrap();
+
+        assert!(summary.anomalies.is_empty(), "{:?}", summary.anomalies);
+        assert_eq!(summary.outputs.len(), 2);
+        assert_eq!(summary.spend_amount(), Amount::from_sat(25_000));
+        assert_eq!(
+            summary.input_value,
+            summary.spend_amount() + summary.change_amount() + summary.fee
+        );
+        assert!(summary.fee > Amount::ZERO);
+        assert!((0.9..2.0).contains(&summary.fee_rate));
+
+        let external = summary
+            .outputs
+            .iter()
+            .find(|o| o.kind == OutputKind::External)
+            .unwrap();
+        assert_eq!(external.address.as_ref().unwrap().to_string(), RECIPIENT);
+    }
+
+    #[test]
+    fn test_without_descriptor() {
+        let psbt = get_spend_psbt();
+        let summary = TransactionSummaryBuilder::new(Fingerprint::from_str("96ae1927").unwrap())
+            .build(&psbt)
+            .unwrap();
+
+        let change = summary
+            .outputs
+            .iter()
+            .position(|o| matches!(o.kind, OutputKind::Change { .. }))
+            .unwrap();
+        assert_eq!(
+            summary.anomalies,
+            vec![Anomaly::UnverifiedChange {
+                output_index: change
+            }]
+        );
+        assert_eq!(summary.spend_amount(), Amount::from_sat(25_000));
+        assert!(summary.outputs.iter().all(|o| o.address.is_none()));
+    }
+
+    #[test]
+    fn test_flags_unverified_input_value() {
+        let mut psbt = get_spend_psbt();
+        psbt.inputs[0].non_witness_utxo = None;
+
+        let summary = builder().build(&psbt).unwrap();
+        assert_eq!(
+            summary.anomalies,
+            vec![Anomaly::UnverifiedInputValue { input_index: 0 }]
+        );
+    }
+
+    #[test]
+    fn test_rejects_mismatched_utxo() {
+        let mut psbt = get_spend_psbt();
+        psbt.inputs[0].witness_utxo.as_mut().unwrap().value += 1_000_000;
+        assert!(matches!(
+            builder().build(&psbt),
+            Err(SummaryError::UtxoMismatch(0))
+        ));
+
+        let mut psbt = get_spend_psbt();
+        psbt.unsigned_tx.input[0].previous_output.txid = Txid::all_zeros();
+        assert!(matches!(
+            builder().build(&psbt),
+            Err(SummaryError::UtxoMismatch(0))
+        ));
+    }
+
+    #[test]
+    fn test_flags_large_fee() {
+        let psbt = get_spend_psbt();
+        let summary = builder()
+            .max_fee(Amount::from_sat(1))
+            .max_fee_rate(0.5)
+            .build(&psbt)
+            .unwrap();
+
+        assert!(matches!(
+            summary.anomalies.as_slice(),
+            [Anomaly::LargeFee { .. }, Anomaly::HighFeeRate { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_flags_unexpected_change_path() {
+        let mut psbt = get_spend_psbt();
+        let (index, output) = psbt
+            .outputs
+            .iter_mut()
+            .enumerate()
+            .find(|(_, o)| !o.bip32_derivation.is_empty())
+            .unwrap();
+        for (_, path) in output.bip32_derivation.values_mut() {
+            *path = path.extend([ChildNumber::from_normal_idx(1000).unwrap()]);
+        }
+
+        let summary = builder().build(&psbt).unwrap();
+        assert!(matches!(
+            summary.anomalies.as_slice(),
+            [Anomaly::UnexpectedChangePath { output_index, .. }] if *output_index == index
+        ));
+        assert_eq!(summary.change_amount(), Amount::ZERO);
+    }
+
+    #[test]
+    fn test_flags_mixed_networks() {
+        let psbt = get_spend_psbt();
+        let summary = builder().network(Network::Bitcoin).build(&psbt).unwrap();
+
+        assert!(summary.anomalies.contains(&Anomaly::MixedNetworks));
+    }
+}
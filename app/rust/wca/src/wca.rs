@@ -1,17 +1,123 @@
+use next_gen::generator;
 use prost::Message;
 
-use crate::{errors::EncodeError, log_buffer::LogBuffer};
+use crate::{
+    errors::{CommandError, EncodeError},
+    log_buffer::LogBuffer,
+};
 use std::time::SystemTime;
 
-const WCA_CLA: u8 = 0x87;
-const WCA_INS_VERSION: u8 = 0x74;
-const WCA_INS_PROTO: u8 = 0x75;
-const WCA_INS_PROTO_CONTINUATION: u8 = 0x77;
-const WCA_INS_GET_RESPONSE: u8 = 0x78;
+pub(crate) const WCA_CLA: u8 = 0x87;
+pub(crate) const WCA_INS_VERSION: u8 = 0x74;
+pub(crate) const WCA_INS_PROTO: u8 = 0x75;
+pub(crate) const WCA_INS_PROTO_CONTINUATION: u8 = 0x77;
+pub(crate) const WCA_INS_GET_RESPONSE: u8 = 0x78;
 
 const MAX_WCA_BUFFER_SIZE: usize = 512;
-const APDU_OVERHEAD_SIZE: usize = 7; // This could be 5 in some situation ... but why bother?
-const MAX_PROTO_SIZE: usize = MAX_WCA_BUFFER_SIZE - APDU_OVERHEAD_SIZE;
+
+/// A transactor's session with the firmware, which holds the largest command APDU it can send.
+///
+/// Commands already yield APDUs that fit the firmware's buffer, so they can be sent as they are to
+/// any transceiver that can send 512 bytes. A session with a smaller APDU size splits them further
+/// into a PROTO followed by PROTO_CONT fragments; the firmware only accepts WCA_CLA, so ISO 7816-4
+/// chaining can't be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApduSession {
+    max_apdu_size: usize,
+}
+
+impl ApduSession {
+    /// Negotiate the largest command APDU to send, given the largest the NFC transceiver can send
+    /// (e.g. `IsoDep.getMaxTransceiveLength()` on Android). The negotiated size never exceeds the
+    /// firmware's own buffer.
+    pub fn new(transceive_length: u32) -> Self {
+        Self {
+            max_apdu_size: (transceive_length as usize).min(MAX_WCA_BUFFER_SIZE),
+        }
+    }
+
+    pub fn max_apdu_size(&self) -> u32 {
+        self.max_apdu_size as u32
+    }
+
+    /// Split an APDU yielded by a command into the APDUs to transmit. They must be sent in order,
+    /// stopping at the first response that isn't 0x9000, and the last response received is passed
+    /// back to the command.
+    pub fn fragment(&self, apdu: &[u8]) -> Result<Vec<Vec<u8>>, CommandError> {
+        if apdu.len() <= self.max_apdu_size {
+            return Ok(vec![apdu.to_vec()]);
+        }
+
+        self.fragment_command(apdu::Command::deserialize(apdu)?)
+    }
+
+    /// Split a PROTO or PROTO_CONT command into serialized fragments. The firmware appends
+    /// PROTO_CONT data until it has the size given by the PROTO, so every fragment after the first
+    /// is sent as a PROTO_CONT.
+    fn fragment_command(&self, command: apdu::Command) -> Result<Vec<Vec<u8>>, CommandError> {
+        let first_is_proto = match command.ins {
+            WCA_INS_PROTO => true,
+            WCA_INS_PROTO_CONTINUATION => false,
+            _ => {
+                let apdu = command.serialize()?;
+                if apdu.len() > self.max_apdu_size {
+                    return Err(apdu::Error::DataTooLong(apdu.len()).into());
+                }
+                return Ok(vec![apdu]);
+            }
+        };
+
+        command
+            .fragment(self.max_apdu_size)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, fragment)| -> Result<Vec<u8>, CommandError> {
+                let fragment = match index {
+                    0 if first_is_proto => fragment,
+                    _ => WCA::ProtoContinuation(fragment.data.unwrap_or_default()).try_into()?,
+                };
+                Ok(fragment.serialize()?)
+            })
+            .collect()
+    }
+
+    /// Send an APDU yielded by a command, fragmenting it as needed, and return the response to
+    /// pass back to the command.
+    pub fn transmit<E: From<CommandError>>(
+        &self,
+        apdu: &[u8],
+        mut send: impl FnMut(&[u8]) -> Result<Vec<u8>, E>,
+    ) -> Result<Vec<u8>, E> {
+        let mut response = vec![];
+        for (index, fragment) in self.fragment(apdu)?.iter().enumerate() {
+            if index > 0 {
+                check_fragment_response(response)?;
+            }
+            response = send(fragment)?;
+        }
+        Ok(response)
+    }
+}
+
+/// Check that the firmware accepted a fragment before sending the next one.
+fn check_fragment_response(response: Vec<u8>) -> Result<(), CommandError> {
+    let response = apdu::Response::from(response);
+    match response.is_ok() {
+        true => Ok(()),
+        false => Err(CommandError::UnexpectedStatusWords(u16::from_be_bytes([
+            response.sw1,
+            response.sw2,
+        ]))),
+    }
+}
+
+impl Default for ApduSession {
+    fn default() -> Self {
+        Self {
+            max_apdu_size: MAX_WCA_BUFFER_SIZE,
+        }
+    }
+}
 
 pub enum WCA {
     Version,
@@ -145,23 +251,32 @@ impl TryFrom<crate::fwpb::CoredumpGetCmd> for apdu::Command {
     }
 }
 
-impl TryFrom<crate::fwpb::wallet_cmd::Msg> for Vec<apdu::Command> {
-    type Error = EncodeError;
-
-    fn try_from(command: crate::fwpb::wallet_cmd::Msg) -> Result<Self, Self::Error> {
-        let mut proto_bytes = Vec::new();
-        command.encode(&mut proto_bytes);
-        let mut fragments = proto_bytes.chunks(MAX_PROTO_SIZE).map(|c| c.to_vec());
-
-        let mut rv = Vec::new();
-        let first = fragments.next().ok_or(EncodeError::TruncatedProto)?;
-        rv.push(WCA::Proto(first).try_into()?);
-        for chunk in fragments {
-            rv.push(WCA::ProtoContinuation(chunk).try_into()?);
+/// Send a command to the firmware and collect its full response.
+///
+/// Commands larger than the firmware's buffer are yielded as a PROTO followed by PROTO_CONT
+/// fragments, so every yielded APDU can be sent as it is. Responses larger than the firmware's
+/// buffer are drained with GET_RESPONSE.
+#[generator(yield(Vec<u8>), resume(Vec<u8>))]
+pub(crate) fn transceive(command: apdu::Command) -> Result<apdu::Response, CommandError> {
+    let mut response = vec![];
+    for (index, fragment) in ApduSession::default()
+        .fragment_command(command)?
+        .into_iter()
+        .enumerate()
+    {
+        if index > 0 {
+            check_fragment_response(response)?;
         }
+        response = yield_!(fragment);
+    }
 
-        Ok(rv)
+    let mut response = apdu::Response::from(response);
+    while response.has_more() {
+        let get_response: apdu::Command = WCA::GetResponse.try_into()?;
+        response.extend(apdu::Response::from(yield_!(get_response.serialize()?)));
     }
+
+    Ok(response)
 }
 
 /// Decode an APDU response into a protobuf, and check for errors set on the global status fields.
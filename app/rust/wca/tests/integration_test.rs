@@ -50,7 +50,7 @@ mod recordings {
         }
     }
 
-    fn get_funded_wallet(base: &DescriptorPublicKey) -> Wallet<AnyDatabase> {
+    pub(crate) fn get_funded_wallet(base: &DescriptorPublicKey) -> Wallet<AnyDatabase> {
         let spending: DescriptorPublicKey =
             extend_descriptor_public_key(base, &[ChildNumber::Normal { index: 0 }]);
         let descriptor = Descriptor::<DescriptorPublicKey>::new_wpkh(spending).unwrap();
@@ -58,7 +58,7 @@ mod recordings {
         wallet
     }
 
-    fn normal_transaction(
+    pub(crate) fn normal_transaction(
         from: &Wallet<AnyDatabase>,
         to: &Wallet<AnyDatabase>,
         amount: u64,
@@ -85,7 +85,7 @@ mod recordings {
         unsigned
     }
 
-    fn is_finalized(psbt: &PartiallySignedTransaction) -> bool {
+    pub(crate) fn is_finalized(psbt: &PartiallySignedTransaction) -> bool {
         psbt.inputs
             .iter()
             .all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some())
@@ -197,3 +197,692 @@ mod recordings {
         assert!(finalized);
     }
 }
+
+#[cfg(feature = "pcsc")]
+mod emulated {
+    use bdk::KeychainKind;
+    use bitcoin::{
+        hashes::{sha256, Hash},
+        secp256k1::{Message, Secp256k1},
+        Amount,
+    };
+    use serial_test::serial;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use wca::{
+        commands::{FingerprintEnrollmentStatus, FirmwareSlot, FwupFinishRspStatus, FwupMode},
+        diagnostics::{DiagnosticsBundle, DiagnosticsCollector},
+        emulator::EmulatedTransactor,
+        errors::CommandError,
+        firmware_update::{FirmwareBundle, FirmwareUpdateError, FirmwareUpdater},
+        fwpb::BtcNetwork::Signet,
+        pcsc::{Performer, Transactor, TransactorError},
+        signing::summary::TransactionSummaryBuilder,
+        ApduSession,
+    };
+
+    use crate::recordings::{get_funded_wallet, is_finalized, normal_transaction};
+
+    const SEED: [u8; 32] = [42; 32];
+
+    #[test]
+    #[serial]
+    fn test_onboarding() {
+        let emulator = EmulatedTransactor::from_seed(SEED);
+
+        assert!(!emulator
+            .perform(wca::commands::QueryAuthentication::new())
+            .unwrap());
+        assert!(matches!(
+            emulator.perform(wca::commands::GetInitialSpendingKey::new(Signet)),
+            Err(TransactorError::CommandError(CommandError::Unauthenticated))
+        ));
+
+        assert!(emulator
+            .perform(wca::commands::StartFingerprintEnrollment::new(
+                0,
+                "left thumb".into()
+            ))
+            .unwrap());
+        let mut polls = 0;
+        while emulator
+            .perform(wca::commands::GetFingerprintEnrollmentStatus::new(true))
+            .unwrap()
+            .status
+            != FingerprintEnrollmentStatus::Complete
+        {
+            polls += 1;
+            assert!(polls < 10, "enrollment never completed");
+        }
+
+        assert!(emulator
+            .perform(wca::commands::QueryAuthentication::new())
+            .unwrap());
+        let enrolled = emulator
+            .perform(wca::commands::GetEnrolledFingerprints::new())
+            .unwrap();
+        assert_eq!(enrolled.max_count, 3);
+        assert_eq!(enrolled.fingerprints.len(), 1);
+        assert_eq!(enrolled.fingerprints[0].label, "left thumb");
+
+        // The device is deterministic in its seed.
+        assert_eq!(
+            emulator
+                .perform(wca::commands::GetInitialSpendingKey::new(Signet))
+                .unwrap(),
+            EmulatedTransactor::onboarded(SEED)
+                .perform(wca::commands::GetInitialSpendingKey::new(Signet))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_authentication() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+
+        let challenge = "0123456789abcdef".as_bytes();
+        let message = Message::from_hashed_data::<sha256::Hash>(challenge);
+
+        let authentication_key = emulator
+            .perform(wca::commands::GetAuthenticationKey::new())
+            .unwrap();
+        for async_sign in [false, true] {
+            let signature = emulator
+                .perform(wca::commands::SignChallenge::new(
+                    challenge.to_vec(),
+                    async_sign,
+                ))
+                .unwrap();
+            Secp256k1::new()
+                .verify_ecdsa(&message, &signature, &authentication_key)
+                .unwrap();
+        }
+
+        let authentication_key = emulator
+            .perform(wca::commands::GetAuthenticationKeyV2::new())
+            .unwrap();
+        let signature = emulator
+            .perform(wca::commands::SignChallengeV2::new(challenge.to_vec()))
+            .unwrap();
+        ring::signature::UnparsedPublicKey::new(
+            &ring::signature::ED25519,
+            &authentication_key.material,
+        )
+        .verify(
+            &sha256::Hash::hash(challenge).to_byte_array(),
+            &signature.signature,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_spending_derive() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+
+        let source = emulator
+            .perform(wca::commands::GetInitialSpendingKey::new(Signet))
+            .unwrap();
+        let destination = emulator
+            .perform(wca::commands::GetNextSpendingKey::new(
+                vec![source.clone()],
+                Signet,
+            ))
+            .unwrap();
+        assert_ne!(source, destination);
+        assert_eq!(
+            source.master_fingerprint(),
+            destination.master_fingerprint()
+        );
+
+        let source_wallet = get_funded_wallet(&source);
+        let destination_wallet = get_funded_wallet(&destination);
+        for async_sign in [false, true] {
+            let unsigned = normal_transaction(&source_wallet, &destination_wallet, 5000);
+            let mut signed = emulator
+                .perform(wca::commands::SignTransaction::new(
+                    unsigned,
+                    source.master_fingerprint(),
+                    async_sign,
+                ))
+                .unwrap();
+            assert!(is_finalized(&signed));
+            assert!(source_wallet
+                .finalize_psbt(&mut signed, Default::default())
+                .unwrap());
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_spending_summary() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+
+        let source = emulator
+            .perform(wca::commands::GetInitialSpendingKey::new(Signet))
+            .unwrap();
+        let destination = emulator
+            .perform(wca::commands::GetNextSpendingKey::new(
+                vec![source.clone()],
+                Signet,
+            ))
+            .unwrap();
+
+        let source_wallet = get_funded_wallet(&source);
+        let destination_wallet = get_funded_wallet(&destination);
+        let unsigned = normal_transaction(&source_wallet, &destination_wallet, 5000);
+        let summary = TransactionSummaryBuilder::new(source.master_fingerprint())
+            .descriptor(
+                source_wallet
+                    .get_descriptor_for_keychain(KeychainKind::External)
+                    .clone(),
+            )
+            .network(bitcoin::Network::Signet);
+        let signed = emulator
+            .perform(wca::commands::SignTransactionWithSummary::new(
+                unsigned, summary, false,
+            ))
+            .unwrap();
+
+        assert!(is_finalized(&signed.psbt));
+        assert!(signed.summary.anomalies.is_empty());
+        assert_eq!(signed.summary.spend_amount(), Amount::from_sat(5000));
+        assert_eq!(
+            signed.summary.input_value,
+            signed.summary.spend_amount() + signed.summary.change_amount() + signed.summary.fee
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_firmware_update() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        let before = emulator
+            .perform(wca::commands::GetFirmwareMetadata::new())
+            .unwrap();
+        assert!(matches!(before.active_slot, FirmwareSlot::A));
+
+        let image = (0..4096u32).map(|i| i as u8).collect::<Vec<_>>();
+        let signature_offset = 600 * 1024;
+        let upload = |signature: &[u8]| {
+            assert!(emulator
+                .perform(wca::commands::FwupStart::new(None, FwupMode::Normal))
+                .unwrap());
+            for (sequence_id, chunk) in image.chunks(452).enumerate() {
+                assert!(emulator
+                    .perform(wca::commands::FwupTransfer::new(
+                        sequence_id as u32,
+                        chunk.to_vec(),
+                        0,
+                        FwupMode::Normal,
+                    ))
+                    .unwrap());
+            }
+            if !signature.is_empty() {
+                assert!(emulator
+                    .perform(wca::commands::FwupTransfer::new(
+                        0,
+                        signature.to_vec(),
+                        signature_offset,
+                        FwupMode::Normal,
+                    ))
+                    .unwrap());
+            }
+            emulator
+                .perform(wca::commands::FwupFinish::new(
+                    0x200,
+                    signature_offset,
+                    FwupMode::Normal,
+                ))
+                .unwrap()
+        };
+
+        assert_eq!(upload(&[]), FwupFinishRspStatus::SignatureInvalid);
+        assert_eq!(upload(&[0x5a; 64]), FwupFinishRspStatus::Success);
+
+        let after = emulator
+            .perform(wca::commands::GetFirmwareMetadata::new())
+            .unwrap();
+        assert!(matches!(after.active_slot, FirmwareSlot::B));
+        assert_ne!(before.version, after.version);
+        assert_ne!(before.hash, after.hash);
+    }
+
+    struct SignedBundle {
+        root: picocert::Certificate,
+        zip: Vec<u8>,
+    }
+
+    // A normal update bundle, with its manifest signed by a three-tier picocert chain. `tamper`
+    // edits the files after their digests are pinned.
+    fn signed_bundle(tamper: impl FnOnce(&mut Vec<(&str, Vec<u8>)>)) -> SignedBundle {
+        use std::io::Write;
+
+        use picocert::KeyUsage;
+
+        let now = picocert::current_time();
+        let root = picocert::issue_v2(
+            None,
+            "root".into(),
+            now - 60,
+            now + 3600,
+            KeyUsage::CERT_SIGNING,
+        )
+        .unwrap();
+        let intermediate = picocert::issue_v2(
+            Some(&root),
+            "intermediate".into(),
+            now - 60,
+            now + 3600,
+            KeyUsage::CERT_SIGNING,
+        )
+        .unwrap();
+        let leaf = picocert::issue_v2(
+            Some(&intermediate),
+            "fwup".into(),
+            now - 60,
+            now + 3600,
+            KeyUsage::FIRMWARE_SIGNING,
+        )
+        .unwrap();
+
+        let image = (0..4096u32).map(|i| i as u8).collect::<Vec<_>>();
+        let mut files = vec![
+            ("app-a.bin", image.clone()),
+            ("app-a.sig", vec![0x5a; 64]),
+            ("app-b.bin", image),
+            ("app-b.sig", vec![0x5b; 64]),
+        ];
+        let digest = |name: &str| {
+            let (_, data) = files.iter().find(|(n, _)| *n == name).unwrap();
+            sha256::Hash::hash(data).to_string()
+        };
+        let file = |name: &str| serde_json::json!({ "name": name, "sha256": digest(name) });
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "manifest_version": "0.0.1",
+            "fwup_bundle": {
+                "product": "w1a",
+                "version": "1.0.1",
+                "assets": {
+                    "application_a": { "image": file("app-a.bin"), "signature": file("app-a.sig") },
+                    "application_b": { "image": file("app-b.bin"), "signature": file("app-b.sig") },
+                },
+                "parameters": {
+                    "wca_chunk_size": 452,
+                    "signature_offset": 600 * 1024,
+                    "app_properties_offset": 0x200,
+                },
+                "signing_chain": [
+                    { "name": "leaf.cert" },
+                    { "name": "intermediate.cert" },
+                    { "name": "root.cert" },
+                ],
+            },
+        }))
+        .unwrap();
+        tamper(&mut files);
+
+        files.push(("leaf.cert", leaf.cert.to_bytes()));
+        files.push(("intermediate.cert", intermediate.cert.to_bytes()));
+        files.push(("root.cert", root.cert.to_bytes()));
+        files.push(("fwup-manifest.sig", leaf.sign(&manifest).unwrap()));
+        files.push(("fwup-manifest.json", manifest));
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        for (name, data) in files {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(&data).unwrap();
+        }
+
+        SignedBundle {
+            root: root.cert,
+            zip: zip.finish().unwrap().into_inner(),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_firmware_updater() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        let bundle = signed_bundle(|_| {});
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareBundle::from_zip(std::io::Cursor::new(&bundle.zip)).unwrap(),
+            &[bundle.root],
+        )
+        .unwrap();
+
+        // Lock the hardware part way through, as if it were pulled away.
+        let mut progress = vec![];
+        let result = updater.update(&emulator, |p| {
+            progress.push(p);
+            if p.chunks_sent == 3 {
+                emulator.lock();
+            }
+        });
+        assert!(matches!(
+            result,
+            Err(FirmwareUpdateError::Transactor(
+                TransactorError::CommandError(CommandError::Unauthenticated)
+            ))
+        ));
+        assert_eq!(updater.next_sequence_id(), Some(3));
+
+        // The update picks up where it left off once unlocked.
+        assert!(emulator.unlock(0));
+        assert_eq!(
+            updater.update(&emulator, |p| progress.push(p)).unwrap(),
+            FwupFinishRspStatus::Success
+        );
+        assert_eq!(updater.next_sequence_id(), None);
+        assert_eq!(
+            progress.iter().map(|p| p.chunks_sent).collect::<Vec<_>>(),
+            (1..=10).collect::<Vec<_>>()
+        );
+        assert!(progress.iter().all(|p| p.total_chunks == 10));
+
+        let after = emulator
+            .perform(wca::commands::GetFirmwareMetadata::new())
+            .unwrap();
+        assert_eq!(after.active_slot, FirmwareSlot::B);
+    }
+
+    #[test]
+    #[serial]
+    fn test_firmware_updater_restarts_after_reset() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        let bundle = signed_bundle(|_| {});
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareBundle::from_zip(std::io::Cursor::new(&bundle.zip)).unwrap(),
+            &[bundle.root],
+        )
+        .unwrap();
+
+        let mut progress = vec![];
+        let result = updater.update(&emulator, |p| {
+            progress.push(p.chunks_sent);
+            if p.chunks_sent == 3 {
+                emulator.power_cycle();
+            }
+        });
+        assert!(matches!(
+            result,
+            Err(FirmwareUpdateError::Transactor(
+                TransactorError::CommandError(CommandError::Unauthenticated)
+            ))
+        ));
+        assert_eq!(updater.next_sequence_id(), Some(3));
+
+        // The hardware lost the update when it reset, so it's started over.
+        assert!(emulator.unlock(0));
+        assert_eq!(
+            updater
+                .update(&emulator, |p| progress.push(p.chunks_sent))
+                .unwrap(),
+            FwupFinishRspStatus::Success
+        );
+        assert_eq!(progress, (1..=3).chain(1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_firmware_bundle_verification() {
+        let bundle = signed_bundle(|_| {});
+        let parse = |zip: &[u8]| FirmwareBundle::from_zip(std::io::Cursor::new(zip)).unwrap();
+
+        assert!(parse(&bundle.zip).verify(&[bundle.root.clone()]).is_ok());
+
+        let other = signed_bundle(|_| {});
+        assert!(matches!(
+            parse(&bundle.zip).verify(&[other.root]),
+            Err(FirmwareUpdateError::UntrustedRoot)
+        ));
+        assert!(matches!(
+            parse(&bundle.zip).verify(&[]),
+            Err(FirmwareUpdateError::UntrustedRoot)
+        ));
+
+        let tampered = signed_bundle(|files| files[2].1[0] ^= 1);
+        assert!(matches!(
+            parse(&tampered.zip).verify(&[tampered.root]),
+            Err(FirmwareUpdateError::DigestMismatch(name)) if name == "app-b.bin"
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_seal_unseal() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        let csek = [0xab; 32];
+
+        let sealed = emulator.perform(wca::commands::SealKey::new(csek)).unwrap();
+        assert_eq!(
+            emulator
+                .perform(wca::commands::UnsealKey::new(sealed.clone()))
+                .unwrap(),
+            csek
+        );
+
+        assert!(emulator.perform(wca::commands::WipeState::new()).unwrap());
+        assert!(emulator
+            .perform(wca::commands::UnsealKey::new(sealed))
+            .is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_diagnostics() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        let coredump = (0..1000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        emulator.push_coredump(coredump.clone());
+        emulator.push_events(&[0x11; 600]);
+
+        assert_eq!(
+            emulator
+                .perform(wca::commands::GetCoredumpCount::new())
+                .unwrap(),
+            1
+        );
+        let mut collected = vec![];
+        loop {
+            let fragment = emulator
+                .perform(wca::commands::GetCoredumpFragment::new(
+                    collected.len() as u32
+                ))
+                .unwrap();
+            collected.extend(fragment.data);
+            assert_eq!(fragment.offset as usize, collected.len());
+            if fragment.complete {
+                assert_eq!(fragment.coredumps_remaining, 0);
+                break;
+            }
+        }
+        assert_eq!(collected, coredump);
+        assert_eq!(
+            emulator
+                .perform(wca::commands::GetCoredumpCount::new())
+                .unwrap(),
+            0
+        );
+
+        let first = emulator.perform(wca::commands::GetEvents::new()).unwrap();
+        assert_eq!(first.fragment.len(), 451);
+        assert_eq!(first.remaining_size, 149);
+        let second = emulator.perform(wca::commands::GetEvents::new()).unwrap();
+        assert_eq!(second.fragment.len(), 149);
+        assert_eq!(second.remaining_size, 0);
+    }
+
+    #[test]
+    #[serial]
+    fn test_diagnostics_collector() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        let first = (0..1000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let second = vec![0xcd; 452];
+        emulator.push_coredump(first.clone());
+        emulator.push_coredump(second.clone());
+        // Three bitlog events, repeated to span several event fragments.
+        let bitlogs =
+            hex::decode("2da70100c8d6680401460400000100c9d6680401460400000100cad66804014604")
+                .unwrap()
+                .repeat(50);
+        emulator.push_events(&bitlogs);
+
+        let path = std::env::temp_dir().join("wca-test-diagnostics.json");
+        let bundle = DiagnosticsCollector::new(&emulator)
+            .collect_to(&path)
+            .unwrap();
+        assert_eq!(bundle.coredumps, vec![second, first]);
+        assert_eq!(bundle.bitlogs, bitlogs);
+        assert_eq!(bundle.events.len(), 150);
+        assert_eq!(bundle.translation_error, None);
+        assert!(!bundle.incomplete);
+        assert_eq!(DiagnosticsBundle::read_from(&path).unwrap(), bundle);
+
+        // Everything has been drained from the hardware.
+        let again = DiagnosticsCollector::new(&emulator)
+            .collect_to(&path)
+            .unwrap();
+        assert!(again.is_empty());
+        assert_eq!(again.device, bundle.device);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Fails every transmit after the first `remaining`, as if the card were pulled away.
+    struct InterruptedTransactor<'a> {
+        inner: &'a EmulatedTransactor,
+        remaining: AtomicUsize,
+    }
+
+    impl Transactor for InterruptedTransactor<'_> {
+        fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+            match self
+                .remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            {
+                Ok(_) => self.inner.transmit(buffer),
+                Err(_) => Err(pcsc::Error::RemovedCard),
+            }
+        }
+
+        fn reset(&mut self) -> Result<(), pcsc::Error> {
+            Ok(())
+        }
+
+        fn session(&self) -> ApduSession {
+            self.inner.session()
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_diagnostics_collector_interrupted() {
+        let path = std::env::temp_dir().join("wca-test-diagnostics-interrupted.json");
+        let coredumps = vec![vec![0xab; 1000], vec![0xcd; 452]];
+        let bitlogs = vec![0x11; 600];
+
+        // Interrupt collection after every possible number of transmits: nothing read off the
+        // hardware is ever lost.
+        for limit in 0.. {
+            let _ = std::fs::remove_file(&path);
+            let emulator = EmulatedTransactor::onboarded(SEED);
+            coredumps
+                .iter()
+                .for_each(|coredump| emulator.push_coredump(coredump.clone()));
+            emulator.push_events(&bitlogs);
+
+            let transactor = InterruptedTransactor {
+                inner: &emulator,
+                remaining: AtomicUsize::new(limit),
+            };
+            if let Ok(bundle) = DiagnosticsCollector::new(&transactor).collect_to(&path) {
+                assert_eq!(bundle.bitlogs, bitlogs);
+                assert_eq!(DiagnosticsBundle::read_from(&path).unwrap(), bundle);
+                break;
+            }
+
+            let bundle = DiagnosticsBundle::read_from(&path).ok();
+            let persisted = bundle.as_ref().map_or(0, |bundle| bundle.coredumps.len());
+            let remaining = emulator
+                .perform(wca::commands::GetCoredumpCount::new())
+                .unwrap();
+            assert_eq!(persisted + remaining as usize, coredumps.len());
+            if let Some(bundle) = bundle {
+                assert!(bundle.incomplete);
+                // Coredumps are read newest first.
+                assert!(coredumps.iter().rev().take(persisted).eq(&bundle.coredumps));
+                assert!(bitlogs.starts_with(&bundle.bitlogs));
+            }
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_small_apdus() {
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        emulator.set_max_response_size(64);
+        assert_eq!(emulator.negotiate_max_apdu_size(128), 128);
+
+        // A full firmware chunk is split across PROTO_CONT commands
+        assert!(emulator
+            .perform(wca::commands::FwupStart::new(None, FwupMode::Normal))
+            .unwrap());
+        assert!(emulator
+            .perform(wca::commands::FwupTransfer::new(
+                0,
+                vec![0xa5; 452],
+                0,
+                FwupMode::Normal,
+            ))
+            .unwrap());
+
+        // and an events fragment is collected with GET_RESPONSE.
+        emulator.push_events(&[0x11; 600]);
+        let events = emulator.perform(wca::commands::GetEvents::new()).unwrap();
+        assert_eq!(events.fragment, vec![0x11; 451]);
+
+        assert_eq!(emulator.negotiate_max_apdu_size(u32::MAX), 512);
+    }
+
+    #[test]
+    #[serial]
+    fn test_large_command_yields_fragments() {
+        use wca::command_interface::{Command, State};
+
+        let emulator = EmulatedTransactor::onboarded(SEED);
+        assert!(emulator
+            .perform(wca::commands::FwupStart::new(None, FwupMode::Normal))
+            .unwrap());
+
+        // The mobile apps send every APDU a command yields straight to the reader, so none may
+        // be larger than the firmware's buffer.
+        let command = wca::commands::FwupTransfer::new(0, vec![0xa5; 1024], 0, FwupMode::Normal);
+        let mut response = vec![];
+        let mut sent = 0;
+        let transferred = loop {
+            match command.next(response).unwrap() {
+                State::Data { response: apdu } => {
+                    assert!(apdu.len() <= 512);
+                    sent += 1;
+                    response = emulator.transmit(&apdu).unwrap();
+                }
+                State::Result { value } => break value,
+            }
+        };
+        assert!(transferred);
+        assert_eq!(sent, 3);
+
+        // A smaller session splits the PROTO_CONT fragments further.
+        assert_eq!(emulator.negotiate_max_apdu_size(128), 128);
+        assert!(emulator
+            .perform(wca::commands::FwupTransfer::new(
+                1,
+                vec![0x5a; 1024],
+                0,
+                FwupMode::Normal,
+            ))
+            .unwrap());
+    }
+}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use wca::{diagnostics::DiagnosticsCollector, pcsc::PCSCTransactor};
+
+pub(crate) fn diagnostics(output: PathBuf) -> Result<()> {
+    let transactor = PCSCTransactor::new()?;
+    let bundle = DiagnosticsCollector::new(&transactor)
+        .collect_to(&output)
+        .with_context(|| {
+            format!(
+                "Diagnostics collection failed; anything already read is saved in {}",
+                output.display()
+            )
+        })?;
+
+    println!(
+        "Collected {} coredump(s) and {} telemetry event(s) from {}",
+        bundle.coredumps.len(),
+        bundle.events.len(),
+        bundle.device.serial,
+    );
+    if let Some(err) = &bundle.translation_error {
+        println!("Could not translate telemetry ({err}); keeping the raw bitlogs");
+    }
+    println!("Wrote {}", output.display());
+
+    Ok(())
+}
@@ -40,7 +40,14 @@ pub fn end_to_end(
     let treasury_address = fund_wallet_from_treasury(client, &db, &blockchain, treasury_root_key)?;
 
     info!("spending coins via server-spend");
-    commands::wallet::server_send(client, &db, blockchain, treasury_address, 9500)?;
+    commands::wallet::server_send(
+        client,
+        &db,
+        blockchain,
+        treasury_address,
+        9500,
+        commands::wallet::FeeOptions::min_relay_fee(),
+    )?;
 
     Ok(())
 }
@@ -1,77 +1,31 @@
-use std::{
-    fs::File,
-    io::{Cursor, Read, Seek},
-};
+use std::{fs::File, io::Cursor, path::PathBuf, thread::sleep, time::Duration};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
+use indicatif::ProgressBar;
+use picocert::Certificate;
 use rustify::blocking::clients::reqwest::Client;
-use serde::Deserialize;
 
-use wca::{commands::FirmwareSlot, pcsc::PCSCTransactor};
-use zip::ZipArchive;
+use wca::{
+    commands::FwupFinishRspStatus,
+    errors::CommandError,
+    firmware_update::{production_roots, FirmwareBundle, FirmwareUpdateError, FirmwareUpdater},
+    pcsc::{PCSCTransactor, Transactor, TransactorError},
+};
 
-use crate::nfc::{NFCTransactions, Upload};
+use crate::nfc::NFCTransactions;
 
 pub(crate) fn metadata() -> Result<()> {
     println!("{:?}", PCSCTransactor::new()?.metadata()?);
     Ok(())
 }
 
-const MANIFEST_FILE: &str = "fwup-manifest.json";
 const MEMFAULT_PROJECT_KEY: &str = "cuMF7SryHhQQcs2gcuEaHqDWV0Z43ha4";
 
-#[derive(Debug, Deserialize)]
-struct Manifest {
-    manifest_version: String,
-    fwup_bundle: FwupBundle,
-}
-
-#[derive(Debug, Deserialize)]
-struct FwupBundle {
-    product: Product,
-    version: String,
-    assets: Assets,
-    parameters: Parameters,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "lowercase")]
-enum Product {
-    W1A,
-}
-
-#[derive(Debug, Deserialize)]
-struct Assets {
-    bootloader: Asset,
-    application_a: Asset,
-    application_b: Asset,
-}
-
-#[derive(Debug, Deserialize)]
-struct Asset {
-    image: FileReference,
-    signature: FileReference,
-}
-
-#[derive(Debug, Deserialize)]
-struct FileReference {
-    name: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct Parameters {
-    wca_chunk_size: usize,
-    signature_offset: u32,
-    app_properties_offset: u32,
-}
-
-fn read_from_zip<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>> {
-    let mut buf = Vec::new();
-    zip.by_name(name)?.read_to_end(&mut buf)?;
-    Ok(buf)
-}
-
-pub(crate) fn upload_latest(client: &Client) -> Result<()> {
+pub(crate) fn upload_latest(
+    client: &Client,
+    trusted_root: Option<PathBuf>,
+    insecure_skip_verify: bool,
+) -> Result<()> {
     let transactor = PCSCTransactor::new()?;
 
     let device_info = transactor.device_info()?;
@@ -101,65 +55,107 @@ pub(crate) fn upload_latest(client: &Client) -> Result<()> {
 
     let firmware = client.http.get(firmware_url).send()?.bytes()?;
 
-    upload(transactor, ZipArchive::new(Cursor::new(&firmware))?)?;
-
-    Ok(())
+    upload(
+        transactor,
+        FirmwareBundle::from_zip(Cursor::new(&firmware))?,
+        trusted_root,
+        insecure_skip_verify,
+    )
 }
 
-pub(crate) fn upload_bundle(bundle: std::path::PathBuf) -> Result<()> {
+pub(crate) fn upload_bundle(
+    bundle: PathBuf,
+    trusted_root: Option<PathBuf>,
+    insecure_skip_verify: bool,
+) -> Result<()> {
     upload(
         PCSCTransactor::new()?,
-        ZipArchive::new(File::open(bundle)?)?,
+        FirmwareBundle::from_zip(File::open(bundle)?)?,
+        trusted_root,
+        insecure_skip_verify,
     )
 }
 
-fn upload<R: Read + Seek>(mut transactor: PCSCTransactor, mut zip: ZipArchive<R>) -> Result<()> {
-    let manifest: Manifest = serde_json::from_reader(zip.by_name(MANIFEST_FILE)?)?;
-
-    let target_slot = match transactor.metadata()?.active_slot {
-        FirmwareSlot::A => FirmwareSlot::B,
-        FirmwareSlot::B => FirmwareSlot::A,
-    };
-    let application = match target_slot {
-        FirmwareSlot::A => manifest.fwup_bundle.assets.application_a,
-        FirmwareSlot::B => manifest.fwup_bundle.assets.application_b,
+fn upload(
+    mut transactor: PCSCTransactor,
+    bundle: FirmwareBundle,
+    trusted_root: Option<PathBuf>,
+    insecure_skip_verify: bool,
+) -> Result<()> {
+    let mut updater = if insecure_skip_verify {
+        println!("Not verifying the bundle's signature; relying on the hardware's own check.");
+        FirmwareUpdater::new_insecure_skip_verify(bundle)
+    } else {
+        let roots = match trusted_root {
+            Some(path) => vec![Certificate::from_file(&path.to_string_lossy())
+                .map_err(|err| anyhow!("Could not read trusted root: {err:?}"))?],
+            None => production_roots()?,
+        };
+        if roots.is_empty() {
+            bail!("This build has no production firmware signing root; pass --trusted-root, or --insecure-skip-verify for development bundles");
+        }
+        FirmwareUpdater::new(bundle, &roots)?
     };
 
-    let upload = Upload {
-        chunk_size: manifest.fwup_bundle.parameters.wca_chunk_size,
-        app_properties_offset: manifest.fwup_bundle.parameters.app_properties_offset,
-        application: crate::nfc::Asset {
-            data: read_from_zip(&mut zip, &application.image.name)?,
-            offset: 0,
-        },
-        signature: crate::nfc::Asset {
-            data: read_from_zip(&mut zip, &application.signature.name)?,
-            offset: manifest.fwup_bundle.parameters.signature_offset,
-        },
+    println!("Uploading {}...", updater.bundle().version());
+    let bar = ProgressBar::new(0);
+    let status = loop {
+        let result = updater.update(&transactor, |progress| {
+            bar.set_length(progress.total_chunks.into());
+            bar.set_position(progress.chunks_sent.into());
+        });
+
+        match result {
+            Err(FirmwareUpdateError::Transactor(TransactorError::CommandError(
+                CommandError::Unauthenticated,
+            ))) => {
+                bar.println("Please unlock your hardware...");
+
+                while !transactor.is_authenticated().is_ok_and(|x| x) {
+                    sleep(Duration::from_secs(1));
+
+                    match transactor.reset() {
+                        Ok(_) | Err(pcsc::Error::NoSmartcard) => continue,
+                        Err(err) => {
+                            bar.abandon_with_message("Giving up due to an error");
+                            return Err(err.into());
+                        }
+                    }
+                }
+            }
+            Err(FirmwareUpdateError::Finish(status)) => {
+                bar.abandon();
+                break status;
+            }
+            Err(err) => {
+                bar.abandon();
+                return Err(err.into());
+            }
+            Ok(status) => {
+                bar.finish_and_clear();
+                break status;
+            }
+        }
     };
 
-    println!(
-        "Uploading {version} to slot {target_slot:?}...",
-        version = manifest.fwup_bundle.version
-    );
-    match transactor.upload(&upload)? {
-        wca::commands::FwupFinishRspStatus::Unspecified => {
+    match status {
+        FwupFinishRspStatus::Unspecified => {
             println!("Upload failed due to an unspecified error. :-(")
         }
-        wca::commands::FwupFinishRspStatus::Success => println!("Upload successful!"),
-        wca::commands::FwupFinishRspStatus::SignatureInvalid => {
+        FwupFinishRspStatus::Success => println!("Upload successful!"),
+        FwupFinishRspStatus::SignatureInvalid => {
             println!("Upload failed due to an invalid signature. :-(")
         }
-        wca::commands::FwupFinishRspStatus::VersionInvalid => {
+        FwupFinishRspStatus::VersionInvalid => {
             println!("Upload failed due to an invalid version. :-(")
         }
-        wca::commands::FwupFinishRspStatus::WillApplyPatch => {
+        FwupFinishRspStatus::WillApplyPatch => {
             println!("Patch uploaded. Waiting for hardware to apply patch...")
         }
-        wca::commands::FwupFinishRspStatus::Unauthenticated => {
+        FwupFinishRspStatus::Unauthenticated => {
             println!("Unauthenticated. Please unlock your hardware.")
         }
-        wca::commands::FwupFinishRspStatus::Error => println!("Upload failed due to an error. :-("),
+        FwupFinishRspStatus::Error => println!("Upload failed due to an error. :-("),
     };
 
     Ok(())
@@ -2,8 +2,10 @@ pub(crate) use wipe::wipe;
 
 pub(crate) mod account;
 pub mod check_keyproofs;
+pub mod diagnostics;
 pub mod end_to_end;
 pub mod firmware;
 pub mod pair;
+pub mod psbt;
 pub mod wallet;
 mod wipe;
@@ -1,8 +1,14 @@
 use std::{thread::sleep, time::Duration};
 
 use anyhow::Result;
-use bdk::bitcoin::Network;
-use wca::pcsc::{PCSCTransactor, Transactor, TransactorError};
+use bdk::bitcoin::{
+    secp256k1::rand::{thread_rng, Rng},
+    Network,
+};
+use wca::{
+    emulator::EmulatedTransactor,
+    pcsc::{PCSCTransactor, Transactor, TransactorError},
+};
 
 use crate::{
     db::transactions::{FromDatabase, ToDatabase},
@@ -16,7 +22,11 @@ pub(crate) fn pair(db: &sled::Db, network: Network, use_fake_hardware: bool) ->
         network,
         application: SeedSigner::new(network, 0),
         hardware: if use_fake_hardware {
-            HardwareSignerProxy::Fake(SeedSigner::new(network, 0))
+            let seed = thread_rng().gen();
+            HardwareSignerProxy::Emulated {
+                seed,
+                signer: pair_real(network, &mut EmulatedTransactor::onboarded(seed))?,
+            }
         } else {
             HardwareSignerProxy::Real(pair_real(network, &mut PCSCTransactor::new()?)?)
         },
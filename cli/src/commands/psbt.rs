@@ -0,0 +1,222 @@
+use std::{fs, path::Path, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use bdk::{
+    bitcoin::{psbt::PartiallySignedTransaction, secp256k1::Secp256k1, Address},
+    blockchain::{log_progress, Blockchain, ElectrumBlockchain},
+    KeychainKind, SignOptions, SyncOptions,
+};
+use clap::ValueEnum;
+use qrcode::{render::unicode, QrCode};
+use rustify::blocking::clients::reqwest::Client;
+use sled::Db;
+use wca::{
+    pcsc::NullTransactor,
+    signing::summary::{OutputKind, TransactionSummaryBuilder},
+};
+
+use crate::{
+    cache::FromCache,
+    commands::wallet::{psbt_from, FeeOptions},
+    db::transactions::FromDatabase,
+    entities::{Account, AuthenticationToken, SignerHistory},
+    nfc::SafeTransactor,
+    requests::{helper::EndpointExt, SignTransactionRequest},
+    signers::Spending,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub(crate) enum Factor {
+    /// The app key, from the local seed
+    App,
+    /// The paired hardware
+    Hardware,
+    /// The server (needs the app's signature first)
+    Server,
+}
+
+pub(crate) fn create(
+    client: &Client,
+    db: &Db,
+    blockchain: ElectrumBlockchain,
+    recipient: Address,
+    amount: Option<u64>,
+    fees: FeeOptions,
+) -> Result<PartiallySignedTransaction> {
+    let account = Account::from_cache(client, db)?;
+    let wallet = SignerHistory::from_database(db)?
+        .active
+        .wallet(&account, db, None)?;
+
+    wallet.sync(
+        &blockchain,
+        SyncOptions {
+            progress: Some(Box::new(log_progress())),
+        },
+    )?;
+
+    let fee_rate = fees.fee_rate(&blockchain)?;
+    let psbt = match amount {
+        Some(amount) => psbt_from(&wallet, recipient, amount, fee_rate)?,
+        None => {
+            let mut builder = wallet.build_tx();
+            builder
+                .drain_wallet()
+                .drain_to(recipient.script_pubkey())
+                .fee_rate(fee_rate)
+                .enable_rbf();
+            builder.finish()?.0
+        }
+    };
+
+    Ok(psbt)
+}
+
+pub(crate) fn export(psbt: &PartiallySignedTransaction, output: &Path, qr: bool) -> Result<()> {
+    write_psbt(output, psbt)?;
+    println!("Wrote {}", output.display());
+
+    if qr {
+        let image = QrCode::new(psbt.to_string())
+            .context("PSBT is too large for a QR code")?
+            .render::<unicode::Dense1x2>()
+            .dark_color(unicode::Dense1x2::Light)
+            .light_color(unicode::Dense1x2::Dark)
+            .build();
+        println!("{image}");
+    }
+
+    Ok(())
+}
+
+pub(crate) fn sign(client: &Client, db: &Db, input: &Path, factor: Factor) -> Result<()> {
+    let mut psbt = read_psbt(input)?;
+    let signers = SignerHistory::from_database(db)?;
+
+    match factor {
+        Factor::App => {
+            let signer = signers
+                .active
+                .application
+                .signer(&SafeTransactor::new(NullTransactor));
+            signer.sign_transaction(&mut psbt, &SignOptions::default(), &Secp256k1::new())?;
+        }
+        Factor::Hardware => {
+            let context = signers.active.hardware.sign_context()?;
+            let signer = signers.active.hardware.signer(&context);
+            signer.sign_transaction(&mut psbt, &SignOptions::default(), &Secp256k1::new())?;
+        }
+        Factor::Server => {
+            let account = Account::from_cache(client, db)?;
+            let response = SignTransactionRequest {
+                account_id: account.id,
+                psbt: psbt.clone(),
+                settings: Default::default(),
+            }
+            .exec_authenticated(client, &AuthenticationToken::from_database(db)?)?;
+
+            // Don't trust the server too much!
+            psbt.combine(response.tx).context("psbt combine error")?;
+        }
+    }
+
+    write_psbt(input, &psbt)?;
+    println!("Signed {}", input.display());
+
+    Ok(())
+}
+
+pub(crate) fn combine(inputs: &[impl AsRef<Path>], output: &Path) -> Result<()> {
+    let Some((first, rest)) = inputs.split_first() else {
+        bail!("nothing to combine");
+    };
+
+    let mut psbt = read_psbt(first.as_ref())?;
+    for input in rest {
+        psbt.combine(read_psbt(input.as_ref())?)
+            .with_context(|| format!("could not combine {}", input.as_ref().display()))?;
+    }
+
+    write_psbt(output, &psbt)?;
+    println!("Wrote {}", output.display());
+
+    Ok(())
+}
+
+pub(crate) fn inspect(client: &Client, db: &Db, input: &Path) -> Result<()> {
+    let psbt = read_psbt(input)?;
+    let account = Account::from_cache(client, db)?;
+    let signers = SignerHistory::from_database(db)?;
+    let wallet = signers.active.wallet(&account, db, None)?;
+
+    let summary = TransactionSummaryBuilder::new(
+        signers.active.application.public_key().master_fingerprint(),
+    )
+    .descriptor(
+        wallet
+            .get_descriptor_for_keychain(KeychainKind::External)
+            .clone(),
+    )
+    .descriptor(
+        wallet
+            .get_descriptor_for_keychain(KeychainKind::Internal)
+            .clone(),
+    )
+    .network(signers.active.network)
+    .build(&psbt)?;
+
+    println!("txid: {}", psbt.unsigned_tx.txid());
+    for output in &summary.outputs {
+        let destination = match &output.address {
+            Some(address) => address.to_string(),
+            None => output.script_pubkey.to_string(),
+        };
+        match output.kind {
+            OutputKind::External => println!("send: {} to {destination}", output.value),
+            OutputKind::Change { .. } => println!("change: {} to {destination}", output.value),
+        }
+    }
+    println!("fee: {} ({:.1} sat/vB)", summary.fee, summary.fee_rate);
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        println!("input {index}: {} signature(s)", input.partial_sigs.len());
+    }
+    for anomaly in &summary.anomalies {
+        println!("WARNING: {anomaly:?}");
+    }
+
+    Ok(())
+}
+
+pub(crate) fn broadcast(
+    client: &Client,
+    db: &Db,
+    blockchain: ElectrumBlockchain,
+    input: &Path,
+) -> Result<()> {
+    let mut psbt = read_psbt(input)?;
+    let account = Account::from_cache(client, db)?;
+    let wallet = SignerHistory::from_database(db)?
+        .active
+        .wallet(&account, db, None)?;
+
+    if !wallet.finalize_psbt(&mut psbt, SignOptions::default())? {
+        bail!("transaction isn't fully signed yet");
+    }
+
+    let transaction = psbt.extract_tx();
+    blockchain.broadcast(&transaction)?;
+    println!("{}", transaction.txid());
+
+    Ok(())
+}
+
+fn read_psbt(path: &Path) -> Result<PartiallySignedTransaction> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    Ok(PartiallySignedTransaction::from_str(contents.trim())?)
+}
+
+fn write_psbt(path: &Path, psbt: &PartiallySignedTransaction) -> Result<()> {
+    fs::write(path, format!("{psbt}\n"))
+        .with_context(|| format!("could not write {}", path.display()))
+}
@@ -0,0 +1,141 @@
+use anyhow::{bail, Context, Result};
+use bdk::{
+    bitcoin::{psbt::PartiallySignedTransaction, Txid},
+    blockchain::{log_progress, Blockchain, ElectrumBlockchain},
+    database::BatchDatabase,
+    wallet::AddressIndex,
+    FeeRate, SyncOptions, TransactionDetails, Wallet,
+};
+use rustify::blocking::clients::reqwest::Client;
+use sled::Db;
+
+use crate::{
+    cache::FromCache,
+    commands::wallet::{hardware_sign, server_sign, FeeOptions},
+    db::transactions::FromDatabase,
+    entities::{Account, SignerHistory},
+};
+
+pub fn bump_fee(
+    client: &Client,
+    db: &Db,
+    blockchain: ElectrumBlockchain,
+    txid: Txid,
+    fees: FeeOptions,
+    server: bool,
+) -> Result<()> {
+    let account = Account::from_cache(client, db)?;
+    let signers =
+        SignerHistory::from_database(db).context("no paired signers found; please `pair` first")?;
+    let context = match server {
+        true => None,
+        false => Some(signers.active.hardware.sign_context()?),
+    };
+    let wallet = signers.active.wallet(&account, db, context.as_ref())?;
+
+    wallet.sync(
+        &blockchain,
+        SyncOptions {
+            progress: Some(Box::new(log_progress())),
+        },
+    )?;
+
+    let details = wallet
+        .get_tx(&txid, true)?
+        .with_context(|| format!("{txid} is not a wallet transaction"))?;
+    if details.confirmation_time.is_some() {
+        bail!("{txid} is already confirmed");
+    }
+    let parent = details
+        .transaction
+        .as_ref()
+        .context("transaction is missing")?;
+    let fee_rate = fees.fee_rate(&blockchain)?;
+
+    // Only a transaction we funded, and which opted in, can be replaced.
+    let psbt = if details.sent > 0 && parent.is_explicitly_rbf() {
+        println!("Replacing {txid}...");
+        replacement(&wallet, txid, fee_rate)?
+    } else {
+        println!("Spending from {txid}...");
+        child(&wallet, &details, fee_rate)?
+    };
+
+    let transaction = match server {
+        true => server_sign(client, db, &account, &wallet, psbt)?,
+        false => hardware_sign(&wallet, psbt)?,
+    };
+    blockchain.broadcast(&transaction)?;
+    println!("{}", transaction.txid());
+
+    Ok(())
+}
+
+fn replacement<D: BatchDatabase>(
+    wallet: &Wallet<D>,
+    txid: Txid,
+    fee_rate: FeeRate,
+) -> Result<PartiallySignedTransaction> {
+    let mut builder = wallet.build_fee_bump(txid)?;
+    builder.fee_rate(fee_rate).enable_rbf();
+    let (psbt, _) = builder.finish()?;
+    Ok(psbt)
+}
+
+// Spend our outputs of the parent back to ourselves, paying enough that the parent and child
+// together reach `fee_rate`.
+fn child<D: BatchDatabase>(
+    wallet: &Wallet<D>,
+    parent: &TransactionDetails,
+    fee_rate: FeeRate,
+) -> Result<PartiallySignedTransaction> {
+    let outpoints = wallet
+        .list_unspent()?
+        .into_iter()
+        .filter(|utxo| utxo.outpoint.txid == parent.txid)
+        .map(|utxo| utxo.outpoint)
+        .collect::<Vec<_>>();
+    if outpoints.is_empty() {
+        bail!("{} has no outputs for us to spend", parent.txid);
+    }
+    let drain_to = wallet
+        .get_internal_address(AddressIndex::New)?
+        .script_pubkey();
+
+    let build = |fee: Option<u64>| -> Result<(PartiallySignedTransaction, TransactionDetails)> {
+        let mut builder = wallet.build_tx();
+        builder
+            .add_utxos(&outpoints)?
+            .manually_selected_only()
+            .drain_to(drain_to.clone())
+            .enable_rbf();
+        match fee {
+            Some(fee) => builder.fee_absolute(fee),
+            None => builder.fee_rate(fee_rate),
+        };
+        Ok(builder.finish()?)
+    };
+
+    // Size the child at the target rate, then have it make up the parent's shortfall too. If the
+    // parent isn't ours, its fee is unknown, so assume it paid nothing.
+    let (_, sized) = build(None)?;
+    let parent_vsize = parent
+        .transaction
+        .as_ref()
+        .context("transaction is missing")?
+        .vsize();
+    let shortfall = fee_rate
+        .fee_vb(parent_vsize)
+        .saturating_sub(parent.fee.unwrap_or(0));
+    let fee = sized.fee.unwrap_or(0) + shortfall;
+    if parent.fee.is_none() {
+        println!(
+            "{} wasn't funded by this wallet, so its fee is unknown. Assuming it paid nothing, \
+             the child pays {fee} sat, which overpays by whatever the parent's fee was.",
+            parent.txid
+        );
+    }
+    let (psbt, _) = build(Some(fee))?;
+
+    Ok(psbt)
+}
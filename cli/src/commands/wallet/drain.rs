@@ -2,13 +2,13 @@ use anyhow::Result;
 use bdk::{
     bitcoin::Address,
     blockchain::{Blockchain, ElectrumBlockchain},
-    FeeRate,
 };
 use rustify::blocking::clients::reqwest::Client;
 use sled::Db;
 
 use crate::{
     cache::FromCache,
+    commands::wallet::{hardware_sign, FeeOptions},
     db::transactions::FromDatabase,
     entities::{Account, SignerHistory},
 };
@@ -18,6 +18,7 @@ pub fn drain(
     db: &Db,
     blockchain: ElectrumBlockchain,
     recipient: Address,
+    fees: FeeOptions,
 ) -> Result<()> {
     let account = Account::from_cache(client, db)?;
     let signers = SignerHistory::from_database(db)?;
@@ -30,14 +31,11 @@ pub fn drain(
     builder
         .drain_wallet()
         .drain_to(recipient.script_pubkey())
-        .fee_rate(FeeRate::default_min_relay_fee())
+        .fee_rate(fees.fee_rate(&blockchain)?)
         .enable_rbf();
-    let (mut psbt, _) = builder.finish()?;
+    let (psbt, _) = builder.finish()?;
 
-    let finalised = wallet.sign(&mut psbt, Default::default())?;
-    assert!(finalised, "transaction wasn't finalised?!");
-
-    let transaction = psbt.extract_tx();
+    let transaction = hardware_sign(&wallet, psbt)?;
     blockchain.broadcast(&transaction)?;
     println!("{}", transaction.txid());
 
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use bdk::{blockchain::Blockchain, FeeRate};
+use clap::Args;
+
+const DEFAULT_TARGET_BLOCKS: usize = 6;
+
+#[derive(Clone, Copy, Debug, Args)]
+pub struct FeeOptions {
+    /// Fee rate in sat/vB, at least the minimum relay fee (defaults to an estimate from the
+    /// Electrum node)
+    #[arg(long, value_parser = parse_fee_rate)]
+    fee_rate: Option<f32>,
+    /// Confirmation target in blocks, for the fee estimate
+    #[arg(long, default_value_t = DEFAULT_TARGET_BLOCKS, conflicts_with = "fee_rate")]
+    target_blocks: usize,
+}
+
+// Nodes won't relay anything paying less than the minimum, so don't let a typo strand a transaction.
+fn parse_fee_rate(s: &str) -> Result<f32, String> {
+    let sat_per_vb = s.parse::<f32>().map_err(|err| err.to_string())?;
+    let min = FeeRate::default_min_relay_fee().as_sat_per_vb();
+    if sat_per_vb.is_nan() || sat_per_vb < min {
+        return Err(format!(
+            "must be at least the minimum relay fee of {min} sat/vB"
+        ));
+    }
+    Ok(sat_per_vb)
+}
+
+impl FeeOptions {
+    /// Skip estimation, and pay the minimum relay fee.
+    pub(crate) fn min_relay_fee() -> Self {
+        Self {
+            fee_rate: Some(FeeRate::default_min_relay_fee().as_sat_per_vb()),
+            target_blocks: DEFAULT_TARGET_BLOCKS,
+        }
+    }
+
+    pub(crate) fn fee_rate(&self, blockchain: &impl Blockchain) -> Result<FeeRate> {
+        if let Some(sat_per_vb) = self.fee_rate {
+            return Ok(FeeRate::from_sat_per_vb(sat_per_vb));
+        }
+
+        // Electrum answers -1 when it has no estimate, and nothing under the minimum relays anyway.
+        let estimate = blockchain.estimate_fee(self.target_blocks)?;
+        if estimate < FeeRate::default_min_relay_fee() {
+            return Ok(FeeRate::default_min_relay_fee());
+        }
+        Ok(estimate)
+    }
+}
@@ -8,7 +8,7 @@ use sled::Db;
 
 use crate::{
     cache::FromCache,
-    commands::wallet::psbt_from,
+    commands::wallet::{hardware_sign, psbt_from, FeeOptions},
     db::transactions::FromDatabase,
     entities::{Account, SignerHistory},
 };
@@ -19,6 +19,7 @@ pub fn hardware_send(
     blockchain: ElectrumBlockchain,
     recipient: Address,
     amount: u64,
+    fees: FeeOptions,
 ) -> Result<()> {
     let account = Account::from_cache(client, db)?;
     let signers =
@@ -28,12 +29,9 @@ pub fn hardware_send(
             .active
             .wallet(&account, db, Some(&signers.active.hardware.sign_context()?))?;
 
-    let mut psbt = psbt_from(&wallet, recipient, amount)?;
+    let psbt = psbt_from(&wallet, recipient, amount, fees.fee_rate(&blockchain)?)?;
 
-    let finalised = wallet.sign(&mut psbt, Default::default())?;
-    assert!(finalised, "transaction wasn't finalised?!");
-
-    let transaction = psbt.extract_tx();
+    let transaction = hardware_sign(&wallet, psbt)?;
     blockchain.broadcast(&transaction)?;
     println!("{}", transaction.txid());
 
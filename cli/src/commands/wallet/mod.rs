@@ -1,6 +1,23 @@
+use anyhow::Context;
+use bdk::{
+    bitcoin::{psbt::PartiallySignedTransaction, Address, Transaction},
+    database::BatchDatabase,
+    FeeRate, Wallet,
+};
+use rustify::blocking::clients::reqwest::Client;
+use sled::Db;
+
+use crate::{
+    db::transactions::FromDatabase,
+    entities::{Account, AuthenticationToken},
+    requests::{helper::EndpointExt, SignTransactionRequest},
+};
+
 pub use balance::balance;
+pub use bump_fee::bump_fee;
 pub use debug::debug;
 pub use drain::drain;
+pub use fees::FeeOptions;
 pub use hardware_send::hardware_send;
 pub use receive::receive;
 pub use server_send::server_send;
@@ -11,8 +28,10 @@ pub use transactions::transactions;
 pub use utxos::utxos;
 
 mod balance;
+mod bump_fee;
 mod debug;
 mod drain;
+mod fees;
 mod hardware_send;
 mod receive;
 pub mod recovery;
@@ -23,13 +42,52 @@ mod status;
 mod transactions;
 mod utxos;
 
-pub(crate) fn psbt_from<D: bdk::database::BatchDatabase>(
-    wallet: &bdk::Wallet<D>,
-    recipient: bdk::bitcoin::Address,
+pub(crate) fn psbt_from<D: BatchDatabase>(
+    wallet: &Wallet<D>,
+    recipient: Address,
     amount: u64,
-) -> Result<bdk::bitcoin::psbt::PartiallySignedTransaction, bdk::Error> {
+    fee_rate: FeeRate,
+) -> Result<PartiallySignedTransaction, bdk::Error> {
     let mut builder = wallet.build_tx();
-    builder.add_recipient(recipient.script_pubkey(), amount);
+    builder
+        .add_recipient(recipient.script_pubkey(), amount)
+        .fee_rate(fee_rate)
+        .enable_rbf();
     let (psbt, _) = builder.finish()?;
     Ok(psbt)
 }
+
+/// Sign with the app and hardware keys, which is enough to finalise the transaction.
+pub(crate) fn hardware_sign<D: BatchDatabase>(
+    wallet: &Wallet<D>,
+    mut psbt: PartiallySignedTransaction,
+) -> anyhow::Result<Transaction> {
+    let finalised = wallet.sign(&mut psbt, Default::default())?;
+    assert!(finalised, "transaction wasn't finalised?!");
+
+    Ok(psbt.extract_tx())
+}
+
+/// Sign with the app key, and have the server co-sign.
+pub(crate) fn server_sign<D: BatchDatabase>(
+    client: &Client,
+    db: &Db,
+    account: &Account,
+    wallet: &Wallet<D>,
+    mut psbt: PartiallySignedTransaction,
+) -> anyhow::Result<Transaction> {
+    let finalised = wallet.sign(&mut psbt, Default::default())?;
+    assert!(!finalised, "transaction was finalised?!");
+
+    let response = SignTransactionRequest {
+        account_id: account.id.clone(),
+        psbt: psbt.clone(),
+        settings: Default::default(),
+    }
+    .exec_authenticated(client, &AuthenticationToken::from_database(db)?)?;
+
+    // Don't trust the server too much!
+    psbt.combine(response.tx).context("psbt combine error")?;
+
+    Ok(psbt.extract_tx())
+}
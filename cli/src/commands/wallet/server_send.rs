@@ -1,14 +1,13 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use bdk::blockchain::ElectrumBlockchain;
 use bdk::{bitcoin::Address, blockchain::Blockchain};
 use rustify::blocking::clients::reqwest::Client;
 use sled::Db;
 
 use crate::cache::FromCache;
+use crate::commands::wallet::{psbt_from, server_sign, FeeOptions};
 use crate::db::transactions::FromDatabase;
-use crate::entities::{Account, AuthenticationToken, SignerHistory};
-use crate::requests::helper::EndpointExt;
-use crate::{commands::wallet::psbt_from, requests::SignTransactionRequest};
+use crate::entities::{Account, SignerHistory};
 
 pub fn server_send(
     client: &Client,
@@ -16,26 +15,15 @@ pub fn server_send(
     blockchain: ElectrumBlockchain,
     recipient: Address,
     amount: u64,
+    fees: FeeOptions,
 ) -> Result<()> {
     let account = Account::from_cache(client, db)?;
     let signers = SignerHistory::from_database(db)?;
 
     let wallet = signers.active.wallet(&account, db, None)?;
-    let mut psbt = psbt_from(&wallet, recipient, amount)?;
-    let finalised = wallet.sign(&mut psbt, Default::default())?;
-    assert!(!finalised, "transaction was finalised?!");
+    let psbt = psbt_from(&wallet, recipient, amount, fees.fee_rate(&blockchain)?)?;
 
-    let response = SignTransactionRequest {
-        account_id: account.id,
-        psbt: psbt.clone(),
-        settings: Default::default(),
-    }
-    .exec_authenticated(client, &AuthenticationToken::from_database(db)?)?;
-
-    // Don't trust the server too much!
-    psbt.combine(response.tx).context("psbt combine error")?;
-
-    let transaction = psbt.extract_tx();
+    let transaction = server_sign(client, db, &account, &wallet, psbt)?;
     blockchain.broadcast(&transaction)?;
     println!("{}", transaction.txid());
 
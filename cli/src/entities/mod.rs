@@ -11,7 +11,10 @@ use bdk::{
     signer::TransactionSigner,
 };
 use serde::{Deserialize, Serialize};
-use wca::pcsc::{NullTransactor, PCSCTransactor, Transactor, TransactorError};
+use wca::{
+    emulator::EmulatedTransactor,
+    pcsc::{NullTransactor, PCSCTransactor, Transactor, TransactorError},
+};
 
 use crate::{
     nfc::SafeTransactor,
@@ -37,8 +40,13 @@ pub(crate) struct SignerPair {
 
 #[derive(Deserialize, Serialize, PartialEq, Eq)]
 pub(crate) enum HardwareSignerProxy {
+    // Pairings made with `--fake` before it was backed by the emulator.
     Fake(SeedSigner),
     Real(HardwareSigner),
+    Emulated {
+        seed: [u8; 32],
+        signer: HardwareSigner,
+    },
 }
 
 impl HardwareSignerProxy {
@@ -46,6 +54,9 @@ impl HardwareSignerProxy {
         match self {
             HardwareSignerProxy::Fake(_) => Ok(SafeTransactor::new(NullTransactor)),
             HardwareSignerProxy::Real(_) => Ok(SafeTransactor::new(PCSCTransactor::new()?)),
+            HardwareSignerProxy::Emulated { seed, .. } => {
+                Ok(SafeTransactor::new(EmulatedTransactor::onboarded(*seed)))
+            }
         }
     }
 }
@@ -55,6 +66,7 @@ impl Authentication for HardwareSignerProxy {
         match self {
             HardwareSignerProxy::Fake(s) => Authentication::public_key(s),
             HardwareSignerProxy::Real(s) => Authentication::public_key(s),
+            HardwareSignerProxy::Emulated { signer, .. } => Authentication::public_key(signer),
         }
     }
 
@@ -66,6 +78,9 @@ impl Authentication for HardwareSignerProxy {
         match self {
             HardwareSignerProxy::Fake(s) => Authentication::sign(s, message, context),
             HardwareSignerProxy::Real(s) => Authentication::sign(s, message, context),
+            HardwareSignerProxy::Emulated { signer, .. } => {
+                Authentication::sign(signer, message, context)
+            }
         }
     }
 }
@@ -75,6 +90,7 @@ impl Spending for HardwareSignerProxy {
         match self {
             HardwareSignerProxy::Fake(s) => Spending::public_key(s),
             HardwareSignerProxy::Real(s) => Spending::public_key(s),
+            HardwareSignerProxy::Emulated { signer, .. } => Spending::public_key(signer),
         }
     }
 
@@ -86,6 +102,10 @@ impl Spending for HardwareSignerProxy {
         let proxy = match self {
             HardwareSignerProxy::Fake(s) => Self::Fake(Spending::next(s, seen, context)?),
             HardwareSignerProxy::Real(s) => Self::Real(Spending::next(s, seen, context)?),
+            HardwareSignerProxy::Emulated { seed, signer } => Self::Emulated {
+                seed: *seed,
+                signer: Spending::next(signer, seen, context)?,
+            },
         };
         Ok(proxy)
     }
@@ -94,6 +114,7 @@ impl Spending for HardwareSignerProxy {
         match self {
             HardwareSignerProxy::Fake(s) => Spending::signer(s, context),
             HardwareSignerProxy::Real(s) => Spending::signer(s, context),
+            HardwareSignerProxy::Emulated { signer, .. } => Spending::signer(signer, context),
         }
     }
 }
@@ -14,11 +14,12 @@ use std::path::PathBuf;
 use anyhow::Result;
 use bdk::bitcoin::{address::NetworkUnchecked, network::constants::Network};
 
-use bdk::bitcoin::Address;
+use bdk::bitcoin::{Address, Txid};
 use bdk::blockchain::ElectrumBlockchain;
 use bdk::electrum_client::Client as ElectrumClient;
 
 use clap::{Parser, Subcommand, ValueEnum};
+use commands::{psbt::Factor, wallet::FeeOptions};
 use rustify::blocking::clients::reqwest::Client;
 use tracing_subscriber::{prelude::*, EnvFilter, Registry};
 
@@ -49,7 +50,7 @@ enum Commands {
         #[clap(short, long, default_value_t = Network::Signet)]
         network: Network,
 
-        /// Fake the hardware key (does NOT talk to the hardware)
+        /// Pair with an emulated hardware wallet (does NOT talk to the hardware)
         #[clap(short, long)]
         fake: bool,
     },
@@ -75,6 +76,17 @@ enum Commands {
         #[clap(subcommand)]
         command: FirmwareCommands,
     },
+    /// Air-gapped spending: create, sign, review and broadcast PSBTs separately
+    Psbt {
+        #[clap(subcommand)]
+        command: PsbtCommands,
+    },
+    /// Collect coredumps and telemetry from the hardware
+    Diagnostics {
+        /// Where to write the diagnostics bundle
+        #[arg(short, long, default_value = "diagnostics.json")]
+        output: PathBuf,
+    },
 
     CheckKeyproofs {
         // default is the account table in dev
@@ -123,16 +135,31 @@ enum WalletCommands {
     /// Drain a wallet (send all funds to an address)
     Drain {
         recipient: Address<NetworkUnchecked>,
+        #[clap(flatten)]
+        fees: FeeOptions,
     },
     /// Send funds (with server authorisation)
     ServerSend {
         recipient: Address<NetworkUnchecked>,
         amount: u64,
+        #[clap(flatten)]
+        fees: FeeOptions,
     },
     /// Send funds (with hardware authorisation)
     HardwareSend {
         recipient: Address<NetworkUnchecked>,
         amount: u64,
+        #[clap(flatten)]
+        fees: FeeOptions,
+    },
+    /// Speed up an unconfirmed transaction, by replacing it (RBF) or spending from it (CPFP)
+    BumpFee {
+        txid: Txid,
+        #[clap(flatten)]
+        fees: FeeOptions,
+        /// Co-sign with the server instead of the hardware
+        #[arg(long)]
+        server: bool,
     },
     /// Display server status
     ServerStatus {},
@@ -175,6 +202,41 @@ enum RecoveryCommands {
     Complete {},
 }
 
+#[derive(Clone, Subcommand)]
+enum PsbtCommands {
+    /// Create an unsigned PSBT (omit the amount to drain the wallet)
+    Create {
+        recipient: Address<NetworkUnchecked>,
+        amount: Option<u64>,
+        #[clap(flatten)]
+        fees: FeeOptions,
+        /// Where to write the PSBT
+        #[arg(short, long, default_value = "unsigned.psbt")]
+        output: PathBuf,
+        /// Also display the PSBT as a QR code
+        #[arg(long)]
+        qr: bool,
+    },
+    /// Sign a PSBT in place with one factor
+    Sign {
+        psbt: PathBuf,
+        #[arg(short, long, value_enum)]
+        factor: Factor,
+    },
+    /// Combine partially signed PSBTs into one
+    Combine {
+        #[arg(required = true)]
+        psbts: Vec<PathBuf>,
+        /// Where to write the combined PSBT
+        #[arg(short, long, default_value = "combined.psbt")]
+        output: PathBuf,
+    },
+    /// Review a PSBT's outputs, fee and signatures before broadcasting it
+    Inspect { psbt: PathBuf },
+    /// Finalize a fully signed PSBT and broadcast it
+    Broadcast { psbt: PathBuf },
+}
+
 #[derive(Clone, Subcommand)]
 enum FirmwareCommands {
     /// Display firmware metadata
@@ -183,6 +245,13 @@ enum FirmwareCommands {
     Upload {
         /// Path to the firmware file (defaults to the latest release from Memfault)
         firmware_bundle: Option<PathBuf>,
+        /// Picocert root certificate to verify the bundle's signature against (defaults to the
+        /// production root)
+        #[arg(long)]
+        trusted_root: Option<PathBuf>,
+        /// Don't verify the bundle's signature, and rely solely on the hardware's own check
+        #[arg(long, conflicts_with = "trusted_root")]
+        insecure_skip_verify: bool,
     },
 }
 
@@ -195,7 +264,8 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
     let client = Client::default(&cli.server);
     let db = sled::open(&cli.wallet)?;
-    let blockchain = blockchain(&cli.electrum)?;
+    // Only connect for the commands that need the chain, so the rest work offline.
+    let electrum = || blockchain(&cli.electrum);
 
     match cli.command {
         Commands::Pair { network, fake } => commands::pair::pair(&db, network, fake)?,
@@ -215,27 +285,44 @@ fn main() -> Result<()> {
         },
         Commands::Wallet { command } => match command {
             WalletCommands::Status {} => commands::wallet::status(&client, &db)?,
-            WalletCommands::Balance {} => commands::wallet::balance(&client, &db, blockchain)?,
+            WalletCommands::Balance {} => commands::wallet::balance(&client, &db, electrum()?)?,
             WalletCommands::Transactions {} => {
-                commands::wallet::transactions(&client, &db, blockchain)?
+                commands::wallet::transactions(&client, &db, electrum()?)?
             }
-            WalletCommands::Drain { recipient } => {
-                commands::wallet::drain(&client, &db, blockchain, recipient.assume_checked())?
-            }
-            WalletCommands::ServerSend { recipient, amount } => commands::wallet::server_send(
+            WalletCommands::Drain { recipient, fees } => commands::wallet::drain(
                 &client,
                 &db,
-                blockchain,
+                electrum()?,
                 recipient.assume_checked(),
+                fees,
+            )?,
+            WalletCommands::ServerSend {
+                recipient,
                 amount,
+                fees,
+            } => commands::wallet::server_send(
+                &client,
+                &db,
+                electrum()?,
+                recipient.assume_checked(),
+                amount,
+                fees,
             )?,
-            WalletCommands::HardwareSend { recipient, amount } => commands::wallet::hardware_send(
+            WalletCommands::HardwareSend {
+                recipient,
+                amount,
+                fees,
+            } => commands::wallet::hardware_send(
                 &client,
                 &db,
-                blockchain,
+                electrum()?,
                 recipient.assume_checked(),
                 amount,
+                fees,
             )?,
+            WalletCommands::BumpFee { txid, fees, server } => {
+                commands::wallet::bump_fee(&client, &db, electrum()?, txid, fees, server)?
+            }
             WalletCommands::Receive {} => commands::wallet::receive(&client, &db)?,
             WalletCommands::ServerStatus {} => commands::wallet::server_status(&client, &db)?,
             WalletCommands::SetupMobilePay { amount } => {
@@ -255,7 +342,7 @@ fn main() -> Result<()> {
                     commands::wallet::recovery::complete::complete_delay_notify(&client, &db)?
                 }
             },
-            WalletCommands::Utxos {} => commands::wallet::utxos(&client, &db, blockchain)?,
+            WalletCommands::Utxos {} => commands::wallet::utxos(&client, &db, electrum()?)?,
             WalletCommands::Debug {
                 account_table,
                 recovery_table,
@@ -275,16 +362,52 @@ fn main() -> Result<()> {
         },
         Commands::EndToEnd {
             ref treasury_root_key,
-        } => commands::end_to_end::end_to_end(&client, blockchain, treasury_root_key)?,
+        } => commands::end_to_end::end_to_end(&client, electrum()?, treasury_root_key)?,
         Commands::Firmware { command } => match command {
             FirmwareCommands::Metadata {} => commands::firmware::metadata()?,
             FirmwareCommands::Upload {
                 firmware_bundle: None,
-            } => commands::firmware::upload_latest(&client)?,
+                trusted_root,
+                insecure_skip_verify,
+            } => commands::firmware::upload_latest(&client, trusted_root, insecure_skip_verify)?,
             FirmwareCommands::Upload {
                 firmware_bundle: Some(firmware_bundle),
-            } => commands::firmware::upload_bundle(firmware_bundle)?,
+                trusted_root,
+                insecure_skip_verify,
+            } => commands::firmware::upload_bundle(
+                firmware_bundle,
+                trusted_root,
+                insecure_skip_verify,
+            )?,
+        },
+        Commands::Psbt { command } => match command {
+            PsbtCommands::Create {
+                recipient,
+                amount,
+                fees,
+                output,
+                qr,
+            } => {
+                let psbt = commands::psbt::create(
+                    &client,
+                    &db,
+                    electrum()?,
+                    recipient.assume_checked(),
+                    amount,
+                    fees,
+                )?;
+                commands::psbt::export(&psbt, &output, qr)?
+            }
+            PsbtCommands::Sign { psbt, factor } => {
+                commands::psbt::sign(&client, &db, &psbt, factor)?
+            }
+            PsbtCommands::Combine { psbts, output } => commands::psbt::combine(&psbts, &output)?,
+            PsbtCommands::Inspect { psbt } => commands::psbt::inspect(&client, &db, &psbt)?,
+            PsbtCommands::Broadcast { psbt } => {
+                commands::psbt::broadcast(&client, &db, electrum()?, &psbt)?
+            }
         },
+        Commands::Diagnostics { output } => commands::diagnostics::diagnostics(output)?,
 
         Commands::CheckKeyproofs {
             account_table,
@@ -1,8 +1,4 @@
-use std::{
-    sync::{Arc, Mutex},
-    thread::sleep,
-    time::Duration,
-};
+use std::sync::{Arc, Mutex};
 
 use bdk::{
     bitcoin::{
@@ -13,20 +9,17 @@ use bdk::{
     miniscript::{descriptor::DescriptorKeyParseError, DescriptorPublicKey},
 };
 use thiserror::Error;
-use wca::{
-    commands::{
-        DeviceInfo, FirmwareMetadata, FwupFinish, FwupFinishRspStatus, FwupStart, FwupTransfer,
-        GetAuthenticationKey, GetFirmwareMetadata, GetInitialSpendingKey, QueryAuthentication,
-        SignTransaction,
-    },
-    pcsc::{Performer, Transactor, TransactorError},
+use wca::commands::{
+    FingerprintEnrollmentStatus, GetDeviceInfo, GetFingerprintEnrollmentStatus, GetNextSpendingKey,
+    SignChallenge, StartFingerprintEnrollment, WipeState,
 };
 use wca::{
     commands::{
-        FingerprintEnrollmentStatus, GetDeviceInfo, GetFingerprintEnrollmentStatus,
-        GetNextSpendingKey, SignChallenge, StartFingerprintEnrollment, WipeState,
+        DeviceInfo, FirmwareMetadata, GetAuthenticationKey, GetFirmwareMetadata,
+        GetInitialSpendingKey, QueryAuthentication, SignTransaction,
     },
-    errors::CommandError,
+    pcsc::{Performer, Transactor, TransactorError},
+    ApduSession,
 };
 
 #[derive(Clone)]
@@ -46,6 +39,10 @@ impl Transactor for SafeTransactor {
     fn reset(&mut self) -> Result<(), pcsc::Error> {
         self.0.lock().unwrap().reset()
     }
+
+    fn session(&self) -> ApduSession {
+        self.0.lock().unwrap().session()
+    }
 }
 
 #[derive(Error, Debug)]
@@ -56,8 +53,6 @@ pub enum PairingError {
     ParseKey(#[from] DescriptorKeyParseError),
     #[error("authentication error: {0:?}")]
     Authentication(FingerprintEnrollmentStatus),
-    #[error("could not start firmware upload")]
-    FwupStart,
 }
 
 pub trait NFCTransactions {
@@ -72,7 +67,6 @@ pub trait NFCTransactions {
     ) -> Result<PartiallySignedTransaction, TransactorError>;
     fn device_info(&self) -> Result<DeviceInfo, TransactorError>;
     fn metadata(&self) -> Result<FirmwareMetadata, TransactorError>;
-    fn upload(&mut self, upload: &Upload) -> Result<FwupFinishRspStatus, PairingError>;
     fn get_authentication_key(&self) -> Result<PublicKey, TransactorError>;
     fn get_initial_spending_key(
         &self,
@@ -128,21 +122,6 @@ impl<T: Transactor + ?Sized> NFCTransactions for T {
         self.perform(GetFirmwareMetadata::new())
     }
 
-    fn upload(&mut self, upload: &Upload) -> Result<FwupFinishRspStatus, PairingError> {
-        if !self.perform(FwupStart::new(None, wca::commands::FwupMode::Normal))? {
-            return Err(PairingError::FwupStart);
-        }
-
-        upload_asset(self, upload.chunk_size, &upload.application)?;
-        upload_asset(self, upload.chunk_size, &upload.signature)?;
-
-        Ok(self.perform(FwupFinish::new(
-            upload.app_properties_offset,
-            upload.signature.offset,
-            wca::commands::FwupMode::Normal,
-        ))?)
-    }
-
     fn get_authentication_key(&self) -> Result<PublicKey, TransactorError> {
         self.perform(GetAuthenticationKey::new())
     }
@@ -166,58 +145,3 @@ impl<T: Transactor + ?Sized> NFCTransactions for T {
         self.perform(WipeState::new())
     }
 }
-
-pub struct Asset {
-    pub data: Vec<u8>,
-    pub offset: u32,
-}
-
-pub struct Upload {
-    pub chunk_size: usize,
-    pub app_properties_offset: u32,
-    pub application: Asset,
-    pub signature: Asset,
-}
-
-fn upload_asset<T: Transactor + ?Sized>(
-    transactor: &mut T,
-    chunk_size: usize,
-    asset: &Asset,
-) -> Result<(), TransactorError> {
-    let chunks = asset.data.chunks(chunk_size).enumerate();
-    let bar = indicatif::ProgressBar::new(chunks.len() as u64);
-    for (sequence_id, chunk) in chunks {
-        let command = transactor.perform(FwupTransfer::new(
-            sequence_id as u32,
-            chunk.to_vec(),
-            asset.offset,
-            wca::commands::FwupMode::Normal,
-        ));
-
-        match command {
-            Err(TransactorError::CommandError(CommandError::Unauthenticated)) => {
-                bar.println("Please unlock your hardware...");
-
-                while !transactor.is_authenticated().is_ok_and(|x| x) {
-                    sleep(Duration::from_secs(1));
-
-                    match transactor.reset() {
-                        Ok(_) | Err(pcsc::Error::NoSmartcard) => continue,
-                        Err(err) => {
-                            bar.abandon_with_message("Giving up due to an error");
-                            return Err(TransactorError::ReaderError(err));
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                bar.abandon();
-                return Err(e);
-            }
-            Ok(_) => bar.inc(1),
-        }
-    }
-    bar.finish_and_clear();
-
-    Ok(())
-}
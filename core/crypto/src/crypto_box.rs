@@ -1,3 +1,4 @@
+use crate::secret::SecretBytes;
 use crypto_box::{
     aead::{Aead, OsRng},
     ChaChaBox, PublicKey, SecretKey,
@@ -86,8 +87,14 @@ impl CryptoBoxKeyPair {
             .to_vec()
     }
 
-    pub fn secret_key(&self) -> Vec<u8> {
-        self.secret_key_mutex.lock().unwrap().to_bytes().to_vec()
+    // `crypto_box::SecretKey` wipes itself on drop.
+    pub fn secret_key(&self) -> SecretBytes {
+        self.secret_key_mutex
+            .lock()
+            .unwrap()
+            .to_bytes()
+            .to_vec()
+            .into()
     }
 }
 
@@ -103,16 +110,22 @@ mod tests {
         let bob_keypair = CryptoBoxKeyPair::new();
 
         // Alice encrypts
-        let alice_crypto_box =
-            CryptoBox::new(&bob_keypair.public_key(), &alice_keypair.secret_key()).unwrap();
+        let alice_crypto_box = CryptoBox::new(
+            &bob_keypair.public_key(),
+            alice_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let mut nonce = [0u8; 24];
         rand::thread_rng().fill_bytes(&mut nonce);
         let plaintext = b"Hello, world!";
         let ciphertext = alice_crypto_box.encrypt(&nonce, plaintext).unwrap();
 
         // Bob decrypts
-        let bob_crypto_box =
-            CryptoBox::new(&alice_keypair.public_key(), &bob_keypair.secret_key()).unwrap();
+        let bob_crypto_box = CryptoBox::new(
+            &alice_keypair.public_key(),
+            bob_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let decrypted_data = bob_crypto_box.decrypt(&nonce, &ciphertext).unwrap();
 
         assert_eq!(plaintext.to_vec(), decrypted_data);
@@ -124,16 +137,22 @@ mod tests {
         let bob_keypair = CryptoBoxKeyPair::new();
 
         // Alice encrypts
-        let alice_crypto_box =
-            CryptoBox::new(&bob_keypair.public_key(), &alice_keypair.secret_key()).unwrap();
+        let alice_crypto_box = CryptoBox::new(
+            &bob_keypair.public_key(),
+            alice_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let mut nonce = [0u8; 24];
         rand::thread_rng().fill_bytes(&mut nonce);
         let empty_plaintext = b"";
         let ciphertext = alice_crypto_box.encrypt(&nonce, empty_plaintext).unwrap();
 
         // Bob decrypts
-        let bob_crypto_box =
-            CryptoBox::new(&alice_keypair.public_key(), &bob_keypair.secret_key()).unwrap();
+        let bob_crypto_box = CryptoBox::new(
+            &alice_keypair.public_key(),
+            bob_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let decrypted_data = bob_crypto_box.decrypt(&nonce, &ciphertext).unwrap();
 
         assert_eq!(empty_plaintext.to_vec(), decrypted_data);
@@ -145,8 +164,11 @@ mod tests {
         let bob_keypair = CryptoBoxKeyPair::new();
 
         // Alice encrypts
-        let alice_crypto_box =
-            CryptoBox::new(&bob_keypair.public_key(), &alice_keypair.secret_key()).unwrap();
+        let alice_crypto_box = CryptoBox::new(
+            &bob_keypair.public_key(),
+            alice_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let mut nonce = [0u8; 24];
         rand::thread_rng().fill_bytes(&mut nonce);
         let plaintext = b"Hello, world!";
@@ -156,8 +178,11 @@ mod tests {
         ciphertext[0] ^= 0x01;
 
         // Bob attempts to decrypt the tampered ciphertext
-        let bob_crypto_box =
-            CryptoBox::new(&alice_keypair.public_key(), &bob_keypair.secret_key()).unwrap();
+        let bob_crypto_box = CryptoBox::new(
+            &alice_keypair.public_key(),
+            bob_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
 
         let result = bob_crypto_box.decrypt(&nonce, &ciphertext);
 
@@ -174,8 +199,11 @@ mod tests {
         let bob_keypair = CryptoBoxKeyPair::new();
 
         // Alice encrypts
-        let alice_crypto_box =
-            CryptoBox::new(&bob_keypair.public_key(), &alice_keypair.secret_key()).unwrap();
+        let alice_crypto_box = CryptoBox::new(
+            &bob_keypair.public_key(),
+            alice_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let mut nonce = [0u8; 24];
         rand::thread_rng().fill_bytes(&mut nonce);
         let plaintext = b"Hello, world!";
@@ -183,8 +211,11 @@ mod tests {
 
         // Bob attempts to decrypt the ciphertext with the wrong key
         let charlie_keypair = CryptoBoxKeyPair::new();
-        let bob_crypto_box =
-            CryptoBox::new(&alice_keypair.public_key(), &charlie_keypair.secret_key()).unwrap();
+        let bob_crypto_box = CryptoBox::new(
+            &alice_keypair.public_key(),
+            charlie_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
 
         let result = bob_crypto_box.decrypt(&nonce, &ciphertext);
 
@@ -211,15 +242,21 @@ mod tests {
         let bob_keypair = CryptoBoxKeyPair::new();
 
         // Alice encrypts
-        let alice_crypto_box =
-            CryptoBox::new(&bob_keypair.public_key(), &alice_keypair.secret_key()).unwrap();
+        let alice_crypto_box = CryptoBox::new(
+            &bob_keypair.public_key(),
+            alice_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let mut nonce = [0u8; 24];
         rand::thread_rng().fill_bytes(&mut nonce);
         let ciphertext = alice_crypto_box.encrypt(&nonce, &plaintext).unwrap();
 
         // Bob decrypts
-        let bob_crypto_box =
-            CryptoBox::new(&alice_keypair.public_key(), &bob_keypair.secret_key()).unwrap();
+        let bob_crypto_box = CryptoBox::new(
+            &alice_keypair.public_key(),
+            bob_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let decrypted_data = bob_crypto_box.decrypt(&nonce, &ciphertext).unwrap();
 
         assert_eq!(plaintext.to_vec(), decrypted_data);
@@ -230,8 +267,11 @@ mod tests {
         let alice_keypair = CryptoBoxKeyPair::new();
         let bob_keypair = CryptoBoxKeyPair::new();
 
-        let alice_crypto_box =
-            CryptoBox::new(&bob_keypair.public_key(), &alice_keypair.secret_key()).unwrap();
+        let alice_crypto_box = CryptoBox::new(
+            &bob_keypair.public_key(),
+            alice_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let ciphertext = alice_crypto_box.encrypt(&nonce, &plaintext);
 
         assert!(ciphertext.is_ok() || matches!(ciphertext, Err(CryptoBoxError::EncryptError)));
@@ -245,8 +285,11 @@ mod tests {
         let mut nonce = [0u8; 24];
         rand::thread_rng().fill_bytes(&mut nonce);
 
-        let bob_crypto_box =
-            CryptoBox::new(&alice_keypair.public_key(), &bob_keypair.secret_key()).unwrap();
+        let bob_crypto_box = CryptoBox::new(
+            &alice_keypair.public_key(),
+            bob_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let decrypted_data = bob_crypto_box.decrypt(&nonce, &ciphertext);
 
         assert!(matches!(decrypted_data, Err(CryptoBoxError::DecryptError)));
@@ -257,8 +300,11 @@ mod tests {
         let alice_keypair = CryptoBoxKeyPair::new();
         let bob_keypair = CryptoBoxKeyPair::new();
 
-        let bob_crypto_box =
-            CryptoBox::new(&alice_keypair.public_key(), &bob_keypair.secret_key()).unwrap();
+        let bob_crypto_box = CryptoBox::new(
+            &alice_keypair.public_key(),
+            bob_keypair.secret_key().expose_secret(),
+        )
+        .unwrap();
         let decrypted_data = bob_crypto_box.decrypt(&nonce, &ciphertext);
 
         assert!(matches!(decrypted_data, Err(CryptoBoxError::DecryptError)));
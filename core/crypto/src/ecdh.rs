@@ -1,4 +1,5 @@
 use crate::keys::{PublicKey, SecretKey};
+use crate::secret::SecretBytes;
 use bitcoin::secp256k1::ecdh::SharedSecret as RustSecp256k1SharedSecret;
 use std::sync::Mutex;
 
@@ -14,12 +15,21 @@ impl Secp256k1SharedSecret {
         }
     }
 
-    pub fn secret_bytes(&self) -> Vec<u8> {
+    pub fn secret_bytes(&self) -> SecretBytes {
         self.shared_secret_mutex
             .lock()
             .unwrap()
             .secret_bytes()
             .to_vec()
+            .into()
+    }
+}
+
+impl Drop for Secp256k1SharedSecret {
+    fn drop(&mut self) {
+        if let Ok(shared_secret) = self.shared_secret_mutex.get_mut() {
+            shared_secret.non_secure_erase();
+        }
     }
 }
 
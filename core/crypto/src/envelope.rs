@@ -0,0 +1,866 @@
+use bitcoin::hashes::{sha256, Hash};
+#[cfg(feature = "x25519")]
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use rand::RngCore;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::chacha20poly1305::XChaCha20Poly1305;
+use crate::ecdh::Secp256k1SharedSecret;
+use crate::hkdf::Hkdf;
+use crate::keys::{PublicKey, SecretKey};
+use crate::secret::SecretBytes;
+
+// A sealed envelope encrypts a payload once under a random content key, and wraps that key for
+// each recipient with a key derived from ECDH between an ephemeral key and the recipient's key.
+//
+// Encoding, version 1. Integers are big-endian.
+//
+//   magic             4 bytes   "BKSE"
+//   version           1 byte    1
+//   suite             1 byte    1 = secp256k1, 2 = X25519
+//   context length    1 byte
+//   context           variable  caller-chosen domain separation label, e.g. "SocRecBackup"
+//   ephemeral key     33 bytes (secp256k1, compressed) or 32 bytes (X25519)
+//   nonce             24 bytes  payload nonce
+//   recipient count   1 byte    at least 1
+//   recipients        count * (8 byte key id || 48 byte wrapped content key)
+//   ciphertext        remainder, XChaCha20-Poly1305 with a 16 byte tag
+//
+// Key derivation, with `||` denoting concatenation:
+//
+//   key id       = SHA256(recipient public key)[..8]
+//   wrapping key = HKDF-SHA256(salt = ephemeral key || recipient public key, ikm = ECDH secret,
+//                              info = "BitkeySealedEnvelope/v1/<suite>/wrap/" || context)
+//   payload key  = HKDF-SHA256(salt = empty, ikm = content key,
+//                              info = "BitkeySealedEnvelope/v1/<suite>/payload/" || context)
+//
+// The secp256k1 ECDH secret is libsecp256k1's default, SHA256 of the compressed shared point.
+// The content key is wrapped with an all-zero nonce, which is safe because every wrapping key is
+// used once. The payload's associated data is the entire encoded header followed by the caller's
+// AAD, so tampering with any header field, including the recipient list, fails decryption.
+//
+// The X25519 suite is behind the default `x25519` feature; builds without it reject X25519
+// envelopes as an unsupported suite.
+
+const MAGIC: &[u8; 4] = b"BKSE";
+pub const ENVELOPE_VERSION: u8 = 1;
+const INFO_PREFIX: &str = "BitkeySealedEnvelope/v1/";
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+const TAG_LENGTH: usize = 16;
+const KEY_ID_LENGTH: usize = 8;
+const WRAPPED_KEY_LENGTH: usize = KEY_LENGTH + TAG_LENGTH;
+const MAX_RECIPIENTS: usize = u8::MAX as usize;
+const MAX_CONTEXT_LENGTH: usize = u8::MAX as usize;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EnvelopeError {
+    #[error("Envelope has no recipients")]
+    NoRecipients,
+    #[error("Envelope has more than {MAX_RECIPIENTS} recipients")]
+    TooManyRecipients,
+    #[error("All recipients of an envelope must use the same suite")]
+    MixedSuites,
+    #[error("Context is longer than {MAX_CONTEXT_LENGTH} bytes")]
+    ContextTooLong,
+    #[error("Invalid key")]
+    InvalidKey,
+    #[error("Unsupported envelope version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unsupported envelope suite {0}")]
+    UnsupportedSuite(u8),
+    #[error("Malformed envelope")]
+    Malformed,
+    #[error("Envelope context does not match")]
+    ContextMismatch,
+    #[error("Key is not a recipient of this envelope")]
+    NotARecipient,
+    #[error("Failed to encrypt")]
+    EncryptError,
+    #[error("Failed to decrypt")]
+    DecryptError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeSuite {
+    Secp256k1,
+    #[cfg(feature = "x25519")]
+    X25519,
+}
+
+impl EnvelopeSuite {
+    fn id(self) -> u8 {
+        match self {
+            EnvelopeSuite::Secp256k1 => 1,
+            #[cfg(feature = "x25519")]
+            EnvelopeSuite::X25519 => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, EnvelopeError> {
+        match id {
+            1 => Ok(EnvelopeSuite::Secp256k1),
+            #[cfg(feature = "x25519")]
+            2 => Ok(EnvelopeSuite::X25519),
+            _ => Err(EnvelopeError::UnsupportedSuite(id)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            EnvelopeSuite::Secp256k1 => "secp256k1",
+            #[cfg(feature = "x25519")]
+            EnvelopeSuite::X25519 => "x25519",
+        }
+    }
+
+    fn public_key_length(self) -> usize {
+        match self {
+            EnvelopeSuite::Secp256k1 => 33,
+            #[cfg(feature = "x25519")]
+            EnvelopeSuite::X25519 => 32,
+        }
+    }
+
+    fn info(self, purpose: &str, context: &[u8]) -> Vec<u8> {
+        let mut info = format!("{}{}/{}/", INFO_PREFIX, self.name(), purpose).into_bytes();
+        info.extend_from_slice(context);
+        info
+    }
+
+    // Returns the public key for `secret` along with the ECDH secret between it and `public_key`.
+    fn agree(
+        self,
+        secret: &[u8],
+        public_key: &[u8],
+    ) -> Result<(Vec<u8>, SecretBytes), EnvelopeError> {
+        match self {
+            EnvelopeSuite::Secp256k1 => {
+                let secret =
+                    SecretKey::new(secret.to_vec()).map_err(|_| EnvelopeError::InvalidKey)?;
+                let public_key =
+                    PublicKey::from_slice(public_key).map_err(|_| EnvelopeError::InvalidKey)?;
+                Ok((
+                    secret.as_public().serialize().to_vec(),
+                    Secp256k1SharedSecret::new(&public_key, &secret).secret_bytes(),
+                ))
+            }
+            #[cfg(feature = "x25519")]
+            EnvelopeSuite::X25519 => {
+                let secret: [u8; 32] = secret.try_into().map_err(|_| EnvelopeError::InvalidKey)?;
+                let public_key: [u8; 32] = public_key
+                    .try_into()
+                    .map_err(|_| EnvelopeError::InvalidKey)?;
+                let shared_secret = SecretBytes::from(
+                    MontgomeryPoint(public_key)
+                        .mul_clamped(secret)
+                        .to_bytes()
+                        .to_vec(),
+                );
+                // A low order public key yields an all-zero secret.
+                if shared_secret.expose_secret() == [0u8; 32] {
+                    return Err(EnvelopeError::InvalidKey);
+                }
+                Ok((
+                    MontgomeryPoint::mul_base_clamped(secret)
+                        .to_bytes()
+                        .to_vec(),
+                    shared_secret,
+                ))
+            }
+        }
+    }
+}
+
+/// A recipient's public key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecipientKey {
+    Secp256k1(PublicKey),
+    #[cfg(feature = "x25519")]
+    X25519([u8; 32]),
+}
+
+impl RecipientKey {
+    pub fn suite(&self) -> EnvelopeSuite {
+        match self {
+            RecipientKey::Secp256k1(_) => EnvelopeSuite::Secp256k1,
+            #[cfg(feature = "x25519")]
+            RecipientKey::X25519(_) => EnvelopeSuite::X25519,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            RecipientKey::Secp256k1(public_key) => public_key.serialize().to_vec(),
+            #[cfg(feature = "x25519")]
+            RecipientKey::X25519(public_key) => public_key.to_vec(),
+        }
+    }
+
+    pub fn key_id(&self) -> [u8; KEY_ID_LENGTH] {
+        key_id(&self.to_bytes())
+    }
+}
+
+/// A recipient's secret key, for opening envelopes.
+pub struct OpeningKey {
+    suite: EnvelopeSuite,
+    secret: SecretBytes,
+    public_key: Vec<u8>,
+}
+
+impl OpeningKey {
+    pub fn secp256k1(secret_bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        let secret_key =
+            SecretKey::new(secret_bytes.to_vec()).map_err(|_| EnvelopeError::InvalidKey)?;
+        Ok(Self {
+            suite: EnvelopeSuite::Secp256k1,
+            secret: secret_key.inner().secret_bytes().to_vec().into(),
+            public_key: secret_key.as_public().serialize().to_vec(),
+        })
+    }
+
+    #[cfg(feature = "x25519")]
+    pub fn x25519(secret_bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        let secret: [u8; 32] = secret_bytes
+            .try_into()
+            .map_err(|_| EnvelopeError::InvalidKey)?;
+        Ok(Self {
+            suite: EnvelopeSuite::X25519,
+            secret: secret_bytes.into(),
+            public_key: MontgomeryPoint::mul_base_clamped(secret)
+                .to_bytes()
+                .to_vec(),
+        })
+    }
+
+    pub fn suite(&self) -> EnvelopeSuite {
+        self.suite
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipientStanza {
+    pub key_id: [u8; KEY_ID_LENGTH],
+    pub wrapped_key: [u8; WRAPPED_KEY_LENGTH],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvelopeHeader {
+    pub version: u8,
+    pub suite: EnvelopeSuite,
+    pub context: Vec<u8>,
+    pub ephemeral_public_key: Vec<u8>,
+    pub nonce: [u8; NONCE_LENGTH],
+    pub recipients: Vec<RecipientStanza>,
+}
+
+impl EnvelopeHeader {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.version);
+        bytes.push(self.suite.id());
+        bytes.push(self.context.len() as u8);
+        bytes.extend_from_slice(&self.context);
+        bytes.extend_from_slice(&self.ephemeral_public_key);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.push(self.recipients.len() as u8);
+        for recipient in &self.recipients {
+            bytes.extend_from_slice(&recipient.key_id);
+            bytes.extend_from_slice(&recipient.wrapped_key);
+        }
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SealedEnvelope {
+    header: EnvelopeHeader,
+    ciphertext: Vec<u8>,
+}
+
+fn key_id(public_key: &[u8]) -> [u8; KEY_ID_LENGTH] {
+    let digest = sha256::Hash::hash(public_key).to_byte_array();
+    digest[..KEY_ID_LENGTH]
+        .try_into()
+        .expect("Digest is longer than a key id")
+}
+
+fn derive_key(salt: &[u8], ikm: &[u8], info: &[u8]) -> SecretBytes {
+    Hkdf::new(salt, ikm)
+        .expand(info, KEY_LENGTH as i32)
+        .expect("HKDF output length is valid")
+        .into()
+}
+
+fn wrapping_key(
+    suite: EnvelopeSuite,
+    context: &[u8],
+    ephemeral_public_key: &[u8],
+    recipient_public_key: &[u8],
+    shared_secret: &[u8],
+) -> SecretBytes {
+    let salt = [ephemeral_public_key, recipient_public_key].concat();
+    derive_key(&salt, shared_secret, &suite.info("wrap", context))
+}
+
+fn payload_key(suite: EnvelopeSuite, context: &[u8], content_key: &[u8]) -> SecretBytes {
+    derive_key(&[], content_key, &suite.info("payload", context))
+}
+
+/// Encrypts `plaintext` to every key in `recipients`, which must all use the same suite.
+/// `context` domain-separates envelopes made for different purposes; `aad` is authenticated but
+/// not stored, so openers must supply it again.
+pub fn seal(
+    context: &[u8],
+    recipients: &[RecipientKey],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<SealedEnvelope, EnvelopeError> {
+    let mut rng = rand::thread_rng();
+    let suite = recipients
+        .first()
+        .ok_or(EnvelopeError::NoRecipients)?
+        .suite();
+
+    // secp256k1 secret keys must be below the curve order, so retry the vanishingly rare miss.
+    let mut ephemeral_secret = Zeroizing::new([0u8; 32]);
+    loop {
+        rng.fill_bytes(ephemeral_secret.as_mut());
+        if suite != EnvelopeSuite::Secp256k1 || SecretKey::new(ephemeral_secret.to_vec()).is_ok() {
+            break;
+        }
+    }
+    let mut content_key = Zeroizing::new([0u8; KEY_LENGTH]);
+    rng.fill_bytes(content_key.as_mut());
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rng.fill_bytes(&mut nonce);
+
+    seal_with(
+        context,
+        recipients,
+        plaintext,
+        aad,
+        ephemeral_secret.as_ref(),
+        content_key.as_ref(),
+        nonce,
+    )
+}
+
+fn seal_with(
+    context: &[u8],
+    recipients: &[RecipientKey],
+    plaintext: &[u8],
+    aad: &[u8],
+    ephemeral_secret: &[u8],
+    content_key: &[u8],
+    nonce: [u8; NONCE_LENGTH],
+) -> Result<SealedEnvelope, EnvelopeError> {
+    let suite = recipients
+        .first()
+        .ok_or(EnvelopeError::NoRecipients)?
+        .suite();
+    if recipients.len() > MAX_RECIPIENTS {
+        return Err(EnvelopeError::TooManyRecipients);
+    }
+    if recipients
+        .iter()
+        .any(|recipient| recipient.suite() != suite)
+    {
+        return Err(EnvelopeError::MixedSuites);
+    }
+    if context.len() > MAX_CONTEXT_LENGTH {
+        return Err(EnvelopeError::ContextTooLong);
+    }
+
+    let mut ephemeral_public_key = Vec::new();
+    let mut stanzas = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        let recipient_public_key = recipient.to_bytes();
+        let (public_key, shared_secret) = suite.agree(ephemeral_secret, &recipient_public_key)?;
+        let key = wrapping_key(
+            suite,
+            context,
+            &public_key,
+            &recipient_public_key,
+            shared_secret.expose_secret(),
+        );
+        let wrapped_key = XChaCha20Poly1305::new(key.expose_secret())
+            .and_then(|cipher| cipher.encrypt(&[0u8; NONCE_LENGTH], content_key, &[]))
+            .map_err(|_| EnvelopeError::EncryptError)?;
+
+        ephemeral_public_key = public_key;
+        stanzas.push(RecipientStanza {
+            key_id: key_id(&recipient_public_key),
+            wrapped_key: wrapped_key
+                .try_into()
+                .map_err(|_| EnvelopeError::EncryptError)?,
+        });
+    }
+
+    let header = EnvelopeHeader {
+        version: ENVELOPE_VERSION,
+        suite,
+        context: context.to_vec(),
+        ephemeral_public_key,
+        nonce,
+        recipients: stanzas,
+    };
+    let payload_aad = [header.to_bytes().as_slice(), aad].concat();
+    let ciphertext =
+        XChaCha20Poly1305::new(payload_key(suite, context, content_key).expose_secret())
+            .and_then(|cipher| cipher.encrypt(&nonce, plaintext, &payload_aad))
+            .map_err(|_| EnvelopeError::EncryptError)?;
+
+    Ok(SealedEnvelope { header, ciphertext })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], EnvelopeError> {
+        if self.bytes.len() < len {
+            return Err(EnvelopeError::Malformed);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn take_u8(&mut self) -> Result<u8, EnvelopeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], EnvelopeError> {
+        Ok(self
+            .take(N)?
+            .try_into()
+            .expect("Slice has the taken length"))
+    }
+}
+
+impl SealedEnvelope {
+    pub fn header(&self) -> &EnvelopeHeader {
+        &self.header
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_bytes();
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(EnvelopeError::Malformed);
+        }
+        let version = reader.take_u8()?;
+        if version != ENVELOPE_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+        let suite = EnvelopeSuite::from_id(reader.take_u8()?)?;
+        let context_length = reader.take_u8()? as usize;
+        let context = reader.take(context_length)?.to_vec();
+        let ephemeral_public_key = reader.take(suite.public_key_length())?.to_vec();
+        let nonce = reader.take_array()?;
+        let recipient_count = reader.take_u8()? as usize;
+        if recipient_count == 0 {
+            return Err(EnvelopeError::Malformed);
+        }
+        let recipients = (0..recipient_count)
+            .map(|_| {
+                Ok(RecipientStanza {
+                    key_id: reader.take_array()?,
+                    wrapped_key: reader.take_array()?,
+                })
+            })
+            .collect::<Result<Vec<_>, EnvelopeError>>()?;
+        if reader.bytes.len() < TAG_LENGTH {
+            return Err(EnvelopeError::Malformed);
+        }
+
+        Ok(Self {
+            header: EnvelopeHeader {
+                version,
+                suite,
+                context,
+                ephemeral_public_key,
+                nonce,
+                recipients,
+            },
+            ciphertext: reader.bytes.to_vec(),
+        })
+    }
+
+    /// Decrypts the envelope with one recipient's key. `context` and `aad` must match what the
+    /// envelope was sealed with.
+    pub fn open(
+        &self,
+        context: &[u8],
+        key: &OpeningKey,
+        aad: &[u8],
+    ) -> Result<Vec<u8>, EnvelopeError> {
+        let header = &self.header;
+        if header.context != context {
+            return Err(EnvelopeError::ContextMismatch);
+        }
+        if header.suite != key.suite {
+            return Err(EnvelopeError::NotARecipient);
+        }
+
+        let (_, shared_secret) = header
+            .suite
+            .agree(key.secret.expose_secret(), &header.ephemeral_public_key)?;
+        let wrapping_key = wrapping_key(
+            header.suite,
+            context,
+            &header.ephemeral_public_key,
+            &key.public_key,
+            shared_secret.expose_secret(),
+        );
+        let cipher = XChaCha20Poly1305::new(wrapping_key.expose_secret())
+            .map_err(|_| EnvelopeError::DecryptError)?;
+
+        // Key ids are short, so a colliding stanza just fails to unwrap and the next is tried.
+        let recipient_key_id = key_id(&key.public_key);
+        let content_key = header
+            .recipients
+            .iter()
+            .filter(|stanza| stanza.key_id == recipient_key_id)
+            .find_map(|stanza| {
+                cipher
+                    .decrypt(&[0u8; NONCE_LENGTH], &stanza.wrapped_key, &[])
+                    .ok()
+                    .map(SecretBytes::from)
+            })
+            .ok_or(EnvelopeError::NotARecipient)?;
+
+        let payload_aad = [header.to_bytes().as_slice(), aad].concat();
+        XChaCha20Poly1305::new(
+            payload_key(header.suite, context, content_key.expose_secret()).expose_secret(),
+        )
+        .and_then(|cipher| cipher.decrypt(&header.nonce, &self.ciphertext, &payload_aad))
+        .map_err(|_| EnvelopeError::DecryptError)
+    }
+}
+
+#[cfg(all(test, feature = "x25519"))]
+mod tests {
+    use super::*;
+
+    const CONTEXT: &[u8] = b"SocRecBackup";
+
+    fn secp256k1_key(byte: u8) -> (OpeningKey, RecipientKey) {
+        let opening_key = OpeningKey::secp256k1(&[byte; 32]).unwrap();
+        let recipient =
+            RecipientKey::Secp256k1(PublicKey::from_slice(opening_key.public_key()).unwrap());
+        (opening_key, recipient)
+    }
+
+    fn x25519_key(byte: u8) -> (OpeningKey, RecipientKey) {
+        let opening_key = OpeningKey::x25519(&[byte; 32]).unwrap();
+        let recipient = RecipientKey::X25519(opening_key.public_key().try_into().unwrap());
+        (opening_key, recipient)
+    }
+
+    #[test]
+    fn test_seal_open_multiple_recipients() {
+        for keys in [
+            [secp256k1_key(1), secp256k1_key(2), secp256k1_key(3)],
+            [x25519_key(1), x25519_key(2), x25519_key(3)],
+        ] {
+            let recipients = keys
+                .iter()
+                .map(|(_, recipient)| recipient.clone())
+                .collect::<Vec<_>>();
+            let envelope = seal(CONTEXT, &recipients, b"backup", b"aad").unwrap();
+            let envelope = SealedEnvelope::from_bytes(&envelope.to_bytes()).unwrap();
+
+            for (opening_key, _) in &keys {
+                assert_eq!(
+                    envelope.open(CONTEXT, opening_key, b"aad").unwrap(),
+                    b"backup"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_open_failures() {
+        let (alice, alice_recipient) = secp256k1_key(1);
+        let (bob, _) = secp256k1_key(2);
+        let (carol, _) = x25519_key(3);
+        let envelope = seal(CONTEXT, &[alice_recipient], b"backup", b"aad").unwrap();
+
+        assert_eq!(
+            envelope.open(CONTEXT, &bob, b"aad"),
+            Err(EnvelopeError::NotARecipient)
+        );
+        assert_eq!(
+            envelope.open(CONTEXT, &carol, b"aad"),
+            Err(EnvelopeError::NotARecipient)
+        );
+        assert_eq!(
+            envelope.open(b"InheritancePackage", &alice, b"aad"),
+            Err(EnvelopeError::ContextMismatch)
+        );
+        assert_eq!(
+            envelope.open(CONTEXT, &alice, b"other aad"),
+            Err(EnvelopeError::DecryptError)
+        );
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        let (alice, alice_recipient) = x25519_key(1);
+        let (_, bob_recipient) = x25519_key(2);
+        let bytes = seal(CONTEXT, &[alice_recipient, bob_recipient], b"backup", &[])
+            .unwrap()
+            .to_bytes();
+
+        // Drop Bob's stanza and fix up the recipient count.
+        let header_length = 4 + 3 + CONTEXT.len() + 32 + 24;
+        let mut tampered = bytes[..header_length].to_vec();
+        tampered.push(1);
+        tampered.extend_from_slice(&bytes[header_length + 1..header_length + 1 + 56]);
+        tampered.extend_from_slice(&bytes[header_length + 1 + 2 * 56..]);
+
+        let envelope = SealedEnvelope::from_bytes(&tampered).unwrap();
+        assert_eq!(
+            envelope.open(CONTEXT, &alice, &[]),
+            Err(EnvelopeError::DecryptError)
+        );
+    }
+
+    #[test]
+    fn test_seal_errors() {
+        let (_, secp256k1_recipient) = secp256k1_key(1);
+        let (_, x25519_recipient) = x25519_key(2);
+
+        assert_eq!(
+            seal(CONTEXT, &[], b"", &[]),
+            Err(EnvelopeError::NoRecipients)
+        );
+        assert_eq!(
+            seal(
+                CONTEXT,
+                &[secp256k1_recipient.clone(), x25519_recipient],
+                b"",
+                &[]
+            ),
+            Err(EnvelopeError::MixedSuites)
+        );
+        assert_eq!(
+            seal(&[0u8; 256], &[secp256k1_recipient.clone()], b"", &[]),
+            Err(EnvelopeError::ContextTooLong)
+        );
+        assert_eq!(
+            seal(CONTEXT, &vec![secp256k1_recipient; 256], b"", &[]),
+            Err(EnvelopeError::TooManyRecipients)
+        );
+        // Low order X25519 points are rejected.
+        assert_eq!(
+            seal(CONTEXT, &[RecipientKey::X25519([0u8; 32])], b"", &[]),
+            Err(EnvelopeError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        let (_, recipient) = x25519_key(1);
+        let bytes = seal(CONTEXT, &[recipient], b"backup", &[])
+            .unwrap()
+            .to_bytes();
+
+        for length in 0..bytes.len() - b"backup".len() - TAG_LENGTH {
+            assert!(SealedEnvelope::from_bytes(&bytes[..length]).is_err());
+        }
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 2;
+        assert_eq!(
+            SealedEnvelope::from_bytes(&wrong_version),
+            Err(EnvelopeError::UnsupportedVersion(2))
+        );
+
+        let mut wrong_suite = bytes;
+        wrong_suite[5] = 3;
+        assert_eq!(
+            SealedEnvelope::from_bytes(&wrong_suite),
+            Err(EnvelopeError::UnsupportedSuite(3))
+        );
+    }
+
+    struct RecipientVector {
+        shared_secret: &'static str,
+        wrapping_key: &'static str,
+        wrapped_key: &'static str,
+    }
+
+    struct Vector {
+        ephemeral_public_key: &'static str,
+        recipients: [RecipientVector; 2],
+        payload_key: &'static str,
+        ciphertext: &'static str,
+        envelope: &'static str,
+    }
+
+    const EPHEMERAL_SECRET: [u8; 32] = [0x11; 32];
+    const CONTENT_KEY: [u8; KEY_LENGTH] = [0x22; KEY_LENGTH];
+
+    // Known-answer vectors pinning the encoding and every step of the key derivation above, so
+    // any change to the format shows up here. They were derived independently by
+    // envelope_vectors.py, from fixed ephemeral keys, content key and nonce.
+    #[test]
+    fn test_vectors() {
+        for (suite_keys, vector) in [
+            ([secp256k1_key(1), secp256k1_key(2)], SECP256K1_VECTOR),
+            ([x25519_key(1), x25519_key(2)], X25519_VECTOR),
+        ] {
+            let recipients = suite_keys
+                .iter()
+                .map(|(_, recipient)| recipient.clone())
+                .collect::<Vec<_>>();
+            let envelope = seal_with(
+                CONTEXT,
+                &recipients,
+                b"Hello, envelope!",
+                b"aad",
+                &EPHEMERAL_SECRET,
+                &CONTENT_KEY,
+                [0x33; 24],
+            )
+            .unwrap();
+            let header = envelope.header();
+            assert_eq!(
+                hex::encode(&header.ephemeral_public_key),
+                vector.ephemeral_public_key
+            );
+
+            for (((opening_key, recipient), expected), stanza) in suite_keys
+                .iter()
+                .zip(&vector.recipients)
+                .zip(&header.recipients)
+            {
+                let recipient_public_key = recipient.to_bytes();
+                let (_, shared_secret) = header
+                    .suite
+                    .agree(&EPHEMERAL_SECRET, &recipient_public_key)
+                    .unwrap();
+                assert_eq!(
+                    hex::encode(shared_secret.expose_secret()),
+                    expected.shared_secret
+                );
+                let (_, recipient_shared_secret) = header
+                    .suite
+                    .agree(
+                        opening_key.secret.expose_secret(),
+                        &header.ephemeral_public_key,
+                    )
+                    .unwrap();
+                assert_eq!(
+                    recipient_shared_secret.expose_secret(),
+                    shared_secret.expose_secret()
+                );
+
+                let key = wrapping_key(
+                    header.suite,
+                    CONTEXT,
+                    &header.ephemeral_public_key,
+                    &recipient_public_key,
+                    shared_secret.expose_secret(),
+                );
+                assert_eq!(hex::encode(key.expose_secret()), expected.wrapping_key);
+                assert_eq!(stanza.key_id, key_id(&recipient_public_key));
+                assert_eq!(hex::encode(stanza.wrapped_key), expected.wrapped_key);
+            }
+
+            let key = payload_key(header.suite, CONTEXT, &CONTENT_KEY);
+            assert_eq!(hex::encode(key.expose_secret()), vector.payload_key);
+            assert_eq!(hex::encode(&envelope.ciphertext), vector.ciphertext);
+            assert_eq!(hex::encode(envelope.to_bytes()), vector.envelope);
+
+            let envelope =
+                SealedEnvelope::from_bytes(&hex::decode(vector.envelope).unwrap()).unwrap();
+            for (opening_key, _) in &suite_keys {
+                assert_eq!(
+                    envelope.open(CONTEXT, opening_key, b"aad").unwrap(),
+                    b"Hello, envelope!"
+                );
+            }
+        }
+    }
+
+    const SECP256K1_VECTOR: Vector = Vector {
+        ephemeral_public_key: "034f355bdcb7cc0af728ef3cceb9615d90684bb5b2ca5f859ab0f0b704075871aa",
+        recipients: [
+            RecipientVector {
+                shared_secret: "a9c41572ebcf5be026ff5e7f36079f9828bfc4d32fbdd9f1bd722b5937bee6c5",
+                wrapping_key: "e22d460d5ccc4f9f25d64be025a45d25384be110cc7066201fcd417ea52a3df6",
+                wrapped_key: concat!(
+                    "08f987fb41f1ea16aef1a99514f3e6510da175cd64db8c0eb903d7bb6410a7ca",
+                    "97cf1a402e05cb77ec3720d164cd69ea",
+                ),
+            },
+            RecipientVector {
+                shared_secret: "c45a2dbecf7d3dd528620952bc5b8f2765ae6d9a63d0e57b109be47fc20b4d24",
+                wrapping_key: "2da415cc90c7068b335252bf924f8a6c29728b54d411834361cba511c7166652",
+                wrapped_key: concat!(
+                    "e8c4cb7abbd56574b9e34d80aa1d7610ab4deae42d80c75d8140e7d6b1a0e4a3",
+                    "0ed51bd53a1c96b52d79569119c5aa13",
+                ),
+            },
+        ],
+        payload_key: "bbe83303676b05dc9a832d52d50ec7080ae38d34faa6b37566230b45872478d0",
+        ciphertext: "2134893b2247ea7bec99062a0067f96c94531b765a9b0015937504d49ce7af39",
+        envelope: concat!(
+            "424b534501010c536f635265634261636b7570034f355bdcb7cc0af728ef3cce",
+            "b9615d90684bb5b2ca5f859ab0f0b704075871aa333333333333333333333333",
+            "33333333333333333333333302f1d12012406b87af08f987fb41f1ea16aef1a9",
+            "9514f3e6510da175cd64db8c0eb903d7bb6410a7ca97cf1a402e05cb77ec3720",
+            "d164cd69ea80a9f99957b29af2e8c4cb7abbd56574b9e34d80aa1d7610ab4dea",
+            "e42d80c75d8140e7d6b1a0e4a30ed51bd53a1c96b52d79569119c5aa13213489",
+            "3b2247ea7bec99062a0067f96c94531b765a9b0015937504d49ce7af39",
+        ),
+    };
+    const X25519_VECTOR: Vector = Vector {
+        ephemeral_public_key: "7b4e909bbe7ffe44c465a220037d608ee35897d31ef972f07f74892cb0f73f13",
+        recipients: [
+            RecipientVector {
+                shared_secret: "e8e6a61bd1da83964bc84d9ae296529bfb7d34d10bb29f74e4da85e08ee3b04b",
+                wrapping_key: "3714eb3b41827a7dda3f631813c18387f91c14270bf68aaf309ec7228c3fd1f6",
+                wrapped_key: concat!(
+                    "e61fbeb071299ad7c9a6e15881d625216e5a29c98ed2edf19eac94e2f35394dd",
+                    "d2002e70a735e5fe9d150dccddd4ce29",
+                ),
+            },
+            RecipientVector {
+                shared_secret: "4a03396486568b056ebec3d2aeffa5dfcc6f96cf62b96522e156364477aaf77a",
+                wrapping_key: "c23a26a6bdc88eae3efe24c7ea47bd7f65eaaff40e5c4b223c219dfbc98c6195",
+                wrapped_key: concat!(
+                    "ee479b76ebc2d0d51baffccd3e1f8a0c170e173399248768c3e28d8f33252080",
+                    "821dbb65641a6a36f8bee02081a2ad57",
+                ),
+            },
+        ],
+        payload_key: "cdafddbcda650763f56d5d1dd6e66976a745a608dd702bdffb02e1ead64c1abd",
+        ciphertext: "c1d50cbbeae4c3794e2552c6abdfce2a3ea1dce04a4d8f54038f6cb39cd46737",
+        envelope: concat!(
+            "424b534501020c536f635265634261636b75707b4e909bbe7ffe44c465a22003",
+            "7d608ee35897d31ef972f07f74892cb0f73f1333333333333333333333333333",
+            "3333333333333333333333021a92f23852dc908de61fbeb071299ad7c9a6e158",
+            "81d625216e5a29c98ed2edf19eac94e2f35394ddd2002e70a735e5fe9d150dcc",
+            "ddd4ce29cfa570c653bd212bee479b76ebc2d0d51baffccd3e1f8a0c170e1733",
+            "99248768c3e28d8f33252080821dbb65641a6a36f8bee02081a2ad57c1d50cbb",
+            "eae4c3794e2552c6abdfce2a3ea1dce04a4d8f54038f6cb39cd46737",
+        ),
+    };
+}
@@ -0,0 +1,187 @@
+#!/usr/bin/env python3
+"""Reference implementation of sealed envelope v1, used to derive the known-answer vectors in
+envelope.rs independently of the Rust code. See the format description at the top of envelope.rs.
+
+Requires the `cryptography` package. secp256k1 point arithmetic and HChaCha20 aren't exposed by
+it, so they are implemented here directly.
+
+    python3 envelope_vectors.py
+"""
+
+import hashlib
+import struct
+
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric.x25519 import X25519PrivateKey, X25519PublicKey
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+from cryptography.hazmat.primitives.kdf.hkdf import HKDF
+
+CONTEXT = b"SocRecBackup"
+PLAINTEXT = b"Hello, envelope!"
+AAD = b"aad"
+EPHEMERAL_SECRET = bytes([0x11] * 32)
+CONTENT_KEY = bytes([0x22] * 32)
+NONCE = bytes([0x33] * 24)
+RECIPIENT_SECRETS = [bytes([1] * 32), bytes([2] * 32)]
+
+# secp256k1
+
+P = 2**256 - 2**32 - 977
+N = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+G = (
+    0x79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798,
+    0x483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8,
+)
+
+
+def point_add(a, b):
+    if a is None:
+        return b
+    if b is None:
+        return a
+    if a[0] == b[0] and (a[1] + b[1]) % P == 0:
+        return None
+    if a == b:
+        slope = 3 * a[0] * a[0] * pow(2 * a[1], -1, P)
+    else:
+        slope = (b[1] - a[1]) * pow(b[0] - a[0], -1, P)
+    x = (slope * slope - a[0] - b[0]) % P
+    return (x, (slope * (a[0] - x) - a[1]) % P)
+
+
+def point_mul(scalar, point):
+    result = None
+    while scalar:
+        if scalar & 1:
+            result = point_add(result, point)
+        point = point_add(point, point)
+        scalar >>= 1
+    return result
+
+
+def compress(point):
+    return bytes([2 + (point[1] & 1)]) + point[0].to_bytes(32, "big")
+
+
+def decompress(key):
+    x = int.from_bytes(key[1:], "big")
+    y = pow((x**3 + 7) % P, (P + 1) // 4, P)
+    if y & 1 != key[0] & 1:
+        y = P - y
+    return (x, y)
+
+
+def secp256k1_public(secret):
+    return compress(point_mul(int.from_bytes(secret, "big") % N, G))
+
+
+def secp256k1_agree(secret, public_key):
+    # libsecp256k1's default ECDH hash: SHA256 of the compressed shared point.
+    shared = point_mul(int.from_bytes(secret, "big") % N, decompress(public_key))
+    return hashlib.sha256(compress(shared)).digest()
+
+
+# X25519
+
+
+def x25519_public(secret):
+    return (
+        X25519PrivateKey.from_private_bytes(secret)
+        .public_key()
+        .public_bytes(serialization.Encoding.Raw, serialization.PublicFormat.Raw)
+    )
+
+
+def x25519_agree(secret, public_key):
+    return X25519PrivateKey.from_private_bytes(secret).exchange(
+        X25519PublicKey.from_public_bytes(public_key)
+    )
+
+
+# XChaCha20-Poly1305 (draft-irtf-cfrg-xchacha), built from HChaCha20 and ChaCha20-Poly1305.
+
+
+def rotl(value, count):
+    return ((value << count) & 0xFFFFFFFF) | (value >> (32 - count))
+
+
+def quarter_round(state, a, b, c, d):
+    state[a] = (state[a] + state[b]) & 0xFFFFFFFF
+    state[d] = rotl(state[d] ^ state[a], 16)
+    state[c] = (state[c] + state[d]) & 0xFFFFFFFF
+    state[b] = rotl(state[b] ^ state[c], 12)
+    state[a] = (state[a] + state[b]) & 0xFFFFFFFF
+    state[d] = rotl(state[d] ^ state[a], 8)
+    state[c] = (state[c] + state[d]) & 0xFFFFFFFF
+    state[b] = rotl(state[b] ^ state[c], 7)
+
+
+def hchacha20(key, nonce):
+    state = list(struct.unpack("<4I", b"expand 32-byte k"))
+    state += list(struct.unpack("<8I", key)) + list(struct.unpack("<4I", nonce))
+    for _ in range(10):
+        quarter_round(state, 0, 4, 8, 12)
+        quarter_round(state, 1, 5, 9, 13)
+        quarter_round(state, 2, 6, 10, 14)
+        quarter_round(state, 3, 7, 11, 15)
+        quarter_round(state, 0, 5, 10, 15)
+        quarter_round(state, 1, 6, 11, 12)
+        quarter_round(state, 2, 7, 8, 13)
+        quarter_round(state, 3, 4, 9, 14)
+    return struct.pack("<8I", *(state[0:4] + state[12:16]))
+
+
+def xchacha20poly1305_encrypt(key, nonce, plaintext, aad):
+    cipher = ChaCha20Poly1305(hchacha20(key, nonce[:16]))
+    return cipher.encrypt(bytes(4) + nonce[16:], plaintext, aad)
+
+
+# Envelope
+
+
+def hkdf(salt, ikm, info):
+    return HKDF(algorithm=hashes.SHA256(), length=32, salt=salt, info=info).derive(ikm)
+
+
+def info(suite_name, purpose):
+    return f"BitkeySealedEnvelope/v1/{suite_name}/{purpose}/".encode() + CONTEXT
+
+
+def vector(suite_id, suite_name, public, agree):
+    print(f"{suite_name}:")
+    ephemeral_public_key = public(EPHEMERAL_SECRET)
+    print(f"  ephemeral public key  {ephemeral_public_key.hex()}")
+
+    stanzas = b""
+    for index, recipient_secret in enumerate(RECIPIENT_SECRETS):
+        recipient_public_key = public(recipient_secret)
+        shared_secret = agree(EPHEMERAL_SECRET, recipient_public_key)
+        assert shared_secret == agree(recipient_secret, ephemeral_public_key)
+        wrapping_key = hkdf(
+            ephemeral_public_key + recipient_public_key, shared_secret, info(suite_name, "wrap")
+        )
+        wrapped_key = xchacha20poly1305_encrypt(wrapping_key, bytes(24), CONTENT_KEY, b"")
+        stanzas += hashlib.sha256(recipient_public_key).digest()[:8] + wrapped_key
+        print(f"  recipient {index} shared secret  {shared_secret.hex()}")
+        print(f"  recipient {index} wrapping key   {wrapping_key.hex()}")
+        print(f"  recipient {index} wrapped key    {wrapped_key.hex()}")
+
+    header = (
+        b"BKSE"
+        + bytes([1, suite_id, len(CONTEXT)])
+        + CONTEXT
+        + ephemeral_public_key
+        + NONCE
+        + bytes([len(RECIPIENT_SECRETS)])
+        + stanzas
+    )
+    payload_key = hkdf(b"", CONTENT_KEY, info(suite_name, "payload"))
+    ciphertext = xchacha20poly1305_encrypt(payload_key, NONCE, PLAINTEXT, header + AAD)
+    print(f"  payload key  {payload_key.hex()}")
+    print(f"  ciphertext   {ciphertext.hex()}")
+    print(f"  envelope     {(header + ciphertext).hex()}")
+
+
+if __name__ == "__main__":
+    vector(1, "secp256k1", secp256k1_public, secp256k1_agree)
+    vector(2, "x25519", x25519_public, x25519_agree)
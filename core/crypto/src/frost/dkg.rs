@@ -11,35 +11,118 @@ use secp256k1_zkp::{
         generate_frost_shares, CoefficientCommitment, FrostPublicKey, FrostShare, VerificationShare,
     },
 };
+use std::fmt;
 use thiserror::Error;
+use zeroize::ZeroizeOnDrop;
 
-use super::{KeyCommitments, Participant, ParticipantIndex, ShareDetails};
+use super::{KeyCommitments, Participant, ParticipantIndex, SecretShare, ShareDetails, REDACTED};
 
-static DKG_THRESHOLD: usize = 2;
+/// Threshold of the two-party App and Server DKG.
+pub(super) static DKG_THRESHOLD: usize = 2;
 
-pub fn generate_share_packages() -> Result<Vec<SharePackage>, KeygenError> {
+/// The participants of a DKG and how many of them are needed to produce a signature.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct DkgParameters {
+    participants: Vec<ParticipantIndex>,
+    threshold: usize,
+}
+
+impl DkgParameters {
+    /// participants – The indices of every participant, which must be unique and non-zero.
+    /// threshold – The number of participants needed to sign, between 2 and the participant count.
+    pub fn new(participants: Vec<ParticipantIndex>, threshold: usize) -> Result<Self, KeygenError> {
+        let has_duplicates = participants
+            .iter()
+            .enumerate()
+            .any(|(i, index)| participants[..i].contains(index));
+        if participants.contains(&ParticipantIndex(0))
+            || has_duplicates
+            || threshold < 2
+            || threshold > participants.len()
+        {
+            return Err(KeygenError::InvalidParticipants);
+        }
+
+        Ok(Self {
+            participants,
+            threshold,
+        })
+    }
+
+    /// Assigns indices 1 through `participant_count`, so the App and Server keep their usual
+    /// indices and any further participants, e.g. hardware, follow them.
+    pub fn with_participant_count(
+        participant_count: u8,
+        threshold: usize,
+    ) -> Result<Self, KeygenError> {
+        Self::new(
+            (1..=participant_count).map(ParticipantIndex).collect(),
+            threshold,
+        )
+    }
+
+    /// The 2-of-2 App and Server DKG.
+    pub fn two_party() -> Self {
+        Self {
+            participants: vec![Participant::App.into(), Participant::Server.into()],
+            threshold: DKG_THRESHOLD,
+        }
+    }
+
+    pub fn participants(&self) -> &[ParticipantIndex] {
+        &self.participants
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+}
+
+/// A share package generated for a single participant, tagged with its recipient so it can be
+/// routed to them.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct AddressedSharePackage {
+    pub recipient: ParticipantIndex,
+    pub share_package: SharePackage,
+}
+
+/// Generates one share package for each participant, in the order of `parameters.participants()`.
+/// The participant keeps its own package, and sends every other one to its recipient.
+pub fn generate_share_packages_for(
+    parameters: &DkgParameters,
+) -> Result<Vec<AddressedSharePackage>, KeygenError> {
     let mut seed = [0u8; 32];
     let mut rng = StdRng::from_entropy();
     rng.fill_bytes(&mut seed);
 
-    let participants = [Participant::App, Participant::Server]
+    let participants = parameters
+        .participants
         .iter()
         .map(|participant| (*participant).into())
         .collect::<Vec<zkp::PublicKey>>();
     let participants_refs = participants.iter().collect::<Vec<&zkp::PublicKey>>();
 
-    let (shares, commitments, pok) =
-        generate_frost_shares(zkp::SECP256K1, &seed, DKG_THRESHOLD, &participants_refs)
-            .expect("Should always succeed since we hardcode the threshold.");
+    let (shares, commitments, pok) = generate_frost_shares(
+        zkp::SECP256K1,
+        &seed,
+        parameters.threshold,
+        &participants_refs,
+    )
+    .map_err(|_| KeygenError::InvalidParticipants)?;
 
     let share_packages = shares
         .into_iter()
         .enumerate()
-        .map(|(index, share)| SharePackage {
-            index: participants[index],
-            coefficient_commitments: commitments.to_public_keys(),
-            proof_of_knowledge: pok,
-            intermediate_share: share,
+        .map(|(index, share)| AddressedSharePackage {
+            recipient: parameters.participants[index],
+            share_package: SharePackage {
+                index: participants[index],
+                coefficient_commitments: commitments.to_public_keys(),
+                proof_of_knowledge: pok,
+                intermediate_share: share.into(),
+            },
         })
         .collect();
 
@@ -48,21 +131,45 @@ pub fn generate_share_packages() -> Result<Vec<SharePackage>, KeygenError> {
 
 /// Aggregate the shares and generates key commitments.
 ///
-/// package – The participant's share package.
-/// peer_package – The peer's share package.
-/// share_agg_params – The participant's share aggregation parameters.
-pub fn aggregate_shares(
-    participant: Participant,
+/// parameters – The parameters every participant agreed on for this DKG.
+/// participant – The participant aggregating its shares.
+/// share_packages – The share packages addressed to the participant, one from every participant
+/// including itself.
+pub fn aggregate_shares_for(
+    parameters: &DkgParameters,
+    participant: ParticipantIndex,
     share_packages: &[&SharePackage],
 ) -> Result<ShareDetails, KeygenError> {
+    if !parameters.participants.contains(&participant) {
+        return Err(KeygenError::InvalidParticipants);
+    }
+    if share_packages.len() != parameters.participants.len() {
+        return Err(KeygenError::MissingSharePackage);
+    }
+
+    let participant_public_key: zkp::PublicKey = participant.into();
+    let is_addressed_to_participant = share_packages.iter().all(|package| {
+        package.index == participant_public_key
+            && package.coefficient_commitments.len() == parameters.threshold
+    });
+    if !is_addressed_to_participant {
+        return Err(KeygenError::InvalidIntermediateShare);
+    }
+    // Packages don't name their sender, but each sender commits to its own polynomial. Counting one
+    // sender's package twice would let it stand in for another participant.
+    let has_duplicate_sender = share_packages.iter().enumerate().any(|(i, package)| {
+        share_packages[..i]
+            .iter()
+            .any(|other| other.coefficient_commitments == package.coefficient_commitments)
+    });
+    if has_duplicate_sender {
+        return Err(KeygenError::DuplicateSharePackage);
+    }
+
     let intermediate_shares = share_packages
         .iter()
-        .map(|package| &package.intermediate_share)
+        .map(|package| package.intermediate_share.expose_secret())
         .collect::<Vec<&FrostShare>>();
-    let participants = share_packages
-        .iter()
-        .map(|package| &package.index)
-        .collect::<Vec<&zkp::PublicKey>>();
     let vss_commitments = share_packages
         .iter()
         .map(|package| {
@@ -83,30 +190,37 @@ pub fn aggregate_shares(
         &intermediate_shares,
         &vss_commitment_refs,
         &poks,
-        &participant.into(),
-        DKG_THRESHOLD,
+        &participant_public_key,
+        parameters.threshold,
     )
     .map_err(|_| KeygenError::ShareAggregationFailed)?;
 
-    let app_verification_share = VerificationShare::new(
-        zkp::SECP256K1,
-        &vss_commitment_refs,
-        &Participant::App.into(),
-        DKG_THRESHOLD,
-    )
-    .map_err(|_| KeygenError::VerificationShareGenerationFailed)?;
-    let server_verification_share = VerificationShare::new(
-        zkp::SECP256K1,
-        &vss_commitment_refs,
-        &Participant::Server.into(),
-        DKG_THRESHOLD,
-    )
-    .map_err(|_| KeygenError::VerificationShareGenerationFailed)?;
+    // Any `threshold` verification shares determine the aggregate public key.
+    let signers = parameters
+        .participants
+        .iter()
+        .take(parameters.threshold)
+        .map(|signer| zkp::PublicKey::from(*signer))
+        .collect::<Vec<zkp::PublicKey>>();
+    let verification_shares = signers
+        .iter()
+        .map(|signer| {
+            VerificationShare::new(
+                zkp::SECP256K1,
+                &vss_commitment_refs,
+                signer,
+                parameters.threshold,
+            )
+            .map_err(|_| KeygenError::VerificationShareGenerationFailed)
+        })
+        .collect::<Result<Vec<VerificationShare>, KeygenError>>()?;
 
     let aggregate_public_key = FrostPublicKey::from_verification_shares(
         zkp::SECP256K1,
-        &[&app_verification_share, &server_verification_share],
-        &participants,
+        &verification_shares
+            .iter()
+            .collect::<Vec<&VerificationShare>>(),
+        &signers.iter().collect::<Vec<&zkp::PublicKey>>(),
     );
 
     Ok(ShareDetails {
@@ -119,10 +233,33 @@ pub fn aggregate_shares(
                 .map(|zkp_public_key| ZkpPublicKey(zkp_public_key).into())
                 .collect::<Vec<PublicKey>>(),
         },
-        secret_share,
+        secret_share: secret_share.into(),
     })
 }
 
+/// Generates the App and Server share packages of the two-party DKG, in that order.
+pub fn generate_share_packages() -> Result<Vec<SharePackage>, KeygenError> {
+    Ok(generate_share_packages_for(&DkgParameters::two_party())?
+        .into_iter()
+        .map(|package| package.share_package)
+        .collect())
+}
+
+/// Aggregate the shares of the two-party DKG and generates key commitments.
+///
+/// participant – The participant aggregating its shares.
+/// share_packages – The participant's own share package, and the one from its peer.
+pub fn aggregate_shares(
+    participant: Participant,
+    share_packages: &[&SharePackage],
+) -> Result<ShareDetails, KeygenError> {
+    aggregate_shares_for(
+        &DkgParameters::two_party(),
+        participant.into(),
+        share_packages,
+    )
+}
+
 pub fn equality_check(
     peer_key_commitments: &KeyCommitments,
     share_details: ShareDetails,
@@ -134,13 +271,26 @@ pub fn equality_check(
     Ok(share_details)
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "bitcoin::secp256k1::serde")]
 pub struct SharePackage {
     index: zkp::PublicKey,
     coefficient_commitments: Vec<zkp::PublicKey>,
     proof_of_knowledge: zkp::schnorr::Signature,
-    intermediate_share: FrostShare,
+    intermediate_share: SecretShare,
+}
+
+impl ZeroizeOnDrop for SharePackage {}
+
+impl fmt::Debug for SharePackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharePackage")
+            .field("index", &self.index)
+            .field("coefficient_commitments", &self.coefficient_commitments)
+            .field("proof_of_knowledge", &self.proof_of_knowledge)
+            .field("intermediate_share", &REDACTED)
+            .finish()
+    }
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -149,6 +299,8 @@ pub enum KeygenError {
     MissingSharePackage,
     #[error("Unable to run DKG for the given participants.")]
     InvalidParticipants,
+    #[error("Received more than one share package from the same participant")]
+    DuplicateSharePackage,
     #[error("Invalid proof of knowledge")]
     InvalidProofOfKnowledge,
     #[error("Invalid intermediate share")]
@@ -159,9 +311,15 @@ pub enum KeygenError {
     ShareAggregationFailed,
     #[error("Unable to generate verification share")]
     VerificationShareGenerationFailed,
+    #[error("Invalid refresh package")]
+    InvalidRefreshPackage,
+    #[error("Unable to refresh share")]
+    ShareRefreshFailed,
+    #[error("Unable to decode the server's response")]
+    InvalidServerResponse,
 }
 
-struct ZkpPublicKey(zkp::PublicKey);
+pub(super) struct ZkpPublicKey(pub(super) zkp::PublicKey);
 // We expose and ingest non-secpZKP publicly outside this crate, so we'd need some way to
 // "translate" between them.
 impl From<PublicKey> for ZkpPublicKey {
@@ -183,11 +341,16 @@ impl From<ZkpPublicKey> for PublicKey {
     }
 }
 
-/// We use participant indices (1, 2, ...) to derive an identity public key.
 impl From<Participant> for zkp::PublicKey {
     fn from(participant: Participant) -> Self {
+        ParticipantIndex::from(participant).into()
+    }
+}
+
+/// We use participant indices (1, 2, ...) to derive an identity public key.
+impl From<ParticipantIndex> for zkp::PublicKey {
+    fn from(index: ParticipantIndex) -> Self {
         let generator_point = get_generator_point();
-        let index: ParticipantIndex = participant.into();
         let mut index_bytes = [0u8; 32];
         index_bytes[31] = index.0;
         let participant_index_tweak =
@@ -307,8 +470,12 @@ mod tests {
 
     use crate::frost::dkg::{equality_check, generate_share_packages};
     use crate::frost::Participant::{self, App, Server};
+    use crate::frost::{ParticipantIndex, ShareDetails};
 
-    use super::{aggregate_shares, app, server, DKG_THRESHOLD};
+    use super::{
+        aggregate_shares, aggregate_shares_for, app, generate_share_packages_for, server,
+        DkgParameters, KeygenError, ZkpPublicKey, DKG_THRESHOLD,
+    };
 
     #[test]
     fn test_equality_check() {
@@ -344,6 +511,156 @@ mod tests {
         assert!(equality_check(&server_share_details.key_commitments, app_share_details).is_ok())
     }
 
+    fn run_dkg(parameters: &DkgParameters) -> Vec<ShareDetails> {
+        let share_packages = parameters
+            .participants()
+            .iter()
+            .map(|_| generate_share_packages_for(parameters).unwrap())
+            .collect::<Vec<_>>();
+
+        parameters
+            .participants()
+            .iter()
+            .map(|participant| {
+                let packages_for_participant = share_packages
+                    .iter()
+                    .flatten()
+                    .filter(|package| package.recipient == *participant)
+                    .map(|package| &package.share_package)
+                    .collect::<Vec<_>>();
+                aggregate_shares_for(parameters, *participant, &packages_for_participant).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dkg_parameters() {
+        assert_eq!(
+            DkgParameters::with_participant_count(2, 2).unwrap(),
+            DkgParameters::two_party()
+        );
+        assert!(DkgParameters::with_participant_count(3, 2).is_ok());
+        assert!(DkgParameters::with_participant_count(3, 3).is_ok());
+
+        for (participants, threshold) in [
+            (vec![1, 2], 1),
+            (vec![1, 2], 3),
+            (vec![1, 1, 2], 2),
+            (vec![0, 1, 2], 2),
+        ] {
+            assert_eq!(
+                DkgParameters::new(
+                    participants.into_iter().map(ParticipantIndex).collect(),
+                    threshold
+                ),
+                Err(KeygenError::InvalidParticipants)
+            );
+        }
+    }
+
+    #[test]
+    fn test_two_of_three() {
+        let parameters = DkgParameters::with_participant_count(3, 2).unwrap();
+        let share_details = run_dkg(&parameters);
+
+        // Every participant agrees on the key commitments.
+        for details in &share_details[1..] {
+            assert!(equality_check(&share_details[0].key_commitments, details.clone()).is_ok());
+        }
+
+        // Any two of the three participants' verification shares recover the aggregate key.
+        let key_commitments = &share_details[0].key_commitments;
+        let vss_commitments = CoefficientCommitment::from_public_keys(
+            key_commitments
+                .vss_commitments
+                .iter()
+                .map(|public_key| ZkpPublicKey::from(*public_key).0)
+                .collect(),
+        );
+        for signers in [[1, 2], [1, 3], [2, 3]] {
+            let signers = signers
+                .map(|index| zkp::PublicKey::from(ParticipantIndex(index)))
+                .to_vec();
+            let verification_shares = signers
+                .iter()
+                .map(|signer| {
+                    VerificationShare::new(
+                        zkp::SECP256K1,
+                        &[&vss_commitments],
+                        signer,
+                        parameters.threshold(),
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<_>>();
+            let frost_public_key = FrostPublicKey::from_verification_shares(
+                zkp::SECP256K1,
+                &verification_shares.iter().collect::<Vec<_>>(),
+                &signers.iter().collect::<Vec<_>>(),
+            );
+
+            assert_eq!(
+                frost_public_key.public_key(zkp::SECP256K1),
+                ZkpPublicKey::from(key_commitments.aggregate_public_key).0
+            );
+        }
+    }
+
+    #[test]
+    fn test_aggregate_rejects_misaddressed_packages() {
+        let parameters = DkgParameters::with_participant_count(3, 2).unwrap();
+        let share_packages = generate_share_packages_for(&parameters).unwrap();
+        let other_share_packages = generate_share_packages_for(&parameters).unwrap();
+        let participant = ParticipantIndex(3);
+
+        // Packages addressed to other participants.
+        assert_eq!(
+            aggregate_shares_for(
+                &parameters,
+                participant,
+                &share_packages
+                    .iter()
+                    .map(|package| &package.share_package)
+                    .collect::<Vec<_>>()
+            ),
+            Err(KeygenError::InvalidIntermediateShare)
+        );
+        // A missing package from the third participant.
+        assert_eq!(
+            aggregate_shares_for(
+                &parameters,
+                participant,
+                &[
+                    &share_packages[2].share_package,
+                    &other_share_packages[2].share_package
+                ]
+            ),
+            Err(KeygenError::MissingSharePackage)
+        );
+        // The same sender's package twice.
+        let other_share_packages = [
+            generate_share_packages_for(&parameters).unwrap(),
+            generate_share_packages_for(&parameters).unwrap(),
+        ];
+        assert_eq!(
+            aggregate_shares_for(
+                &parameters,
+                participant,
+                &[
+                    &share_packages[2].share_package,
+                    &other_share_packages[0][2].share_package,
+                    &other_share_packages[0][2].share_package
+                ]
+            ),
+            Err(KeygenError::DuplicateSharePackage)
+        );
+        // A participant that isn't part of the DKG.
+        assert_eq!(
+            aggregate_shares_for(&parameters, ParticipantIndex(4), &[]),
+            Err(KeygenError::InvalidParticipants)
+        );
+    }
+
     #[test]
     fn test_wrappers() {
         let app_initiate_result = app::initiate_dkg().unwrap();
@@ -438,7 +755,7 @@ mod tests {
         let (app_secret_nonce, app_public_nonce) = new_frost_nonce_pair(
             zkp::SECP256K1,
             FrostSessionId::random(),
-            &app_share_details.secret_share,
+            app_share_details.secret_share.expose_secret(),
             &aggregate_pubkey,
             &msg,
             None,
@@ -447,7 +764,7 @@ mod tests {
         let (server_secret_nonce, server_public_nonce) = new_frost_nonce_pair(
             zkp::SECP256K1,
             FrostSessionId::random(),
-            &server_share_details.secret_share,
+            server_share_details.secret_share.expose_secret(),
             &aggregate_pubkey,
             &msg,
             None,
@@ -476,13 +793,13 @@ mod tests {
         let app_partial_sig = app_frost_session.partial_sign(
             zkp::SECP256K1,
             app_secret_nonce,
-            &app_share_details.secret_share,
+            app_share_details.secret_share.expose_secret(),
             &aggregate_pubkey,
         );
         let server_partial_sig = server_frost_session.partial_sign(
             zkp::SECP256K1,
             server_secret_nonce,
-            &server_share_details.secret_share,
+            server_share_details.secret_share.expose_secret(),
             &aggregate_pubkey,
         );
 
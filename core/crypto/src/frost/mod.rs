@@ -3,18 +3,90 @@ use bitcoin::secp256k1::{
     PublicKey,
 };
 
+use std::fmt;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
 pub use secp256k1_zkp::frost::FrostShare;
 
 pub mod dkg;
+pub mod refresh;
+pub mod signing;
+
+/// A `FrostShare` that is wiped from memory when dropped, and redacted when debug-printed.
+/// Serializes exactly like the `FrostShare` it wraps.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde", transparent)]
+pub struct SecretShare(FrostShare);
+
+impl SecretShare {
+    pub fn expose_secret(&self) -> &FrostShare {
+        &self.0
+    }
+}
+
+impl From<FrostShare> for SecretShare {
+    fn from(share: FrostShare) -> Self {
+        Self(share)
+    }
+}
+
+impl PartialEq for SecretShare {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .serialize()
+            .as_slice()
+            .ct_eq(other.0.serialize().as_slice())
+            .into()
+    }
+}
+
+impl Zeroize for SecretShare {
+    fn zeroize(&mut self) {
+        // SAFETY: `FrostShare` is a plain 32 byte array with no pointers or drop glue, and all
+        // zeros is a valid, if useless, value for it.
+        unsafe { zeroize::zeroize_flat_type(&mut self.0) }
+    }
+}
+
+impl Drop for SecretShare {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretShare {}
+
+impl fmt::Debug for SecretShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
 
 /// Output of the DKG and Refresh protocol, containing the secret share and VSS commitments.
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+///
+/// The share is wiped when dropped. `ShareDetails` has no `Drop` of its own, so its fields can
+/// still be moved out, e.g. by the FFI bindings.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "bitcoin::secp256k1::serde")]
 pub struct ShareDetails {
-    pub secret_share: FrostShare,
+    pub secret_share: SecretShare,
     pub key_commitments: KeyCommitments,
 }
 
+impl ZeroizeOnDrop for ShareDetails {}
+
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+impl fmt::Debug for ShareDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShareDetails")
+            .field("secret_share", &REDACTED)
+            .field("key_commitments", &self.key_commitments)
+            .finish()
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(crate = "bitcoin::secp256k1::serde")]
 pub struct KeyCommitments {
@@ -51,3 +123,15 @@ impl From<Participant> for ParticipantIndex {
         }
     }
 }
+
+impl TryFrom<ParticipantIndex> for Participant {
+    type Error = ParticipantIndex;
+
+    fn try_from(index: ParticipantIndex) -> Result<Self, Self::Error> {
+        match index {
+            APP_PARTICIPANT_INDEX => Ok(Participant::App),
+            SERVER_PARTICIPANT_INDEX => Ok(Participant::Server),
+            _ => Err(index),
+        }
+    }
+}
@@ -0,0 +1,584 @@
+use bitcoin::secp256k1::{
+    serde::{Deserialize, Serialize},
+    PublicKey, Scalar, SecretKey, SECP256K1,
+};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use std::fmt;
+use zeroize::ZeroizeOnDrop;
+
+use super::{
+    dkg::{DkgParameters, KeygenError},
+    FrostShare, KeyCommitments, Participant, ParticipantIndex, SecretShare, ShareDetails, REDACTED,
+};
+
+/// A refresh share sent from one participant to another.
+///
+/// Refresh shares are evaluations of a random polynomial whose constant term is zero, so adding
+/// them to an existing secret share re-randomizes it without changing the aggregate public key.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct RefreshPackage {
+    sender: ParticipantIndex,
+    recipient: ParticipantIndex,
+    /// Commitments to the non-constant coefficients of the sender's polynomial. There is no
+    /// commitment for the constant term since it is always zero.
+    coefficient_commitments: Vec<PublicKey>,
+    intermediate_share: SecretShare,
+}
+
+impl ZeroizeOnDrop for RefreshPackage {}
+
+impl RefreshPackage {
+    pub fn recipient(&self) -> ParticipantIndex {
+        self.recipient
+    }
+}
+
+impl fmt::Debug for RefreshPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshPackage")
+            .field("sender", &self.sender)
+            .field("recipient", &self.recipient)
+            .field("coefficient_commitments", &self.coefficient_commitments)
+            .field("intermediate_share", &REDACTED)
+            .finish()
+    }
+}
+
+/// Generates one refresh package per participant of the two-party App and Server key, in the same
+/// order as `dkg::generate_share_packages`.
+pub fn generate_refresh_packages(sender: Participant) -> Result<Vec<RefreshPackage>, KeygenError> {
+    generate_refresh_packages_for(&DkgParameters::two_party(), sender.into())
+}
+
+/// Generates one refresh package per participant of a key generated with `parameters`, in the
+/// order of `parameters.participants()`.
+pub fn generate_refresh_packages_for(
+    parameters: &DkgParameters,
+    sender: ParticipantIndex,
+) -> Result<Vec<RefreshPackage>, KeygenError> {
+    if !parameters.participants().contains(&sender) {
+        return Err(KeygenError::InvalidParticipants);
+    }
+
+    let mut rng = StdRng::from_entropy();
+    let mut coefficients = (1..parameters.threshold())
+        .map(|_| random_secret_key(&mut rng))
+        .collect::<Vec<SecretKey>>();
+    let coefficient_commitments = coefficients
+        .iter()
+        .map(|coefficient| coefficient.public_key(SECP256K1))
+        .collect::<Vec<PublicKey>>();
+
+    let refresh_packages = parameters
+        .participants()
+        .iter()
+        .map(|recipient| {
+            let mut intermediate_share = evaluate_polynomial(&coefficients, *recipient)?;
+            let refresh_package = RefreshPackage {
+                sender,
+                recipient: *recipient,
+                coefficient_commitments: coefficient_commitments.clone(),
+                intermediate_share: secret_key_to_share(&intermediate_share)?.into(),
+            };
+            intermediate_share.non_secure_erase();
+            Ok(refresh_package)
+        })
+        .collect();
+
+    // `SecretKey` is `Copy`, so this is best effort: it wipes the polynomial we keep, not every
+    // copy made while evaluating it.
+    coefficients
+        .iter_mut()
+        .for_each(SecretKey::non_secure_erase);
+    refresh_packages
+}
+
+/// Adds the refresh shares addressed to `participant` of the two-party App and Server key to its
+/// existing secret share, and updates the VSS commitments accordingly.
+///
+/// share_details – The participant's current share details.
+/// refresh_packages – One refresh package from every participant, including itself.
+pub fn aggregate_refresh_shares(
+    participant: Participant,
+    share_details: &ShareDetails,
+    refresh_packages: &[&RefreshPackage],
+) -> Result<ShareDetails, KeygenError> {
+    aggregate_refresh_shares_for(
+        &DkgParameters::two_party(),
+        participant.into(),
+        share_details,
+        refresh_packages,
+    )
+}
+
+/// Like `aggregate_refresh_shares`, for a participant of a key generated with `parameters`. Every
+/// participant must refresh, so there must be exactly one package from each of them.
+pub fn aggregate_refresh_shares_for(
+    parameters: &DkgParameters,
+    participant: ParticipantIndex,
+    share_details: &ShareDetails,
+    refresh_packages: &[&RefreshPackage],
+) -> Result<ShareDetails, KeygenError> {
+    let mut senders = refresh_packages
+        .iter()
+        .map(|package| package.sender)
+        .collect::<Vec<ParticipantIndex>>();
+    senders.sort_by_key(|index| index.0);
+    let mut participants = parameters.participants().to_vec();
+    participants.sort_by_key(|index| index.0);
+    if !participants.contains(&participant) || senders != participants {
+        return Err(KeygenError::InvalidParticipants);
+    }
+
+    let threshold = parameters.threshold();
+    let mut secret_share = share_to_secret_key(share_details.secret_share.expose_secret())?;
+    let mut vss_commitments = share_details.key_commitments.vss_commitments.clone();
+    if vss_commitments.len() != threshold {
+        return Err(KeygenError::InvalidKeyCommitments);
+    }
+
+    for package in refresh_packages {
+        if package.recipient != participant
+            || package.coefficient_commitments.len() != threshold - 1
+        {
+            return Err(KeygenError::InvalidRefreshPackage);
+        }
+
+        let refresh_share = share_to_secret_key(package.intermediate_share.expose_secret())?;
+        verify_refresh_share(
+            &refresh_share,
+            &package.coefficient_commitments,
+            participant,
+        )?;
+
+        secret_share = secret_share
+            .add_tweak(&Scalar::from(refresh_share))
+            .map_err(|_| KeygenError::ShareRefreshFailed)?;
+        // The constant term's commitment is the aggregate public key, which stays the same.
+        for (commitment, refresh_commitment) in vss_commitments
+            .iter_mut()
+            .skip(1)
+            .zip(&package.coefficient_commitments)
+        {
+            *commitment = commitment
+                .combine(refresh_commitment)
+                .map_err(|_| KeygenError::ShareRefreshFailed)?;
+        }
+    }
+
+    let refreshed_share = secret_key_to_share(&secret_share);
+    secret_share.non_secure_erase();
+
+    Ok(ShareDetails {
+        secret_share: refreshed_share?.into(),
+        key_commitments: KeyCommitments {
+            vss_commitments,
+            aggregate_public_key: share_details.key_commitments.aggregate_public_key,
+        },
+    })
+}
+
+/// Checks that a refresh share lies on the polynomial committed to by the sender.
+fn verify_refresh_share(
+    refresh_share: &SecretKey,
+    coefficient_commitments: &[PublicKey],
+    recipient: ParticipantIndex,
+) -> Result<(), KeygenError> {
+    let index = index_scalar(recipient);
+
+    // Horner's method over the commitments, with an implicit zero constant term.
+    let mut expected: Option<PublicKey> = None;
+    for commitment in coefficient_commitments.iter().rev() {
+        let accumulated = match expected {
+            Some(expected) => expected
+                .combine(commitment)
+                .map_err(|_| KeygenError::InvalidRefreshPackage)?,
+            None => *commitment,
+        };
+        expected = Some(
+            accumulated
+                .mul_tweak(SECP256K1, &index)
+                .map_err(|_| KeygenError::InvalidRefreshPackage)?,
+        );
+    }
+
+    match expected {
+        Some(expected) if expected == refresh_share.public_key(SECP256K1) => Ok(()),
+        _ => Err(KeygenError::InvalidRefreshPackage),
+    }
+}
+
+fn evaluate_polynomial(
+    coefficients: &[SecretKey],
+    index: ParticipantIndex,
+) -> Result<SecretKey, KeygenError> {
+    let index = index_scalar(index);
+
+    let mut result: Option<SecretKey> = None;
+    for coefficient in coefficients.iter().rev() {
+        let accumulated = match result {
+            Some(result) => result
+                .add_tweak(&Scalar::from(*coefficient))
+                .map_err(|_| KeygenError::ShareRefreshFailed)?,
+            None => *coefficient,
+        };
+        result = Some(
+            accumulated
+                .mul_tweak(&index)
+                .map_err(|_| KeygenError::ShareRefreshFailed)?,
+        );
+    }
+
+    result.ok_or(KeygenError::ShareRefreshFailed)
+}
+
+fn random_secret_key(rng: &mut StdRng) -> SecretKey {
+    loop {
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        // Rejection sampling; out-of-range bytes are astronomically unlikely.
+        if let Ok(secret_key) = SecretKey::from_slice(&bytes) {
+            return secret_key;
+        }
+    }
+}
+
+fn index_scalar(index: ParticipantIndex) -> Scalar {
+    let mut index_bytes = [0u8; 32];
+    index_bytes[31] = index.0;
+    Scalar::from_be_bytes(index_bytes).expect("Scalar should always be valid.")
+}
+
+fn share_to_secret_key(share: &FrostShare) -> Result<SecretKey, KeygenError> {
+    SecretKey::from_slice(&share.serialize()).map_err(|_| KeygenError::InvalidIntermediateShare)
+}
+
+fn secret_key_to_share(secret_key: &SecretKey) -> Result<FrostShare, KeygenError> {
+    FrostShare::from_slice(&secret_key.secret_bytes())
+        .map_err(|_| KeygenError::InvalidIntermediateShare)
+}
+
+pub mod server {
+    use crate::frost::{dkg::equality_check, KeyCommitments, Participant, ShareDetails};
+    use bitcoin::secp256k1::serde::{Deserialize, Serialize};
+
+    use super::{aggregate_refresh_shares, generate_refresh_packages, KeygenError, RefreshPackage};
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(crate = "bitcoin::secp256k1::serde")]
+    pub struct InitiateRefreshResult {
+        pub refresh_package: RefreshPackage,
+        pub share_details: ShareDetails,
+    }
+
+    pub fn initiate_refresh(
+        share_details: &ShareDetails,
+        peer_refresh_package: &RefreshPackage,
+    ) -> Result<InitiateRefreshResult, KeygenError> {
+        let mut refresh_packages = generate_refresh_packages(Participant::Server)?;
+
+        let refresh_package_for_server = refresh_packages
+            .pop()
+            .expect("server refresh package should exist.");
+
+        let refresh_package_for_app = refresh_packages
+            .pop()
+            .expect("app refresh package should exist.");
+
+        let share_details = aggregate_refresh_shares(
+            Participant::Server,
+            share_details,
+            &[peer_refresh_package, &refresh_package_for_server],
+        )?;
+
+        Ok(InitiateRefreshResult {
+            refresh_package: refresh_package_for_app,
+            share_details,
+        })
+    }
+
+    pub fn continue_refresh(
+        share_details: ShareDetails,
+        peer_key_commitments: &KeyCommitments,
+    ) -> Result<ShareDetails, KeygenError> {
+        equality_check(peer_key_commitments, share_details)
+    }
+}
+
+pub mod app {
+    use crate::frost::{dkg::equality_check, KeyCommitments, Participant, ShareDetails};
+
+    use super::{aggregate_refresh_shares, generate_refresh_packages, KeygenError, RefreshPackage};
+
+    pub struct InitialRefreshPackage {
+        pub refresh_package: RefreshPackage,
+        pub refresh_package_for_peer: RefreshPackage,
+    }
+
+    pub fn initiate_refresh() -> Result<InitialRefreshPackage, KeygenError> {
+        let mut refresh_packages = generate_refresh_packages(Participant::App)?;
+
+        let refresh_package_for_server = refresh_packages
+            .pop()
+            .expect("Should have a RefreshPackage for Server");
+
+        let refresh_package_for_app = refresh_packages
+            .pop()
+            .expect("Should have an App RefreshPackage");
+
+        Ok(InitialRefreshPackage {
+            refresh_package: refresh_package_for_app,
+            refresh_package_for_peer: refresh_package_for_server,
+        })
+    }
+
+    pub fn continue_refresh(
+        share_details: &ShareDetails,
+        refresh_package: &RefreshPackage,
+        peer_refresh_package: &RefreshPackage,
+        peer_key_commitments: &KeyCommitments,
+    ) -> Result<ShareDetails, KeygenError> {
+        let share_details = aggregate_refresh_shares(
+            Participant::App,
+            share_details,
+            &[refresh_package, peer_refresh_package],
+        )?;
+        equality_check(peer_key_commitments, share_details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frost::{
+        dkg::{self, DkgParameters, KeygenError},
+        Participant, ParticipantIndex, ShareDetails,
+    };
+
+    use super::{
+        aggregate_refresh_shares, aggregate_refresh_shares_for, app, generate_refresh_packages,
+        generate_refresh_packages_for, server,
+    };
+
+    fn run_dkg() -> (ShareDetails, ShareDetails) {
+        let app_initiate_result = dkg::app::initiate_dkg().unwrap();
+        let server_initiate_result =
+            dkg::server::initiate_dkg(&app_initiate_result.share_package_for_peer).unwrap();
+        let app_share_details = dkg::app::continue_dkg(
+            &app_initiate_result.share_package,
+            &server_initiate_result.share_package,
+            &server_initiate_result.share_details.key_commitments,
+        )
+        .unwrap();
+
+        (app_share_details, server_initiate_result.share_details)
+    }
+
+    #[test]
+    fn test_refresh_preserves_aggregate_public_key() {
+        let (app_share_details, server_share_details) = run_dkg();
+
+        let app_initiate_result = app::initiate_refresh().unwrap();
+        let server_initiate_result = server::initiate_refresh(
+            &server_share_details,
+            &app_initiate_result.refresh_package_for_peer,
+        )
+        .unwrap();
+        let refreshed_app_share_details = app::continue_refresh(
+            &app_share_details,
+            &app_initiate_result.refresh_package,
+            &server_initiate_result.refresh_package,
+            &server_initiate_result.share_details.key_commitments,
+        )
+        .unwrap();
+        let refreshed_server_share_details = server::continue_refresh(
+            server_initiate_result.share_details,
+            &refreshed_app_share_details.key_commitments,
+        )
+        .unwrap();
+
+        assert_eq!(
+            refreshed_app_share_details
+                .key_commitments
+                .aggregate_public_key,
+            app_share_details.key_commitments.aggregate_public_key
+        );
+        assert_eq!(
+            refreshed_server_share_details
+                .key_commitments
+                .aggregate_public_key,
+            server_share_details.key_commitments.aggregate_public_key
+        );
+        assert_ne!(
+            refreshed_app_share_details.secret_share,
+            app_share_details.secret_share
+        );
+        assert_ne!(
+            refreshed_server_share_details.secret_share,
+            server_share_details.secret_share
+        );
+    }
+
+    #[test]
+    fn test_refreshed_shares_can_sign() {
+        use crate::frost::signing::{self, generate_nonce_pair, partial_sign, SigningPackage};
+        use bitcoin::{hashes::Hash, sighash::TapSighash};
+
+        let (app_share_details, server_share_details) = run_dkg();
+        let app_initiate_result = app::initiate_refresh().unwrap();
+        let server_initiate_result = server::initiate_refresh(
+            &server_share_details,
+            &app_initiate_result.refresh_package_for_peer,
+        )
+        .unwrap();
+        let app_share_details = app::continue_refresh(
+            &app_share_details,
+            &app_initiate_result.refresh_package,
+            &server_initiate_result.refresh_package,
+            &server_initiate_result.share_details.key_commitments,
+        )
+        .unwrap();
+        let server_share_details = server_initiate_result.share_details;
+
+        let sighash = TapSighash::from_byte_array([7u8; 32]);
+        let (app_secret_nonce, app_commitment) =
+            generate_nonce_pair(Participant::App, &app_share_details, sighash, None).unwrap();
+        let server_result =
+            signing::server::sign(&server_share_details, sighash, None, app_commitment.clone())
+                .unwrap();
+        let signing_package = SigningPackage::new(
+            sighash,
+            None,
+            vec![app_commitment, server_result.commitment],
+        )
+        .unwrap();
+        let app_partial_signature = partial_sign(
+            Participant::App,
+            &app_share_details,
+            &signing_package,
+            app_secret_nonce,
+        )
+        .unwrap();
+
+        signing::aggregate(
+            &app_share_details.key_commitments,
+            &signing_package,
+            &[&app_partial_signature, &server_result.partial_signature],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_refresh_package_for_wrong_recipient_is_rejected() {
+        let (app_share_details, _) = run_dkg();
+
+        let app_refresh_packages = generate_refresh_packages(Participant::App).unwrap();
+        let server_refresh_packages = generate_refresh_packages(Participant::Server).unwrap();
+
+        // The App aggregating the Server's packages.
+        assert_eq!(
+            aggregate_refresh_shares(
+                Participant::App,
+                &app_share_details,
+                &[
+                    app_refresh_packages.first().unwrap(),
+                    server_refresh_packages.get(1).unwrap(),
+                ],
+            ),
+            Err(KeygenError::InvalidRefreshPackage)
+        );
+    }
+
+    #[test]
+    fn test_tampered_refresh_share_is_rejected() {
+        let (app_share_details, _) = run_dkg();
+
+        let app_refresh_packages = generate_refresh_packages(Participant::App).unwrap();
+        let server_refresh_packages = generate_refresh_packages(Participant::Server).unwrap();
+        let mut tampered_package = server_refresh_packages.first().unwrap().clone();
+        tampered_package.intermediate_share = server_refresh_packages
+            .get(1)
+            .unwrap()
+            .intermediate_share
+            .clone();
+
+        assert_eq!(
+            aggregate_refresh_shares(
+                Participant::App,
+                &app_share_details,
+                &[app_refresh_packages.first().unwrap(), &tampered_package],
+            ),
+            Err(KeygenError::InvalidRefreshPackage)
+        );
+    }
+
+    #[test]
+    fn test_refresh_two_of_three() {
+        let parameters = DkgParameters::with_participant_count(3, 2).unwrap();
+        let share_packages = parameters
+            .participants()
+            .iter()
+            .map(|_| dkg::generate_share_packages_for(&parameters).unwrap())
+            .collect::<Vec<_>>();
+        let share_details = parameters
+            .participants()
+            .iter()
+            .map(|participant| {
+                let packages = share_packages
+                    .iter()
+                    .flatten()
+                    .filter(|package| package.recipient == *participant)
+                    .map(|package| &package.share_package)
+                    .collect::<Vec<_>>();
+                dkg::aggregate_shares_for(&parameters, *participant, &packages).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let refresh_packages = parameters
+            .participants()
+            .iter()
+            .map(|sender| generate_refresh_packages_for(&parameters, *sender).unwrap())
+            .collect::<Vec<_>>();
+        let refreshed_share_details = parameters
+            .participants()
+            .iter()
+            .zip(&share_details)
+            .map(|(participant, details)| {
+                let packages = refresh_packages
+                    .iter()
+                    .flatten()
+                    .filter(|package| package.recipient() == *participant)
+                    .collect::<Vec<_>>();
+                aggregate_refresh_shares_for(&parameters, *participant, details, &packages).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        for (refreshed, details) in refreshed_share_details.iter().zip(&share_details) {
+            assert_eq!(
+                refreshed.key_commitments,
+                refreshed_share_details[0].key_commitments
+            );
+            assert_eq!(
+                refreshed.key_commitments.aggregate_public_key,
+                details.key_commitments.aggregate_public_key
+            );
+            assert_ne!(refreshed.secret_share, details.secret_share);
+        }
+
+        // Every participant has to refresh; a package missing from the third one is rejected.
+        let packages_for_first = refresh_packages[..2]
+            .iter()
+            .flatten()
+            .filter(|package| package.recipient() == ParticipantIndex(1))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            aggregate_refresh_shares_for(
+                &parameters,
+                ParticipantIndex(1),
+                &share_details[0],
+                &packages_for_first
+            ),
+            Err(KeygenError::InvalidParticipants)
+        );
+    }
+}
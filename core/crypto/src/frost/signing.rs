@@ -0,0 +1,706 @@
+use bitcoin::{
+    hashes::Hash,
+    key::{TapTweak, XOnlyPublicKey},
+    secp256k1::{
+        schnorr::Signature,
+        serde::{Deserialize, Serialize},
+    },
+    sighash::TapSighash,
+    taproot::{TapNodeHash, TapTweakHash},
+};
+use secp256k1_zkp::{
+    self as zkp,
+    frost::{
+        CoefficientCommitment, FrostPartialSignature, FrostPubNonce, FrostPublicKey, FrostSession,
+        FrostSessionId, VerificationShare,
+    },
+    new_frost_nonce_pair, Message,
+};
+use thiserror::Error;
+
+pub use secp256k1_zkp::frost::FrostSecNonce;
+
+use super::{
+    dkg::{DkgParameters, ZkpPublicKey},
+    KeyCommitments, Participant, ParticipantIndex, ShareDetails,
+};
+
+/// Round 1 output of a participant: the public half of its signing nonce.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct SigningCommitment {
+    pub participant_index: ParticipantIndex,
+    pub public_nonce: FrostPubNonce,
+}
+
+/// Everything a participant needs to produce a partial signature: the sighash being signed, the
+/// taproot script tree (if any) committed to by the output key, and the nonce commitments of every
+/// signer.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct SigningPackage {
+    sighash: TapSighash,
+    merkle_root: Option<TapNodeHash>,
+    commitments: Vec<SigningCommitment>,
+}
+
+/// Round 2 output of a participant.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct PartialSignature {
+    pub participant_index: ParticipantIndex,
+    pub signature: FrostPartialSignature,
+}
+
+impl SigningPackage {
+    /// A signing package for the two-party App and Server key.
+    pub fn new(
+        sighash: TapSighash,
+        merkle_root: Option<TapNodeHash>,
+        commitments: Vec<SigningCommitment>,
+    ) -> Result<Self, SigningError> {
+        Self::new_for(
+            &DkgParameters::two_party(),
+            sighash,
+            merkle_root,
+            commitments,
+        )
+    }
+
+    /// A signing package for a key generated with `parameters`. There must be exactly one
+    /// commitment from each of `threshold` distinct participants.
+    pub fn new_for(
+        parameters: &DkgParameters,
+        sighash: TapSighash,
+        merkle_root: Option<TapNodeHash>,
+        commitments: Vec<SigningCommitment>,
+    ) -> Result<Self, SigningError> {
+        let has_unknown_or_duplicate_signer =
+            commitments.iter().enumerate().any(|(i, commitment)| {
+                !parameters
+                    .participants()
+                    .contains(&commitment.participant_index)
+                    || commitments[..i]
+                        .iter()
+                        .any(|other| other.participant_index == commitment.participant_index)
+            });
+        if has_unknown_or_duplicate_signer || commitments.len() != parameters.threshold() {
+            return Err(SigningError::InvalidSigningCommitments);
+        }
+
+        Ok(Self {
+            sighash,
+            merkle_root,
+            commitments,
+        })
+    }
+
+    pub fn sighash(&self) -> TapSighash {
+        self.sighash
+    }
+
+    fn message(&self) -> Message {
+        Message::from_digest(self.sighash.to_byte_array())
+    }
+
+    fn public_nonces(&self) -> Vec<&FrostPubNonce> {
+        self.commitments
+            .iter()
+            .map(|commitment| &commitment.public_nonce)
+            .collect()
+    }
+
+    fn signers(&self) -> Vec<ParticipantIndex> {
+        self.commitments
+            .iter()
+            .map(|commitment| commitment.participant_index)
+            .collect()
+    }
+
+    fn commitment_for(&self, index: ParticipantIndex) -> Result<&SigningCommitment, SigningError> {
+        self.commitments
+            .iter()
+            .find(|commitment| commitment.participant_index == index)
+            .ok_or(SigningError::MissingSigningCommitment)
+    }
+}
+
+/// Generates a fresh nonce pair for signing `sighash` with the two-party App and Server output key
+/// committing to `merkle_root`.
+///
+/// The secret nonce MUST be kept by the caller and used for exactly one call to `partial_sign`;
+/// it is consumed there to make reuse impossible. Only the `SigningCommitment` is sent to peers.
+pub fn generate_nonce_pair(
+    participant: Participant,
+    share_details: &ShareDetails,
+    sighash: TapSighash,
+    merkle_root: Option<TapNodeHash>,
+) -> Result<(FrostSecNonce, SigningCommitment), SigningError> {
+    generate_nonce_pair_for(
+        &DkgParameters::two_party(),
+        participant.into(),
+        share_details,
+        sighash,
+        merkle_root,
+    )
+}
+
+/// Like `generate_nonce_pair`, for a key generated with `parameters`.
+pub fn generate_nonce_pair_for(
+    parameters: &DkgParameters,
+    participant: ParticipantIndex,
+    share_details: &ShareDetails,
+    sighash: TapSighash,
+    merkle_root: Option<TapNodeHash>,
+) -> Result<(FrostSecNonce, SigningCommitment), SigningError> {
+    if !parameters.participants().contains(&participant) {
+        return Err(SigningError::InvalidParticipant);
+    }
+    if share_details.key_commitments.vss_commitments.len() != parameters.threshold() {
+        return Err(SigningError::InvalidKeyCommitments);
+    }
+    let frost_public_key = frost_public_key(
+        &share_details.key_commitments,
+        parameters.participants(),
+        merkle_root,
+    )?;
+
+    let (secret_nonce, public_nonce) = new_frost_nonce_pair(
+        zkp::SECP256K1,
+        FrostSessionId::random(),
+        share_details.secret_share.expose_secret(),
+        &frost_public_key,
+        &Message::from_digest(sighash.to_byte_array()),
+        None,
+    );
+
+    Ok((
+        secret_nonce,
+        SigningCommitment {
+            participant_index: participant,
+            public_nonce,
+        },
+    ))
+}
+
+/// Produces this participant's partial signature over the signing package.
+pub fn partial_sign(
+    participant: Participant,
+    share_details: &ShareDetails,
+    signing_package: &SigningPackage,
+    secret_nonce: FrostSecNonce,
+) -> Result<PartialSignature, SigningError> {
+    partial_sign_for(
+        participant.into(),
+        share_details,
+        signing_package,
+        secret_nonce,
+    )
+}
+
+/// Like `partial_sign`, for a participant of a key generated with any `DkgParameters`. The signers
+/// are the ones the signing package was built for.
+pub fn partial_sign_for(
+    participant: ParticipantIndex,
+    share_details: &ShareDetails,
+    signing_package: &SigningPackage,
+    secret_nonce: FrostSecNonce,
+) -> Result<PartialSignature, SigningError> {
+    // Make sure our own commitment made it into the package, otherwise the session's aggregate
+    // nonce would not match the secret nonce we're about to use.
+    signing_package.commitment_for(participant)?;
+
+    let frost_public_key = frost_public_key(
+        &share_details.key_commitments,
+        &signing_package.signers(),
+        signing_package.merkle_root,
+    )?;
+    let session = signing_session(participant, signing_package, &frost_public_key);
+
+    let signature = session.partial_sign(
+        zkp::SECP256K1,
+        secret_nonce,
+        share_details.secret_share.expose_secret(),
+        &frost_public_key,
+    );
+
+    Ok(PartialSignature {
+        participant_index: participant,
+        signature,
+    })
+}
+
+/// Verifies a peer's partial signature against its verification share, which is derived from the
+/// VSS commitments in `key_commitments`.
+pub fn verify_partial_signature(
+    key_commitments: &KeyCommitments,
+    signing_package: &SigningPackage,
+    partial_signature: &PartialSignature,
+) -> Result<(), SigningError> {
+    let participant = partial_signature.participant_index;
+    let commitment = signing_package.commitment_for(participant)?;
+
+    let frost_public_key = frost_public_key(
+        key_commitments,
+        &signing_package.signers(),
+        signing_package.merkle_root,
+    )?;
+    let verification_share = verification_share(key_commitments, participant)?;
+    let session = signing_session(participant, signing_package, &frost_public_key);
+
+    if !session.partial_verify(
+        zkp::SECP256K1,
+        &partial_signature.signature,
+        &commitment.public_nonce,
+        &verification_share,
+        &frost_public_key,
+    ) {
+        return Err(SigningError::InvalidPartialSignature);
+    }
+
+    Ok(())
+}
+
+/// Verifies every partial signature and aggregates them into a BIP340 Schnorr signature valid for
+/// a taproot key-path spend with `key_commitments.aggregate_public_key` as the internal key.
+pub fn aggregate(
+    key_commitments: &KeyCommitments,
+    signing_package: &SigningPackage,
+    partial_signatures: &[&PartialSignature],
+) -> Result<Signature, SigningError> {
+    if partial_signatures.len() != signing_package.commitments.len() {
+        return Err(SigningError::MissingPartialSignature);
+    }
+    for commitment in &signing_package.commitments {
+        if !partial_signatures
+            .iter()
+            .any(|sig| sig.participant_index == commitment.participant_index)
+        {
+            return Err(SigningError::MissingPartialSignature);
+        }
+    }
+    for partial_signature in partial_signatures {
+        verify_partial_signature(key_commitments, signing_package, partial_signature)?;
+    }
+
+    let signers = signing_package.signers();
+    let frost_public_key =
+        frost_public_key(key_commitments, &signers, signing_package.merkle_root)?;
+    // The aggregating session's own participant is irrelevant; any signer will do.
+    let session = signing_session(signers[0], signing_package, &frost_public_key);
+    let signature = session.aggregate_partial_sigs(
+        zkp::SECP256K1,
+        &partial_signatures
+            .iter()
+            .map(|sig| &sig.signature)
+            .collect::<Vec<&FrostPartialSignature>>(),
+    );
+
+    let signature = Signature::from_slice(&signature[..])
+        .map_err(|_| SigningError::SignatureAggregationFailed)?;
+    bitcoin::secp256k1::SECP256K1
+        .verify_schnorr(
+            &signature,
+            &bitcoin::secp256k1::Message::from(signing_package.sighash),
+            &output_key(key_commitments, signing_package.merkle_root),
+        )
+        .map_err(|_| SigningError::SignatureAggregationFailed)?;
+
+    Ok(signature)
+}
+
+/// The taproot output key that signatures produced by this module are valid for. Without a script
+/// tree this is the BIP86 output key.
+pub fn output_key(
+    key_commitments: &KeyCommitments,
+    merkle_root: Option<TapNodeHash>,
+) -> XOnlyPublicKey {
+    let (internal_key, _) = key_commitments.aggregate_public_key.x_only_public_key();
+    let (output_key, _) = internal_key.tap_tweak(bitcoin::secp256k1::SECP256K1, merkle_root);
+    output_key.to_inner()
+}
+
+fn signing_session(
+    participant: ParticipantIndex,
+    signing_package: &SigningPackage,
+    frost_public_key: &FrostPublicKey,
+) -> FrostSession {
+    FrostSession::new(
+        zkp::SECP256K1,
+        &signing_package.public_nonces(),
+        &signing_package.message(),
+        frost_public_key,
+        &participant.into(),
+        &signing_participants(signing_package)
+            .iter()
+            .collect::<Vec<_>>(),
+        None,
+    )
+}
+
+fn signing_participants(signing_package: &SigningPackage) -> Vec<zkp::PublicKey> {
+    signing_package
+        .signers()
+        .into_iter()
+        .map(zkp::PublicKey::from)
+        .collect()
+}
+
+/// Derives a participant's public verification share from the aggregated VSS commitments. The
+/// commitments are a sum over every participant's polynomial, so they can be evaluated directly.
+fn verification_share(
+    key_commitments: &KeyCommitments,
+    participant: ParticipantIndex,
+) -> Result<VerificationShare, SigningError> {
+    let vss_commitments = CoefficientCommitment::from_public_keys(
+        key_commitments
+            .vss_commitments
+            .iter()
+            .map(|public_key| ZkpPublicKey::from(*public_key).0)
+            .collect(),
+    );
+
+    VerificationShare::new(
+        zkp::SECP256K1,
+        &[&vss_commitments],
+        &participant.into(),
+        key_commitments.vss_commitments.len(),
+    )
+    .map_err(|_| SigningError::VerificationShareGenerationFailed)
+}
+
+/// Rebuilds the FROST key from the key commitments, with the BIP341 taproot tweak applied so that
+/// resulting signatures are valid key-path spends. Any `threshold` of the participants determine
+/// the key, where the threshold is the number of VSS commitments.
+fn frost_public_key(
+    key_commitments: &KeyCommitments,
+    participants: &[ParticipantIndex],
+    merkle_root: Option<TapNodeHash>,
+) -> Result<FrostPublicKey, SigningError> {
+    let threshold = key_commitments.vss_commitments.len();
+    if participants.len() < threshold {
+        return Err(SigningError::InvalidSigningCommitments);
+    }
+    let participants = &participants[..threshold];
+    let verification_shares = participants
+        .iter()
+        .map(|participant| verification_share(key_commitments, *participant))
+        .collect::<Result<Vec<VerificationShare>, SigningError>>()?;
+    let participant_public_keys = participants
+        .iter()
+        .map(|participant| zkp::PublicKey::from(*participant))
+        .collect::<Vec<zkp::PublicKey>>();
+
+    let mut frost_public_key = FrostPublicKey::from_verification_shares(
+        zkp::SECP256K1,
+        &verification_shares.iter().collect::<Vec<_>>(),
+        &participant_public_keys.iter().collect::<Vec<_>>(),
+    );
+
+    let expected_public_key = ZkpPublicKey::from(key_commitments.aggregate_public_key).0;
+    if frost_public_key.public_key(zkp::SECP256K1) != expected_public_key {
+        return Err(SigningError::InvalidKeyCommitments);
+    }
+
+    let (internal_key, _) = key_commitments.aggregate_public_key.x_only_public_key();
+    let tweak = zkp::Scalar::from_be_bytes(
+        TapTweakHash::from_key_and_tweak(internal_key, merkle_root)
+            .to_scalar()
+            .to_be_bytes(),
+    )
+    .map_err(|_| SigningError::InvalidKeyCommitments)?;
+    frost_public_key
+        .add_x_only_tweak(zkp::SECP256K1, tweak)
+        .map_err(|_| SigningError::InvalidKeyCommitments)?;
+
+    Ok(frost_public_key)
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SigningError {
+    #[error("Sighash must be 32 bytes")]
+    InvalidSighash,
+    #[error("Unknown participant")]
+    InvalidParticipant,
+    #[error("Signing package must contain exactly one commitment from each signer")]
+    InvalidSigningCommitments,
+    #[error("Signing package is missing a participant's commitment")]
+    MissingSigningCommitment,
+    #[error("Missing partial signature from a participant")]
+    MissingPartialSignature,
+    #[error("Invalid partial signature")]
+    InvalidPartialSignature,
+    #[error("Key commitments do not match the aggregate public key")]
+    InvalidKeyCommitments,
+    #[error("Unable to generate verification share")]
+    VerificationShareGenerationFailed,
+    #[error("Unable to aggregate partial signatures")]
+    SignatureAggregationFailed,
+    #[error("Signer is missing a nonce. Did you forget to generate a signing commitment?")]
+    MissingNonce,
+    #[error("Unable to decode the server's response")]
+    InvalidServerResponse,
+}
+
+pub mod server {
+    use bitcoin::{
+        secp256k1::serde::{Deserialize, Serialize},
+        sighash::TapSighash,
+        taproot::TapNodeHash,
+    };
+
+    use crate::frost::{Participant, ShareDetails};
+
+    use super::{
+        generate_nonce_pair, partial_sign, PartialSignature, SigningCommitment, SigningError,
+        SigningPackage,
+    };
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(crate = "bitcoin::secp256k1::serde")]
+    pub struct SignResult {
+        pub commitment: SigningCommitment,
+        pub partial_signature: PartialSignature,
+    }
+
+    /// The server commits to a nonce and signs in one step, after it has seen the app's
+    /// commitment. This saves a round-trip and means the server never has to persist a nonce.
+    pub fn sign(
+        share_details: &ShareDetails,
+        sighash: TapSighash,
+        merkle_root: Option<TapNodeHash>,
+        peer_commitment: SigningCommitment,
+    ) -> Result<SignResult, SigningError> {
+        let (secret_nonce, commitment) =
+            generate_nonce_pair(Participant::Server, share_details, sighash, merkle_root)?;
+        let signing_package =
+            SigningPackage::new(sighash, merkle_root, vec![peer_commitment, commitment])?;
+        let partial_signature = partial_sign(
+            Participant::Server,
+            share_details,
+            &signing_package,
+            secret_nonce,
+        )?;
+
+        Ok(SignResult {
+            commitment: signing_package
+                .commitment_for(Participant::Server.into())?
+                .clone(),
+            partial_signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{hashes::Hash, sighash::TapSighash};
+    use rand::{thread_rng, RngCore};
+
+    use crate::frost::{
+        dkg::{
+            aggregate_shares_for, app, generate_share_packages_for, server as dkg_server,
+            DkgParameters,
+        },
+        Participant, ParticipantIndex, ShareDetails,
+    };
+
+    use super::{
+        aggregate, generate_nonce_pair, generate_nonce_pair_for, output_key, partial_sign,
+        partial_sign_for, server, verify_partial_signature, SigningError, SigningPackage,
+    };
+
+    fn run_dkg() -> (ShareDetails, ShareDetails) {
+        let app_initiate_result = app::initiate_dkg().unwrap();
+        let server_initiate_result =
+            dkg_server::initiate_dkg(&app_initiate_result.share_package_for_peer).unwrap();
+        let app_share_details = app::continue_dkg(
+            &app_initiate_result.share_package,
+            &server_initiate_result.share_package,
+            &server_initiate_result.share_details.key_commitments,
+        )
+        .unwrap();
+
+        (app_share_details, server_initiate_result.share_details)
+    }
+
+    fn random_sighash() -> TapSighash {
+        let mut bytes = [0u8; 32];
+        thread_rng().fill_bytes(&mut bytes);
+        TapSighash::from_byte_array(bytes)
+    }
+
+    #[test]
+    fn test_sign_and_aggregate() {
+        let (app_share_details, server_share_details) = run_dkg();
+        let sighash = random_sighash();
+
+        let (app_secret_nonce, app_commitment) =
+            generate_nonce_pair(Participant::App, &app_share_details, sighash, None).unwrap();
+        let server_result =
+            server::sign(&server_share_details, sighash, None, app_commitment.clone()).unwrap();
+
+        let signing_package = SigningPackage::new(
+            sighash,
+            None,
+            vec![app_commitment, server_result.commitment],
+        )
+        .unwrap();
+        verify_partial_signature(
+            &app_share_details.key_commitments,
+            &signing_package,
+            &server_result.partial_signature,
+        )
+        .unwrap();
+
+        let app_partial_signature = partial_sign(
+            Participant::App,
+            &app_share_details,
+            &signing_package,
+            app_secret_nonce,
+        )
+        .unwrap();
+
+        let signature = aggregate(
+            &app_share_details.key_commitments,
+            &signing_package,
+            &[&app_partial_signature, &server_result.partial_signature],
+        )
+        .unwrap();
+
+        bitcoin::secp256k1::SECP256K1
+            .verify_schnorr(
+                &signature,
+                &bitcoin::secp256k1::Message::from(sighash),
+                &output_key(&server_share_details.key_commitments, None),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_partial_signature_over_wrong_package_is_rejected() {
+        let (app_share_details, server_share_details) = run_dkg();
+        let sighash = random_sighash();
+
+        let (_, app_commitment) =
+            generate_nonce_pair(Participant::App, &app_share_details, sighash, None).unwrap();
+        let server_result =
+            server::sign(&server_share_details, sighash, None, app_commitment.clone()).unwrap();
+
+        // Same commitments, different message.
+        let other_package = SigningPackage::new(
+            random_sighash(),
+            None,
+            vec![app_commitment, server_result.commitment],
+        )
+        .unwrap();
+
+        assert_eq!(
+            verify_partial_signature(
+                &app_share_details.key_commitments,
+                &other_package,
+                &server_result.partial_signature,
+            ),
+            Err(SigningError::InvalidPartialSignature)
+        );
+    }
+
+    #[test]
+    fn test_signing_package_requires_both_commitments() {
+        let (app_share_details, _) = run_dkg();
+        let sighash = random_sighash();
+
+        let (_, app_commitment) =
+            generate_nonce_pair(Participant::App, &app_share_details, sighash, None).unwrap();
+
+        assert_eq!(
+            SigningPackage::new(sighash, None, vec![app_commitment.clone()]),
+            Err(SigningError::InvalidSigningCommitments)
+        );
+        assert_eq!(
+            SigningPackage::new(sighash, None, vec![app_commitment.clone(), app_commitment]),
+            Err(SigningError::InvalidSigningCommitments)
+        );
+    }
+
+    #[test]
+    fn test_any_threshold_of_participants_can_sign() {
+        let parameters = DkgParameters::with_participant_count(3, 2).unwrap();
+        let share_packages = parameters
+            .participants()
+            .iter()
+            .map(|_| generate_share_packages_for(&parameters).unwrap())
+            .collect::<Vec<_>>();
+        let share_details = parameters
+            .participants()
+            .iter()
+            .map(|participant| {
+                let packages = share_packages
+                    .iter()
+                    .flatten()
+                    .filter(|package| package.recipient == *participant)
+                    .map(|package| &package.share_package)
+                    .collect::<Vec<_>>();
+                aggregate_shares_for(&parameters, *participant, &packages).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let key_commitments = &share_details[0].key_commitments;
+
+        for signers in [[1, 2], [1, 3], [2, 3]] {
+            let sighash = random_sighash();
+            let (secret_nonces, commitments): (Vec<_>, Vec<_>) = signers
+                .iter()
+                .map(|index| {
+                    generate_nonce_pair_for(
+                        &parameters,
+                        ParticipantIndex(*index),
+                        &share_details[*index as usize - 1],
+                        sighash,
+                        None,
+                    )
+                    .unwrap()
+                })
+                .unzip();
+            let signing_package =
+                SigningPackage::new_for(&parameters, sighash, None, commitments).unwrap();
+            let partial_signatures = signers
+                .iter()
+                .zip(secret_nonces)
+                .map(|(index, secret_nonce)| {
+                    partial_sign_for(
+                        ParticipantIndex(*index),
+                        &share_details[*index as usize - 1],
+                        &signing_package,
+                        secret_nonce,
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            let signature = aggregate(
+                key_commitments,
+                &signing_package,
+                &partial_signatures.iter().collect::<Vec<_>>(),
+            )
+            .unwrap();
+            bitcoin::secp256k1::SECP256K1
+                .verify_schnorr(
+                    &signature,
+                    &bitcoin::secp256k1::Message::from(sighash),
+                    &output_key(key_commitments, None),
+                )
+                .unwrap();
+        }
+
+        // A participant outside the key.
+        assert!(matches!(
+            generate_nonce_pair_for(
+                &parameters,
+                ParticipantIndex(4),
+                &share_details[0],
+                random_sighash(),
+                None,
+            ),
+            Err(SigningError::InvalidParticipant)
+        ));
+    }
+}
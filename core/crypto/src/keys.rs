@@ -41,6 +41,14 @@ impl SecretKey {
     }
 }
 
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        if let Ok(seckey) = self.0.get_mut() {
+            seckey.non_secure_erase();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,13 +1,15 @@
 pub mod chacha20poly1305;
 pub mod crypto_box;
 pub mod ecdh;
+pub mod envelope;
 pub mod frost;
 pub mod hkdf;
 pub mod hmac;
 pub mod keys;
+pub mod secret;
 pub mod signature_verifier;
 
-#[cfg(feature = "spake2")]
+#[cfg(any(feature = "spake2", feature = "spake2-rust"))]
 pub mod spake2;
 
 #[cfg(feature = "noise")]
@@ -15,6 +15,10 @@ use std::{
 };
 use thiserror::Error;
 
+use crate::secret::SecretBytes;
+
+pub mod session;
+
 #[derive(Debug, thiserror::Error)]
 pub enum DhError {
     #[error("Invalid public key")]
@@ -29,7 +33,7 @@ pub struct HardwareBackedKeyPair {
 }
 
 pub enum PrivateKey {
-    InMemory { secret_bytes: Vec<u8> },
+    InMemory { secret_bytes: SecretBytes },
     HardwareBacked { name: String },
 }
 
@@ -130,7 +134,7 @@ impl Dh for HardwareBackedDhAdapter {
 // This is used for Android phones that lack a TEE or SE which supports hardware-backed ECDH.
 #[derive(Default)]
 pub struct SoftwareP256DhAdapter {
-    private_key: Vec<u8>,
+    private_key: SecretBytes,
     public_key: Vec<u8>,
 }
 
@@ -182,7 +186,7 @@ impl Dh for SoftwareP256DhAdapter {
         let private_key = EcKey::from_private_components(&group, &priv_bn, &pub_key)
             .expect("Failed to create private key");
 
-        self.private_key = privkey.to_vec();
+        self.private_key = privkey.into();
         self.public_key = private_key
             .public_key()
             .to_bytes(&group, PointConversionForm::COMPRESSED, &mut ctx)
@@ -195,7 +199,7 @@ impl Dh for SoftwareP256DhAdapter {
 
         let private_key = EcKey::generate(&group).expect("Failed to generate private key");
 
-        self.private_key = private_key.private_key().to_vec();
+        self.private_key = private_key.private_key().to_vec().into();
 
         // Compute and store the public key in compressed form
         let mut ctx = BigNumContext::new().expect("Failed to create context");
@@ -210,7 +214,7 @@ impl Dh for SoftwareP256DhAdapter {
     }
 
     fn privkey(&self) -> &[u8] {
-        &self.private_key
+        self.private_key.expose_secret()
     }
 
     fn dh(&self, pubkey: &[u8], out: &mut [u8]) -> Result<(), snow::Error> {
@@ -228,9 +232,11 @@ impl Dh for SoftwareP256DhAdapter {
             EcPoint::from_bytes(&group, pubkey, &mut ctx).map_err(|_| snow::Error::Dh)?;
 
         // Recreate the private key from the stored private key bytes
-        let priv_bn = BigNum::from_slice(&self.private_key).map_err(|_| snow::Error::Dh)?;
+        let priv_bn =
+            BigNum::from_slice(self.private_key.expose_secret()).map_err(|_| snow::Error::Dh)?;
 
-        let public_key = compute_pubkey(&self.private_key).map_err(|_| snow::Error::Dh)?;
+        let public_key =
+            compute_pubkey(self.private_key.expose_secret()).map_err(|_| snow::Error::Dh)?;
         let pubkey_ecpoint =
             EcPoint::from_bytes(&group, &public_key, &mut ctx).map_err(|_| snow::Error::Dh)?;
         let private_key = EcKey::from_private_components(&group, &priv_bn, &pubkey_ecpoint)
@@ -377,18 +383,18 @@ impl NoiseContext {
 
         let privkey = match privkey {
             PrivateKey::InMemory { secret_bytes } => secret_bytes,
-            PrivateKey::HardwareBacked { name } => name.as_bytes().to_vec(),
+            PrivateKey::HardwareBacked { name } => name.into_bytes().into(),
         };
 
         let handshake = match role {
             NoiseRole::Initiator => builder
                 .prologue(NOISE_PROLOGUE)
-                .local_private_key(&privkey)
+                .local_private_key(privkey.expose_secret())
                 .remote_public_key(&sec1_public_key.expect("No public key provided"))
                 .build_initiator(),
             NoiseRole::Responder => builder
                 .prologue(NOISE_PROLOGUE)
-                .local_private_key(&privkey)
+                .local_private_key(privkey.expose_secret())
                 .build_responder(),
         }?;
 
@@ -655,7 +661,7 @@ mod tests {
         let server = NoiseContext::new(
             NoiseRole::Responder,
             PrivateKey::InMemory {
-                secret_bytes: server_keypair.0,
+                secret_bytes: server_keypair.0.into(),
             },
             Some(client_keypair.1),
             None,
@@ -664,7 +670,7 @@ mod tests {
         let client = NoiseContext::new(
             NoiseRole::Initiator,
             PrivateKey::InMemory {
-                secret_bytes: client_keypair.0,
+                secret_bytes: client_keypair.0.into(),
             },
             Some(server_keypair.1),
             None,
@@ -0,0 +1,696 @@
+use rand::RngCore;
+use snow::{params::NoiseParams, Builder, TransportState};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use super::{
+    compute_pubkey, create_params, NoiseContext, NoiseRole, NoiseWrapperError, PrivateKey,
+    SoftwareP256Resolver, NOISE_MAX_MESSAGE_SIZE, NOISE_PROLOGUE,
+};
+use crate::chacha20poly1305::XChaCha20Poly1305;
+use crate::hkdf::Hkdf;
+use crate::secret::SecretBytes;
+
+// Sessions layered on top of a finished `NoiseContext` handshake. A session rekeys each direction
+// every `rekey_interval` messages, refuses to send or receive once `max_messages` have been used
+// in a direction, and frames payloads of any size into Noise transport messages.
+//
+// Once the handshake completes, the responder can issue a session ticket. The initiator can
+// later present it to resume with a `Noise_NNpsk0` handshake keyed by the previous session's
+// resumption secret, instead of repeating the static-key IK handshake.
+
+const RESUMPTION_PARAMS: &str = "Noise_NNpsk0_p256_ChaChaPoly_SHA256";
+
+const TAG_LEN: usize = 16;
+// Each frame's plaintext is a one byte flag followed by up to this many payload bytes.
+const MAX_FRAME_PAYLOAD: usize = NOISE_MAX_MESSAGE_SIZE - TAG_LEN - 1;
+const FRAME_FINAL: u8 = 0x00;
+const FRAME_MORE: u8 = 0x01;
+
+const SESSION_KDF_SALT: &[u8] = b"bitkey-noise-session";
+const RESUMPTION_SECRET_INFO: &[u8] = b"resumption secret";
+const SESSION_ID_INFO: &[u8] = b"session id";
+const RESUMPTION_SECRET_LEN: usize = 32;
+const SESSION_ID_LEN: usize = 16;
+
+const TICKET_VERSION: u8 = 1;
+const TICKET_NONCE_LEN: usize = 24;
+const TICKET_KEY_LEN: usize = 32;
+// Plaintext of the responder-sealed ticket: resumption secret || expires_at.
+const SEALED_TICKET_PLAINTEXT_LEN: usize = RESUMPTION_SECRET_LEN + 8;
+const SEALED_TICKET_LEN: usize = 1 + TICKET_NONCE_LEN + SEALED_TICKET_PLAINTEXT_LEN + TAG_LEN;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error(transparent)]
+    Noise(#[from] NoiseWrapperError),
+    #[error("Internal error: {0}")]
+    InternalError(#[from] snow::Error),
+    #[error("Handshake not finished")]
+    HandshakeNotFinished,
+    #[error("Invalid key")]
+    InvalidKey,
+    #[error("Session has used all of its messages and must be re-established")]
+    Exhausted,
+    #[error("Malformed frame")]
+    MalformedFrame,
+    #[error("Invalid session ticket")]
+    InvalidTicket,
+    #[error("Session ticket expired")]
+    TicketExpired,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionPolicy {
+    // Each direction is rekeyed after this many messages; zero disables rekeying.
+    pub rekey_interval: u64,
+    // Messages allowed in each direction before the session must be re-established. Can't exceed
+    // the largest nonce snow will use.
+    pub max_messages: u64,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            rekey_interval: 1 << 16,
+            max_messages: u64::MAX - 1,
+        }
+    }
+}
+
+impl SessionPolicy {
+    fn max_messages(&self) -> u64 {
+        self.max_messages.min(u64::MAX - 1)
+    }
+
+    fn should_rekey(&self, messages: u64) -> bool {
+        self.rekey_interval != 0 && messages % self.rekey_interval == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SessionId(pub [u8; SESSION_ID_LEN]);
+
+// The size of `NoiseSession::encrypt`'s output for a payload of `payload_len` bytes: each frame
+// carries a length prefix, a flag byte and a tag.
+pub const fn encrypted_len(payload_len: usize) -> usize {
+    let frame_count = match payload_len {
+        0 => 1,
+        _ => payload_len.div_ceil(MAX_FRAME_PAYLOAD),
+    };
+    payload_len + frame_count * (3 + TAG_LEN)
+}
+
+impl SessionId {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Debug)]
+pub struct NoiseSession {
+    transport: TransportState,
+    policy: SessionPolicy,
+    id: SessionId,
+    resumption_secret: SecretBytes,
+    scratch: Vec<u8>,
+}
+
+// Keys the session id and resumption secret to the handshake transcript, so both parties derive
+// the same values without sending them.
+fn derive_session_secrets(handshake_hash: &[u8]) -> (SessionId, SecretBytes) {
+    let hkdf = Hkdf::new(SESSION_KDF_SALT, handshake_hash);
+    let expand = |info: &[u8], len: usize| {
+        hkdf.expand(info, len as i32)
+            .expect("Output length is well under the HKDF limit")
+    };
+
+    let mut id = [0u8; SESSION_ID_LEN];
+    id.copy_from_slice(&expand(SESSION_ID_INFO, SESSION_ID_LEN));
+    let resumption_secret =
+        SecretBytes::from(expand(RESUMPTION_SECRET_INFO, RESUMPTION_SECRET_LEN));
+
+    (SessionId(id), resumption_secret)
+}
+
+fn create_resumption_params() -> NoiseParams {
+    NoiseParams {
+        handshake: "NNpsk0".parse().expect("Invalid handshake argument"),
+        name: RESUMPTION_PARAMS.to_string(),
+        ..create_params()
+    }
+}
+
+fn resumption_builder(resumption_secret: &[u8]) -> Builder<'_> {
+    Builder::with_resolver(
+        create_resumption_params(),
+        Box::new(SoftwareP256Resolver::default()),
+    )
+    .prologue(NOISE_PROLOGUE)
+    .psk(0, resumption_secret)
+}
+
+impl NoiseSession {
+    // Takes the finished handshake out of `context`, which can't be used for transport afterwards.
+    pub fn from_context(
+        context: &NoiseContext,
+        policy: SessionPolicy,
+    ) -> Result<Self, SessionError> {
+        let mut state = context.state.lock().expect("Failed to lock state");
+        match &state.handshake {
+            Some(handshake) if handshake.is_handshake_finished() => {}
+            _ => return Err(SessionError::HandshakeNotFinished),
+        }
+        let handshake = state
+            .handshake
+            .take()
+            .ok_or(SessionError::HandshakeNotFinished)?;
+
+        let (id, resumption_secret) = derive_session_secrets(handshake.get_handshake_hash());
+        let transport = handshake.into_transport_mode()?;
+
+        Ok(Self::new(transport, policy, id, resumption_secret))
+    }
+
+    fn new(
+        transport: TransportState,
+        policy: SessionPolicy,
+        id: SessionId,
+        resumption_secret: SecretBytes,
+    ) -> Self {
+        Self {
+            transport,
+            policy,
+            id,
+            resumption_secret,
+            scratch: vec![0u8; NOISE_MAX_MESSAGE_SIZE],
+        }
+    }
+
+    pub fn id(&self) -> SessionId {
+        self.id
+    }
+
+    pub fn role(&self) -> NoiseRole {
+        if self.transport.is_initiator() {
+            NoiseRole::Initiator
+        } else {
+            NoiseRole::Responder
+        }
+    }
+
+    pub fn messages_sent(&self) -> u64 {
+        self.transport.sending_nonce()
+    }
+
+    pub fn messages_received(&self) -> u64 {
+        self.transport.receiving_nonce()
+    }
+
+    // Encrypts a payload of any size into one or more frames, each a big-endian u16 length
+    // followed by a Noise transport message. Fails without sending anything if the payload would
+    // take the session past its message limit.
+    pub fn encrypt(&mut self, payload: &[u8]) -> Result<Vec<u8>, SessionError> {
+        let frame_count = payload.len().div_ceil(MAX_FRAME_PAYLOAD).max(1) as u64;
+        if self.messages_sent().saturating_add(frame_count) > self.policy.max_messages() {
+            return Err(SessionError::Exhausted);
+        }
+
+        let mut result = Vec::with_capacity(encrypted_len(payload.len()));
+        let mut chunks = payload.chunks(MAX_FRAME_PAYLOAD).peekable();
+        let mut plaintext = Vec::with_capacity(MAX_FRAME_PAYLOAD + 1);
+        loop {
+            let chunk = chunks.next().unwrap_or_default();
+            let flag = if chunks.peek().is_some() {
+                FRAME_MORE
+            } else {
+                FRAME_FINAL
+            };
+
+            plaintext.clear();
+            plaintext.push(flag);
+            plaintext.extend_from_slice(chunk);
+
+            let len = self
+                .transport
+                .write_message(&plaintext, &mut self.scratch)?;
+            result.extend_from_slice(&(len as u16).to_be_bytes());
+            result.extend_from_slice(&self.scratch[..len]);
+
+            if self.policy.should_rekey(self.messages_sent()) {
+                self.transport.rekey_outgoing();
+            }
+            if flag == FRAME_FINAL {
+                return Ok(result);
+            }
+        }
+    }
+
+    // Decrypts frames produced by `encrypt`. Any error leaves the session out of sync with its
+    // peer, so it should be discarded.
+    pub fn decrypt(&mut self, frames: &[u8]) -> Result<Vec<u8>, SessionError> {
+        let mut result = Vec::with_capacity(frames.len());
+        let mut remaining = frames;
+        loop {
+            if self.messages_received() >= self.policy.max_messages() {
+                return Err(SessionError::Exhausted);
+            }
+
+            if remaining.len() < 2 {
+                return Err(SessionError::MalformedFrame);
+            }
+            let len = u16::from_be_bytes([remaining[0], remaining[1]]) as usize;
+            let message = remaining
+                .get(2..2 + len)
+                .ok_or(SessionError::MalformedFrame)?;
+            remaining = &remaining[2 + len..];
+
+            let len = self.transport.read_message(message, &mut self.scratch)?;
+            if self.policy.should_rekey(self.messages_received()) {
+                self.transport.rekey_incoming();
+            }
+
+            let (flag, chunk) = self.scratch[..len]
+                .split_first()
+                .ok_or(SessionError::MalformedFrame)?;
+            result.extend_from_slice(chunk);
+
+            match *flag {
+                FRAME_MORE => continue,
+                FRAME_FINAL if remaining.is_empty() => return Ok(result),
+                _ => return Err(SessionError::MalformedFrame),
+            }
+        }
+    }
+
+    // Called by the initiator with a message from `Responder::issue_ticket`.
+    pub fn accept_ticket(&mut self, message: &[u8]) -> Result<SessionTicket, SessionError> {
+        let plaintext = self.decrypt(message)?;
+        if plaintext.len() != 8 + SEALED_TICKET_LEN {
+            return Err(SessionError::InvalidTicket);
+        }
+        let (expires_at, sealed) = plaintext.split_at(8);
+
+        Ok(SessionTicket {
+            expires_at: u64::from_be_bytes(expires_at.try_into().expect("Length checked above")),
+            resumption_secret: self.resumption_secret.clone(),
+            sealed: sealed.to_vec(),
+        })
+    }
+}
+
+// Held by the initiator and persisted between connections. The resumption secret is as sensitive
+// as a session key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionTicket {
+    // Unix time after which the responder will refuse the ticket.
+    pub expires_at: u64,
+    resumption_secret: SecretBytes,
+    // Opaque to the initiator; only the responder's ticket key can open it.
+    sealed: Vec<u8>,
+}
+
+impl SessionTicket {
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    // Layout: version (1) || expires_at (8) || resumption secret (32) || sealed ticket
+    pub fn to_bytes(&self) -> SecretBytes {
+        let mut bytes = Vec::with_capacity(1 + 8 + RESUMPTION_SECRET_LEN + self.sealed.len());
+        bytes.push(TICKET_VERSION);
+        bytes.extend_from_slice(&self.expires_at.to_be_bytes());
+        bytes.extend_from_slice(self.resumption_secret.expose_secret());
+        bytes.extend_from_slice(&self.sealed);
+        bytes.into()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SessionError> {
+        if bytes.len() != 1 + 8 + RESUMPTION_SECRET_LEN + SEALED_TICKET_LEN
+            || bytes[0] != TICKET_VERSION
+        {
+            return Err(SessionError::InvalidTicket);
+        }
+
+        Ok(Self {
+            expires_at: u64::from_be_bytes(bytes[1..9].try_into().expect("Length checked above")),
+            resumption_secret: SecretBytes::from(&bytes[9..9 + RESUMPTION_SECRET_LEN]),
+            sealed: bytes[9 + RESUMPTION_SECRET_LEN..].to_vec(),
+        })
+    }
+
+    // Starts resuming the session the ticket was issued for. The returned message goes to the
+    // responder's `Responder::resume`.
+    pub fn resume(&self) -> Result<(ResumingSession, Vec<u8>), SessionError> {
+        let mut handshake =
+            resumption_builder(self.resumption_secret.expose_secret()).build_initiator()?;
+        let mut scratch = vec![0u8; NOISE_MAX_MESSAGE_SIZE];
+        let len = handshake.write_message(&[], &mut scratch)?;
+
+        let mut message = Vec::with_capacity(self.sealed.len() + len);
+        message.extend_from_slice(&self.sealed);
+        message.extend_from_slice(&scratch[..len]);
+
+        Ok((ResumingSession { handshake, scratch }, message))
+    }
+}
+
+#[derive(Debug)]
+pub struct ResumingSession {
+    handshake: snow::HandshakeState,
+    scratch: Vec<u8>,
+}
+
+impl ResumingSession {
+    pub fn finish(
+        mut self,
+        responder_message: &[u8],
+        policy: SessionPolicy,
+    ) -> Result<NoiseSession, SessionError> {
+        self.handshake
+            .read_message(responder_message, &mut self.scratch)?;
+        if !self.handshake.is_handshake_finished() {
+            return Err(SessionError::HandshakeNotFinished);
+        }
+
+        let (id, resumption_secret) = derive_session_secrets(self.handshake.get_handshake_hash());
+        let transport = self.handshake.into_transport_mode()?;
+        Ok(NoiseSession::new(transport, policy, id, resumption_secret))
+    }
+}
+
+// The server side of a session: accepts IK handshakes with its static key, and issues and
+// redeems session tickets sealed with its ticket key. Holds no per-session state, so it can be
+// shared between requests and instances.
+pub struct Responder {
+    static_private_key: SecretBytes,
+    static_public_key: Vec<u8>,
+    ticket_cipher: XChaCha20Poly1305,
+    ticket_lifetime_secs: u64,
+    policy: SessionPolicy,
+}
+
+impl Responder {
+    pub fn new(
+        static_private_key: SecretBytes,
+        ticket_key: &[u8],
+        ticket_lifetime_secs: u64,
+        policy: SessionPolicy,
+    ) -> Result<Self, SessionError> {
+        if ticket_key.len() != TICKET_KEY_LEN {
+            return Err(SessionError::InvalidKey);
+        }
+        let ticket_cipher =
+            XChaCha20Poly1305::new(ticket_key).map_err(|_| SessionError::InvalidKey)?;
+        let static_public_key = compute_pubkey(static_private_key.expose_secret())
+            .map_err(|_| SessionError::InvalidKey)?;
+
+        Ok(Self {
+            static_private_key,
+            static_public_key,
+            ticket_cipher,
+            ticket_lifetime_secs,
+            policy,
+        })
+    }
+
+    // Compressed SEC1 encoding of the static public key initiators must be configured with.
+    pub fn public_key(&self) -> &[u8] {
+        &self.static_public_key
+    }
+
+    // Handles the initiator's first IK message, returning the established session and the reply.
+    pub fn accept(
+        &self,
+        initiator_message: &[u8],
+    ) -> Result<(NoiseSession, Vec<u8>), SessionError> {
+        let context = NoiseContext::new(
+            NoiseRole::Responder,
+            PrivateKey::InMemory {
+                secret_bytes: self.static_private_key.clone(),
+            },
+            None,
+            None,
+        )?;
+        let reply = context
+            .advance_handshake(initiator_message.to_vec())?
+            .ok_or(SessionError::HandshakeNotFinished)?;
+
+        Ok((NoiseSession::from_context(&context, self.policy)?, reply))
+    }
+
+    // Returns a message for the initiator's `NoiseSession::accept_ticket`, encrypted under
+    // `session`.
+    pub fn issue_ticket(
+        &self,
+        session: &mut NoiseSession,
+        now: u64,
+    ) -> Result<Vec<u8>, SessionError> {
+        let expires_at = now.saturating_add(self.ticket_lifetime_secs);
+
+        let mut plaintext = Zeroizing::new(Vec::with_capacity(SEALED_TICKET_PLAINTEXT_LEN));
+        plaintext.extend_from_slice(session.resumption_secret.expose_secret());
+        plaintext.extend_from_slice(&expires_at.to_be_bytes());
+
+        let mut nonce = [0u8; TICKET_NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .ticket_cipher
+            .encrypt(&nonce, &plaintext, &[TICKET_VERSION])
+            .map_err(|_| SessionError::InvalidTicket)?;
+
+        let mut message = Vec::with_capacity(8 + SEALED_TICKET_LEN);
+        message.extend_from_slice(&expires_at.to_be_bytes());
+        message.push(TICKET_VERSION);
+        message.extend_from_slice(&nonce);
+        message.extend_from_slice(&ciphertext);
+
+        session.encrypt(&message)
+    }
+
+    // Handles a message from `SessionTicket::resume`, returning the resumed session and the reply
+    // for `ResumingSession::finish`.
+    pub fn resume(
+        &self,
+        initiator_message: &[u8],
+        now: u64,
+    ) -> Result<(NoiseSession, Vec<u8>), SessionError> {
+        if initiator_message.len() < SEALED_TICKET_LEN || initiator_message[0] != TICKET_VERSION {
+            return Err(SessionError::InvalidTicket);
+        }
+        let (sealed, handshake_message) = initiator_message.split_at(SEALED_TICKET_LEN);
+        let (nonce, ciphertext) = sealed[1..].split_at(TICKET_NONCE_LEN);
+
+        let plaintext = SecretBytes::from(
+            self.ticket_cipher
+                .decrypt(nonce, ciphertext, &[TICKET_VERSION])
+                .map_err(|_| SessionError::InvalidTicket)?,
+        );
+        let (resumption_secret, expires_at) =
+            plaintext.expose_secret().split_at(RESUMPTION_SECRET_LEN);
+        let expires_at = u64::from_be_bytes(
+            expires_at
+                .try_into()
+                .map_err(|_| SessionError::InvalidTicket)?,
+        );
+        if now >= expires_at {
+            return Err(SessionError::TicketExpired);
+        }
+
+        let mut handshake = resumption_builder(resumption_secret).build_responder()?;
+        let mut scratch = vec![0u8; NOISE_MAX_MESSAGE_SIZE];
+        handshake.read_message(handshake_message, &mut scratch)?;
+        let len = handshake.write_message(&[], &mut scratch)?;
+        let reply = scratch[..len].to_vec();
+
+        let (id, resumption_secret) = derive_session_secrets(handshake.get_handshake_hash());
+        let transport = handshake.into_transport_mode()?;
+        Ok((
+            NoiseSession::new(transport, self.policy, id, resumption_secret),
+            reply,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn generate_keypair() -> (Vec<u8>, Vec<u8>) {
+        let builder: Builder =
+            Builder::with_resolver(create_params(), Box::new(SoftwareP256Resolver::default()));
+        let keypair = builder.generate_keypair().unwrap();
+        (keypair.private, keypair.public)
+    }
+
+    fn establish(policy: SessionPolicy) -> (Responder, NoiseSession, NoiseSession) {
+        let (server_private, server_public) = generate_keypair();
+        let (client_private, _) = generate_keypair();
+        let responder = Responder::new(server_private.into(), &[7u8; 32], 3600, policy).unwrap();
+        assert_eq!(responder.public_key(), server_public);
+
+        let client = NoiseContext::new(
+            NoiseRole::Initiator,
+            PrivateKey::InMemory {
+                secret_bytes: client_private.into(),
+            },
+            Some(server_public),
+            None,
+        )
+        .unwrap();
+        let (server_session, reply) = responder
+            .accept(&client.initiate_handshake().unwrap())
+            .unwrap();
+        assert!(client.advance_handshake(reply).unwrap().is_none());
+        let client_session = NoiseSession::from_context(&client, policy).unwrap();
+
+        (responder, client_session, server_session)
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let (_, mut client, mut server) = establish(SessionPolicy::default());
+        assert_eq!(client.id(), server.id());
+
+        let ciphertext = client.encrypt(b"Hello, server!").unwrap();
+        assert_eq!(server.decrypt(&ciphertext).unwrap(), b"Hello, server!");
+
+        let ciphertext = server.encrypt(b"").unwrap();
+        assert_eq!(ciphertext.len(), encrypted_len(0));
+        assert_eq!(client.decrypt(&ciphertext).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_large_payloads_are_framed() {
+        let (_, mut client, mut server) = establish(SessionPolicy::default());
+
+        let payload: Vec<u8> = (0..3 * MAX_FRAME_PAYLOAD + 5).map(|i| i as u8).collect();
+        let ciphertext = client.encrypt(&payload).unwrap();
+        assert_eq!(ciphertext.len(), encrypted_len(payload.len()));
+        assert_eq!(client.messages_sent(), 4);
+        assert_eq!(server.decrypt(&ciphertext).unwrap(), payload);
+        assert_eq!(server.messages_received(), 4);
+
+        // Truncated and trailing frames are both rejected.
+        let ciphertext = client.encrypt(&payload).unwrap();
+        let (_, mut other_client, mut other_server) = establish(SessionPolicy::default());
+        assert!(matches!(
+            server.decrypt(&ciphertext[..ciphertext.len() - 1]),
+            Err(SessionError::MalformedFrame)
+        ));
+        let mut doubled = other_client.encrypt(b"one").unwrap();
+        doubled.extend(other_client.encrypt(b"two").unwrap());
+        assert!(matches!(
+            other_server.decrypt(&doubled),
+            Err(SessionError::MalformedFrame)
+        ));
+    }
+
+    #[test]
+    fn test_rekeying() {
+        let policy = SessionPolicy {
+            rekey_interval: 3,
+            ..Default::default()
+        };
+        let (_, mut client, mut server) = establish(policy);
+
+        for i in 0..10u8 {
+            let ciphertext = client.encrypt(&[i]).unwrap();
+            assert_eq!(server.decrypt(&ciphertext).unwrap(), [i]);
+            let ciphertext = server.encrypt(&[i]).unwrap();
+            assert_eq!(client.decrypt(&ciphertext).unwrap(), [i]);
+        }
+
+        // A peer that doesn't rekey on the same schedule can't read past the first interval.
+        let (_, mut client, mut server) = establish(policy);
+        server.policy.rekey_interval = 100;
+        for _ in 0..3 {
+            server.decrypt(&client.encrypt(b"ok").unwrap()).unwrap();
+        }
+        assert!(server.decrypt(&client.encrypt(b"not ok").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_exhaustion() {
+        let policy = SessionPolicy {
+            max_messages: 2,
+            ..Default::default()
+        };
+        let (_, mut client, mut server) = establish(policy);
+
+        // A payload needing more frames than remain is refused up front.
+        assert!(matches!(
+            client.encrypt(&vec![0u8; 2 * MAX_FRAME_PAYLOAD + 1]),
+            Err(SessionError::Exhausted)
+        ));
+        assert_eq!(client.messages_sent(), 0);
+
+        for _ in 0..2 {
+            server.decrypt(&client.encrypt(b"hi").unwrap()).unwrap();
+        }
+        assert!(matches!(
+            client.encrypt(b"hi"),
+            Err(SessionError::Exhausted)
+        ));
+        assert!(matches!(
+            server.decrypt(&[0, 0]),
+            Err(SessionError::Exhausted)
+        ));
+    }
+
+    #[test]
+    fn test_ticket_resumption() {
+        let (responder, mut client, mut server) = establish(SessionPolicy::default());
+
+        let message = responder.issue_ticket(&mut server, NOW).unwrap();
+        let ticket = client.accept_ticket(&message).unwrap();
+        assert_eq!(ticket.expires_at, NOW + 3600);
+
+        // Tickets survive serialization.
+        let ticket = SessionTicket::from_bytes(ticket.to_bytes().expose_secret()).unwrap();
+
+        let (resuming, message) = ticket.resume().unwrap();
+        let (mut server, reply) = responder.resume(&message, NOW + 60).unwrap();
+        let mut client = resuming.finish(&reply, SessionPolicy::default()).unwrap();
+        assert_eq!(client.id(), server.id());
+
+        let ciphertext = client.encrypt(b"resumed").unwrap();
+        assert_eq!(server.decrypt(&ciphertext).unwrap(), b"resumed");
+
+        // Expired tickets are refused.
+        let (_, message) = ticket.resume().unwrap();
+        assert!(matches!(
+            responder.resume(&message, NOW + 3600),
+            Err(SessionError::TicketExpired)
+        ));
+    }
+
+    #[test]
+    fn test_tickets_are_bound_to_the_responder() {
+        let (responder, mut client, mut server) = establish(SessionPolicy::default());
+        let ticket = client
+            .accept_ticket(&responder.issue_ticket(&mut server, NOW).unwrap())
+            .unwrap();
+
+        let other_responder = Responder::new(
+            generate_keypair().0.into(),
+            &[8u8; 32],
+            3600,
+            SessionPolicy::default(),
+        )
+        .unwrap();
+        let (_, message) = ticket.resume().unwrap();
+        assert!(matches!(
+            other_responder.resume(&message, NOW),
+            Err(SessionError::InvalidTicket)
+        ));
+
+        // A ticket with the wrong resumption secret fails the handshake.
+        let mut bytes = ticket.to_bytes().expose_secret().to_vec();
+        bytes[9] ^= 1;
+        let (_, message) = SessionTicket::from_bytes(&bytes).unwrap().resume().unwrap();
+        assert!(responder.resume(&message, NOW).is_err());
+    }
+}
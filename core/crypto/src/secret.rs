@@ -0,0 +1,84 @@
+use std::fmt;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Secret key material that is wiped from memory when dropped, and redacted when debug-printed.
+/// The bytes are only reachable through `expose_secret`, and comparisons run in constant time.
+///
+/// Returning a `SecretBytes` across the FFI boundary hands the foreign side its own copy, which is
+/// the caller's to manage; the copy on the Rust side is still wiped.
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_vec())
+    }
+}
+
+// Only the contents are compared in constant time; the lengths of secrets aren't secret.
+impl ConstantTimeEq for SecretBytes {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.as_slice().ct_eq(other.0.as_slice())
+    }
+}
+
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_is_redacted() {
+        let secret = SecretBytes::from(vec![0xab; 4]);
+        assert_eq!(format!("{:?}", secret), "SecretBytes([REDACTED; 4])");
+        assert!(!format!("{:?}", secret).contains("ab"));
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut secret = SecretBytes::from(vec![0xab; 4]);
+        secret.zeroize();
+        assert!(secret.expose_secret().is_empty());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        let secret = SecretBytes::from(vec![0xab; 4]);
+        assert!(bool::from(secret.ct_eq(&SecretBytes::from(vec![0xab; 4]))));
+        assert!(!bool::from(
+            secret.ct_eq(&SecretBytes::from(vec![0xab, 0xab, 0xab, 0xac]))
+        ));
+        assert!(!bool::from(secret.ct_eq(&SecretBytes::from(vec![0xab; 3]))));
+        assert_eq!(secret, SecretBytes::from(vec![0xab; 4]));
+    }
+}
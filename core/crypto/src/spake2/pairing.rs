@@ -0,0 +1,358 @@
+use thiserror::Error;
+
+use crate::spake2::{
+    derive_keys, generate_key_conf_msg, process_key_conf_msg, Spake2Backend, Spake2Error,
+    Spake2Keys, Spake2Role,
+};
+
+/// Prefix for the AAD bound into the confirmation keys during trusted contact enrollment.
+pub const PAKE_ENROLLMENT_LABEL: &[u8] = b"Bitkey Social Recovery PAKE Enrollment Version 1.0";
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Error)]
+pub enum PairingError {
+    #[error(transparent)]
+    Spake2(#[from] Spake2Error),
+    #[error("Too many failed pairing attempts")]
+    LockedOut,
+    #[error("Key confirmation failed, {remaining_attempts} attempts remaining")]
+    ConfirmationFailed { remaining_attempts: u32 },
+}
+
+/// The identifiers of the trusted contact enrollment a pairing belongs to. Both parties must agree
+/// on all of them, or key confirmation fails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnrollmentBinding {
+    pub recovery_relationship_id: String,
+    pub protected_customer_account_id: String,
+    pub trusted_contact_account_id: String,
+}
+
+impl EnrollmentBinding {
+    /// The AAD for this enrollment: the label followed by each identifier, prefixed with its
+    /// length as a big-endian u32 so no two bindings encode the same way.
+    pub fn aad(&self) -> Vec<u8> {
+        let mut aad = PAKE_ENROLLMENT_LABEL.to_vec();
+        for field in [
+            &self.recovery_relationship_id,
+            &self.protected_customer_account_id,
+            &self.trusted_contact_account_id,
+        ] {
+            aad.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            aad.extend_from_slice(field.as_bytes());
+        }
+        aad
+    }
+}
+
+/// Counts failed key confirmations for an enrollment. Callers persist `failed_attempts` between
+/// pairings and restore it with `with_failed_attempts`, so a fresh exchange doesn't reset it.
+///
+/// This runs on the client, so it only stops an honest app from retrying; anyone driving the
+/// protocol directly can ignore it. The server enforces the real limit by refusing an invitation
+/// after `DEFAULT_MAX_ATTEMPTS` wrong codes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttemptLimiter {
+    max_attempts: u32,
+    failed_attempts: u32,
+}
+
+impl Default for AttemptLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ATTEMPTS)
+    }
+}
+
+impl AttemptLimiter {
+    pub fn new(max_attempts: u32) -> Self {
+        Self::with_failed_attempts(max_attempts, 0)
+    }
+
+    pub fn with_failed_attempts(max_attempts: u32, failed_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            failed_attempts,
+        }
+    }
+
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    pub fn remaining_attempts(&self) -> u32 {
+        self.max_attempts.saturating_sub(self.failed_attempts)
+    }
+
+    pub fn is_locked_out(&self) -> bool {
+        self.remaining_attempts() == 0
+    }
+
+    fn record_failure(&mut self) {
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+    }
+
+    fn reset(&mut self) {
+        self.failed_attempts = 0;
+    }
+}
+
+/// A pairing that hasn't sent its SPAKE2 message yet. Each state consumes itself on transition,
+/// so steps can't be repeated or taken out of order.
+pub struct Start<B: Spake2Backend> {
+    backend: B,
+    binding: EnrollmentBinding,
+}
+
+/// Our message has been sent; waiting on the peer's.
+pub struct MsgSent<B: Spake2Backend> {
+    backend: B,
+    binding: EnrollmentBinding,
+}
+
+/// Keys have been derived but not yet confirmed with the peer, and must not be used.
+pub struct KeysDerived {
+    role: Spake2Role,
+    keys: Spake2Keys,
+}
+
+/// Both parties proved they derived the same keys for the same enrollment.
+#[derive(Debug)]
+pub struct Confirmed {
+    role: Spake2Role,
+    keys: Spake2Keys,
+}
+
+impl<B: Spake2Backend> Start<B> {
+    pub fn new(
+        backend: B,
+        binding: EnrollmentBinding,
+        limiter: &AttemptLimiter,
+    ) -> Result<Self, PairingError> {
+        if limiter.is_locked_out() {
+            return Err(PairingError::LockedOut);
+        }
+
+        Ok(Self { backend, binding })
+    }
+
+    pub fn generate_msg(mut self, password: &[u8]) -> Result<(MsgSent<B>, Vec<u8>), PairingError> {
+        let msg = self.backend.generate_msg(password)?;
+        Ok((
+            MsgSent {
+                backend: self.backend,
+                binding: self.binding,
+            },
+            msg,
+        ))
+    }
+}
+
+impl<B: Spake2Backend> MsgSent<B> {
+    pub fn process_msg(mut self, their_msg: &[u8]) -> Result<KeysDerived, PairingError> {
+        let key_material = self.backend.process_msg(their_msg)?;
+        let keys = derive_keys(key_material.expose_secret(), Some(self.binding.aad()))?;
+        Ok(KeysDerived {
+            role: self.backend.role(),
+            keys,
+        })
+    }
+}
+
+impl KeysDerived {
+    pub fn key_conf_msg(&self) -> Result<Vec<u8>, PairingError> {
+        Ok(generate_key_conf_msg(self.role, &self.keys)?)
+    }
+
+    /// Checks the peer's key confirmation message. A mismatch, whether from a wrong password or a
+    /// different enrollment, counts against `limiter`; success resets it.
+    pub fn confirm(
+        self,
+        their_key_conf_msg: &[u8],
+        limiter: &mut AttemptLimiter,
+    ) -> Result<Confirmed, PairingError> {
+        if limiter.is_locked_out() {
+            return Err(PairingError::LockedOut);
+        }
+
+        match process_key_conf_msg(self.role, their_key_conf_msg, &self.keys) {
+            Ok(()) => {
+                limiter.reset();
+                Ok(Confirmed {
+                    role: self.role,
+                    keys: self.keys,
+                })
+            }
+            Err(Spake2Error::MacError) => {
+                limiter.record_failure();
+                if limiter.is_locked_out() {
+                    Err(PairingError::LockedOut)
+                } else {
+                    Err(PairingError::ConfirmationFailed {
+                        remaining_attempts: limiter.remaining_attempts(),
+                    })
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Confirmed {
+    pub fn keys(&self) -> &Spake2Keys {
+        &self.keys
+    }
+
+    /// The key we encrypt to the peer with.
+    pub fn encryption_key(&self) -> &[u8] {
+        match self.role {
+            Spake2Role::Alice => self.keys.alice_encryption_key.expose_secret(),
+            Spake2Role::Bob => self.keys.bob_encryption_key.expose_secret(),
+        }
+    }
+
+    /// The key the peer encrypts to us with.
+    pub fn decryption_key(&self) -> &[u8] {
+        match self.role {
+            Spake2Role::Alice => self.keys.bob_encryption_key.expose_secret(),
+            Spake2Role::Bob => self.keys.alice_encryption_key.expose_secret(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pairing is backend-agnostic, so run against whichever backend this build enables.
+    #[cfg(feature = "spake2")]
+    fn backend(role: Spake2Role, my_name: &str, their_name: &str) -> impl Spake2Backend {
+        crate::spake2::Spake2Context::new(role, my_name.to_string(), their_name.to_string())
+            .unwrap()
+    }
+
+    #[cfg(not(feature = "spake2"))]
+    fn backend(role: Spake2Role, my_name: &str, their_name: &str) -> impl Spake2Backend {
+        crate::spake2::RustSpake2::new(role, my_name.to_string(), their_name.to_string())
+    }
+
+    fn binding() -> EnrollmentBinding {
+        EnrollmentBinding {
+            recovery_relationship_id: "urn:wallet-recovery-relationship:01".to_string(),
+            protected_customer_account_id: "urn:wallet-account:01".to_string(),
+            trusted_contact_account_id: "urn:wallet-account:02".to_string(),
+        }
+    }
+
+    fn derive(
+        alice_password: &[u8],
+        bob_password: &[u8],
+        alice_binding: EnrollmentBinding,
+        bob_binding: EnrollmentBinding,
+    ) -> (KeysDerived, KeysDerived) {
+        let limiter = AttemptLimiter::default();
+        let alice = Start::new(
+            backend(Spake2Role::Alice, "alice", "bob"),
+            alice_binding,
+            &limiter,
+        )
+        .unwrap();
+        let bob = Start::new(
+            backend(Spake2Role::Bob, "bob", "alice"),
+            bob_binding,
+            &limiter,
+        )
+        .unwrap();
+
+        let (alice, alice_msg) = alice.generate_msg(alice_password).unwrap();
+        let (bob, bob_msg) = bob.generate_msg(bob_password).unwrap();
+        (
+            alice.process_msg(&bob_msg).unwrap(),
+            bob.process_msg(&alice_msg).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_pairing() {
+        let (alice, bob) = derive(b"password", b"password", binding(), binding());
+        let alice_conf_msg = alice.key_conf_msg().unwrap();
+        let bob_conf_msg = bob.key_conf_msg().unwrap();
+
+        let mut limiter = AttemptLimiter::default();
+        let alice = alice.confirm(&bob_conf_msg, &mut limiter).unwrap();
+        let bob = bob.confirm(&alice_conf_msg, &mut limiter).unwrap();
+
+        assert_eq!(alice.keys(), bob.keys());
+        assert_eq!(alice.encryption_key(), bob.decryption_key());
+        assert_eq!(alice.decryption_key(), bob.encryption_key());
+    }
+
+    #[test]
+    fn test_different_enrollment() {
+        let mut other_binding = binding();
+        other_binding.trusted_contact_account_id = "urn:wallet-account:03".to_string();
+
+        let (alice, bob) = derive(b"password", b"password", binding(), other_binding);
+        let mut limiter = AttemptLimiter::default();
+        assert!(matches!(
+            alice.confirm(&bob.key_conf_msg().unwrap(), &mut limiter),
+            Err(PairingError::ConfirmationFailed {
+                remaining_attempts: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_binding_fields_are_length_prefixed() {
+        let a = EnrollmentBinding {
+            recovery_relationship_id: "ab".to_string(),
+            protected_customer_account_id: "c".to_string(),
+            trusted_contact_account_id: "d".to_string(),
+        };
+        let b = EnrollmentBinding {
+            recovery_relationship_id: "a".to_string(),
+            protected_customer_account_id: "bc".to_string(),
+            trusted_contact_account_id: "d".to_string(),
+        };
+        assert_ne!(a.aad(), b.aad());
+    }
+
+    #[test]
+    fn test_lockout() {
+        let mut limiter = AttemptLimiter::new(2);
+
+        let (alice, bob) = derive(b"password", b"passworf", binding(), binding());
+        assert!(matches!(
+            alice.confirm(&bob.key_conf_msg().unwrap(), &mut limiter),
+            Err(PairingError::ConfirmationFailed {
+                remaining_attempts: 1
+            })
+        ));
+
+        let (alice, bob) = derive(b"password", b"passworf", binding(), binding());
+        assert!(matches!(
+            alice.confirm(&bob.key_conf_msg().unwrap(), &mut limiter),
+            Err(PairingError::LockedOut)
+        ));
+
+        // Even the right password is refused once locked out.
+        assert!(matches!(
+            Start::new(
+                backend(Spake2Role::Alice, "alice", "bob"),
+                binding(),
+                &limiter,
+            ),
+            Err(PairingError::LockedOut)
+        ));
+    }
+
+    #[test]
+    fn test_success_resets_limiter() {
+        let mut limiter = AttemptLimiter::with_failed_attempts(5, 3);
+        let (alice, bob) = derive(b"password", b"password", binding(), binding());
+        alice
+            .confirm(&bob.key_conf_msg().unwrap(), &mut limiter)
+            .unwrap();
+        assert_eq!(limiter.failed_attempts(), 0);
+    }
+}
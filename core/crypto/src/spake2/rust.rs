@@ -0,0 +1,218 @@
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use rand::RngCore;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+use crate::secret::SecretBytes;
+use crate::spake2::{Spake2Backend, Spake2Error, Spake2Role};
+
+// M and N for edwards25519, from RFC 9382 section 6.
+const M: [u8; 32] = [
+    0xd0, 0x48, 0x03, 0x2c, 0x6e, 0xa0, 0xb6, 0xd6, 0x97, 0xdd, 0xc2, 0xe8, 0x6b, 0xda, 0x85, 0xa3,
+    0x3a, 0xda, 0xc9, 0x20, 0xf1, 0xbf, 0x18, 0xe1, 0xb0, 0xc6, 0xd1, 0x66, 0xa5, 0xce, 0xcd, 0xaf,
+];
+const N: [u8; 32] = [
+    0xd3, 0xbf, 0xb5, 0x18, 0xf4, 0x4f, 0x34, 0x30, 0xf2, 0x9d, 0x0c, 0x92, 0xaf, 0x50, 0x38, 0x65,
+    0xa1, 0xed, 0x32, 0x81, 0xdc, 0x69, 0xb3, 0x5d, 0xd8, 0x68, 0xba, 0x85, 0xf8, 0x86, 0xc4, 0xab,
+];
+const MSG_LENGTH: usize = 32;
+
+enum State {
+    Init,
+    MsgGenerated {
+        private_key: Zeroizing<Scalar>,
+        password_scalar: Zeroizing<Scalar>,
+        my_msg: [u8; MSG_LENGTH],
+    },
+    Done,
+}
+
+/// SPAKE2 over edwards25519 with SHA-512, following RFC 9382. Like `Spake2Context`, each instance
+/// can only be used for a single exchange.
+pub struct RustSpake2 {
+    role: Spake2Role,
+    my_name: Vec<u8>,
+    their_name: Vec<u8>,
+    state: State,
+}
+
+fn decompress(bytes: [u8; 32]) -> Option<EdwardsPoint> {
+    CompressedEdwardsY(bytes).decompress()
+}
+
+fn blinding_point(role: Spake2Role) -> EdwardsPoint {
+    let bytes = match role {
+        Spake2Role::Alice => M,
+        Spake2Role::Bob => N,
+    };
+    decompress(bytes).expect("RFC 9382 constants are valid points")
+}
+
+fn random_scalar() -> Zeroizing<Scalar> {
+    let mut bytes = Zeroizing::new([0u8; 64]);
+    rand::thread_rng().fill_bytes(bytes.as_mut());
+    Zeroizing::new(Scalar::from_bytes_mod_order_wide(&bytes))
+}
+
+fn password_scalar(password: &[u8]) -> Zeroizing<Scalar> {
+    let mut hash = Zeroizing::new([0u8; 64]);
+    hash.copy_from_slice(&Sha512::digest(password));
+    Zeroizing::new(Scalar::from_bytes_mod_order_wide(&hash))
+}
+
+// Each transcript element is prefixed with its length as a little-endian u64.
+fn append_to_transcript(transcript: &mut Vec<u8>, element: &[u8]) {
+    transcript.extend_from_slice(&(element.len() as u64).to_le_bytes());
+    transcript.extend_from_slice(element);
+}
+
+impl RustSpake2 {
+    pub fn new(my_role: Spake2Role, my_name: String, their_name: String) -> Self {
+        Self {
+            role: my_role,
+            my_name: my_name.into_bytes(),
+            their_name: their_name.into_bytes(),
+            state: State::Init,
+        }
+    }
+}
+
+impl Spake2Backend for RustSpake2 {
+    fn role(&self) -> Spake2Role {
+        self.role
+    }
+
+    fn generate_msg(&mut self, password: &[u8]) -> Result<Vec<u8>, Spake2Error> {
+        if !matches!(self.state, State::Init) {
+            return Err(Spake2Error::GenerateMessageError);
+        }
+
+        let private_key = random_scalar();
+        let password_scalar = password_scalar(password);
+        let my_msg = (EdwardsPoint::mul_base(&private_key)
+            + blinding_point(self.role) * *password_scalar)
+            .compress()
+            .to_bytes();
+
+        self.state = State::MsgGenerated {
+            private_key,
+            password_scalar,
+            my_msg,
+        };
+        Ok(my_msg.to_vec())
+    }
+
+    fn process_msg(&mut self, their_msg: &[u8]) -> Result<SecretBytes, Spake2Error> {
+        let State::MsgGenerated {
+            private_key,
+            password_scalar,
+            my_msg,
+        } = std::mem::replace(&mut self.state, State::Done)
+        else {
+            return Err(Spake2Error::ProcessMessageError);
+        };
+
+        let their_msg: [u8; MSG_LENGTH] = their_msg
+            .try_into()
+            .map_err(|_| Spake2Error::ProcessMessageError)?;
+        let their_point = decompress(their_msg)
+            .filter(|point| !point.is_small_order())
+            .ok_or(Spake2Error::ProcessMessageError)?;
+
+        let their_role = match self.role {
+            Spake2Role::Alice => Spake2Role::Bob,
+            Spake2Role::Bob => Spake2Role::Alice,
+        };
+        // K = h * x * (their_msg - w * their blinding point)
+        let shared_point = (their_point - blinding_point(their_role) * *password_scalar)
+            .mul_by_cofactor()
+            * *private_key;
+        if shared_point.is_identity() {
+            return Err(Spake2Error::ProcessMessageError);
+        }
+
+        // TT = A || B || pA || pB || K || w, with A and pA always Alice's.
+        let (alice_name, bob_name, alice_msg, bob_msg) = match self.role {
+            Spake2Role::Alice => (&self.my_name, &self.their_name, &my_msg, &their_msg),
+            Spake2Role::Bob => (&self.their_name, &self.my_name, &their_msg, &my_msg),
+        };
+        let mut transcript = Zeroizing::new(Vec::new());
+        append_to_transcript(&mut transcript, alice_name);
+        append_to_transcript(&mut transcript, bob_name);
+        append_to_transcript(&mut transcript, alice_msg);
+        append_to_transcript(&mut transcript, bob_msg);
+        append_to_transcript(&mut transcript, shared_point.compress().as_bytes());
+        append_to_transcript(&mut transcript, password_scalar.as_bytes());
+
+        Ok(Sha512::digest(transcript.as_slice()).to_vec().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(alice_password: &[u8], bob_password: &[u8]) -> (SecretBytes, SecretBytes) {
+        let mut alice = RustSpake2::new(Spake2Role::Alice, "alice".to_string(), "bob".to_string());
+        let mut bob = RustSpake2::new(Spake2Role::Bob, "bob".to_string(), "alice".to_string());
+
+        let alice_msg = alice.generate_msg(alice_password).unwrap();
+        let bob_msg = bob.generate_msg(bob_password).unwrap();
+        (
+            alice.process_msg(&bob_msg).unwrap(),
+            bob.process_msg(&alice_msg).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_constants_are_valid_points() {
+        for role in [Spake2Role::Alice, Spake2Role::Bob] {
+            assert!(!blinding_point(role).is_small_order());
+        }
+    }
+
+    #[test]
+    fn test_good() {
+        let (alice_key_material, bob_key_material) = exchange(b"password", b"password");
+        assert_eq!(alice_key_material, bob_key_material);
+        assert_eq!(alice_key_material.expose_secret().len(), 64);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let (alice_key_material, bob_key_material) = exchange(b"password", b"passworf");
+        assert_ne!(alice_key_material, bob_key_material);
+    }
+
+    #[test]
+    fn test_out_of_order_calls() {
+        let mut alice = RustSpake2::new(Spake2Role::Alice, "alice".to_string(), "bob".to_string());
+        assert!(matches!(
+            alice.process_msg(&[0u8; 32]),
+            Err(Spake2Error::ProcessMessageError)
+        ));
+
+        let mut alice = RustSpake2::new(Spake2Role::Alice, "alice".to_string(), "bob".to_string());
+        alice.generate_msg(b"password").unwrap();
+        assert!(matches!(
+            alice.generate_msg(b"password"),
+            Err(Spake2Error::GenerateMessageError)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_small_order_messages() {
+        let mut alice = RustSpake2::new(Spake2Role::Alice, "alice".to_string(), "bob".to_string());
+        alice.generate_msg(b"password").unwrap();
+
+        // The identity point.
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        assert!(matches!(
+            alice.process_msg(&identity),
+            Err(Spake2Error::ProcessMessageError)
+        ));
+    }
+}
@@ -23,6 +23,8 @@ use x509_parser::prelude::*;
 
 use aws_nitro_enclaves_image_format::utils::eif_reader::EifReader;
 
+pub mod policy;
+
 #[derive(Error, Debug)]
 pub enum EnclaveToolsError {
     #[error("I/O error")]
@@ -41,6 +43,8 @@ pub enum EnclaveToolsError {
     CoseError(#[from] CoseError),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EnclaveCodesigningKeyType {
     Development, // Includes staging and named stacks.
     Production,
@@ -174,6 +178,7 @@ impl AttestationDocument {
         &self,
         signed_cose_document: &CoseSign1,
         root_public_key: &[u8],
+        now: ASN1Time,
     ) -> Result<(), EnclaveToolsError> {
         if self.cabundle.is_empty() {
             return Err(EnclaveToolsError::CertChainValidationError);
@@ -181,7 +186,7 @@ impl AttestationDocument {
 
         // Root comes first and is self-signed.
         let root = &bytebuf_to_x509_cert(&self.cabundle[0])?;
-        verify_directly_issued_by(root, root)?;
+        verify_directly_issued_by(root, root, now)?;
 
         // The root public key must match the hardcoded AWS Nitro Enclave root CA public key.
         match root.subject_pki.parsed() {
@@ -197,13 +202,13 @@ impl AttestationDocument {
         for i in 1..self.cabundle.len() {
             let subject = &bytebuf_to_x509_cert(&self.cabundle[i])?;
             let issuer = &bytebuf_to_x509_cert(&self.cabundle[i - 1])?;
-            verify_directly_issued_by(subject, issuer)?;
+            verify_directly_issued_by(subject, issuer, now)?;
         }
 
         // Verify the leaf.
         let leaf = &bytebuf_to_x509_cert(&self.certificate)?;
         let issuer = &bytebuf_to_x509_cert(&self.cabundle[self.cabundle.len() - 1])?;
-        verify_directly_issued_by(leaf, issuer)?;
+        verify_directly_issued_by(leaf, issuer, now)?;
 
         // Do the actual signature verification over the signed attestation document.
         let pubkey = EnclaveToolsSigningPublicKey::new_from_x509_cert(leaf)?;
@@ -320,14 +325,15 @@ const BYPASS_EXPIRY_CHECK: bool = false;
 fn check_cert_expiry(
     cert: &X509Certificate,
     issuer: &X509Certificate,
+    now: ASN1Time,
 ) -> Result<(), EnclaveToolsError> {
     if !BYPASS_EXPIRY_CHECK {
-        if !cert.validity().is_valid() {
+        if !cert.validity().is_valid_at(now) {
             return Err(EnclaveToolsError::IssuanceError(
                 "Subject expired".to_string(),
             ));
         }
-        if !issuer.validity().is_valid() {
+        if !issuer.validity().is_valid_at(now) {
             return Err(EnclaveToolsError::IssuanceError(
                 "Issuer expired".to_string(),
             ));
@@ -346,6 +352,7 @@ fn check_cert_expiry(
 fn verify_directly_issued_by(
     cert: &X509Certificate,
     issuer: &X509Certificate,
+    now: ASN1Time,
 ) -> Result<(), EnclaveToolsError> {
     if cert.issuer() != issuer.subject() {
         return Err(EnclaveToolsError::IssuanceError(
@@ -357,7 +364,7 @@ fn verify_directly_issued_by(
         return Err(EnclaveToolsError::IssuanceError("Not a CA".to_string()));
     }
 
-    check_cert_expiry(cert, issuer)?;
+    check_cert_expiry(cert, issuer, now)?;
 
     if cert.verify_signature(Some(issuer.public_key())).is_err() {
         return Err(EnclaveToolsError::IssuanceError(
@@ -433,13 +440,16 @@ impl NitroEnclaveVerifier {
     fn verify_chain_and_doc(
         &self,
         signed_cose_document: &CoseSign1,
+        now: ASN1Time,
     ) -> Result<AttestationDocument, EnclaveToolsError> {
         let doc_bytes = signed_cose_document.get_payload::<Openssl>(None)?;
         let attestation_document: AttestationDocument =
             ciborium::de::from_reader(&doc_bytes[..]).map_err(|_| EnclaveToolsError::ParseError)?;
-        match attestation_document
-            .verify_aws_nitro_enclave_cert_chain(signed_cose_document, &self.root_public_key)
-        {
+        match attestation_document.verify_aws_nitro_enclave_cert_chain(
+            signed_cose_document,
+            &self.root_public_key,
+            now,
+        ) {
             Ok(_) => Ok(attestation_document),
             Err(e) => Err(e),
         }
@@ -532,13 +542,23 @@ fn verify_enclave_signature(
 pub fn parse_and_verify_signed_attestation_document(
     signed_document: Vec<u8>,
     challenge: Option<Vec<u8>>,
+) -> Result<AttestationDocument, EnclaveToolsError> {
+    parse_and_verify_signed_attestation_document_at(signed_document, challenge, ASN1Time::now())
+}
+
+// Like `parse_and_verify_signed_attestation_document`, but checks certificate expiry as of `now`
+// rather than the current time.
+pub fn parse_and_verify_signed_attestation_document_at(
+    signed_document: Vec<u8>,
+    challenge: Option<Vec<u8>>,
+    now: ASN1Time,
 ) -> Result<AttestationDocument, EnclaveToolsError> {
     // References:
     // https://github.com/aws/aws-nitro-enclaves-nsm-api/blob/main/docs/attestation_process.md
     // https://aws.amazon.com/blogs/compute/validating-attestation-documents-produced-by-aws-nitro-enclaves/
     let cose_doc = CoseSign1::from_bytes(&signed_document)?;
     let verifier = NitroEnclaveVerifier::new();
-    let doc = verifier.verify_chain_and_doc(&cose_doc)?;
+    let doc = verifier.verify_chain_and_doc(&cose_doc, now)?;
 
     match challenge {
         Some(challenge) => {
@@ -617,13 +637,14 @@ fn verify_codesigning_certificate_chain(
     cabundle: Vec<Vec<u8>>,
     key_type: EnclaveCodesigningKeyType,
     pcr8: Vec<u8>,
+    now: ASN1Time,
 ) -> Result<CertWrapper, EnclaveToolsError> {
     let certs = collect_der_encoded_certs(cabundle, leaf)?;
 
     let cabundle = certs.cabundle;
     let root = &cabundle[0].cert()?;
 
-    verify_directly_issued_by(root, root)?;
+    verify_directly_issued_by(root, root, now)?;
 
     // The root public key must match our codesigning root CA public key for the specific key type.
     match root.subject_pki.parsed() {
@@ -639,13 +660,13 @@ fn verify_codesigning_certificate_chain(
     for i in 1..cabundle.len() {
         let subject = &cabundle[i].cert()?;
         let issuer = &cabundle[i - 1].cert()?;
-        verify_directly_issued_by(subject, issuer)?;
+        verify_directly_issued_by(subject, issuer, now)?;
     }
 
     // Verify the leaf.
     let leaf = certs.leaf.cert()?;
     let issuer = &cabundle[cabundle.len() - 1].cert()?;
-    verify_directly_issued_by(&leaf, issuer)?;
+    verify_directly_issued_by(&leaf, issuer, now)?;
 
     // At this point, we trust the certificate chain and the leaf.
 
@@ -712,6 +733,7 @@ pub fn verify_enclave(
         codesigning_cabundle,
         key_type,
         pcr8.to_vec(),
+        ASN1Time::now(),
     ) {
         Ok(l) => l,
         Err(e) => {
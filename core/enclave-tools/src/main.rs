@@ -1,7 +1,17 @@
 use aws_nitro_enclaves_image_format::utils::eif_reader::EifReader;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use clap::{Parser, Subcommand};
-use enclave_tools::{calculate_pcrs, parse_and_verify_signed_attestation_document};
+use enclave_tools::policy::{verify_attestation_policy, AttestationPolicy, PolicyVerdict};
+use enclave_tools::{
+    calculate_pcrs, parse_and_verify_signed_attestation_document, EnclaveToolsError,
+};
+use serde_json::json;
+use std::fs;
+
+// Exit codes for `verify`: the attestation document doesn't satisfy the policy, or it couldn't be
+// checked at all because an input couldn't be read or parsed.
+const EXIT_NOT_VERIFIED: i32 = 1;
+const EXIT_INVALID_INPUT: i32 = 2;
 
 #[derive(Parser, Debug)]
 struct Cli {
@@ -21,6 +31,26 @@ enum Command {
         #[arg(long, required = true, help = "Base64 encoded attestation document")]
         attestation: String,
     },
+    Verify {
+        #[arg(long, required = true, help = "Base64 encoded attestation document")]
+        attestation: String,
+        #[arg(long, required = true, help = "Path to JSON policy file")]
+        policy: String,
+        #[arg(
+            long,
+            required = true,
+            help = "Path to DER codesigning leaf certificate"
+        )]
+        codesigning_leaf: String,
+        #[arg(
+            long,
+            required = true,
+            value_delimiter = ' ',
+            num_args = 1..,
+            help = "Paths to DER codesigning CA certificates; root comes FIRST"
+        )]
+        codesigning_cabundle: Vec<String>,
+    },
 }
 
 fn main() {
@@ -41,5 +71,70 @@ fn main() {
                 .expect("Unable to parse attestation document");
             println!("{}", parsed_doc);
         }
+        Command::Verify {
+            attestation,
+            policy,
+            codesigning_leaf,
+            codesigning_cabundle,
+        } => {
+            let (output, exit_code) = match verify(
+                &attestation,
+                &policy,
+                &codesigning_leaf,
+                &codesigning_cabundle,
+            ) {
+                Ok(verdict) => {
+                    let exit_code = if verdict.verified {
+                        0
+                    } else {
+                        EXIT_NOT_VERIFIED
+                    };
+                    (json!(verdict), exit_code)
+                }
+                Err(error) => (
+                    json!({ "verified": false, "error": error }),
+                    EXIT_INVALID_INPUT,
+                ),
+            };
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&output).expect("Unable to serialize verdict")
+            );
+            std::process::exit(exit_code);
+        }
+    }
+}
+
+// Reads the inputs and checks the attestation document against the policy. Problems with the
+// document itself are failures in the verdict; an error means the inputs couldn't be used.
+fn verify(
+    attestation: &str,
+    policy_path: &str,
+    codesigning_leaf_path: &str,
+    codesigning_cabundle_paths: &[String],
+) -> Result<PolicyVerdict, String> {
+    let read = |path: &str, name: &str| {
+        fs::read(path).map_err(|e| format!("Unable to read {} {}: {}", name, path, e))
+    };
+
+    let decoded_doc = BASE64
+        .decode(attestation.trim().as_bytes())
+        .map_err(|e| format!("Attestation document is not valid Base64: {}", e))?;
+    let policy =
+        AttestationPolicy::from_json(&read(policy_path, "policy file")?).map_err(describe)?;
+    let codesigning_leaf = read(codesigning_leaf_path, "codesigning leaf")?;
+    let codesigning_cabundle = codesigning_cabundle_paths
+        .iter()
+        .map(|path| read(path.as_str(), "codesigning CA certificate"))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    verify_attestation_policy(decoded_doc, codesigning_leaf, codesigning_cabundle, &policy)
+        .map_err(describe)
+}
+
+fn describe(error: EnclaveToolsError) -> String {
+    match error {
+        EnclaveToolsError::InternalError(message) => message,
+        error => error.to_string(),
     }
 }
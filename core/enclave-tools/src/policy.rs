@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use x509_parser::prelude::ASN1Time;
+
+use crate::{
+    parse_and_verify_signed_attestation_document_at, pcr_string_to_u8,
+    verify_codesigning_certificate_chain, EnclaveCodesigningKeyType, EnclaveToolsError,
+};
+
+// PCRs every policy must pin: the enclave image, kernel, application and codesigning certificate.
+const REQUIRED_PCRS: [&str; 4] = ["PCR0", "PCR1", "PCR2", "PCR8"];
+
+// What an attestation document is expected to attest to. Loaded from JSON, e.g.
+//
+// {
+//   "pcrs": { "PCR0": "cf1c...", "PCR1": "5d39...", "PCR2": "0066...", "PCR8": "180e..." },
+//   "codesigning_key_type": "development",
+//   "nonce": "040506",
+//   "now": 1717629556
+// }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttestationPolicy {
+    // Hex encoded PCR values, keyed by "PCR0", "PCR1", etc.
+    pub pcrs: BTreeMap<String, String>,
+    pub codesigning_key_type: EnclaveCodesigningKeyType,
+    // Hex encoded nonce the document must carry, if any.
+    #[serde(default)]
+    pub nonce: Option<String>,
+    // Hex encoded user data the document must carry, if any.
+    #[serde(default)]
+    pub user_data: Option<String>,
+    // Unix time at which certificates are checked for expiry; defaults to the current time.
+    #[serde(default)]
+    pub now: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyVerdict {
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    pub failures: Vec<String>,
+}
+
+impl PolicyVerdict {
+    fn new(module_id: Option<String>, timestamp: Option<u64>, failures: Vec<String>) -> Self {
+        Self {
+            verified: failures.is_empty(),
+            module_id,
+            timestamp,
+            failures,
+        }
+    }
+}
+
+impl AttestationPolicy {
+    pub fn from_json(json: &[u8]) -> Result<Self, EnclaveToolsError> {
+        let policy: Self = serde_json::from_slice(json)
+            .map_err(|e| EnclaveToolsError::InternalError(format!("Invalid policy: {}", e)))?;
+
+        for pcr in REQUIRED_PCRS {
+            if !policy.pcrs.contains_key(pcr) {
+                return Err(EnclaveToolsError::InternalError(format!(
+                    "Invalid policy: {} is required",
+                    pcr
+                )));
+            }
+        }
+
+        Ok(policy)
+    }
+
+    fn now(&self) -> Result<ASN1Time, EnclaveToolsError> {
+        match self.now {
+            Some(now) => ASN1Time::from_timestamp(now).map_err(|_| {
+                EnclaveToolsError::InternalError(format!("Invalid policy: bad time {}", now))
+            }),
+            None => Ok(ASN1Time::now()),
+        }
+    }
+}
+
+fn decode_hex_field(name: &str, value: &str) -> Result<Vec<u8>, EnclaveToolsError> {
+    hex::decode(value).map_err(|_| {
+        EnclaveToolsError::InternalError(format!("Invalid policy: {} is not hex", name))
+    })
+}
+
+fn check_field(failures: &mut Vec<String>, name: &str, expected: &[u8], actual: Option<&[u8]>) {
+    match actual {
+        Some(actual) if actual == expected => {}
+        Some(actual) => failures.push(format!(
+            "{} mismatch: attestation document: {}, policy: {}",
+            name,
+            hex::encode(actual),
+            hex::encode(expected)
+        )),
+        None => failures.push(format!("{} not found in attestation document", name)),
+    }
+}
+
+// Verify a signed attestation document against a policy, without needing the EIF. The
+// codesigning certificates are checked against the policy's key type and the attested PCR8.
+//
+// Only a malformed policy is an error; everything wrong with the document is reported in the
+// verdict.
+pub fn verify_attestation_policy(
+    signed_attestation_document: Vec<u8>,
+    codesigning_leaf_der: Vec<u8>,
+    codesigning_cabundle: Vec<Vec<u8>>,
+    policy: &AttestationPolicy,
+) -> Result<PolicyVerdict, EnclaveToolsError> {
+    let now = policy.now()?;
+    let expected_pcrs = policy
+        .pcrs
+        .iter()
+        .map(|(pcr, value)| Ok((pcr_string_to_u8(pcr)?, decode_hex_field(pcr, value)?)))
+        .collect::<Result<BTreeMap<u8, Vec<u8>>, EnclaveToolsError>>()?;
+    let expected_nonce = policy
+        .nonce
+        .as_deref()
+        .map(|nonce| decode_hex_field("nonce", nonce))
+        .transpose()?;
+    let expected_user_data = policy
+        .user_data
+        .as_deref()
+        .map(|user_data| decode_hex_field("user_data", user_data))
+        .transpose()?;
+
+    // Without a verified document there's nothing else to check.
+    let attestation_document = match parse_and_verify_signed_attestation_document_at(
+        signed_attestation_document,
+        None,
+        now,
+    ) {
+        Ok(doc) => doc,
+        Err(e) => {
+            return Ok(PolicyVerdict::new(
+                None,
+                None,
+                vec![format!("Attestation document verification failed: {:?}", e)],
+            ))
+        }
+    };
+
+    let mut failures = Vec::new();
+
+    for (pcr_num, expected) in &expected_pcrs {
+        check_field(
+            &mut failures,
+            &format!("PCR{}", pcr_num),
+            expected,
+            attestation_document
+                .pcrs
+                .get(pcr_num)
+                .map(|pcr| pcr.as_slice()),
+        );
+    }
+
+    if let Some(expected) = &expected_nonce {
+        check_field(
+            &mut failures,
+            "Nonce",
+            expected,
+            attestation_document
+                .nonce
+                .as_ref()
+                .map(|nonce| nonce.as_slice()),
+        );
+    }
+
+    if let Some(expected) = &expected_user_data {
+        check_field(
+            &mut failures,
+            "User data",
+            expected,
+            attestation_document
+                .user_data
+                .as_ref()
+                .map(|user_data| user_data.as_slice()),
+        );
+    }
+
+    // A missing PCR8 was already reported above, since every policy pins it.
+    if let Some(pcr8) = attestation_document.pcrs.get(&8) {
+        if let Err(e) = verify_codesigning_certificate_chain(
+            codesigning_leaf_der,
+            codesigning_cabundle,
+            policy.codesigning_key_type,
+            pcr8.to_vec(),
+            now,
+        ) {
+            failures.push(format!(
+                "Codesigning certificate chain verification failed: {:?}",
+                e
+            ));
+        }
+    }
+
+    Ok(PolicyVerdict::new(
+        Some(attestation_document.module_id.clone()),
+        Some(attestation_document.timestamp),
+        failures,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+    use super::*;
+
+    fn test_policy() -> AttestationPolicy {
+        AttestationPolicy::from_json(
+            br#"{
+                "pcrs": {
+                    "PCR0": "cf1c8e372a01e48e6d1cf4639ecb6c40b3ccf6fc89d68b1f119efd0491448c4e34354c66f24964ccff992c613318bb4d",
+                    "PCR1": "5d3938eb05288e20a981038b1861062ff4174884968a39aee5982b312894e60561883576cc7381d1a7d05b809936bd16",
+                    "PCR2": "0066e9542c2ad76af8304c5c2549663d36146238c6b41ea074b21d03b95ae42cc34183a58941e6a7d3a3c511788a69cb",
+                    "PCR8": "180ed42dffb8e71132b21c3180388e412973099f267e831d9c3dd71ad9ffd5f405515d1029dc4cd92364b082a8ed65e1"
+                },
+                "codesigning_key_type": "development",
+                "nonce": "040506",
+                "now": 1717629556
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn verify(policy: &AttestationPolicy) -> PolicyVerdict {
+        let signed_doc_b64 = include_str!("../test-data/signed-attestation-document.b64");
+        let signed_doc_bytes = BASE64.decode(signed_doc_b64.as_bytes()).unwrap();
+
+        let codesigning_leaf_der = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../server/src/wsm/keys/nitro-enclave-codesigning-cert-staging-leaf.der"
+        ))
+        .to_vec();
+        let codesigning_cabundle = vec![
+            include_bytes!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../../server/src/wsm/keys/nitro-enclave-codesigning-cert-staging-root.der"
+            ))
+            .to_vec(),
+            include_bytes!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../../server/src/wsm/keys/nitro-enclave-codesigning-cert-staging-intermediate.der"
+            ))
+            .to_vec(),
+        ];
+
+        verify_attestation_policy(
+            signed_doc_bytes,
+            codesigning_leaf_der,
+            codesigning_cabundle,
+            policy,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_attestation_policy() {
+        let verdict = verify(&test_policy());
+        assert!(verdict.verified, "{:?}", verdict.failures);
+        assert!(verdict.module_id.is_some());
+    }
+
+    #[test]
+    fn test_verify_attestation_policy_mismatches() {
+        let mut policy = test_policy();
+        policy.pcrs.insert("PCR1".to_string(), "00".repeat(48));
+        policy.nonce = Some("000000".to_string());
+        policy.user_data = Some("01".to_string());
+
+        let verdict = verify(&policy);
+        assert!(!verdict.verified);
+        assert_eq!(verdict.failures.len(), 3, "{:?}", verdict.failures);
+        assert!(verdict.failures[0].starts_with("PCR1 mismatch"));
+        assert!(verdict.failures[1].starts_with("Nonce mismatch"));
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        // Missing PCR8.
+        assert!(AttestationPolicy::from_json(
+            br#"{ "pcrs": { "PCR0": "00", "PCR1": "00", "PCR2": "00" }, "codesigning_key_type": "development" }"#
+        )
+        .is_err());
+
+        let mut policy = test_policy();
+        policy
+            .pcrs
+            .insert("PCR2".to_string(), "not hex".to_string());
+        assert!(verify_attestation_policy(vec![], vec![], vec![], &policy).is_err());
+    }
+}
@@ -1,17 +1,26 @@
-use rand_core::OsRng;
+use rand_core::{OsRng, RngCore};
 use secp256k1::{
     ecdsa::Signature,
     hashes::{sha256, Hash},
     Keypair, Message, PublicKey, SecretKey,
 };
 
+use std::fmt;
+use std::str::FromStr;
 #[cfg(not(test))]
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const MAX_NAME_LEN: usize = 16;
 pub const MAX_PUBKEY_LEN: usize = 33;
 pub const MAX_SIG_LEN: usize = 64;
-pub const CURRENT_VERSION: u8 = 1;
+pub const VERSION_1: u8 = 1;
+// Version 2 appends a serial number and key usage to the version 1 fields.
+pub const VERSION_2: u8 = 2;
+pub const CURRENT_VERSION: u8 = VERSION_2;
+pub const REVOCATION_LIST_VERSION: u8 = 1;
+// Revocation lists older than this are rejected, so that an old list can't be replayed to hide a
+// later revocation. Issuers must reissue their list within this period, even if it hasn't changed.
+pub const MAX_REVOCATION_LIST_AGE_SECS: u64 = 90 * 24 * 60 * 60;
 
 // Copy bytes from src to a fixed size array, padding with zeros if necessary
 fn copy_with_zeros<const N: usize>(src: &[u8]) -> [u8; N] {
@@ -21,24 +30,122 @@ fn copy_with_zeros<const N: usize>(src: &[u8]) -> [u8; N] {
     arr
 }
 
+// The discriminants are stable, since the picocert CLI exits with them. Only append new variants.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Error {
-    Ok,
-    Invalid,
-    Expired,
-    Signature,
-    Issuer,
-    Version,
-    NotSelfSigned,
-    InvalidValidityPeriod,
-    MalformedSignatureInput,
-    InvalidPublicKey,
-    InvalidPrivateKey,
-    InvalidFromTime,
-    InvalidToTime,
-    InvalidIssuer,
-    InvalidSubject,
-    InvalidSignature,
+    Ok = 0,
+    Invalid = 1,
+    Expired = 2,
+    Signature = 3,
+    Issuer = 4,
+    Version = 5,
+    NotSelfSigned = 6,
+    InvalidValidityPeriod = 7,
+    MalformedSignatureInput = 8,
+    InvalidPublicKey = 9,
+    InvalidPrivateKey = 10,
+    InvalidFromTime = 11,
+    InvalidToTime = 12,
+    InvalidIssuer = 13,
+    InvalidSubject = 14,
+    InvalidSignature = 15,
+    KeyUsage = 16,
+    Revoked = 17,
+    InvalidRevocationList = 18,
+    StaleRevocationList = 19,
+}
+
+// What the key in a version 2 certificate may be used for. Version 1 certificates carry no key
+// usage and are unrestricted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyUsage(u32);
+
+impl KeyUsage {
+    // Issuing certificates and revocation lists.
+    pub const CERT_SIGNING: KeyUsage = KeyUsage(0x1);
+    pub const FIRMWARE_SIGNING: KeyUsage = KeyUsage(0x2);
+    pub const ATTESTATION: KeyUsage = KeyUsage(0x4);
+    pub const BATCH_SIGNING: KeyUsage = KeyUsage(0x8);
+
+    const NAMED: [(KeyUsage, &'static str); 4] = [
+        (KeyUsage::CERT_SIGNING, "cert-signing"),
+        (KeyUsage::FIRMWARE_SIGNING, "firmware-signing"),
+        (KeyUsage::ATTESTATION, "attestation"),
+        (KeyUsage::BATCH_SIGNING, "batch-signing"),
+    ];
+
+    pub const fn empty() -> Self {
+        KeyUsage(0)
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        KeyUsage(bits)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(&self, other: KeyUsage) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // The names of the usages held, with any unknown bits in hex.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = KeyUsage::NAMED
+            .iter()
+            .filter(|(usage, _)| self.contains(*usage))
+            .map(|(_, name)| name.to_string())
+            .collect::<Vec<String>>();
+        let unknown = self.0
+            & !KeyUsage::NAMED
+                .iter()
+                .fold(0, |bits, (usage, _)| bits | usage.0);
+        if unknown != 0 {
+            names.push(format!("{:#x}", unknown));
+        }
+        names
+    }
+}
+
+impl std::ops::BitOr for KeyUsage {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        KeyUsage(self.0 | rhs.0)
+    }
+}
+
+impl fmt::Display for KeyUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = self.names();
+        if names.is_empty() {
+            names.push("none".to_string());
+        }
+        write!(f, "{}", names.join(","))
+    }
+}
+
+// Parses a comma-separated list of key usage names, e.g. "cert-signing,firmware-signing".
+impl FromStr for KeyUsage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .try_fold(KeyUsage::empty(), |key_usage, name| {
+                KeyUsage::NAMED
+                    .iter()
+                    .find(|(_, known)| *known == name)
+                    .map(|(usage, _)| key_usage | *usage)
+                    .ok_or_else(|| format!("unknown key usage: {}", name))
+            })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -49,6 +156,9 @@ pub struct Certificate {
     valid_from: u64,
     valid_to: u64,
     public_key: [u8; MAX_PUBKEY_LEN],
+    // Version 2 only; zero in version 1 certificates.
+    serial: u64,
+    key_usage: KeyUsage,
     signature: [u8; MAX_SIG_LEN],
 }
 
@@ -88,6 +198,8 @@ impl Certificate {
             valid_from: 0,
             valid_to: 0,
             public_key: [0; MAX_PUBKEY_LEN],
+            serial: 0,
+            key_usage: KeyUsage::empty(),
             signature: [0; MAX_SIG_LEN],
         }
     }
@@ -108,6 +220,34 @@ impl Certificate {
         Self::parse_null_terminated_string(&self.subject)
     }
 
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn valid_from(&self) -> u64 {
+        self.valid_from
+    }
+
+    pub fn valid_to(&self) -> u64 {
+        self.valid_to
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    pub fn key_usage(&self) -> KeyUsage {
+        self.key_usage
+    }
+
     pub fn is_self_signed(&self) -> bool {
         self.issuer == self.subject
     }
@@ -121,13 +261,26 @@ impl Certificate {
         bytes.extend_from_slice(&self.valid_from.to_le_bytes());
         bytes.extend_from_slice(&self.valid_to.to_le_bytes());
         bytes.extend_from_slice(&self.public_key);
+        if self.version >= VERSION_2 {
+            bytes.extend_from_slice(&self.serial.to_le_bytes());
+            bytes.extend_from_slice(&self.key_usage.bits().to_le_bytes());
+        }
         bytes
     }
 
+    // Size of a version 1 certificate.
     pub fn size_without_padding() -> usize {
         1 + MAX_NAME_LEN * 2 + 8 * 2 + MAX_PUBKEY_LEN + MAX_SIG_LEN
     }
 
+    pub fn size_for_version(version: u8) -> Result<usize, Error> {
+        match version {
+            VERSION_1 => Ok(Self::size_without_padding()),
+            VERSION_2 => Ok(Self::size_without_padding() + 8 + 4),
+            _ => Err(Error::Version),
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         // Full serialized cert
         let mut bytes = self.signable();
@@ -136,14 +289,12 @@ impl Certificate {
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
-        if bytes.len() != Certificate::size_without_padding() {
+        let version = *bytes.first().ok_or(Error::Invalid)?;
+        if bytes.len() != Certificate::size_for_version(version)? {
             return Err(Error::Invalid);
         }
 
-        let mut cursor = 0;
-
-        let version = bytes[cursor];
-        cursor += 1;
+        let mut cursor = 1;
 
         let issuer: [u8; MAX_NAME_LEN] = bytes[cursor..cursor + MAX_NAME_LEN]
             .try_into()
@@ -174,6 +325,26 @@ impl Certificate {
             .map_err(|_| Error::InvalidPublicKey)?;
         cursor += MAX_PUBKEY_LEN;
 
+        let (serial, key_usage) = if version >= VERSION_2 {
+            let serial = u64::from_le_bytes(
+                bytes[cursor..cursor + 8]
+                    .try_into()
+                    .map_err(|_| Error::Invalid)?,
+            );
+            cursor += 8;
+
+            let key_usage = u32::from_le_bytes(
+                bytes[cursor..cursor + 4]
+                    .try_into()
+                    .map_err(|_| Error::Invalid)?,
+            );
+            cursor += 4;
+
+            (serial, KeyUsage::from_bits(key_usage))
+        } else {
+            (0, KeyUsage::empty())
+        };
+
         let signature: [u8; MAX_SIG_LEN] = bytes[cursor..cursor + MAX_SIG_LEN]
             .try_into()
             .map_err(|_| Error::InvalidSignature)?;
@@ -185,6 +356,8 @@ impl Certificate {
             valid_from,
             valid_to,
             public_key,
+            serial,
+            key_usage,
             signature,
         })
     }
@@ -195,6 +368,118 @@ impl Certificate {
     }
 }
 
+// A list of revoked certificate serial numbers, signed by the certificate authority that issued
+// them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevocationList {
+    version: u8,
+    issuer: [u8; MAX_NAME_LEN],
+    issued_at: u64,
+    // Sorted and unique, so that the encoding is canonical.
+    serials: Vec<u64>,
+    signature: [u8; MAX_SIG_LEN],
+}
+
+impl RevocationList {
+    pub fn issuer(&self) -> String {
+        Certificate::parse_null_terminated_string(&self.issuer)
+    }
+
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    pub fn serials(&self) -> &[u64] {
+        &self.serials
+    }
+
+    pub fn is_revoked(&self, serial: u64) -> bool {
+        self.serials.binary_search(&serial).is_ok()
+    }
+
+    pub fn signable(&self) -> Vec<u8> {
+        // Everything but the signature
+        let mut bytes = Vec::new();
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.issuer);
+        bytes.extend_from_slice(&self.issued_at.to_le_bytes());
+        bytes.extend_from_slice(&(self.serials.len() as u32).to_le_bytes());
+        for serial in &self.serials {
+            bytes.extend_from_slice(&serial.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.signable();
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let header_len = 1 + MAX_NAME_LEN + 8 + 4;
+        if bytes.len() < header_len + MAX_SIG_LEN {
+            return Err(Error::InvalidRevocationList);
+        }
+
+        let version = bytes[0];
+        if version != REVOCATION_LIST_VERSION {
+            return Err(Error::Version);
+        }
+
+        let mut cursor = 1;
+
+        let issuer: [u8; MAX_NAME_LEN] = bytes[cursor..cursor + MAX_NAME_LEN]
+            .try_into()
+            .map_err(|_| Error::InvalidIssuer)?;
+        cursor += MAX_NAME_LEN;
+
+        let issued_at = u64::from_le_bytes(
+            bytes[cursor..cursor + 8]
+                .try_into()
+                .map_err(|_| Error::InvalidRevocationList)?,
+        );
+        cursor += 8;
+
+        let count = u32::from_le_bytes(
+            bytes[cursor..cursor + 4]
+                .try_into()
+                .map_err(|_| Error::InvalidRevocationList)?,
+        ) as usize;
+        cursor += 4;
+
+        if bytes.len() != header_len + count * 8 + MAX_SIG_LEN {
+            return Err(Error::InvalidRevocationList);
+        }
+
+        let serials = bytes[cursor..cursor + count * 8]
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes")))
+            .collect::<Vec<u64>>();
+        cursor += count * 8;
+        if serials.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(Error::InvalidRevocationList);
+        }
+
+        let signature: [u8; MAX_SIG_LEN] = bytes[cursor..cursor + MAX_SIG_LEN]
+            .try_into()
+            .map_err(|_| Error::InvalidSignature)?;
+
+        Ok(Self {
+            version,
+            issuer,
+            issued_at,
+            serials,
+            signature,
+        })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let bytes = std::fs::read(path).map_err(|_| Error::InvalidRevocationList)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
 #[cfg(not(test))]
 pub fn current_time() -> u64 {
     SystemTime::now()
@@ -229,7 +514,13 @@ pub fn verify(cert: &Certificate, data: &[u8], sig: &[u8]) -> Result<(), Error>
 }
 
 pub fn validate_cert(issuer: &Certificate, subject: &Certificate) -> Result<(), Error> {
-    if issuer.version != CURRENT_VERSION || subject.version != CURRENT_VERSION {
+    let is_known_version = |version| version == VERSION_1 || version == VERSION_2;
+    if !is_known_version(issuer.version) || !is_known_version(subject.version) {
+        return Err(Error::Version);
+    }
+
+    // A version 2 issuer must not issue version 1 certificates, which would escape its key usage.
+    if issuer.version == VERSION_2 && subject.version != VERSION_2 {
         return Err(Error::Version);
     }
 
@@ -251,17 +542,73 @@ pub fn validate_cert(issuer: &Certificate, subject: &Certificate) -> Result<(),
         return Err(Error::InvalidValidityPeriod);
     }
 
+    // A version 2 issuer must be allowed to sign certificates, and can only delegate the key
+    // usages it holds itself.
+    if issuer.version == VERSION_2
+        && (!issuer.key_usage.contains(KeyUsage::CERT_SIGNING)
+            || !issuer.key_usage.contains(subject.key_usage))
+    {
+        return Err(Error::KeyUsage);
+    }
+
     let subject_bytes = subject.signable();
     verify(issuer, &subject_bytes, &subject.signature)
 }
 
+// Check that the revocation list was signed by the issuer.
+pub fn validate_revocation_list(
+    issuer: &Certificate,
+    revocation_list: &RevocationList,
+) -> Result<(), Error> {
+    if revocation_list.issuer != issuer.subject {
+        return Err(Error::Issuer);
+    }
+
+    if issuer.version == VERSION_2 && !issuer.key_usage.contains(KeyUsage::CERT_SIGNING) {
+        return Err(Error::KeyUsage);
+    }
+
+    verify(
+        issuer,
+        &revocation_list.signable(),
+        &revocation_list.signature,
+    )
+}
+
 pub fn validate_cert_chain(cert_chain: &[Certificate]) -> Result<(), Error> {
+    validate_cert_chain_for(cert_chain, KeyUsage::empty(), &[])
+}
+
+// Validate the chain, and additionally check that the leaf may be used for `key_usage` and that no
+// certificate in the chain has been revoked by its issuer. Revocation lists from issuers outside
+// of the chain are ignored, and those in it must be at most `MAX_REVOCATION_LIST_AGE_SECS` old.
+pub fn validate_cert_chain_for(
+    cert_chain: &[Certificate],
+    key_usage: KeyUsage,
+    revocation_lists: &[RevocationList],
+) -> Result<(), Error> {
     if cert_chain.is_empty() {
         return Err(Error::Invalid);
     }
 
     for i in 0..cert_chain.len() - 1 {
-        validate_cert(&cert_chain[i + 1], &cert_chain[i])?;
+        let (subject, issuer) = (&cert_chain[i], &cert_chain[i + 1]);
+        validate_cert(issuer, subject)?;
+
+        for revocation_list in revocation_lists
+            .iter()
+            .filter(|revocation_list| revocation_list.issuer == issuer.subject)
+        {
+            validate_revocation_list(issuer, revocation_list)?;
+            if current_time().saturating_sub(revocation_list.issued_at)
+                > MAX_REVOCATION_LIST_AGE_SECS
+            {
+                return Err(Error::StaleRevocationList);
+            }
+            if subject.version == VERSION_2 && revocation_list.is_revoked(subject.serial) {
+                return Err(Error::Revoked);
+            }
+        }
     }
 
     let root = &cert_chain[cert_chain.len() - 1];
@@ -269,7 +616,14 @@ pub fn validate_cert_chain(cert_chain: &[Certificate]) -> Result<(), Error> {
         return Err(Error::NotSelfSigned);
     }
 
-    validate_cert(root, root)
+    validate_cert(root, root)?;
+
+    let leaf = &cert_chain[0];
+    if !key_usage.is_empty() && (leaf.version != VERSION_2 || !leaf.key_usage.contains(key_usage)) {
+        return Err(Error::KeyUsage);
+    }
+
+    Ok(())
 }
 
 pub fn verify_and_validate_chain(
@@ -285,7 +639,24 @@ pub fn verify_and_validate_chain(
     verify(&cert_chain[0], data, sig)
 }
 
-// If the issuer is NOT set, the certificate is self-signed.
+// Like `verify_and_validate_chain`, but with the key usage and revocation checks of
+// `validate_cert_chain_for`.
+pub fn verify_and_validate_chain_for(
+    cert_chain: &[Certificate],
+    data: &[u8],
+    sig: &[u8],
+    key_usage: KeyUsage,
+    revocation_lists: &[RevocationList],
+) -> Result<(), Error> {
+    if cert_chain.is_empty() || data.is_empty() {
+        return Err(Error::Invalid);
+    }
+
+    validate_cert_chain_for(cert_chain, key_usage, revocation_lists)?;
+    verify(&cert_chain[0], data, sig)
+}
+
+// Issue a version 1 certificate. If the issuer is NOT set, the certificate is self-signed.
 pub fn issue(
     issuer: Option<&CertificateWithPrivateKey>,
     subject: String,
@@ -293,11 +664,63 @@ pub fn issue(
     valid_to: u64,
 ) -> Result<CertificateWithPrivateKey, Error> {
     let mut cert = Certificate::new();
-    cert.version = CURRENT_VERSION;
+    cert.version = VERSION_1;
+    cert.subject = copy_with_zeros::<MAX_NAME_LEN>(subject.as_bytes());
+    cert.valid_from = valid_from;
+    cert.valid_to = valid_to;
+
+    sign_and_validate(issuer, cert)
+}
+
+// Issue a version 2 certificate with a random serial number. If the issuer is NOT set, the
+// certificate is self-signed, and so must be allowed to sign certificates.
+pub fn issue_v2(
+    issuer: Option<&CertificateWithPrivateKey>,
+    subject: String,
+    valid_from: u64,
+    valid_to: u64,
+    key_usage: KeyUsage,
+) -> Result<CertificateWithPrivateKey, Error> {
+    let mut cert = Certificate::new();
+    cert.version = VERSION_2;
     cert.subject = copy_with_zeros::<MAX_NAME_LEN>(subject.as_bytes());
     cert.valid_from = valid_from;
     cert.valid_to = valid_to;
+    cert.serial = OsRng.next_u64();
+    cert.key_usage = key_usage;
+
+    sign_and_validate(issuer, cert)
+}
+
+// Issue a revocation list for certificates issued by `issuer`.
+pub fn issue_revocation_list(
+    issuer: &CertificateWithPrivateKey,
+    serials: &[u64],
+    issued_at: u64,
+) -> Result<RevocationList, Error> {
+    let mut serials = serials.to_vec();
+    serials.sort_unstable();
+    serials.dedup();
+
+    let mut revocation_list = RevocationList {
+        version: REVOCATION_LIST_VERSION,
+        issuer: issuer.cert.subject,
+        issued_at,
+        serials,
+        signature: [0; MAX_SIG_LEN],
+    };
+    let sig = issuer.sign(&revocation_list.signable())?;
+    revocation_list.signature = copy_with_zeros::<MAX_SIG_LEN>(sig.as_ref());
 
+    validate_revocation_list(&issuer.cert, &revocation_list)?;
+
+    Ok(revocation_list)
+}
+
+fn sign_and_validate(
+    issuer: Option<&CertificateWithPrivateKey>,
+    mut cert: Certificate,
+) -> Result<CertificateWithPrivateKey, Error> {
     // Generate a new keypair for the subject
     let keypair = secp256k1_generate_keypair();
 
@@ -704,7 +1127,7 @@ mod tests {
     #[rustfmt::skip]
     fn test_parse_raw() {
         let bytes = vec![
-            CURRENT_VERSION, // version
+            VERSION_1, // version
             // issuer (16 bytes)
             1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
             // subject (16 bytes)
@@ -723,6 +1146,233 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    fn issue_v2_chain(leaf_key_usage: KeyUsage) -> Vec<CertificateWithPrivateKey> {
+        let valid_from = current_time();
+        let valid_to = valid_from + 60 * 60 * 24 * 365; // 1 year validity
+
+        let root = issue_v2(
+            None,
+            "root".to_string(),
+            valid_from,
+            valid_to,
+            KeyUsage::CERT_SIGNING | KeyUsage::FIRMWARE_SIGNING | KeyUsage::ATTESTATION,
+        )
+        .unwrap();
+        let intermediate = issue_v2(
+            Some(&root),
+            "intermediate".to_string(),
+            valid_from,
+            valid_to,
+            KeyUsage::CERT_SIGNING | KeyUsage::FIRMWARE_SIGNING,
+        )
+        .unwrap();
+        let leaf = issue_v2(
+            Some(&intermediate),
+            "leaf".to_string(),
+            valid_from,
+            valid_to,
+            leaf_key_usage,
+        )
+        .unwrap();
+
+        vec![leaf, intermediate, root]
+    }
+
+    fn certs(chain: &[CertificateWithPrivateKey]) -> Vec<Certificate> {
+        chain.iter().map(|ckp| ckp.cert.clone()).collect()
+    }
+
+    #[test]
+    fn test_v2_roundtrip_serialization() {
+        let chain = issue_v2_chain(KeyUsage::FIRMWARE_SIGNING);
+        let leaf = &chain[0].cert;
+        assert_eq!(leaf.version(), VERSION_2);
+        assert_eq!(leaf.key_usage(), KeyUsage::FIRMWARE_SIGNING);
+
+        let cert_bytes = leaf.to_bytes();
+        assert_eq!(
+            cert_bytes.len(),
+            Certificate::size_for_version(VERSION_2).unwrap()
+        );
+        assert_eq!(&Certificate::from_bytes(&cert_bytes).unwrap(), leaf);
+
+        // Truncated to the size of a version 1 certificate.
+        assert_eq!(
+            Certificate::from_bytes(&cert_bytes[..Certificate::size_without_padding()]),
+            Err(Error::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_v2_key_usage() {
+        let chain = certs(&issue_v2_chain(KeyUsage::FIRMWARE_SIGNING));
+
+        assert!(validate_cert_chain(&chain).is_ok());
+        assert!(validate_cert_chain_for(&chain, KeyUsage::FIRMWARE_SIGNING, &[]).is_ok());
+        assert_eq!(
+            validate_cert_chain_for(&chain, KeyUsage::BATCH_SIGNING, &[]),
+            Err(Error::KeyUsage)
+        );
+
+        // A version 1 leaf carries no key usage.
+        let v1_root = issue(
+            None,
+            "root".to_string(),
+            current_time(),
+            current_time() + 60,
+        )
+        .unwrap();
+        assert_eq!(
+            validate_cert_chain_for(&[v1_root.cert], KeyUsage::FIRMWARE_SIGNING, &[]),
+            Err(Error::KeyUsage)
+        );
+    }
+
+    #[test]
+    fn test_v2_key_usage_delegation() {
+        let chain = issue_v2_chain(KeyUsage::FIRMWARE_SIGNING);
+        let intermediate = &chain[1];
+        let leaf = &chain[0];
+        let valid_from = current_time();
+        let valid_to = valid_from + 60 * 60;
+
+        // The intermediate can't grant a key usage it doesn't hold.
+        assert_eq!(
+            issue_v2(
+                Some(intermediate),
+                "attestation".to_string(),
+                valid_from,
+                valid_to,
+                KeyUsage::ATTESTATION
+            )
+            .err(),
+            Some(Error::KeyUsage)
+        );
+
+        // The leaf isn't allowed to sign certificates at all.
+        assert_eq!(
+            issue_v2(
+                Some(leaf),
+                "subleaf".to_string(),
+                valid_from,
+                valid_to,
+                KeyUsage::FIRMWARE_SIGNING
+            )
+            .err(),
+            Some(Error::KeyUsage)
+        );
+
+        // Nor can a version 2 issuer issue an unrestricted version 1 certificate.
+        assert_eq!(
+            issue(Some(intermediate), "v1".to_string(), valid_from, valid_to).err(),
+            Some(Error::Version)
+        );
+    }
+
+    #[test]
+    fn test_revocation() {
+        let chain = issue_v2_chain(KeyUsage::FIRMWARE_SIGNING);
+        let (leaf, intermediate, root) = (&chain[0], &chain[1], &chain[2]);
+        let cert_chain = certs(&chain);
+
+        let unrelated_revocation_list = issue_revocation_list(
+            intermediate,
+            &[leaf.cert.serial().wrapping_add(1)],
+            current_time(),
+        )
+        .unwrap();
+        assert!(validate_cert_chain_for(
+            &cert_chain,
+            KeyUsage::FIRMWARE_SIGNING,
+            &[unrelated_revocation_list]
+        )
+        .is_ok());
+
+        let leaf_revocation_list =
+            issue_revocation_list(intermediate, &[leaf.cert.serial()], current_time()).unwrap();
+        assert_eq!(
+            validate_cert_chain_for(
+                &cert_chain,
+                KeyUsage::empty(),
+                &[leaf_revocation_list.clone()]
+            ),
+            Err(Error::Revoked)
+        );
+
+        let intermediate_revocation_list =
+            issue_revocation_list(root, &[intermediate.cert.serial()], current_time()).unwrap();
+        assert_eq!(
+            validate_cert_chain_for(
+                &cert_chain,
+                KeyUsage::empty(),
+                &[intermediate_revocation_list]
+            ),
+            Err(Error::Revoked)
+        );
+
+        // An old revocation list, from before the leaf was revoked.
+        let stale_revocation_list = issue_revocation_list(
+            intermediate,
+            &[],
+            current_time() - MAX_REVOCATION_LIST_AGE_SECS - 1,
+        )
+        .unwrap();
+        assert_eq!(
+            validate_cert_chain_for(&cert_chain, KeyUsage::empty(), &[stale_revocation_list]),
+            Err(Error::StaleRevocationList)
+        );
+
+        // A revocation list that claims to be from the intermediate but isn't signed by it.
+        let mut forged_revocation_list = leaf_revocation_list;
+        forged_revocation_list.serials = vec![];
+        assert_eq!(
+            validate_cert_chain_for(&cert_chain, KeyUsage::empty(), &[forged_revocation_list]),
+            Err(Error::Signature)
+        );
+    }
+
+    #[test]
+    fn test_revocation_list_roundtrip_serialization() {
+        let chain = issue_v2_chain(KeyUsage::FIRMWARE_SIGNING);
+        let revocation_list =
+            issue_revocation_list(&chain[1], &[3, 1, 2, 1], current_time()).unwrap();
+        assert_eq!(revocation_list.serials(), &[1, 2, 3]);
+        assert_eq!(revocation_list.issuer(), "intermediate");
+
+        let bytes = revocation_list.to_bytes();
+        assert_eq!(RevocationList::from_bytes(&bytes).unwrap(), revocation_list);
+
+        assert_eq!(
+            RevocationList::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidRevocationList)
+        );
+
+        // Serials must be sorted.
+        let mut unsorted = bytes.clone();
+        let serials_start = 1 + MAX_NAME_LEN + 8 + 4;
+        unsorted[serials_start..serials_start + 8].copy_from_slice(&5u64.to_le_bytes());
+        assert_eq!(
+            RevocationList::from_bytes(&unsorted),
+            Err(Error::InvalidRevocationList)
+        );
+    }
+
+    #[test]
+    fn test_key_usage_parsing() {
+        assert_eq!(
+            "cert-signing, firmware-signing".parse::<KeyUsage>(),
+            Ok(KeyUsage::CERT_SIGNING | KeyUsage::FIRMWARE_SIGNING)
+        );
+        assert_eq!("".parse::<KeyUsage>(), Ok(KeyUsage::empty()));
+        assert!("root".parse::<KeyUsage>().is_err());
+
+        assert_eq!(
+            (KeyUsage::ATTESTATION | KeyUsage::BATCH_SIGNING).to_string(),
+            "attestation,batch-signing"
+        );
+        assert_eq!(KeyUsage::empty().to_string(), "none");
+    }
+
     #[test]
     fn test_too_short_cert() {
         let short_bytes = vec![1, 2, 3];
@@ -1,10 +1,27 @@
 use clap::{ArgGroup, Parser, Subcommand};
-use picocert::{issue, validate_cert_chain, Certificate, CertificateWithPrivateKey, Error};
+use picocert::{
+    issue, issue_revocation_list, issue_v2, validate_cert_chain_for, validate_revocation_list,
+    verify, verify_and_validate_chain_for, Certificate, CertificateWithPrivateKey, Error, KeyUsage,
+    RevocationList,
+};
+use serde::Serialize;
 use std::fs;
+use std::process::ExitCode;
+
+// Exit codes, so that scripts can react to failures:
+//   0 on success,
+//   1 if a file can't be read or written,
+//   2 on invalid arguments (from clap),
+//   10 + the `picocert::Error` discriminant for certificate errors, e.g. 12 for `Error::Expired`.
+const EXIT_IO: u8 = 1;
+const EXIT_PICOCERT_ERROR_BASE: u8 = 10;
 
 #[derive(Parser, Debug)]
 #[command(name = "picocert")]
 struct Cli {
+    /// Print results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
     #[command(subcommand)]
     cmd: Command,
 }
@@ -23,16 +40,290 @@ enum Command {
         subject: String,
         #[arg(long)]
         validity_in_days: u64,
+        /// Issues a version 2 certificate with these key usages, e.g. "cert-signing,attestation".
+        #[arg(long)]
+        key_usage: Option<KeyUsage>,
     },
     ValidateChain {
         #[arg(long, required = true, value_delimiter = ' ', num_args = 1..)]
         cert_chain: Vec<String>, // List of certificate file paths; root comes LAST
+        #[arg(long, value_delimiter = ' ', num_args = 1..)]
+        revocation_list: Vec<String>,
+        /// Key usages the leaf must hold.
+        #[arg(long)]
+        key_usage: Option<KeyUsage>,
+    },
+    Revoke {
+        #[arg(long)]
+        issuer: String,
+        #[arg(long)]
+        issuer_key: String,
+        #[arg(long, required = true, value_delimiter = ' ', num_args = 1..)]
+        serial: Vec<u64>,
+        /// An existing revocation list from the same issuer to extend.
+        #[arg(long)]
+        revocation_list: Option<String>,
+    },
+    Inspect {
+        /// A certificate or revocation list.
+        path: String,
+    },
+    /// Sign a file, writing the raw 64-byte signature.
+    Sign {
+        #[arg(long)]
+        cert: String,
+        #[arg(long)]
+        key: String,
+        #[arg(long)]
+        input: String,
+        /// Defaults to the input path with a ".sig" suffix.
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Verify a detached signature made by the leaf of a certificate chain.
+    VerifySignature {
+        #[arg(long, required = true, value_delimiter = ' ', num_args = 1..)]
+        cert_chain: Vec<String>, // List of certificate file paths; leaf comes FIRST, root LAST
+        #[arg(long)]
+        input: String,
+        #[arg(long)]
+        signature: String,
+        #[arg(long, value_delimiter = ' ', num_args = 1..)]
+        revocation_list: Vec<String>,
+        /// Key usages the leaf must hold.
+        #[arg(long)]
+        key_usage: Option<KeyUsage>,
     },
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Debug)]
+enum CliError {
+    Io(String),
+    Picocert(Error),
+}
 
+impl CliError {
+    fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Io(_) => ExitCode::from(EXIT_IO),
+            CliError::Picocert(err) => ExitCode::from(EXIT_PICOCERT_ERROR_BASE + *err as u8),
+        }
+    }
+}
+
+impl From<Error> for CliError {
+    fn from(err: Error) -> Self {
+        CliError::Picocert(err)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Inspection {
+    Certificate {
+        version: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        serial: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        key_usage: Option<Vec<String>>,
+        issuer: String,
+        subject: String,
+        valid_from: u64,
+        valid_to: u64,
+        public_key: String,
+        signature: String,
+    },
+    RevocationList {
+        issuer: String,
+        issued_at: u64,
+        serials: Vec<u64>,
+    },
+}
+
+// What issue, revoke and sign wrote, for `--json`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Written {
+    Certificate {
+        cert: String,
+        private_key: String,
+        valid_from: u64,
+        valid_to: u64,
+    },
+    RevocationList {
+        revocation_list: String,
+        serials: Vec<u64>,
+    },
+    Signature {
+        input: String,
+        signature: String,
+    },
+}
+
+#[derive(Serialize)]
+struct Verdict {
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, CliError> {
+    fs::read(path).map_err(|err| CliError::Io(format!("Unable to read {}: {}", path, err)))
+}
+
+fn write_file(path: &str, contents: &[u8]) -> Result<(), CliError> {
+    fs::write(path, contents)
+        .map_err(|err| CliError::Io(format!("Unable to write {}: {}", path, err)))
+}
+
+fn read_certificate(path: &str) -> Result<Certificate, CliError> {
+    Certificate::from_bytes(&read_file(path)?).map_err(|err| {
+        eprintln!("Unable to parse certificate {}: {:?}", path, err);
+        err.into()
+    })
+}
+
+fn read_revocation_list(path: &str) -> Result<RevocationList, CliError> {
+    RevocationList::from_bytes(&read_file(path)?).map_err(|err| {
+        eprintln!("Unable to parse revocation list {}: {:?}", path, err);
+        err.into()
+    })
+}
+
+fn read_certificate_with_key(cert: &str, key: &str) -> Result<CertificateWithPrivateKey, CliError> {
+    Ok(CertificateWithPrivateKey {
+        cert: read_certificate(cert)?,
+        private_key: read_file(key)?,
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn inspect_certificate(cert: &Certificate) -> Inspection {
+    let is_v2 = cert.version() >= picocert::VERSION_2;
+    Inspection::Certificate {
+        version: cert.version(),
+        serial: is_v2.then_some(cert.serial()),
+        key_usage: is_v2.then(|| cert.key_usage().names()),
+        issuer: cert.issuer(),
+        subject: cert.subject(),
+        valid_from: cert.valid_from(),
+        valid_to: cert.valid_to(),
+        public_key: to_hex(cert.public_key()),
+        signature: to_hex(cert.signature()),
+    }
+}
+
+fn inspect_revocation_list(revocation_list: &RevocationList) -> Inspection {
+    Inspection::RevocationList {
+        issuer: revocation_list.issuer(),
+        issued_at: revocation_list.issued_at(),
+        serials: revocation_list.serials().to_vec(),
+    }
+}
+
+fn print_inspection(inspection: &Inspection) {
+    match inspection {
+        Inspection::Certificate {
+            version,
+            serial,
+            key_usage,
+            issuer,
+            subject,
+            valid_from,
+            valid_to,
+            public_key,
+            signature,
+        } => {
+            println!("Version: {}", version);
+            if let Some(serial) = serial {
+                println!("Serial: {}", serial);
+            }
+            if let Some(key_usage) = key_usage {
+                println!("Key usage: {}", key_usage.join(", "));
+            }
+            println!("Issuer: {}", issuer);
+            println!("Subject: {}", subject);
+            println!("Valid from: {}", valid_from);
+            println!("Valid to: {}", valid_to);
+            println!("Public key: {}", public_key);
+            println!("Signature: {}", signature);
+        }
+        Inspection::RevocationList {
+            issuer,
+            issued_at,
+            serials,
+        } => {
+            println!("Revocation list");
+            println!("Issuer: {}", issuer);
+            println!("Issued at: {}", issued_at);
+            println!(
+                "Revoked serials: {}",
+                serials
+                    .iter()
+                    .map(|serial| serial.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("Serializing to JSON should never fail")
+    );
+}
+
+fn report_written(json: bool, written: &Written) {
+    if json {
+        print_json(written);
+        return;
+    }
+
+    match written {
+        Written::Certificate {
+            cert,
+            private_key,
+            valid_from,
+            valid_to,
+        } => {
+            println!("Valid from: {}\nValid to: {}", valid_from, valid_to);
+            println!(
+                "New certificate issued.\nCert: {}\nPrivate key: {}",
+                cert, private_key
+            );
+        }
+        Written::RevocationList {
+            revocation_list, ..
+        } => println!("New revocation list issued: {}", revocation_list),
+        Written::Signature { input, signature } => {
+            println!("Signed {}.\nSignature: {}", input, signature)
+        }
+    }
+}
+
+// Print whether validation succeeded, and pass on the error so it sets the exit code.
+fn report_verdict(json: bool, success: &str, result: Result<(), Error>) -> Result<(), CliError> {
+    if json {
+        print_json(&Verdict {
+            valid: result.is_ok(),
+            error: result.err().map(|err| format!("{:?}", err)),
+        });
+    } else {
+        match result {
+            Ok(()) => println!("{}", success),
+            Err(err) => println!("Validation failed: {:?}", err),
+        }
+    }
+
+    result.map_err(CliError::from)
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
     match cli.cmd {
         Command::Issue {
             self_signed,
@@ -40,76 +331,210 @@ fn main() {
             issuer_key,
             subject,
             validity_in_days,
+            key_usage,
         } => {
-            let issuer_option = if let (Some(issuer), Some(issuer_key)) = (issuer, issuer_key) {
-                let issuer_key_bytes = fs::read(issuer_key).expect("Unable to read issuer key");
-                let issuer_cert =
-                    Certificate::from_file(&issuer).expect("Unable to read issuer cert");
-                Some(CertificateWithPrivateKey {
-                    cert: issuer_cert,
-                    private_key: issuer_key_bytes,
-                })
-            } else {
-                None
+            let issuer_option = match (issuer, issuer_key) {
+                (Some(issuer), Some(issuer_key)) => {
+                    Some(read_certificate_with_key(&issuer, &issuer_key)?)
+                }
+                _ => None,
             };
 
             if self_signed && issuer_option.is_some() {
                 eprintln!("Cannot specify both --self_signed and --issuer options.");
-                std::process::exit(1);
+                return Err(CliError::Picocert(Error::InvalidIssuer));
             }
 
             let valid_from = picocert::current_time();
             let valid_to = valid_from + validity_in_days * 24 * 60 * 60;
-            println!("Valid from: {}\nValid to: {}", valid_from, valid_to);
 
-            match issue(
-                issuer_option.as_ref(),
-                subject.clone(),
-                valid_from,
-                valid_to,
-            ) {
-                Ok(issued) => {
-                    let cert = issued.cert;
-                    let private_key = issued.private_key;
-
-                    let cert_path = subject.clone() + ".pcrt";
-                    let private_key_path = subject.clone() + ".priv.der";
-
-                    fs::write(cert_path.clone(), cert.to_bytes())
-                        .expect("Unable to write certificate");
-                    fs::write(private_key_path.clone(), private_key)
-                        .expect("Unable to write private key");
-
-                    println!(
-                        "New certificate issued.\nCert: {}\nPrivate key: {}",
-                        cert_path, private_key_path
-                    );
-                }
-                Err(err) => {
-                    println!("Failed to issue certificate: {:?}", err);
-                }
+            let issued = match key_usage {
+                Some(key_usage) => issue_v2(
+                    issuer_option.as_ref(),
+                    subject.clone(),
+                    valid_from,
+                    valid_to,
+                    key_usage,
+                ),
+                None => issue(
+                    issuer_option.as_ref(),
+                    subject.clone(),
+                    valid_from,
+                    valid_to,
+                ),
             }
+            .map_err(|err| {
+                eprintln!("Failed to issue certificate: {:?}", err);
+                err
+            })?;
+
+            let cert_path = subject.clone() + ".pcrt";
+            let private_key_path = subject.clone() + ".priv.der";
+
+            write_file(&cert_path, &issued.cert.to_bytes())?;
+            write_file(&private_key_path, &issued.private_key)?;
+
+            report_written(
+                cli.json,
+                &Written::Certificate {
+                    cert: cert_path,
+                    private_key: private_key_path,
+                    valid_from,
+                    valid_to,
+                },
+            );
         }
-        Command::ValidateChain { cert_chain } => {
-            // Read and parse the certificate chain
-            let certificates: Result<Vec<Certificate>, Error> = cert_chain
+        Command::ValidateChain {
+            cert_chain,
+            revocation_list,
+            key_usage,
+        } => {
+            // Read and parse the certificate chain and revocation lists
+            let certificates = cert_chain
                 .iter()
-                .map(|path| Certificate::from_file(path))
-                .collect();
+                .map(|path| read_certificate(path))
+                .collect::<Result<Vec<Certificate>, CliError>>()?;
+            let revocation_lists = revocation_list
+                .iter()
+                .map(|path| read_revocation_list(path))
+                .collect::<Result<Vec<RevocationList>, CliError>>()?;
 
-            match certificates {
-                Ok(certificates) => match validate_cert_chain(&certificates) {
-                    Ok(_) => {
-                        println!("Certificate chain is valid.");
-                    }
-                    Err(err) => {
-                        println!("Validation failed: {:?}", err);
+            report_verdict(
+                cli.json,
+                "Certificate chain is valid.",
+                validate_cert_chain_for(
+                    &certificates,
+                    key_usage.unwrap_or_default(),
+                    &revocation_lists,
+                ),
+            )?;
+        }
+        Command::Revoke {
+            issuer,
+            issuer_key,
+            serial,
+            revocation_list,
+        } => {
+            let issuer = read_certificate_with_key(&issuer, &issuer_key)?;
+
+            let mut serials = serial;
+            if let Some(path) = revocation_list {
+                let existing = read_revocation_list(&path)?;
+                validate_revocation_list(&issuer.cert, &existing).map_err(|err| {
+                    eprintln!("Existing revocation list is invalid: {:?}", err);
+                    err
+                })?;
+                serials.extend_from_slice(existing.serials());
+            }
+
+            let revocation_list =
+                issue_revocation_list(&issuer, &serials, picocert::current_time()).map_err(
+                    |err| {
+                        eprintln!("Failed to issue revocation list: {:?}", err);
+                        err
+                    },
+                )?;
+
+            let revocation_list_path = issuer.cert.subject() + ".pcrl";
+            write_file(&revocation_list_path, &revocation_list.to_bytes())?;
+
+            report_written(
+                cli.json,
+                &Written::RevocationList {
+                    revocation_list: revocation_list_path,
+                    serials: revocation_list.serials().to_vec(),
+                },
+            );
+        }
+        Command::Inspect { path } => {
+            let bytes = read_file(&path)?;
+
+            let inspection = match Certificate::from_bytes(&bytes) {
+                Ok(cert) => inspect_certificate(&cert),
+                Err(cert_err) => match RevocationList::from_bytes(&bytes) {
+                    Ok(revocation_list) => inspect_revocation_list(&revocation_list),
+                    Err(_) => {
+                        eprintln!("{} is neither a certificate nor a revocation list.", path);
+                        return Err(cert_err.into());
                     }
                 },
-                Err(err) => {
-                    println!("Failed to read certificates: {:?}", err);
-                }
+            };
+
+            if cli.json {
+                print_json(&inspection);
+            } else {
+                print_inspection(&inspection);
+            }
+        }
+        Command::Sign {
+            cert,
+            key,
+            input,
+            output,
+        } => {
+            let signer = read_certificate_with_key(&cert, &key)?;
+            let data = read_file(&input)?;
+
+            let signature = signer.sign(&data)?;
+            // Catch a private key that doesn't belong to the certificate.
+            verify(&signer.cert, &data, &signature)?;
+
+            let output = output.unwrap_or_else(|| input.clone() + ".sig");
+            write_file(&output, &signature)?;
+
+            report_written(
+                cli.json,
+                &Written::Signature {
+                    input,
+                    signature: output,
+                },
+            );
+        }
+        Command::VerifySignature {
+            cert_chain,
+            input,
+            signature,
+            revocation_list,
+            key_usage,
+        } => {
+            let certificates = cert_chain
+                .iter()
+                .map(|path| read_certificate(path))
+                .collect::<Result<Vec<Certificate>, CliError>>()?;
+            let revocation_lists = revocation_list
+                .iter()
+                .map(|path| read_revocation_list(path))
+                .collect::<Result<Vec<RevocationList>, CliError>>()?;
+            let data = read_file(&input)?;
+            let signature = read_file(&signature)?;
+
+            report_verdict(
+                cli.json,
+                "Signature is valid.",
+                verify_and_validate_chain_for(
+                    &certificates,
+                    &data,
+                    &signature,
+                    key_usage.unwrap_or_default(),
+                    &revocation_lists,
+                ),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            if let CliError::Io(message) = &err {
+                eprintln!("{}", message);
             }
+            err.exit_code()
         }
     }
 }
@@ -4,6 +4,8 @@ import yaml
 import shutil
 import json
 import semver
+import hashlib
+import subprocess
 
 from dataclasses import dataclass
 
@@ -13,6 +15,10 @@ from pathlib import Path
 from bitkey_proto import wallet_pb2 as wallet_pb
 from .firmware_signer import FwupDeltaPatchGenerator
 
+# Detached picocert signature over fwup-manifest.json. Must match MANIFEST_SIGNATURE_FILE in
+# app/rust/wca/src/firmware_update.rs.
+MANIFEST_SIGNATURE_FILE = "fwup-manifest.sig"
+
 
 @dataclass
 class FwupDeltaInfo:
@@ -27,6 +33,23 @@ class FwupDeltaInfo:
         return f"fwup-bundle-delta-{self.from_version}-to-{self.to_version}"
 
 
+@dataclass
+class ManifestSigner:
+    """Signs the manifest with picocert, as the leaf of a firmware signing chain."""
+    cert: Path
+    key: Path
+    signing_chain: list  # Certificate files, leaf first and root last.
+
+    def sign(self, manifest: Path) -> Path:
+        signature = manifest.with_name(MANIFEST_SIGNATURE_FILE)
+        subprocess.run(["picocert-ca-tool", "sign",
+                        "--cert", str(self.cert),
+                        "--key", str(self.key),
+                        "--input", str(manifest),
+                        "--output", str(signature)], check=True)
+        return signature
+
+
 @dataclass
 class Patch:
     path: Path
@@ -95,12 +118,34 @@ class FwupBundler:
         open(out_file, "w+").write(template.render(dict))
         return Path(out_file)
 
-    def _write_json(self, yaml_file: Path):
+    def _write_json(self, yaml_file: Path, signer: ManifestSigner = None):
+        """Write the manifest as JSON, pinning the SHA-256 of every asset file in the bundle.
+
+        With a signer, the signing chain is copied into the bundle and listed in the manifest,
+        and the JSON is signed. Clients verify the signature over these exact bytes, so the
+        JSON must not be rewritten afterwards.
+        """
         with open(yaml_file, 'r') as f:
             contents = yaml.safe_load(f)
-            json_contents = json.dumps(contents)
-        with open(yaml_file.with_suffix(".json"), 'w+') as f:
-            f.write(json_contents)
+
+        bundle_dir = yaml_file.parent
+        for asset in contents["fwup_bundle"]["assets"].values():
+            for file in asset.values():
+                file["sha256"] = hashlib.sha256(
+                    (bundle_dir / file["name"]).read_bytes()).hexdigest()
+
+        if signer:
+            for cert in signer.signing_chain:
+                copy(cert, bundle_dir)
+            contents["fwup_bundle"]["signing_chain"] = [
+                {"name": Path(cert).name} for cert in signer.signing_chain]
+
+        json_file = yaml_file.with_suffix(".json")
+        with open(json_file, 'w+') as f:
+            f.write(json.dumps(contents))
+
+        if signer:
+            signer.sign(json_file)
 
     def bootloader_name(self):
         return f"{self.product}-{self.hardware_revision}-loader-{self.image_type}"
@@ -120,11 +165,14 @@ class FwupBundler:
             if path.is_file():
                 path.unlink()
 
-    def generate_full(self, output_dir, files, version):
+    def generate_full(self, output_dir, files, version, signer: ManifestSigner = None):
         """Generate a FWUP bundle for a full firmware release."""
 
         self._ensure_clean_dir(output_dir)
 
+        for file in files:
+            copy(file, output_dir)
+
         params = {"manifest_version": "0.0.1",
                   "product": self.product,
                   "version": version,
@@ -137,10 +185,7 @@ class FwupBundler:
 
         # Write JSON in addition to YAML since some mobile clients
         # have builtin support for JSON, but not YAML.
-        self._write_json(yaml_file)
-
-        for file in files:
-            copy(file, output_dir)
+        self._write_json(yaml_file, signer)
 
         shutil.make_archive(output_dir, "zip", output_dir)
 
@@ -162,7 +207,8 @@ class FwupBundler:
 
         return Patch(path=Path(patch_file), size=os.stat(patch_file).st_size)
 
-    def generate_delta(self, info: FwupDeltaInfo, output_dir: Path, patch_signing_key_pem: str) -> DeltaBundle:
+    def generate_delta(self, info: FwupDeltaInfo, output_dir: Path, patch_signing_key_pem: str,
+                       signer: ManifestSigner = None) -> DeltaBundle:
         """Generate a FWUP bundle for a delta firmware release."""
         bundle_dir = Path(output_dir).joinpath(info.bundle_name)
 
@@ -177,15 +223,15 @@ class FwupBundler:
                   "application_a_name": self.application_name("a"),
                   "application_b_name": self.application_name("b"),
                   }
-        yaml_file = self._render_template(
-            "fwup-delta-manifest.jinja.yml", bundle_dir, params)
-        self._write_json(yaml_file)
-
         a2b = self._generate_patch_and_copy_sig(
             "a", "b", "a2b_patch_name", info, bundle_dir, params, patch_signing_key_pem)
         b2a = self._generate_patch_and_copy_sig(
             "b", "a", "b2a_patch_name", info, bundle_dir, params, patch_signing_key_pem)
 
+        yaml_file = self._render_template(
+            "fwup-delta-manifest.jinja.yml", bundle_dir, params)
+        self._write_json(yaml_file, signer)
+
         shutil.make_archive(bundle_dir, "zip", bundle_dir)
 
         # Note: don't use with_suffix here, since it'll lop off the stuff
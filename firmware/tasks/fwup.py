@@ -8,7 +8,7 @@ from invoke import task
 from pathlib import Path
 
 from bitkey.fwup import Fwup
-from bitkey.fwup_bundler import FwupBundler, FwupDeltaInfo, load_patch_signing_key
+from bitkey.fwup_bundler import FwupBundler, FwupDeltaInfo, ManifestSigner, load_patch_signing_key
 from bitkey.meson import MesonBuild
 
 from .lib.paths import (BUILD_FW_DIR, BUILD_FWUP_BUNDLE_DIR)
@@ -17,6 +17,14 @@ from bitkey.comms import WalletComms, ShellTransaction
 from .memfault import released_versions, fetch_release
 
 
+def manifest_signer(manifest_cert, manifest_key, signing_chain):
+    """The manifest signer from task arguments; `signing_chain` is comma separated, leaf first."""
+    if not manifest_cert:
+        return None
+    return ManifestSigner(Path(manifest_cert), Path(manifest_key),
+                          [Path(cert) for cert in signing_chain.split(",")])
+
+
 def check_exists(path: str):
     if not path:
         return None
@@ -131,8 +139,12 @@ def bl_upgrade(c, binary=None, signature=None, metadata=None, serial_port=None,
     "version": "",
     "build_dir": "",
     "bundle_dir": "",
+    "manifest_cert": "Picocert certificate to sign the manifest with",
+    "manifest_key": "Private key for manifest_cert",
+    "signing_chain": "Comma separated certificates from manifest_cert (first) to the root (last)",
 })
-def bundle(c, product=None, hardware_revision=None, image_type=None, version=None, build_dir=None, bundle_dir=None):
+def bundle(c, product=None, hardware_revision=None, image_type=None, version=None, build_dir=None, bundle_dir=None,
+           manifest_cert=None, manifest_key=None, signing_chain=None):
     """Generate fwup bundle"""
     if not version:
         version = fw_version.get()
@@ -155,7 +167,8 @@ def bundle(c, product=None, hardware_revision=None, image_type=None, version=Non
         f(bundler.application_name("b") + ".detached_signature"),
     ]
 
-    bundler.generate_full(bundle_dir, files, version)
+    bundler.generate_full(bundle_dir, files, version,
+                          manifest_signer(manifest_cert, manifest_key, signing_chain))
 
 
 @task(help={
@@ -167,9 +180,13 @@ def bundle(c, product=None, hardware_revision=None, image_type=None, version=Non
     "from_dir": "",
     "to_dir": "",
     "bundle_dir": "",
+    "manifest_cert": "Picocert certificate to sign the manifest with",
+    "manifest_key": "Private key for manifest_cert",
+    "signing_chain": "Comma separated certificates from manifest_cert (first) to the root (last)",
 })
 def bundle_delta(c, product=None, hardware_revision=None, image_type=None,
-                 from_version=None, to_version=None, from_dir=None, to_dir=None, bundle_dir=None):
+                 from_version=None, to_version=None, from_dir=None, to_dir=None, bundle_dir=None,
+                 manifest_cert=None, manifest_key=None, signing_chain=None):
     """Generate a FWUP delta bundle"""
 
     # All args are required.
@@ -183,7 +200,8 @@ def bundle_delta(c, product=None, hardware_revision=None, image_type=None,
 
     bundler = FwupBundler(product, hardware_revision, image_type)
     bundler.generate_delta(FwupDeltaInfo(
-        from_version, to_version, from_dir, to_dir), bundle_dir, key_pem)
+        from_version, to_version, from_dir, to_dir), bundle_dir, key_pem,
+        manifest_signer(manifest_cert, manifest_key, signing_chain))
 
 
 @task(help={
@@ -0,0 +1,126 @@
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::sighash::Prevouts;
+use bdk::bitcoin::Address;
+use bdk::miniscript::interpreter::Interpreter;
+use wsm_common::bip322::decode_signature;
+
+use crate::error::BdkUtilError;
+
+/// Verifies a BIP-322 simple or full signature of `message` by the owner of `address`, by running
+/// the signed `to_sign` transaction through the script interpreter.
+pub fn verify_message_signature(
+    address: &Address,
+    message: &str,
+    signature: &str,
+) -> Result<(), BdkUtilError> {
+    let script_pubkey = address.script_pubkey();
+    let (to_sign, spent_output) =
+        decode_signature(signature, &script_pubkey, message.as_bytes())
+            .map_err(|e| BdkUtilError::InvalidMessageSignature(e.to_string()))?;
+    let input = &to_sign.input[0];
+
+    let interpreter = Interpreter::from_txdata(
+        &script_pubkey,
+        &input.script_sig,
+        &input.witness,
+        input.sequence,
+        to_sign.lock_time,
+    )
+    .map_err(|e| BdkUtilError::InvalidMessageSignature(e.to_string()))?;
+
+    let secp = Secp256k1::verification_only();
+    let prevouts = [spent_output];
+    for constraint in interpreter.iter(&secp, &to_sign, 0, &Prevouts::All(&prevouts)) {
+        constraint.map_err(|e| BdkUtilError::InvalidMessageSignature(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::bitcoin::bip32::{ExtendedPrivKey, ExtendedPubKey};
+    use bdk::bitcoin::secp256k1::Secp256k1;
+    use bdk::bitcoin::Network;
+    use bdk::database::MemoryDatabase;
+    use bdk::miniscript::psbt::PsbtExt;
+    use bdk::wallet::AddressIndex;
+    use bdk::{KeychainKind, SignOptions, Wallet};
+    use wsm_common::bip322::{encode_signature, to_sign_psbt, Bip322SignatureFormat};
+
+    use super::verify_message_signature;
+
+    /// A 2-of-3 wallet holding two of the private keys, like the App and Server together.
+    fn two_of_three_wallet() -> Wallet<MemoryDatabase> {
+        let secp = Secp256k1::new();
+        let xprvs: Vec<ExtendedPrivKey> = (0..3)
+            .map(|i| ExtendedPrivKey::new_master(Network::Testnet, &[i; 32]).unwrap())
+            .collect();
+        let xpub = ExtendedPubKey::from_priv(&secp, &xprvs[2]);
+        let descriptor = format!(
+            "wsh(sortedmulti(2,{}/0/*,{}/0/*,{}/0/*))",
+            xprvs[0], xprvs[1], xpub
+        );
+
+        Wallet::new(
+            &descriptor,
+            None,
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap()
+    }
+
+    fn sign(wallet: &Wallet<MemoryDatabase>, message: &str) -> bdk::bitcoin::Transaction {
+        let address = wallet.get_address(AddressIndex::Peek(0)).unwrap().address;
+        let mut psbt = to_sign_psbt(&address.script_pubkey(), message.as_bytes());
+        let descriptor = wallet
+            .get_descriptor_for_keychain(KeychainKind::External)
+            .at_derivation_index(0)
+            .unwrap();
+        psbt.update_input_with_descriptor(0, &descriptor).unwrap();
+
+        let finalized = wallet
+            .sign(
+                &mut psbt,
+                SignOptions {
+                    trust_witness_utxo: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(finalized);
+        psbt.extract_tx()
+    }
+
+    #[test]
+    fn test_verify_message_signature() {
+        let wallet = two_of_three_wallet();
+        let address = wallet.get_address(AddressIndex::Peek(0)).unwrap().address;
+        let other_address = wallet.get_address(AddressIndex::Peek(1)).unwrap().address;
+        let to_sign = sign(&wallet, "Hello World");
+
+        for format in [Bip322SignatureFormat::Simple, Bip322SignatureFormat::Full] {
+            let signature = encode_signature(&to_sign, format).unwrap();
+
+            verify_message_signature(&address, "Hello World", &signature).unwrap();
+            assert!(verify_message_signature(&address, "Goodbye World", &signature).is_err());
+            assert!(verify_message_signature(&other_address, "Hello World", &signature).is_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_message_signature_with_one_signature() {
+        let wallet = two_of_three_wallet();
+        let address = wallet.get_address(AddressIndex::Peek(0)).unwrap().address;
+        let mut to_sign = sign(&wallet, "Hello World");
+
+        // Drop one of the two signatures, leaving the multisig unsatisfied.
+        let mut witness = to_sign.input[0].witness.to_vec();
+        witness.remove(1);
+        to_sign.input[0].witness = witness.into();
+
+        let signature = encode_signature(&to_sign, Bip322SignatureFormat::Simple).unwrap();
+        assert!(verify_message_signature(&address, "Hello World", &signature).is_err());
+    }
+}
@@ -16,6 +16,8 @@ pub enum BdkUtilError {
     ParseXPub(String),
     #[error("Invalid Signature with message: {0} and signature: {1}")]
     SignatureMismatch(String, String),
+    #[error("Invalid BIP-322 message signature: {0}")]
+    InvalidMessageSignature(String),
     #[error("Couldn't sync wallet to the blockchain")]
     WalletSync(bdk::Error),
     #[error("Couldn't cache wallet addresses")]
@@ -57,6 +59,7 @@ impl From<BdkUtilError> for ApiError {
             | BdkUtilError::DecodeHexSignature(_)
             | BdkUtilError::ParseXPub(_)
             | BdkUtilError::SignatureMismatch(_, _)
+            | BdkUtilError::InvalidMessageSignature(_)
             | BdkUtilError::PsbtNotAddressedToAWallet(_)
             | BdkUtilError::PsbtInconsistentDerivationPaths
             | BdkUtilError::MalformedDerivationPath
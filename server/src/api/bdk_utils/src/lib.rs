@@ -32,6 +32,7 @@ use flags::{
     FLAG_MAINNET_ELECTRUM_RPC_URI, FLAG_SIGNET_ELECTRUM_RPC_URI, FLAG_TESTNET_ELECTRUM_RPC_URI,
 };
 
+pub mod bip322;
 pub mod constants;
 pub mod error;
 pub mod flags;
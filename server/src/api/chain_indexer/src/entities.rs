@@ -15,6 +15,9 @@ pub struct Block {
     pub network: Network, // GSI Partition Key
     #[serde(with = "rfc3339")]
     pub created_at: OffsetDateTime,
+    /// Set once a re-org takes the block out of the best chain.
+    #[serde(default)]
+    pub stale: bool,
 }
 
 impl Block {
@@ -27,6 +30,60 @@ impl Block {
             time: bdk_block.header.time,
             created_at: OffsetDateTime::now_utc(),
             network,
+            stale: false,
         })
     }
 }
+
+/// How far through the chain payment notifications have been sent, for one network.
+///
+/// It's stored alongside the blocks, under a key that can't be a block hash. It has no `height`
+/// attribute, so it stays out of the network/height index.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct Cursor {
+    #[serde(rename = "block_hash")]
+    key: String, // Partition Key
+    pub network: Network,
+    pub tip_hash: BlockHash,
+    pub tip_height: u64,
+    #[serde(with = "rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl Cursor {
+    pub fn new(tip: &Block) -> Self {
+        Self {
+            key: Self::key(tip.network),
+            network: tip.network,
+            tip_hash: tip.block_hash,
+            tip_height: tip.height,
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    pub(crate) fn key(network: Network) -> String {
+        format!("cursor#{network}")
+    }
+}
+
+/// The blocks connected since the last poll, and any stored blocks that were re-orged out to make
+/// way for them.
+#[derive(Debug)]
+pub struct ChainUpdate {
+    pub blocks: Vec<BdkBlock>,
+    pub reorg: Option<Reorg>,
+}
+
+#[derive(Debug)]
+pub struct Reorg {
+    /// The last stored block that's still in the best chain.
+    pub fork_point: BlockHash,
+    pub fork_height: u64,
+    /// How many blocks deep the re-org went.
+    pub depth: u64,
+    /// The orphaned blocks' hashes, in height order.
+    pub orphaned: Vec<BlockHash>,
+    /// The orphaned blocks that could still be fetched, in height order. Some sources, like
+    /// Electrum, can only fetch blocks in the best chain.
+    pub orphaned_blocks: Vec<BdkBlock>,
+}
@@ -4,8 +4,10 @@ use tracing::{event, instrument, Level};
 
 use super::ChainIndexerRepository;
 use crate::{
-    entities::Block,
-    repository::{NETWORK_HEIGHT_INDEX, NETWORK_HEIGHT_PARTITION_KEY, PARTITION_KEY},
+    entities::{Block, Cursor},
+    repository::{
+        NETWORK_HEIGHT_INDEX, NETWORK_HEIGHT_PARTITION_KEY, NETWORK_HEIGHT_SORT_KEY, PARTITION_KEY,
+    },
 };
 
 impl ChainIndexerRepository {
@@ -31,6 +33,34 @@ impl ChainIndexerRepository {
             .transpose()
     }
 
+    #[instrument(skip(self))]
+    pub(crate) async fn fetch_cursor(
+        &self,
+        network: Network,
+    ) -> Result<Option<Cursor>, DatabaseError> {
+        let table_name = self.get_table_name().await?;
+        let database_object = self.get_database_object();
+
+        self.connection
+            .client
+            .get_item()
+            .table_name(table_name)
+            .key(
+                PARTITION_KEY,
+                try_to_attribute_val(Cursor::key(network), database_object)?,
+            )
+            .send()
+            .await
+            .map_err(|err| {
+                let service_err = err.into_service_error();
+                event!(Level::ERROR, "Could not fetch cursor: {service_err:?}",);
+                DatabaseError::FetchError(database_object)
+            })?
+            .item
+            .map(|cursor| try_from_item(cursor, database_object))
+            .transpose()
+    }
+
     #[instrument(skip(self))]
     pub(crate) async fn fetch_init_block(
         &self,
@@ -63,4 +93,60 @@ impl ChainIndexerRepository {
             .map(|block| try_from_item(block, database_object))
             .transpose()
     }
+
+    /// Every stored block above `height`, stale or not, in height order.
+    #[instrument(skip(self))]
+    pub(crate) async fn fetch_above_height(
+        &self,
+        network: Network,
+        height: u64,
+    ) -> Result<Vec<Block>, DatabaseError> {
+        let table_name = self.get_table_name().await?;
+        let database_object = self.get_database_object();
+
+        let mut blocks = vec![];
+        let mut exclusive_start_key = None;
+        loop {
+            let item_output = self
+                .connection
+                .client
+                .query()
+                .table_name(table_name.clone())
+                .index_name(NETWORK_HEIGHT_INDEX)
+                .key_condition_expression("#network = :network AND #height > :height")
+                .expression_attribute_names("#network", NETWORK_HEIGHT_PARTITION_KEY)
+                .expression_attribute_names("#height", NETWORK_HEIGHT_SORT_KEY)
+                .expression_attribute_values(
+                    ":network",
+                    try_to_attribute_val(network, database_object)?,
+                )
+                .expression_attribute_values(
+                    ":height",
+                    try_to_attribute_val(height, database_object)?,
+                )
+                .scan_index_forward(true)
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(|err| {
+                    let service_err = err.into_service_error();
+                    event!(
+                        Level::ERROR,
+                        "Could not fetch blocks above height {height}: {service_err:?}",
+                    );
+                    DatabaseError::FetchError(database_object)
+                })?;
+
+            for block in item_output.items.unwrap_or_default() {
+                blocks.push(try_from_item(block, database_object)?);
+            }
+
+            match item_output.last_evaluated_key {
+                Some(last_evaluated_key) => exclusive_start_key = Some(last_evaluated_key),
+                None => break,
+            }
+        }
+
+        Ok(blocks)
+    }
 }
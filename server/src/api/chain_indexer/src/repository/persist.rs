@@ -1,11 +1,15 @@
+use bdk_utils::bdk::bitcoin::BlockHash;
 use database::{
-    aws_sdk_dynamodb::error::ProvideErrorMetadata,
-    ddb::{try_to_item, DatabaseError, Repository},
+    aws_sdk_dynamodb::{error::ProvideErrorMetadata, types::AttributeValue},
+    ddb::{try_to_attribute_val, try_to_item, DatabaseError, Repository},
 };
 use tracing::{event, instrument, Level};
 
 use super::ChainIndexerRepository;
-use crate::entities::Block;
+use crate::{
+    entities::{Block, Cursor},
+    repository::PARTITION_KEY,
+};
 
 impl ChainIndexerRepository {
     #[instrument(skip(self))]
@@ -19,7 +23,9 @@ impl ChainIndexerRepository {
             .put_item()
             .table_name(table_name)
             .set_item(Some(item))
-            .condition_expression("attribute_not_exists(block_hash)")
+            // A stale block can come back if the chain re-orgs back onto it.
+            .condition_expression("attribute_not_exists(block_hash) OR stale = :stale")
+            .expression_attribute_values(":stale", AttributeValue::Bool(true))
             .send()
             .await
             .map_err(|err| {
@@ -34,4 +40,58 @@ impl ChainIndexerRepository {
 
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    pub async fn persist_cursor(&self, cursor: &Cursor) -> Result<(), DatabaseError> {
+        let table_name = self.get_table_name().await?;
+        let database_object = self.get_database_object();
+        let item = try_to_item(cursor, database_object)?;
+
+        self.connection
+            .client
+            .put_item()
+            .table_name(table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|err| {
+                let service_err = err.into_service_error();
+                event!(
+                    Level::ERROR,
+                    "Could not persist cursor: {service_err:?} with message: {:?}",
+                    service_err.message()
+                );
+                DatabaseError::PersistenceError(database_object)
+            })?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn mark_stale(&self, hash: BlockHash) -> Result<(), DatabaseError> {
+        let table_name = self.get_table_name().await?;
+        let database_object = self.get_database_object();
+
+        self.connection
+            .client
+            .update_item()
+            .table_name(table_name)
+            .key(PARTITION_KEY, try_to_attribute_val(hash, database_object)?)
+            .condition_expression("attribute_exists(block_hash)")
+            .update_expression("SET stale = :stale")
+            .expression_attribute_values(":stale", AttributeValue::Bool(true))
+            .send()
+            .await
+            .map_err(|err| {
+                let service_err = err.into_service_error();
+                event!(
+                    Level::ERROR,
+                    "Could not mark block {hash} stale: {service_err:?} with message: {:?}",
+                    service_err.message()
+                );
+                DatabaseError::UpdateError(database_object)
+            })?;
+
+        Ok(())
+    }
 }
@@ -1,27 +1,34 @@
-use super::Service;
-use crate::ChainIndexerError;
-use bdk_utils::bdk::bitcoin::{consensus::encode::deserialize, Block as BdkBlock, BlockHash};
+use std::collections::HashSet;
+
+use super::{Service, RECENT_BLOCKS_CACHE_SIZE};
+use crate::{
+    entities::{Block, ChainUpdate, Cursor, Reorg},
+    ChainIndexerError,
+};
+use bdk_utils::bdk::bitcoin::{Block as BdkBlock, BlockHash};
+use chain_source::ChainSourceError;
 use tracing::{event, Level};
 
 impl Service {
-    pub async fn get_new_blocks(&self) -> Result<Vec<BdkBlock>, ChainIndexerError> {
+    pub async fn get_new_blocks(&self) -> Result<ChainUpdate, ChainIndexerError> {
         event!(
             Level::INFO,
-            "Getting new blocks for network {} using base url {}",
+            "Getting new blocks for network {} from {}",
             self.settings.network,
-            self.settings.base_url,
+            self.source,
         );
         let tip_hash = self.get_tip_hash().await?;
         event!(Level::INFO, "Retrieved tip hash {tip_hash} from network");
         let mut new_blocks = Vec::new();
+        let mut common_ancestor = None;
 
         if let Some(init_block) = self.repo.fetch_init_block(self.settings.network).await? {
             let mut current_hash = tip_hash;
             loop {
-                // We've already seen the block, so we've found a common parent with the tip.
-                if self.repo.fetch(current_hash).await?.is_some() {
-                    // TODO: to detect re-orgs, we should query the DB for any blocks with a
-                    // prev_hash that is equal to the current_hash.
+                // We've already seen the block, so we've found a common parent with the tip. Stale
+                // blocks don't count: the chain has re-orged back onto them, so they're new again.
+                if let Some(block) = self.repo.fetch(current_hash).await?.filter(|b| !b.stale) {
+                    common_ancestor = Some(block);
                     break;
                 }
 
@@ -53,36 +60,129 @@ impl Service {
         }
 
         new_blocks.reverse();
-        Ok(new_blocks)
+
+        // If the tip has only moved backwards, there's no competing chain yet, just a lagging
+        // backend, so nothing has been orphaned.
+        let reorg = match common_ancestor {
+            Some(common_ancestor) if !new_blocks.is_empty() => {
+                self.detect_reorg(common_ancestor).await?
+            }
+            _ => None,
+        };
+
+        Ok(ChainUpdate {
+            blocks: new_blocks,
+            reorg,
+        })
     }
 
-    pub(crate) async fn get_block(
+    /// Find the stored blocks that build on `fork_point` but that the new tip doesn't, by following
+    /// their `prev_hash` chains up from it.
+    async fn detect_reorg(&self, fork_point: Block) -> Result<Option<Reorg>, ChainIndexerError> {
+        let mut branch = HashSet::from([fork_point.block_hash]);
+        let mut orphaned = Vec::new();
+        for block in self
+            .repo
+            .fetch_above_height(self.settings.network, fork_point.height)
+            .await?
+        {
+            if !block.stale && branch.contains(&block.prev_hash) {
+                branch.insert(block.block_hash);
+                orphaned.push(block);
+            }
+        }
+
+        let Some(depth) = orphaned
+            .iter()
+            .map(|block| block.height - fork_point.height)
+            .max()
+        else {
+            return Ok(None);
+        };
+        event!(
+            Level::WARN,
+            "Re-org detected! {depth} block(s) above {} at height {} were orphaned.",
+            fork_point.block_hash,
+            fork_point.height,
+        );
+
+        // We only store headers, so get the orphaned blocks again to find out what was in them.
+        // They're usually recent enough to still be cached, but after a restart the source may
+        // no longer have them. They're orphaned all the same, so carry on without their contents.
+        let mut orphaned_blocks = Vec::with_capacity(orphaned.len());
+        for block in &orphaned {
+            match self.get_block(&block.block_hash).await {
+                Ok(orphaned_block) => orphaned_blocks.push(orphaned_block),
+                Err(ChainIndexerError::ChainSourceError(ChainSourceError::BlockNotFound(_))) => {
+                    event!(
+                        Level::WARN,
+                        "Orphaned block {} is no longer available, so payments it confirmed \
+                        won't be reported as pending again.",
+                        block.block_hash,
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(Some(Reorg {
+            fork_point: fork_point.block_hash,
+            fork_height: fork_point.height,
+            depth,
+            orphaned: orphaned.iter().map(|block| block.block_hash).collect(),
+            orphaned_blocks,
+        }))
+    }
+
+    pub async fn get_cursor(&self) -> Result<Option<Cursor>, ChainIndexerError> {
+        Ok(self.repo.fetch_cursor(self.settings.network).await?)
+    }
+
+    pub async fn get_stored_block(
         &self,
-        block_hash: &BlockHash,
-    ) -> Result<BdkBlock, ChainIndexerError> {
-        Ok(deserialize(
-            &self
-                .http_client
-                .get(&format!(
-                    "{}/block/{block_hash}/raw",
-                    self.settings.base_url
-                ))
-                .send()
-                .await?
-                .bytes()
-                .await
-                .map(|bytes| bytes.to_vec())?,
-        )?)
+        block_hash: BlockHash,
+    ) -> Result<Option<Block>, ChainIndexerError> {
+        Ok(self.repo.fetch(block_hash).await?)
     }
 
-    pub(crate) async fn get_tip_hash(&self) -> Result<BlockHash, ChainIndexerError> {
+    /// The stored blocks in the best chain above `height`, in height order.
+    pub async fn get_best_chain_above(&self, height: u64) -> Result<Vec<Block>, ChainIndexerError> {
         Ok(self
-            .http_client
-            .get(&format!("{}/blocks/tip/hash", self.settings.base_url))
-            .send()
+            .repo
+            .fetch_above_height(self.settings.network, height)
             .await?
-            .text()
-            .await?
-            .parse()?)
+            .into_iter()
+            .filter(|block| !block.stale)
+            .collect())
+    }
+
+    pub async fn get_block(&self, block_hash: &BlockHash) -> Result<BdkBlock, ChainIndexerError> {
+        let recent_block = self
+            .recent_blocks
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|block| block.block_hash() == *block_hash)
+            .cloned();
+        if let Some(block) = recent_block {
+            return Ok(block);
+        }
+
+        let block = self.fetch_block(block_hash).await?;
+        let mut recent_blocks = self.recent_blocks.lock().unwrap();
+        if recent_blocks.len() == RECENT_BLOCKS_CACHE_SIZE {
+            recent_blocks.pop_front();
+        }
+        recent_blocks.push_back(block.clone());
+
+        Ok(block)
+    }
+
+    async fn fetch_block(&self, block_hash: &BlockHash) -> Result<BdkBlock, ChainIndexerError> {
+        Ok(self.source.block(block_hash).await?)
+    }
+
+    pub(crate) async fn get_tip_hash(&self) -> Result<BlockHash, ChainIndexerError> {
+        Ok(self.source.tip_hash().await?)
     }
 }
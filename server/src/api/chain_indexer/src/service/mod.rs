@@ -1,57 +1,42 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
 use crate::repository::ChainIndexerRepository;
-use bdk_utils::bdk::bitcoin::Network;
-use config::{Config, ConfigError, Environment};
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
-use serde::Deserialize;
+use bdk_utils::bdk::bitcoin::{Block as BdkBlock, Network};
+use chain_source::{ChainSource, Esplora, IndexerSettings};
 
 mod fetch_blockchain_data;
 mod update_blockchain_data;
 
-const MEMPOOL_SPACE_SIGNET_URL: &str = "https://bitkey.mempool.space/signet/api";
+// Enough to notify at every confirmation depth without fetching a block twice.
+const RECENT_BLOCKS_CACHE_SIZE: usize = 12;
 
 #[derive(Clone)]
 pub struct Service {
     repo: ChainIndexerRepository,
-    http_client: ClientWithMiddleware,
-    settings: Settings,
-}
-
-#[derive(Clone, Deserialize)]
-pub struct Settings {
-    base_url: String,
-    network: Network,
-}
-
-impl Settings {
-    pub fn new() -> Result<Self, ConfigError> {
-        Config::builder()
-            .set_default("base_url", MEMPOOL_SPACE_SIGNET_URL)?
-            .set_default("network", Network::Signet.to_string())?
-            .add_source(Environment::with_prefix("CHAIN_INDEXER"))
-            .build()?
-            .try_deserialize()
-    }
+    source: Arc<dyn ChainSource>,
+    settings: IndexerSettings,
+    recent_blocks: Arc<Mutex<VecDeque<BdkBlock>>>,
 }
 
 impl Service {
     pub fn new(repo: ChainIndexerRepository) -> Self {
-        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(5);
-        let http_client = ClientBuilder::new(Client::new())
-            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-            .build();
-        let settings = Settings::new().unwrap();
+        let settings = IndexerSettings::from_env("CHAIN_INDEXER").unwrap();
 
         Self {
             repo,
-            http_client,
+            source: settings.source().build(settings.network),
             settings,
+            recent_blocks: Arc::new(Mutex::new(VecDeque::with_capacity(
+                RECENT_BLOCKS_CACHE_SIZE,
+            ))),
         }
     }
 
     pub fn set_mock_server(mut self, base_url: String) -> Self {
-        self.settings.base_url = base_url;
+        self.source = Arc::new(Esplora::new(base_url));
         self
     }
 
@@ -1,5 +1,8 @@
 use super::Service;
-use crate::{entities::Block, ChainIndexerError};
+use crate::{
+    entities::{Block, Cursor, Reorg},
+    ChainIndexerError,
+};
 use bdk_utils::bdk::bitcoin::Block as BdkBlock;
 
 impl Service {
@@ -10,4 +13,19 @@ impl Service {
 
         Ok(())
     }
+
+    pub async fn set_cursor(&self, tip: &Block) -> Result<(), ChainIndexerError> {
+        self.repo.persist_cursor(&Cursor::new(tip)).await?;
+
+        Ok(())
+    }
+
+    /// Mark a re-org's orphaned blocks as stale, so they're no longer treated as part of the chain.
+    pub async fn remove_orphaned_blocks(&self, reorg: &Reorg) -> Result<(), ChainIndexerError> {
+        for block_hash in &reorg.orphaned {
+            self.repo.mark_stale(*block_hash).await?;
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,215 @@
+//! A Bitcoin Core node, over JSON-RPC.
+//!
+//! Works with a pruned node and without `-txindex`: transactions are only ever looked up while
+//! they're still in the mempool.
+
+use std::{collections::HashSet, fmt};
+
+use async_trait::async_trait;
+use bdk_utils::bdk::bitcoin::{
+    consensus::{encode::deserialize, Decodable},
+    hashes::hex::FromHex,
+    Address, Block, BlockHash, Network, Transaction, Txid,
+};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{ChainSource, ChainSourceError, MempoolTransaction, TransactionOutput};
+
+// RPC_INVALID_ADDRESS_OR_KEY, which getmempoolentry returns for a transaction not in the mempool.
+const RPC_NOT_FOUND: i64 = -5;
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct MempoolEntry {
+    fees: MempoolEntryFees,
+}
+
+#[derive(Deserialize)]
+struct MempoolEntryFees {
+    base: f64,
+}
+
+/// Talks to the node directly, without retries: Core answers RPC errors with HTTP 500, which
+/// isn't worth retrying.
+#[derive(Clone)]
+pub struct BitcoinCore {
+    rpc_url: String,
+    rpc_user: Option<String>,
+    rpc_password: Option<String>,
+    network: Network,
+    http_client: Client,
+}
+
+impl BitcoinCore {
+    pub fn new(
+        rpc_url: String,
+        rpc_user: Option<String>,
+        rpc_password: Option<String>,
+        network: Network,
+    ) -> Self {
+        Self {
+            rpc_url,
+            rpc_user,
+            rpc_password,
+            network,
+            http_client: Client::new(),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, ChainSourceError> {
+        let mut request = self.http_client.post(&self.rpc_url).json(&json!({
+            "jsonrpc": "1.0",
+            "id": "chain_source",
+            "method": method,
+            "params": params,
+        }));
+        if let Some(rpc_user) = &self.rpc_user {
+            request = request.basic_auth(rpc_user, self.rpc_password.as_ref());
+        }
+
+        let response: RpcResponse<T> = request.send().await?.json().await?;
+        match (response.result, response.error) {
+            (_, Some(RpcError { code, message })) => {
+                Err(ChainSourceError::RpcError { code, message })
+            }
+            (Some(result), None) => Ok(result),
+            // A null result, which only deserializes into an Option.
+            (None, None) => Ok(serde_json::from_value(Value::Null)?),
+        }
+    }
+
+    async fn call_hex<T: Decodable>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, ChainSourceError> {
+        let hex: String = self.call(method, params).await?;
+        Ok(deserialize(&Vec::<u8>::from_hex(&hex)?)?)
+    }
+}
+
+impl fmt::Display for BitcoinCore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bitcoin Core at {}", self.rpc_url)
+    }
+}
+
+#[async_trait]
+impl ChainSource for BitcoinCore {
+    async fn tip_hash(&self) -> Result<BlockHash, ChainSourceError> {
+        self.call("getbestblockhash", json!([])).await
+    }
+
+    async fn block(&self, block_hash: &BlockHash) -> Result<Block, ChainSourceError> {
+        self.call_hex("getblock", json!([block_hash, 0])).await
+    }
+
+    async fn mempool_txids(&self) -> Result<HashSet<Txid>, ChainSourceError> {
+        self.call("getrawmempool", json!([])).await
+    }
+
+    async fn mempool_transaction(
+        &self,
+        txid: &Txid,
+    ) -> Result<Option<MempoolTransaction>, ChainSourceError> {
+        let entry: MempoolEntry = match self.call("getmempoolentry", json!([txid])).await {
+            Ok(entry) => entry,
+            Err(ChainSourceError::RpcError { code, .. }) if code == RPC_NOT_FOUND => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e),
+        };
+        let transaction: Transaction = self.call_hex("getrawtransaction", json!([txid])).await?;
+
+        Ok(Some(MempoolTransaction {
+            txid: *txid,
+            outputs: transaction
+                .output
+                .iter()
+                .map(|output| TransactionOutput {
+                    address: Address::from_script(&output.script_pubkey, self.network)
+                        .ok()
+                        .map(|address| address.to_string()),
+                    value: output.value,
+                })
+                .collect(),
+            weight: transaction.weight(),
+            fee: (entry.fees.base * SATS_PER_BTC).round() as u64,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk_utils::bdk::bitcoin::{BlockHash, Network, Txid};
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::json;
+
+    use super::BitcoinCore;
+    use crate::ChainSource;
+
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    #[tokio::test]
+    async fn test_tip_hash() {
+        let server = MockServer::start_async().await;
+        let tip_hash = "000000000000000000026fc3f2f5ff5fbc0ad7c41a3b3a8c7e4c8e2e1c3b8f7a";
+        server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/")
+                    .body_contains("getbestblockhash");
+                then.status(200)
+                    .json_body(json!({"result": tip_hash, "error": null, "id": "chain_source"}));
+            })
+            .await;
+
+        let source = BitcoinCore::new(server.base_url(), None, None, Network::Regtest);
+        assert_eq!(
+            source.tip_hash().await.unwrap(),
+            tip_hash.parse::<BlockHash>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mempool_transaction_not_in_mempool() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/").body_contains("getmempoolentry");
+                then.status(500).json_body(json!({
+                    "result": null,
+                    "error": {"code": -5, "message": "Transaction not in mempool"},
+                    "id": "chain_source",
+                }));
+            })
+            .await;
+
+        let source = BitcoinCore::new(server.base_url(), None, None, Network::Regtest);
+        assert_eq!(
+            source
+                .mempool_transaction(&TXID.parse::<Txid>().unwrap())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! An Electrum server.
+//!
+//! The protocol has no way to list the mempool, so this can back the chain indexer but not the
+//! mempool indexer. It can't look blocks up by hash either, so only blocks in the best chain within
+//! [`HEADER_SEARCH_DEPTH`] of the tip can be fetched, and the server has to support
+//! `blockchain.transaction.id_from_pos` (ElectrumX, Fulcrum and Blockstream's electrs all do).
+
+use std::{collections::HashSet, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use bdk_utils::bdk::{
+    bitcoin::{Address, Block, BlockHash, Network, Txid},
+    electrum_client::{Batch, Client, ElectrumApi, Error as ElectrumClientError, Param},
+};
+use once_cell::sync::OnceCell;
+use tokio::task::spawn_blocking;
+
+use crate::{ChainSource, ChainSourceError, MempoolTransaction, TransactionOutput};
+
+/// How far back from the tip to look for a block, which covers a day's worth.
+pub const HEADER_SEARCH_DEPTH: usize = 144;
+const ID_FROM_POS_BATCH_SIZE: usize = 500;
+
+/// Connects on first use, so an unreachable server doesn't stop the indexer from starting.
+#[derive(Clone)]
+pub struct Electrum {
+    url: String,
+    network: Network,
+    client: Arc<OnceCell<Client>>,
+}
+
+impl Electrum {
+    pub fn new(url: String, network: Network) -> Self {
+        Self {
+            url,
+            network,
+            client: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Runs a blocking Electrum request off the async runtime.
+    async fn with_client<T, F>(&self, f: F) -> Result<T, ChainSourceError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client, Network) -> Result<T, ChainSourceError> + Send + 'static,
+    {
+        let (url, network, client) = (self.url.clone(), self.network, self.client.clone());
+        spawn_blocking(move || f(client.get_or_try_init(|| Client::new(&url))?, network)).await?
+    }
+}
+
+impl fmt::Display for Electrum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Electrum at {}", self.url)
+    }
+}
+
+#[async_trait]
+impl ChainSource for Electrum {
+    async fn tip_hash(&self) -> Result<BlockHash, ChainSourceError> {
+        self.with_client(|client, _| Ok(client.block_headers_subscribe()?.header.block_hash()))
+            .await
+    }
+
+    async fn block(&self, block_hash: &BlockHash) -> Result<Block, ChainSourceError> {
+        let block_hash = *block_hash;
+        self.with_client(move |client, _| {
+            let tip_height = client.block_headers_subscribe()?.height;
+            let start_height = tip_height.saturating_sub(HEADER_SEARCH_DEPTH - 1);
+            let (height, header) = client
+                .block_headers(start_height, tip_height + 1 - start_height)?
+                .headers
+                .into_iter()
+                .enumerate()
+                .find(|(_, header)| header.block_hash() == block_hash)
+                .map(|(offset, header)| (start_height + offset, header))
+                .ok_or(ChainSourceError::BlockNotFound(block_hash))?;
+
+            let txids = block_txids(client, height)?;
+            let block = Block {
+                header,
+                txdata: client.batch_transaction_get(&txids)?,
+            };
+            // Catches a server that stopped listing the block's transactions early.
+            if !block.check_merkle_root() {
+                return Err(ChainSourceError::IncompleteBlock(block_hash));
+            }
+
+            Ok(block)
+        })
+        .await
+    }
+
+    async fn mempool_txids(&self) -> Result<HashSet<Txid>, ChainSourceError> {
+        Err(ChainSourceError::Unsupported("Listing the mempool"))
+    }
+
+    async fn mempool_transaction(
+        &self,
+        txid: &Txid,
+    ) -> Result<Option<MempoolTransaction>, ChainSourceError> {
+        let txid = *txid;
+        self.with_client(move |client, network| {
+            let transaction = client.transaction_get(&txid)?;
+
+            // Electrum only reports confirmations through a script's history.
+            if let Some(output) = transaction.output.first() {
+                let confirmed = client
+                    .script_get_history(&output.script_pubkey)?
+                    .iter()
+                    .any(|entry| entry.tx_hash == txid && entry.height > 0);
+                if confirmed {
+                    return Ok(None);
+                }
+            }
+
+            let previous_transactions = client.batch_transaction_get(
+                transaction
+                    .input
+                    .iter()
+                    .map(|input| &input.previous_output.txid),
+            )?;
+            let input_value: u64 = transaction
+                .input
+                .iter()
+                .filter_map(|input| {
+                    previous_transactions
+                        .iter()
+                        .find(|previous| previous.txid() == input.previous_output.txid)
+                        .and_then(|previous| {
+                            previous.output.get(input.previous_output.vout as usize)
+                        })
+                        .map(|output| output.value)
+                })
+                .sum();
+            let output_value: u64 = transaction.output.iter().map(|output| output.value).sum();
+
+            Ok(Some(MempoolTransaction {
+                txid,
+                outputs: transaction
+                    .output
+                    .iter()
+                    .map(|output| TransactionOutput {
+                        address: Address::from_script(&output.script_pubkey, network)
+                            .ok()
+                            .map(|address| address.to_string()),
+                        value: output.value,
+                    })
+                    .collect(),
+                weight: transaction.weight(),
+                fee: input_value.saturating_sub(output_value),
+            }))
+        })
+        .await
+    }
+}
+
+/// List a block's transactions by position, a batch at a time. The server errors on the first
+/// position past the end of the block, which fails the whole batch, so the last batch is finished
+/// one position at a time.
+fn block_txids(client: &Client, height: usize) -> Result<Vec<Txid>, ChainSourceError> {
+    let id_from_pos = |position: usize| {
+        (
+            "blockchain.transaction.id_from_pos".to_string(),
+            vec![Param::Usize(height), Param::Usize(position)],
+        )
+    };
+
+    let mut txids = Vec::new();
+    loop {
+        let mut batch = Batch::default();
+        for position in txids.len()..txids.len() + ID_FROM_POS_BATCH_SIZE {
+            let (method, params) = id_from_pos(position);
+            batch.raw(method, params);
+        }
+
+        match client.batch_call(&batch) {
+            Ok(values) => {
+                for value in values {
+                    txids.push(serde_json::from_value(value)?);
+                }
+            }
+            Err(ElectrumClientError::Protocol(_)) => loop {
+                let (method, params) = id_from_pos(txids.len());
+                match client.raw_call(&method, params) {
+                    Ok(value) => txids.push(serde_json::from_value(value)?),
+                    Err(ElectrumClientError::Protocol(_)) => return Ok(txids),
+                    Err(e) => return Err(e.into()),
+                }
+            },
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use bdk_utils::bdk::bitcoin::{
+        block::Header, blockdata::constants::genesis_block, consensus::encode::serialize_hex,
+        Network,
+    };
+    use serde_json::{json, Value};
+
+    use super::{Electrum, HEADER_SEARCH_DEPTH};
+    use crate::{ChainSource, ChainSourceError};
+
+    // A chain of headers that only need to hash differently.
+    fn headers(count: usize) -> Vec<Header> {
+        let genesis = genesis_block(Network::Regtest).header;
+        (0..count as u32)
+            .map(|nonce| Header { nonce, ..genesis })
+            .collect()
+    }
+
+    // Serves the best chain's headers from a local Electrum server, for a single connection.
+    fn serve(chain: Vec<Header>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let result = match request["method"].as_str().unwrap() {
+                    "blockchain.headers.subscribe" => json!({
+                        "height": chain.len() - 1,
+                        "hex": serialize_hex(chain.last().unwrap()),
+                    }),
+                    "blockchain.block.headers" => {
+                        let start = request["params"][0].as_u64().unwrap() as usize;
+                        let count = request["params"][1].as_u64().unwrap() as usize;
+                        let headers = &chain[start..(start + count).min(chain.len())];
+                        let hex: String = headers.iter().map(serialize_hex).collect();
+                        json!({"count": headers.len(), "hex": hex, "max": 2016})
+                    }
+                    "server.version" => json!(["mock", "1.4"]),
+                    method => panic!("unexpected method {method}"),
+                };
+                let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                writeln!(writer, "{response}").unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_block_not_in_best_chain() {
+        let chain = headers(3);
+        let orphaned = Header {
+            nonce: u32::MAX,
+            ..chain[2]
+        };
+
+        let source = Electrum::new(serve(chain), Network::Regtest);
+        assert!(matches!(
+            source.block(&orphaned.block_hash()).await,
+            Err(ChainSourceError::BlockNotFound(hash)) if hash == orphaned.block_hash()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_block_too_deep() {
+        let chain = headers(HEADER_SEARCH_DEPTH + 1);
+        let too_deep = chain[0].block_hash();
+
+        let source = Electrum::new(serve(chain), Network::Regtest);
+        assert!(matches!(
+            source.block(&too_deep).await,
+            Err(ChainSourceError::BlockNotFound(hash)) if hash == too_deep
+        ));
+    }
+}
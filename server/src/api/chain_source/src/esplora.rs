@@ -0,0 +1,171 @@
+//! An Esplora-style HTTP API, such as mempool.space or Blockstream's.
+
+use std::{collections::HashSet, fmt};
+
+use async_trait::async_trait;
+use bdk_utils::bdk::bitcoin::{consensus::encode::deserialize, Block, BlockHash, Txid, Weight};
+use reqwest::{Client, StatusCode};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use serde::Deserialize;
+
+use crate::{ChainSource, ChainSourceError, MempoolTransaction, TransactionOutput};
+
+#[derive(Deserialize)]
+struct TransactionResponse {
+    txid: Txid,
+    vout: Vec<TransactionVout>,
+    weight: Weight,
+    fee: u64,
+    status: TransactionStatus,
+}
+
+#[derive(Deserialize)]
+struct TransactionVout {
+    #[serde(default)]
+    scriptpubkey_address: Option<String>,
+    value: u64,
+}
+
+#[derive(Deserialize)]
+struct TransactionStatus {
+    confirmed: bool,
+}
+
+#[derive(Clone)]
+pub struct Esplora {
+    base_url: String,
+    http_client: ClientWithMiddleware,
+}
+
+impl Esplora {
+    pub fn new(base_url: String) -> Self {
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(5);
+        let http_client = ClientBuilder::new(Client::new())
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build();
+
+        Self {
+            base_url,
+            http_client,
+        }
+    }
+}
+
+impl fmt::Display for Esplora {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Esplora at {}", self.base_url)
+    }
+}
+
+#[async_trait]
+impl ChainSource for Esplora {
+    async fn tip_hash(&self) -> Result<BlockHash, ChainSourceError> {
+        Ok(self
+            .http_client
+            .get(&format!("{}/blocks/tip/hash", self.base_url))
+            .send()
+            .await?
+            .text()
+            .await?
+            .parse()?)
+    }
+
+    async fn block(&self, block_hash: &BlockHash) -> Result<Block, ChainSourceError> {
+        let response = self
+            .http_client
+            .get(&format!("{}/block/{block_hash}/raw", self.base_url))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(ChainSourceError::BlockNotFound(*block_hash));
+        }
+
+        Ok(deserialize(&response.error_for_status()?.bytes().await?)?)
+    }
+
+    async fn mempool_txids(&self) -> Result<HashSet<Txid>, ChainSourceError> {
+        Ok(self
+            .http_client
+            .get(&format!("{}/mempool/txids", self.base_url))
+            .send()
+            .await?
+            .json()
+            .await?)
+    }
+
+    async fn mempool_transaction(
+        &self,
+        txid: &Txid,
+    ) -> Result<Option<MempoolTransaction>, ChainSourceError> {
+        let response: TransactionResponse = self
+            .http_client
+            .get(&format!("{}/tx/{txid}", self.base_url))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if response.status.confirmed {
+            return Ok(None);
+        }
+
+        Ok(Some(MempoolTransaction {
+            txid: response.txid,
+            outputs: response
+                .vout
+                .into_iter()
+                .map(|vout| TransactionOutput {
+                    address: vout.scriptpubkey_address,
+                    value: vout.value,
+                })
+                .collect(),
+            weight: response.weight,
+            fee: response.fee,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk_utils::bdk::bitcoin::{
+        blockdata::constants::genesis_block, consensus::encode::serialize, Network,
+    };
+    use httpmock::{Method::GET, MockServer};
+
+    use super::Esplora;
+    use crate::{ChainSource, ChainSourceError};
+
+    #[tokio::test]
+    async fn test_block() {
+        let server = MockServer::start_async().await;
+        let block = genesis_block(Network::Regtest);
+        server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path(format!("/block/{}/raw", block.block_hash()));
+                then.status(200).body(serialize(&block));
+            })
+            .await;
+
+        let source = Esplora::new(server.base_url());
+        assert_eq!(source.block(&block.block_hash()).await.unwrap(), block);
+    }
+
+    #[tokio::test]
+    async fn test_block_not_found() {
+        let server = MockServer::start_async().await;
+        let block_hash = genesis_block(Network::Regtest).block_hash();
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path(format!("/block/{block_hash}/raw"));
+                then.status(404).body("Block not found");
+            })
+            .await;
+
+        let source = Esplora::new(server.base_url());
+        assert!(matches!(
+            source.block(&block_hash).await,
+            Err(ChainSourceError::BlockNotFound(hash)) if hash == block_hash
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+//! Where the indexers get their chain and mempool data from.
+//!
+//! Each network's backend is picked in the indexer's settings: an Esplora-style HTTP API (such as
+//! mempool.space), a Bitcoin Core node over JSON-RPC, or an Electrum server.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use bdk_utils::bdk::{
+    bitcoin::{Block, BlockHash, Network, Txid, Weight},
+    electrum_client,
+};
+use config::{Config, ConfigError, Environment};
+use serde::Deserialize;
+use thiserror::Error;
+
+pub mod bitcoin_core;
+pub mod electrum;
+pub mod esplora;
+
+pub use bitcoin_core::BitcoinCore;
+pub use electrum::Electrum;
+pub use esplora::Esplora;
+
+const MEMPOOL_SPACE_SIGNET_URL: &str = "https://bitkey.mempool.space/signet/api";
+
+#[derive(Error, Debug)]
+pub enum ChainSourceError {
+    #[error("Unable to request HTTP data due to error {0}")]
+    HttpClientError(#[from] reqwest::Error),
+    #[error("Unable to request HTTP data due to error {0}")]
+    HttpMiddlewareError(#[from] reqwest_middleware::Error),
+    #[error("Unable to deserialize data due to error {0}")]
+    DeserializationError(#[from] bdk_utils::bdk::bitcoin::consensus::encode::Error),
+    #[error("Unable to parse hash as hex {0}")]
+    HashParseError(#[from] bdk_utils::bdk::bitcoin::hashes::hex::Error),
+    #[error("Unable to parse JSON due to error {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Bitcoin Core RPC error {code}: {message}")]
+    RpcError { code: i64, message: String },
+    #[error("Electrum error {0}")]
+    ElectrumError(#[from] electrum_client::Error),
+    #[error("Background task failed {0}")]
+    JoinError(#[from] tokio::task::JoinError),
+    #[error("Block {0} not found")]
+    BlockNotFound(BlockHash),
+    #[error("Block {0} doesn't match its merkle root")]
+    IncompleteBlock(BlockHash),
+    #[error("{0} isn't supported by this chain source")]
+    Unsupported(&'static str),
+}
+
+/// A transaction that's waiting in the mempool, with just what the mempool indexer records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MempoolTransaction {
+    pub txid: Txid,
+    pub outputs: Vec<TransactionOutput>,
+    pub weight: Weight,
+    pub fee: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionOutput {
+    pub address: Option<String>,
+    pub value: u64,
+}
+
+#[async_trait]
+pub trait ChainSource: fmt::Display + Send + Sync {
+    async fn tip_hash(&self) -> Result<BlockHash, ChainSourceError>;
+
+    async fn block(&self, block_hash: &BlockHash) -> Result<Block, ChainSourceError>;
+
+    async fn mempool_txids(&self) -> Result<HashSet<Txid>, ChainSourceError>;
+
+    /// Returns `None` if the transaction has already left the mempool.
+    async fn mempool_transaction(
+        &self,
+        txid: &Txid,
+    ) -> Result<Option<MempoolTransaction>, ChainSourceError>;
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChainSourceSettings {
+    Esplora {
+        base_url: String,
+    },
+    BitcoinCore {
+        rpc_url: String,
+        #[serde(default)]
+        rpc_user: Option<String>,
+        #[serde(default)]
+        rpc_password: Option<String>,
+    },
+    Electrum {
+        url: String,
+    },
+}
+
+impl ChainSourceSettings {
+    pub fn build(&self, network: Network) -> Arc<dyn ChainSource> {
+        match self {
+            ChainSourceSettings::Esplora { base_url } => Arc::new(Esplora::new(base_url.clone())),
+            ChainSourceSettings::BitcoinCore {
+                rpc_url,
+                rpc_user,
+                rpc_password,
+            } => Arc::new(BitcoinCore::new(
+                rpc_url.clone(),
+                rpc_user.clone(),
+                rpc_password.clone(),
+                network,
+            )),
+            ChainSourceSettings::Electrum { url } => Arc::new(Electrum::new(url.clone(), network)),
+        }
+    }
+}
+
+/// An indexer's network and chain sources, read from environment variables under its prefix.
+#[derive(Clone, Debug, Deserialize)]
+pub struct IndexerSettings {
+    base_url: String,
+    pub network: Network,
+    /// Each network's chain source, e.g. `CHAIN_INDEXER_SOURCES__REGTEST__TYPE=bitcoin_core`.
+    /// Networks without one use the Esplora API at `base_url`.
+    #[serde(default)]
+    sources: HashMap<Network, ChainSourceSettings>,
+}
+
+impl IndexerSettings {
+    pub fn from_env(prefix: &str) -> Result<Self, ConfigError> {
+        Self::from_environment(environment(prefix))
+    }
+
+    fn from_environment(environment: Environment) -> Result<Self, ConfigError> {
+        Config::builder()
+            .set_default("base_url", MEMPOOL_SPACE_SIGNET_URL)?
+            .set_default("network", Network::Signet.to_string())?
+            .add_source(environment)
+            .build()?
+            .try_deserialize()
+    }
+
+    /// The chain source for the configured network.
+    pub fn source(&self) -> ChainSourceSettings {
+        self.sources
+            .get(&self.network)
+            .cloned()
+            .unwrap_or_else(|| ChainSourceSettings::Esplora {
+                base_url: self.base_url.clone(),
+            })
+    }
+}
+
+// As deployed: a single underscore after the prefix, and double ones between nested keys.
+fn environment(prefix: &str) -> Environment {
+    Environment::with_prefix(prefix)
+        .prefix_separator("_")
+        .separator("__")
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk_utils::bdk::bitcoin::Network;
+
+    use super::{environment, ChainSourceSettings, IndexerSettings};
+
+    #[test]
+    fn test_deserialize_settings() {
+        let settings: ChainSourceSettings = serde_json::from_str(
+            r#"{"type": "bitcoin_core", "rpc_url": "http://127.0.0.1:18443", "rpc_user": "user"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            settings,
+            ChainSourceSettings::BitcoinCore {
+                rpc_url: "http://127.0.0.1:18443".to_string(),
+                rpc_user: Some("user".to_string()),
+                rpc_password: None,
+            }
+        );
+
+        let settings: ChainSourceSettings =
+            serde_json::from_str(r#"{"type": "electrum", "url": "tcp://127.0.0.1:50001"}"#)
+                .unwrap();
+        assert_eq!(
+            settings,
+            ChainSourceSettings::Electrum {
+                url: "tcp://127.0.0.1:50001".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_indexer_settings_from_env() {
+        let variables = [
+            ("CHAIN_INDEXER_BASE_URL", "https://bitkey.mempool.space/api"),
+            ("CHAIN_INDEXER_NETWORK", "bitcoin"),
+            ("CHAIN_INDEXER_SOURCES__REGTEST__TYPE", "bitcoin_core"),
+            (
+                "CHAIN_INDEXER_SOURCES__REGTEST__RPC_URL",
+                "http://127.0.0.1:18443",
+            ),
+            // Other indexers' variables are ignored.
+            ("MEMPOOL_INDEXER_NETWORK", "testnet"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let settings =
+            IndexerSettings::from_environment(environment("CHAIN_INDEXER").source(Some(variables)))
+                .unwrap();
+        assert_eq!(settings.network, Network::Bitcoin);
+        assert_eq!(
+            settings.source(),
+            ChainSourceSettings::Esplora {
+                base_url: "https://bitkey.mempool.space/api".to_string(),
+            }
+        );
+        assert_eq!(
+            settings.sources.get(&Network::Regtest),
+            Some(&ChainSourceSettings::BitcoinCore {
+                rpc_url: "http://127.0.0.1:18443".to_string(),
+                rpc_user: None,
+                rpc_password: None,
+            })
+        );
+
+        let settings = IndexerSettings::from_environment(
+            environment("CHAIN_INDEXER").source(Some(Default::default())),
+        )
+        .unwrap();
+        assert_eq!(settings.network, Network::Signet);
+        assert_eq!(
+            settings.source(),
+            ChainSourceSettings::Esplora {
+                base_url: "https://bitkey.mempool.space/signet/api".to_string(),
+            }
+        );
+    }
+}
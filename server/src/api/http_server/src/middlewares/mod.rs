@@ -1,2 +1,3 @@
 pub mod identifier_generator;
+pub mod noise;
 pub mod wsm;
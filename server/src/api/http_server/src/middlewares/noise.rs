@@ -0,0 +1,465 @@
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Path, Request, State},
+    http::{header::CONTENT_LENGTH, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use crypto::noise::session::{
+    encrypted_len, NoiseSession, Responder, SessionError, SessionId, SessionPolicy,
+};
+use crypto::secret::SecretBytes;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::router::RouterBuilder;
+
+// Requests carrying this header have Noise encrypted bodies, and get Noise encrypted responses.
+// The value is the hex encoded id of a session established through `/api/noise/handshake` or
+// `/api/noise/resume`.
+pub const SESSION_ID_HEADER: &str = "noise-session-id";
+
+// The largest plaintext carried over a Noise channel, in either direction.
+const MAX_PAYLOAD_SIZE: usize = 1024 * 1024;
+const MAX_REQUEST_BODY_SIZE: usize = encrypted_len(MAX_PAYLOAD_SIZE);
+// Handshakes are unauthenticated, so a session isn't bound to an account until the first
+// authenticated request on it. Unbound sessions are capped separately, and when the cap is hit
+// the oldest is evicted rather than refusing the handshake: a flood of handshakes can't lock
+// anyone out, since clients use their session as soon as it's established.
+const MAX_UNBOUND_SESSIONS: usize = 10_000;
+const UNBOUND_SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+// Once bound, each account holds at most this many sessions; the least recently used is evicted.
+const MAX_SESSIONS_PER_ACCOUNT: usize = 8;
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+const TICKET_LIFETIME_SECS: u64 = 7 * 24 * 60 * 60;
+
+// Well-known keys for local and test profiles only.
+const TEST_STATIC_PRIVATE_KEY: &str =
+    "1111111111111111111111111111111111111111111111111111111111111111";
+const TEST_TICKET_KEY: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+
+#[derive(Clone, Deserialize)]
+pub struct Config {
+    pub noise: NoiseMode,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "mode")]
+pub enum NoiseMode {
+    Test,
+    // Hex encoded 32 byte keys are read from NOISE_STATIC_PRIVATE_KEY, the server's static key
+    // the app pins, and NOISE_TICKET_KEY, which seals session tickets and must be shared by every
+    // instance. Deployed from the `fromagerie/noise/keys` secret; processes that don't serve the
+    // API set SERVER_NOISE to `{mode=test}` instead.
+    Environment,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0} environment variable not set")]
+    MissingKey(&'static str),
+    #[error("{0} is not hex encoded")]
+    InvalidKey(&'static str),
+    #[error(transparent)]
+    Session(#[from] SessionError),
+}
+
+impl Config {
+    pub fn to_service(self) -> Result<Service, Error> {
+        let (static_private_key, ticket_key) = match self.noise {
+            NoiseMode::Test => (
+                TEST_STATIC_PRIVATE_KEY.to_owned(),
+                TEST_TICKET_KEY.to_owned(),
+            ),
+            NoiseMode::Environment => (
+                env::var("NOISE_STATIC_PRIVATE_KEY")
+                    .map_err(|_| Error::MissingKey("NOISE_STATIC_PRIVATE_KEY"))?,
+                env::var("NOISE_TICKET_KEY").map_err(|_| Error::MissingKey("NOISE_TICKET_KEY"))?,
+            ),
+        };
+
+        let static_private_key = hex::decode(static_private_key)
+            .map(SecretBytes::from)
+            .map_err(|_| Error::InvalidKey("NOISE_STATIC_PRIVATE_KEY"))?;
+        let ticket_key = hex::decode(ticket_key)
+            .map(SecretBytes::from)
+            .map_err(|_| Error::InvalidKey("NOISE_TICKET_KEY"))?;
+        Service::new(static_private_key, ticket_key.expose_secret())
+    }
+}
+
+struct SessionEntry {
+    session: Arc<AsyncMutex<NoiseSession>>,
+    last_used: Instant,
+    // The account whose authenticated request first used the session. Only that account's
+    // requests may use it afterwards.
+    account_id: Option<String>,
+}
+
+impl SessionEntry {
+    fn is_expired(&self, now: Instant) -> bool {
+        let timeout = match self.account_id {
+            Some(_) => SESSION_IDLE_TIMEOUT,
+            None => UNBOUND_SESSION_TIMEOUT,
+        };
+        now.duration_since(self.last_used) >= timeout
+    }
+}
+
+// The session table. Unbound sessions are also kept ordered by when they were established, which
+// is the order they expire in, so expiring and evicting them only ever touches the oldest. Bound
+// sessions are indexed by account, and idle ones are swept once per idle timeout.
+struct Sessions {
+    entries: HashMap<SessionId, SessionEntry>,
+    unbound: BTreeSet<(Instant, SessionId)>,
+    accounts: HashMap<String, Vec<SessionId>>,
+    last_sweep: Instant,
+}
+
+impl Sessions {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            unbound: BTreeSet::new(),
+            accounts: HashMap::new(),
+            last_sweep: Instant::now(),
+        }
+    }
+
+    fn insert(&mut self, session: NoiseSession, now: Instant) -> SessionId {
+        while let Some(&(established, session_id)) = self.unbound.first() {
+            if now.duration_since(established) < UNBOUND_SESSION_TIMEOUT
+                && self.unbound.len() < MAX_UNBOUND_SESSIONS
+            {
+                break;
+            }
+            self.remove(&session_id);
+        }
+        if now.duration_since(self.last_sweep) >= SESSION_IDLE_TIMEOUT {
+            self.sweep(now);
+        }
+
+        let session_id = session.id();
+        self.unbound.insert((now, session_id));
+        self.entries.insert(
+            session_id,
+            SessionEntry {
+                session: Arc::new(AsyncMutex::new(session)),
+                last_used: now,
+                account_id: None,
+            },
+        );
+        session_id
+    }
+
+    fn get(&mut self, session_id: &SessionId, account_id: &str, now: Instant) -> Lookup {
+        let Some(entry) = self.entries.get_mut(session_id) else {
+            return Lookup::Unknown;
+        };
+        if entry.is_expired(now) {
+            self.remove(session_id);
+            return Lookup::Unknown;
+        }
+        match &entry.account_id {
+            Some(bound) if bound != account_id => return Lookup::WrongAccount,
+            Some(_) => {
+                entry.last_used = now;
+                Lookup::Found(entry.session.clone())
+            }
+            None => {
+                self.unbound.remove(&(entry.last_used, *session_id));
+                entry.account_id = Some(account_id.to_owned());
+                entry.last_used = now;
+                let session = entry.session.clone();
+
+                let bound = self.accounts.entry(account_id.to_owned()).or_default();
+                bound.push(*session_id);
+                // The session being bound was just used, so it's never the one evicted.
+                if bound.len() > MAX_SESSIONS_PER_ACCOUNT {
+                    let least_recently_used = bound
+                        .iter()
+                        .min_by_key(|session_id| self.entries[*session_id].last_used)
+                        .copied();
+                    if let Some(session_id) = least_recently_used {
+                        self.remove(&session_id);
+                    }
+                }
+                Lookup::Found(session)
+            }
+        }
+    }
+
+    fn remove(&mut self, session_id: &SessionId) {
+        let Some(entry) = self.entries.remove(session_id) else {
+            return;
+        };
+        match entry.account_id {
+            None => {
+                self.unbound.remove(&(entry.last_used, *session_id));
+            }
+            Some(account_id) => {
+                if let Some(bound) = self.accounts.get_mut(&account_id) {
+                    bound.retain(|bound| bound != session_id);
+                    if bound.is_empty() {
+                        self.accounts.remove(&account_id);
+                    }
+                }
+            }
+        }
+    }
+
+    // Drops bound sessions that have been idle too long. Unbound ones are expired as new ones
+    // are established.
+    fn sweep(&mut self, now: Instant) {
+        let expired = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.account_id.is_some() && entry.is_expired(now))
+            .map(|(session_id, _)| *session_id)
+            .collect::<Vec<_>>();
+        for session_id in expired {
+            self.remove(&session_id);
+        }
+        self.last_sweep = now;
+    }
+}
+
+enum Lookup {
+    Found(Arc<AsyncMutex<NoiseSession>>),
+    Unknown,
+    // The session is bound to a different account; it is left untouched.
+    WrongAccount,
+}
+
+// Terminates Noise channels from the app. Sessions live in memory on the instance that
+// established them; a client routed to another instance resumes with its session ticket, which
+// any instance sharing the ticket key can redeem.
+#[derive(Clone)]
+pub struct Service {
+    responder: Arc<Responder>,
+    sessions: Arc<Mutex<Sessions>>,
+}
+
+impl Service {
+    pub fn new(static_private_key: SecretBytes, ticket_key: &[u8]) -> Result<Self, Error> {
+        let responder = Responder::new(
+            static_private_key,
+            ticket_key,
+            TICKET_LIFETIME_SECS,
+            SessionPolicy::default(),
+        )?;
+
+        Ok(Self {
+            responder: Arc::new(responder),
+            sessions: Arc::new(Mutex::new(Sessions::new())),
+        })
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        self.responder.public_key()
+    }
+
+    fn insert(&self, session: NoiseSession) -> SessionId {
+        self.sessions
+            .lock()
+            .expect("Failed to lock sessions")
+            .insert(session, Instant::now())
+    }
+
+    // Looks up a session for a request authenticated as `account_id`, binding the session to the
+    // account if this is its first use.
+    fn get(&self, session_id: &SessionId, account_id: &str) -> Lookup {
+        self.sessions.lock().expect("Failed to lock sessions").get(
+            session_id,
+            account_id,
+            Instant::now(),
+        )
+    }
+
+    // Only called for a session already bound to the requesting account.
+    fn remove(&self, session_id: &SessionId) {
+        self.sessions
+            .lock()
+            .expect("Failed to lock sessions")
+            .remove(session_id);
+    }
+}
+
+impl RouterBuilder for Service {
+    fn unauthed_router(&self) -> Router {
+        Router::new()
+            .route("/api/noise/handshake", post(handshake))
+            .route("/api/noise/resume", post(resume))
+            .with_state(self.clone())
+    }
+
+    fn account_authed_router(&self) -> Router {
+        Router::new()
+            .route("/api/accounts/:account_id/noise/ticket", post(issue_ticket))
+            .with_state(self.clone())
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+fn session_id_from_headers(headers: &HeaderMap) -> Option<Result<SessionId, Response>> {
+    let value = headers.get(SESSION_ID_HEADER)?;
+    Some(
+        value
+            .to_str()
+            .ok()
+            .and_then(|value| hex::decode(value).ok())
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(SessionId)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid Noise session id").into_response()),
+    )
+}
+
+fn established(service: &Service, session: NoiseSession, reply: Vec<u8>) -> Response {
+    let session_id = service.insert(session);
+    (
+        [(SESSION_ID_HEADER, hex::encode(session_id.as_bytes()))],
+        reply,
+    )
+        .into_response()
+}
+
+fn lookup_failed(lookup: Lookup) -> Response {
+    match lookup {
+        Lookup::WrongAccount => (
+            StatusCode::FORBIDDEN,
+            "Noise session belongs to another account",
+        )
+            .into_response(),
+        _ => (StatusCode::UNAUTHORIZED, "Unknown Noise session").into_response(),
+    }
+}
+
+// The body is the app's first IK handshake message; the response body is the server's reply.
+async fn handshake(State(service): State<Service>, message: axum::body::Bytes) -> Response {
+    match service.responder.accept(&message) {
+        Ok((session, reply)) => established(&service, session, reply),
+        Err(_) => (StatusCode::BAD_REQUEST, "Noise handshake failed").into_response(),
+    }
+}
+
+// The body is a resumption message built from a session ticket.
+async fn resume(State(service): State<Service>, message: axum::body::Bytes) -> Response {
+    match service.responder.resume(&message, unix_time()) {
+        Ok((session, reply)) => established(&service, session, reply),
+        Err(SessionError::TicketExpired) => {
+            (StatusCode::UNAUTHORIZED, "Noise session ticket expired").into_response()
+        }
+        Err(_) => (StatusCode::BAD_REQUEST, "Noise resumption failed").into_response(),
+    }
+}
+
+// Returns a session ticket encrypted under the session named by the session id header. Issuing a
+// ticket uses a message on the session, so only the account the session is bound to may ask.
+async fn issue_ticket(
+    State(service): State<Service>,
+    Path(account_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let session_id = match session_id_from_headers(&headers) {
+        Some(Ok(session_id)) => session_id,
+        Some(Err(response)) => return response,
+        None => return (StatusCode::BAD_REQUEST, "Missing Noise session id").into_response(),
+    };
+    let session = match service.get(&session_id, &account_id) {
+        Lookup::Found(session) => session,
+        lookup => return lookup_failed(lookup),
+    };
+
+    let mut session = session.lock().await;
+    match service.responder.issue_ticket(&mut session, unix_time()) {
+        Ok(message) => message.into_response(),
+        Err(_) => {
+            service.remove(&session_id);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to issue ticket").into_response()
+        }
+    }
+}
+
+// Route layer for routes that accept Noise encrypted bodies. Requests without the session id
+// header pass through untouched. Otherwise the request body is decrypted before it reaches the
+// handler and the handler's response body, whatever its status, is encrypted on the way out.
+//
+// The session is held for the whole request so responses are encrypted in the order requests
+// were decrypted; clients must wait for each response before sending the next request on a
+// session. A session that fails to decrypt is out of sync with the client and is dropped.
+//
+// Must run inside `authorize_token_for_path`, so the path's account id is the authenticated
+// account. The session is bound to that account on first use, and requests from any other
+// account are refused before anything is decrypted.
+pub async fn noise_channel(
+    State(service): State<Service>,
+    Path(path): Path<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let session_id = match session_id_from_headers(request.headers()) {
+        Some(Ok(session_id)) => session_id,
+        Some(Err(response)) => return response,
+        None => return next.run(request).await,
+    };
+    let Some(account_id) = path.get("account_id") else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let session = match service.get(&session_id, account_id) {
+        Lookup::Found(session) => session,
+        lookup => return lookup_failed(lookup),
+    };
+    let mut session = session.lock().await;
+
+    let (mut parts, body) = request.into_parts();
+    let Ok(ciphertext) = to_bytes(body, MAX_REQUEST_BODY_SIZE).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let plaintext = match session.decrypt(&ciphertext) {
+        Ok(plaintext) => plaintext,
+        Err(_) => {
+            service.remove(&session_id);
+            return (StatusCode::BAD_REQUEST, "Failed to decrypt request").into_response();
+        }
+    };
+    parts
+        .headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(plaintext.len()));
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(plaintext)))
+        .await;
+
+    let (mut parts, body) = response.into_parts();
+    let ciphertext = match to_bytes(body, MAX_PAYLOAD_SIZE).await {
+        Ok(plaintext) => session.encrypt(&plaintext),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let Ok(ciphertext) = ciphertext else {
+        service.remove(&session_id);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to encrypt response",
+        )
+            .into_response();
+    };
+    parts
+        .headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(ciphertext.len()));
+
+    Response::from_parts(parts, Body::from(ciphertext))
+}
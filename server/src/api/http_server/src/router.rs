@@ -9,6 +9,12 @@ pub trait RouterBuilder {
         Router::new()
     }
 
+    // Account authenticated routes whose bodies may be sent over a Noise channel. See
+    // `middlewares::noise::noise_channel`.
+    fn account_authed_noise_router(&self) -> Router {
+        Router::new()
+    }
+
     fn recovery_authed_router(&self) -> Router {
         Router::new()
     }
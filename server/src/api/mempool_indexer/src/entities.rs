@@ -1,9 +1,10 @@
 use std::hash::{Hash, Hasher};
 
 use bdk_utils::bdk::{
-    bitcoin::{Network, Txid, Weight},
+    bitcoin::{Network, Txid},
     FeeRate,
 };
+use chain_source::{MempoolTransaction, TransactionOutput};
 use serde::{Deserialize, Serialize};
 use time::{serde::rfc3339, Duration, OffsetDateTime};
 use types::serde::{deserialize_ts, serialize_ts};
@@ -17,24 +18,12 @@ pub struct TransactionVout {
     pub value: u64,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-pub struct TransactionRecordStatus {
-    confirmed: bool,
-}
-
-#[derive(Deserialize, Serialize, Debug, Clone)]
-pub(crate) struct TransactionResponse {
-    txid: Txid,
-    vout: Vec<TransactionVout>,
-    size: usize,
-    weight: Weight,
-    fee: u64,
-    status: TransactionRecordStatus,
-}
-
-impl TransactionResponse {
-    pub(crate) fn is_confirmed(&self) -> bool {
-        self.status.confirmed
+impl From<&TransactionOutput> for TransactionVout {
+    fn from(output: &TransactionOutput) -> Self {
+        Self {
+            scriptpubkey_address: output.address.clone(),
+            value: output.value,
+        }
     }
 }
 
@@ -69,12 +58,12 @@ impl Hash for TransactionRecord {
 }
 
 impl TransactionRecord {
-    pub(crate) fn from_mempool_tx(tx: &TransactionResponse, network: Network) -> Self {
+    pub(crate) fn from_mempool_tx(tx: &MempoolTransaction, network: Network) -> Self {
         let now: OffsetDateTime = OffsetDateTime::now_utc();
         TransactionRecord {
             txid: tx.txid,
             network,
-            received: tx.vout.clone(),
+            received: tx.outputs.iter().map(TransactionVout::from).collect(),
             fee_rate: FeeRate::from_wu(tx.fee, tx.weight).as_sat_per_vb(),
             first_seen: now,
             expiring_at: now + Duration::days(RETENTION_DAYS),
@@ -8,10 +8,7 @@ use time::OffsetDateTime;
 use tracing::{event, Level};
 
 use super::Service;
-use crate::{
-    entities::{TransactionRecord, TransactionResponse},
-    MempoolIndexerError,
-};
+use crate::{entities::TransactionRecord, MempoolIndexerError};
 
 // Refresh the recorded txids every 6 hours
 pub const EXPIRY_UPDATE_WINDOW_MINS: Duration = Duration::from_secs(6 * 60 * 60);
@@ -24,9 +21,9 @@ impl Service {
             let network = self.settings.network;
             event!(
                 Level::INFO,
-                "Getting new txs for network {} using base url {}",
+                "Getting new txs for network {} from {}",
                 network,
-                self.settings.base_url,
+                self.source,
             );
 
             // If this is the first time we're calling this method or every 6 hours, fetch the recorded txids from the database
@@ -79,15 +76,13 @@ impl Service {
                 let mut tx_records = Vec::new();
 
                 for tx_id in &unrecorded_tx_ids {
-                    match self.fetch_transaction_from_mempool(tx_id).await {
-                        Ok(transaction_response) => {
-                            // If the tx is already confirmed, no use vending a processing tx for it
-                            if transaction_response.is_confirmed() {
-                                continue;
-                            }
-                            let record = TransactionRecord::from_mempool_tx(&transaction_response, network);
+                    match self.source.mempool_transaction(tx_id).await {
+                        Ok(Some(transaction)) => {
+                            let record = TransactionRecord::from_mempool_tx(&transaction, network);
                             tx_records.push(record);
                         }
+                        // If the tx is already confirmed, no use vending a processing tx for it
+                        Ok(None) => continue,
                         Err(e) => {
                             event!(
                                 Level::ERROR,
@@ -112,28 +107,9 @@ impl Service {
         }
     }
 
-    async fn fetch_transaction_from_mempool(
-        &self,
-        tx_id: &Txid,
-    ) -> Result<TransactionResponse, MempoolIndexerError> {
-        self.http_client
-            .get(&format!("{}/tx/{tx_id}", self.settings.base_url))
-            .send()
-            .await?
-            .json()
-            .await
-            .map_err(MempoolIndexerError::from)
-    }
-
     pub async fn get_transaction_ids_from_mempool(
         &self,
     ) -> Result<HashSet<Txid>, MempoolIndexerError> {
-        self.http_client
-            .get(&format!("{}/mempool/txids", self.settings.base_url))
-            .send()
-            .await?
-            .json()
-            .await
-            .map_err(MempoolIndexerError::from)
+        Ok(self.source.mempool_txids().await?)
     }
 }
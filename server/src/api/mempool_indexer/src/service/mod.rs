@@ -2,58 +2,32 @@ use std::{collections::HashSet, sync::Arc};
 
 use crate::repository::MempoolIndexerRepository;
 use bdk_utils::bdk::bitcoin::{Network, Txid};
-use config::{Config, ConfigError, Environment};
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
-use serde::Deserialize;
+use chain_source::{ChainSource, Esplora, IndexerSettings};
 use time::OffsetDateTime;
 use tokio::sync::RwLock;
 
 mod get_new_txs;
 mod get_stale_txs;
 
-const MEMPOOL_SPACE_SIGNET_URL: &str = "https://bitkey.mempool.space/signet/api";
-
 #[derive(Clone)]
 pub struct Service {
     pub repo: MempoolIndexerRepository,
-    http_client: ClientWithMiddleware,
-    settings: Settings,
+    source: Arc<dyn ChainSource>,
+    settings: IndexerSettings,
     recorded_txids: Arc<RwLock<HashSet<Txid>>>,
     last_refreshed_recorded_txids: Arc<RwLock<OffsetDateTime>>,
     current_mempool_txids: Arc<RwLock<HashSet<Txid>>>,
     stale_txs_expiring_after: Arc<RwLock<Option<OffsetDateTime>>>,
 }
 
-#[derive(Clone, Deserialize)]
-pub struct Settings {
-    base_url: String,
-    network: Network,
-}
-
-impl Settings {
-    pub fn new() -> Result<Self, ConfigError> {
-        Config::builder()
-            .set_default("base_url", MEMPOOL_SPACE_SIGNET_URL)?
-            .set_default("network", Network::Signet.to_string())?
-            .add_source(Environment::with_prefix("MEMPOOL_INDEXER"))
-            .build()?
-            .try_deserialize()
-    }
-}
-
 impl Service {
     pub fn new(repo: MempoolIndexerRepository) -> Self {
-        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(5);
-        let http_client = ClientBuilder::new(Client::new())
-            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-            .build();
-        let settings = Settings::new().unwrap();
+        // Electrum can't list the mempool, so it can't be this indexer's source.
+        let settings = IndexerSettings::from_env("MEMPOOL_INDEXER").unwrap();
 
         Self {
             repo,
-            http_client,
+            source: settings.source().build(settings.network),
             settings,
             recorded_txids: Arc::new(RwLock::new(HashSet::new())),
             last_refreshed_recorded_txids: Arc::new(RwLock::new(OffsetDateTime::UNIX_EPOCH)),
@@ -63,7 +37,7 @@ impl Service {
     }
 
     pub fn set_mock_server(mut self, base_url: String) -> Self {
-        self.settings.base_url = base_url;
+        self.source = Arc::new(Esplora::new(base_url));
         self
     }
 
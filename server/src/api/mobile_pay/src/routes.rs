@@ -69,14 +69,6 @@ pub struct RouteState(
 impl RouterBuilder for RouteState {
     fn account_authed_router(&self) -> Router {
         Router::new()
-            .route(
-                "/api/accounts/:account_id/sign-transaction",
-                post(sign_transaction_with_active_keyset),
-            )
-            .route(
-                "/api/accounts/:account_id/keysets/:keyset_id/sign-transaction",
-                post(sign_transaction_with_keyset),
-            )
             .route(
                 "/api/accounts/:account_id/mobile-pay",
                 put(setup_mobile_pay_for_account),
@@ -95,6 +87,23 @@ impl RouterBuilder for RouteState {
             )
             .with_state(self.to_owned())
     }
+
+    fn account_authed_noise_router(&self) -> Router {
+        Router::new()
+            .route(
+                "/api/accounts/:account_id/sign-transaction",
+                post(sign_transaction_with_active_keyset),
+            )
+            .route(
+                "/api/accounts/:account_id/keysets/:keyset_id/sign-transaction",
+                post(sign_transaction_with_keyset),
+            )
+            .route_layer(
+                mobile_pay_metrics::FACTORY
+                    .route_layer(mobile_pay_metrics::FACTORY_NAME.to_owned()),
+            )
+            .with_state(self.to_owned())
+    }
 }
 
 impl From<RouteState> for SwaggerEndpoint {
@@ -211,10 +211,13 @@ mod tests {
     use std::sync::Arc;
     use types::account::identifiers::KeysetId;
     use wsm_common::messages::api::{
-        AttestationDocResponse, ContinueDistributedKeygenResponse, GetIntegritySigResponse,
-        InitiateDistributedKeygenResponse,
+        AttestationDocResponse, ContinueDistributedKeygenResponse, ContinueShareRefreshResponse,
+        GetIntegritySigResponse, InitiateDistributedKeygenResponse, InitiateShareRefreshResponse,
+    };
+    use wsm_rust_client::{
+        Bip322SignatureFormat, CreatedSigningKey, Error, SetSpendPolicyResponse, SignedMessage,
+        SignedPsbt, SignedSpendPolicy, SigningService,
     };
-    use wsm_rust_client::{CreatedSigningKey, Error, SignedPsbt, SigningService};
 
     mock! {
         WsmSigner {}
@@ -238,6 +241,18 @@ mod tests {
                 network: Network,
                 sealed_request: &str
             ) -> Result<ContinueDistributedKeygenResponse, Error>;
+            async fn initiate_share_refresh(
+                &self,
+                root_key_id: &str,
+                network: Network,
+                sealed_request: &str,
+            ) -> Result<InitiateShareRefreshResponse, Error>;
+            async fn continue_share_refresh(
+                &self,
+                root_key_id: &str,
+                network: Network,
+                sealed_request: &str,
+            ) -> Result<ContinueShareRefreshResponse, Error>;
             async fn sign_psbt(
                 &self,
                 root_key_id: &str,
@@ -245,6 +260,22 @@ mod tests {
                 change_descriptor: &str,
                 psbt: &str,
             ) -> Result<SignedPsbt, Error>;
+            async fn sign_message(
+                &self,
+                root_key_id: &str,
+                descriptor: &str,
+                change_descriptor: &str,
+                message: &str,
+                psbt: &str,
+                format: Bip322SignatureFormat,
+            ) -> Result<SignedMessage, Error>;
+            async fn set_spend_policy(
+                &self,
+                root_key_id: &str,
+                descriptor: &str,
+                change_descriptor: &str,
+                spend_policy: &SignedSpendPolicy,
+            ) -> Result<SetSpendPolicyResponse, Error>;
             async fn get_key_integrity_sig(
                 &self,
                 root_key_id: &str,
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use types::account::identifiers::AccountId;
+use types::{account::identifiers::AccountId, notification::PaymentConfirmationDepth};
 
 use crate::{
     entities::NotificationCompositeKey, push::AndroidChannelId, push::SNSPushPayload,
@@ -11,6 +11,11 @@ pub struct ConfirmedPaymentPayload {
     pub account_id: AccountId,
     #[serde(default)]
     pub is_addressed_to_inactive_keyset: bool,
+    #[serde(default)]
+    pub confirmation_depth: PaymentConfirmationDepth,
+    // Whether this is the highest confirmation depth the account is notified at.
+    #[serde(default)]
+    pub is_settled: bool,
 }
 
 impl TryFrom<(NotificationCompositeKey, ConfirmedPaymentPayload)> for NotificationMessage {
@@ -21,16 +26,24 @@ impl TryFrom<(NotificationCompositeKey, ConfirmedPaymentPayload)> for Notificati
     ) -> Result<Self, Self::Error> {
         let (composite_key, payload) = v;
         let message = if payload.is_addressed_to_inactive_keyset {
-            "Action required: your bitcoin deposit was sent to an inactive wallet. Transfer funds to your current wallet now."
+            "Action required: your bitcoin deposit was sent to an inactive wallet. Transfer funds to your current wallet now.".to_owned()
         } else {
-            "Your bitcoin deposit is now complete."
+            let confirmations = match payload.confirmation_depth.confirmations() {
+                1 => "1 confirmation".to_owned(),
+                n => format!("{n} confirmations"),
+            };
+            if payload.is_settled {
+                format!("Your bitcoin deposit now has {confirmations} and is fully settled.")
+            } else {
+                format!("Your bitcoin deposit has been received, with {confirmations}.")
+            }
         };
         Ok(NotificationMessage {
             composite_key,
             account_id: payload.account_id,
             email_payload: None,
             push_payload: Some(SNSPushPayload {
-                message: message.to_owned(),
+                message,
                 android_channel_id: AndroidChannelId::Transactions,
                 ..Default::default()
             }),
@@ -64,3 +77,53 @@ impl TryFrom<(NotificationCompositeKey, PendingPaymentPayload)> for Notification
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use types::account::identifiers::AccountId;
+    use types::notification::PaymentConfirmationDepth;
+
+    use crate::identifiers::NotificationId;
+    use crate::payloads::payment::ConfirmedPaymentPayload;
+    use crate::NotificationMessage;
+
+    fn message(
+        confirmation_depth: PaymentConfirmationDepth,
+        is_settled: bool,
+        is_addressed_to_inactive_keyset: bool,
+    ) -> String {
+        let account_id = AccountId::gen().expect("Valid AccountId");
+        let payload = ConfirmedPaymentPayload {
+            account_id: account_id.clone(),
+            is_addressed_to_inactive_keyset,
+            confirmation_depth,
+            is_settled,
+        };
+        let notification_message: NotificationMessage =
+            ((account_id, NotificationId::gen_customer()), payload)
+                .try_into()
+                .unwrap();
+        notification_message.push_payload.unwrap().message
+    }
+
+    #[test]
+    fn test_confirmed_payment_messages() {
+        assert_eq!(
+            message(PaymentConfirmationDepth::One, false, false),
+            "Your bitcoin deposit has been received, with 1 confirmation."
+        );
+        assert_eq!(
+            message(PaymentConfirmationDepth::Three, false, false),
+            "Your bitcoin deposit has been received, with 3 confirmations."
+        );
+        assert_eq!(
+            message(PaymentConfirmationDepth::One, true, false),
+            "Your bitcoin deposit now has 1 confirmation and is fully settled."
+        );
+        assert_eq!(
+            message(PaymentConfirmationDepth::Three, true, false),
+            "Your bitcoin deposit now has 3 confirmations and is fully settled."
+        );
+        assert!(message(PaymentConfirmationDepth::One, false, true).starts_with("Action required"));
+    }
+}
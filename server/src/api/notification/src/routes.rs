@@ -21,7 +21,7 @@ use serde::{Deserialize, Serialize};
 use tracing::{event, instrument, Level};
 use types::{
     account::identifiers::AccountId,
-    notification::{NotificationChannel, NotificationsPreferences},
+    notification::{NotificationChannel, NotificationsPreferences, PaymentConfirmationDepth},
 };
 use userpool::userpool::UserPoolService;
 use utoipa::{OpenApi, ToSchema};
@@ -102,7 +102,7 @@ impl From<RouteState> for SwaggerEndpoint {
     components(
         schemas(SendTestPushData, SendTestPushResponse),
         schemas(RegisterWatchAddressRequest, RegisterWatchAddressResponse),
-        schemas(NotificationsPreferences, NotificationChannel),
+        schemas(NotificationsPreferences, NotificationChannel, PaymentConfirmationDepth),
     ),
     tags(
         (name = "Notification", description = "Touchpoints with Users")
@@ -320,16 +320,15 @@ pub async fn set_notifications_preferences(
     key_proof: KeyClaims,
     Json(request): Json<NotificationsPreferences>,
 ) -> Result<Json<NotificationsPreferences>, ApiError> {
-    let new_notifications_preferences = request;
-    notification_service
+    let notifications_preferences = notification_service
         .update_notifications_preferences(UpdateNotificationsPreferencesInput {
             account_id: &account_id,
-            notifications_preferences: &new_notifications_preferences,
+            notifications_preferences: &request,
             key_proof: Some(key_proof),
         })
         .await?;
 
-    Ok(Json(new_notifications_preferences))
+    Ok(Json(notifications_preferences))
 }
 
 #[instrument(err, skip(notification_service))]
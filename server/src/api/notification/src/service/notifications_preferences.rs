@@ -52,7 +52,7 @@ impl Service {
     pub async fn update_notifications_preferences(
         &self,
         input: UpdateNotificationsPreferencesInput<'_>,
-    ) -> Result<(), ApiError> {
+    ) -> Result<NotificationsPreferences, ApiError> {
         let account = self
             .account_repo
             .fetch(input.account_id)
@@ -118,7 +118,11 @@ impl Service {
         )
         .await?;
 
-        Ok(())
+        Ok(updated_account
+            .get_common_fields()
+            .notifications_preferences_state
+            .clone()
+            .into())
     }
 }
 
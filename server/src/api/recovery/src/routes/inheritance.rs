@@ -73,10 +73,6 @@ impl RouterBuilder for RouteState {
                 "/api/accounts/:account_id/recovery/inheritance/claims",
                 post(create_inheritance_claim),
             )
-            .route(
-                "/api/accounts/:account_id/recovery/inheritance/packages",
-                post(upload_inheritance_packages),
-            )
             .route(
                 "/api/accounts/:account_id/recovery/inheritance/claims/:inheritance_claim_id/cancel",
                 post(cancel_inheritance_claim),
@@ -89,6 +85,16 @@ impl RouterBuilder for RouteState {
             .with_state(self.to_owned())
     }
 
+    fn account_authed_noise_router(&self) -> Router {
+        Router::new()
+            .route(
+                "/api/accounts/:account_id/recovery/inheritance/packages",
+                post(upload_inheritance_packages),
+            )
+            .route_layer(metrics::FACTORY.route_layer("recovery".to_owned()))
+            .with_state(self.to_owned())
+    }
+
     fn recovery_authed_router(&self) -> Router {
         Router::new()
             .route(
@@ -12,6 +12,9 @@ use types::recovery::social::relationship::{
 use super::{error::ServiceError, Service};
 
 const MAX_PROTECTED_CUSTOMERS: usize = 10;
+// Matches the app's `DEFAULT_MAX_ATTEMPTS` for PAKE pairing. The customer must reissue the
+// invitation, with a new code, once it's used up.
+const MAX_INVITATION_CODE_ATTEMPTS: u32 = 5;
 
 /// The input for the `accept_recovery_relationship_invitation` function
 ///
@@ -83,7 +86,16 @@ impl Service {
             return Err(ServiceError::InvitationExpired);
         }
 
+        if invitation.failed_code_attempts >= MAX_INVITATION_CODE_ATTEMPTS {
+            return Err(ServiceError::InvitationCodeAttemptsExceeded);
+        }
+
         if invitation.code != input.code {
+            let mut invitation = invitation.to_owned();
+            invitation.failed_code_attempts += 1;
+            self.repository
+                .persist_recovery_relationship(&RecoveryRelationship::Invitation(invitation))
+                .await?;
             return Err(ServiceError::InvitationCodeMismatch);
         }
 
@@ -26,6 +26,8 @@ pub enum ServiceError {
     InvitationExpired,
     #[error("Recovery relationship invitation code mismatch")]
     InvitationCodeMismatch,
+    #[error("Too many wrong codes for recovery relationship invitation")]
+    InvitationCodeAttemptsExceeded,
     #[error("Customer cannot be trusted contact")]
     CustomerIsTrustedContact,
     #[error("Unauthorized recovery relationship deletion")]
@@ -82,7 +84,8 @@ impl From<ServiceError> for ApiError {
             | ServiceError::UnauthorizedRelationshipUpdate
             | ServiceError::CustomerIsTrustedContact
             | ServiceError::InvalidKeyProof
-            | ServiceError::InvalidOperationForAccessToken => Self::GenericForbidden(msg),
+            | ServiceError::InvalidOperationForAccessToken
+            | ServiceError::InvitationCodeAttemptsExceeded => Self::GenericForbidden(msg),
             ServiceError::InvitationExpired => ApiError::Specific {
                 code: ErrorCode::InvitationExpired,
                 detail: Some(msg),
@@ -20,6 +20,7 @@ use exchange_rate::service::Service as ExchangeRateService;
 use feature_flags::service::Service as FeatureFlagsService;
 use http_server::config::Config;
 use http_server::middlewares::identifier_generator::IdentifierGenerator;
+use http_server::middlewares::noise::{self, noise_channel};
 use http_server::middlewares::wsm;
 use http_server::router::RouterBuilder;
 use http_server::swagger::SwaggerEndpoint;
@@ -130,6 +131,8 @@ pub enum BootstrapError {
     #[error(transparent)]
     Wsm(#[from] wsm::Error),
     #[error(transparent)]
+    Noise(#[from] noise::Error),
+    #[error(transparent)]
     Telemetry(#[from] wallet_telemetry::Error),
     #[error(transparent)]
     Metrics(#[from] instrumentation::metrics::error::MetricsError),
@@ -557,6 +560,7 @@ impl BootstrapBuilder {
 
         let analytics_state = config::extract::<analytics::routes::Config>(profile)?.to_state();
         let health_checks_state = healthcheck::Service;
+        let noise_service = config::extract::<noise::Config>(profile)?.to_service()?;
 
         let authorizer =
             AuthorizerConfig::from(config::extract::<userpool::userpool::Config>(profile)?)
@@ -566,8 +570,20 @@ impl BootstrapBuilder {
                 .map_err(BootstrapError::AuthorizerInit)?
                 .into_layer();
 
+        // Authenticated routes whose bodies may be Noise encrypted, protected by the same
+        // middleware as the rest of the account authenticated routes below
+        let account_authed_noise_router = Router::new()
+            .merge(mobile_pay_state.account_authed_noise_router())
+            .merge(inheritance_state.account_authed_noise_router())
+            .route_layer(middleware::from_fn_with_state(
+                noise_service.clone(),
+                noise_channel,
+            ));
+
         // Authenticated routes protected by "authorize_token_for_path" middleware
         let account_authed_router = Router::new()
+            .merge(account_authed_noise_router)
+            .merge(noise_service.account_authed_router())
             .merge(privileged_action_state.account_authed_router())
             .merge(notification_state.account_authed_router())
             .merge(mobile_pay_state.account_authed_router())
@@ -622,7 +638,8 @@ impl BootstrapBuilder {
             .merge(delay_notify_state.unauthed_router())
             .merge(exchange_rate_state.unauthed_router())
             .merge(experimentation_state.unauthed_router())
-            .merge(inheritance_state.unauthed_router());
+            .merge(inheritance_state.unauthed_router())
+            .merge(noise_service.unauthed_router());
 
         // Swagger UI with all endpoints
         let swagger_router = SwaggerUi::new("/docs/swagger-ui").urls(vec![
@@ -1,17 +1,20 @@
 use std::collections::{HashMap, HashSet};
 
 use account::service::{AddPushTouchpointToAccountInput, FetchAccountInput};
+use bdk_utils::bdk::bitcoin::{consensus::encode::deserialize, Block as BdkBlock};
 use httpmock::{prelude::*, Mock, MockExt};
 use notification::address_repo::AddressAndKeysetId;
-use notification::service::FetchForAccountInput;
 use notification::service::Service as NotificationService;
+use notification::service::{
+    FetchForAccountInput, FetchNotificationsPreferencesInput, UpdateNotificationsPreferencesInput,
+};
 use notification::NotificationPayloadType;
 use onboarding::routes::RotateSpendingKeysetRequest;
 use queue::sqs::SqsQueue;
 use types::account::entities::{FullAccount, TouchpointPlatform};
 use types::{
     account::identifiers::AccountId,
-    notification::{NotificationChannel, NotificationsPreferences},
+    notification::{NotificationChannel, NotificationsPreferences, PaymentConfirmationDepth},
 };
 
 use super::lib::create_inactive_spending_keyset_for_account;
@@ -138,6 +141,268 @@ async fn test_init_block_received_payment() {
     test_queue_message(&notification_service, &sqs_queue, &account.id, 2, false).await;
 }
 
+// Made-up blocks for re-org and confirmation depth tests; their coinbases pay only to an OP_RETURN.
+const FORK_BLOCK_107038: &str = "000000fd54d5b6389fea33993c0bf0968ffa7aab8d3e8692bb4507e538a51110";
+const FORK_BLOCK_107039: &str = "000000543c9247598dec918ad7caeb1f56732378edde2672def1d07a6532ec7a";
+const FORK_BLOCK_107040: &str = "000000e1b7076ab723879fcc9acbb09ae96d399fdb328c4e35cb107780555be4";
+const FORK_BLOCK_107041: &str = "000000cdd912ce6bad4fbfbe176757e422d5aa7f70b164b4fd4170203d67d998";
+
+#[tokio::test]
+async fn test_reorg_retracts_and_resends_payment() {
+    let (mock_server, account, worker, services, mut chain_mock_data) =
+        setup_full_accounts_and_server().await;
+
+    let notification_service = services.notification_service;
+    let sqs_queue = services.sqs;
+    let address_service = services.address_service;
+
+    // A competing block at 107038 that pays no-one, and a block building on the original 107038.
+    let fork_mock_data = setup_raw_block_mocks(
+        &mock_server,
+        vec![
+            BlockHeader {
+                block_hash: FORK_BLOCK_107038,
+                prev_hash: "000000d3d12016125e10320dc1e2b3a719266c48313c8529d812cd58b190d0d4",
+            },
+            BlockHeader {
+                block_hash: FORK_BLOCK_107039,
+                prev_hash: "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+            },
+        ],
+    );
+    chain_mock_data.blocks.extend(fork_mock_data.blocks);
+
+    // Initializing service with block 107036
+    run_and_test_blockchain_polling_worker(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        "00000049405168aecc9bdc996f2d35ae8f7855685dfd4c6513f68679428cdbfe",
+    )
+    .await;
+    test_queue_message(&notification_service, &sqs_queue, &account.id, 1, true).await;
+
+    // Watch the destination address from mocked block 107038, and advance to it.
+    let fake_registration: AddressAndKeysetId = AddressAndKeysetId::new(
+        "tb1pks5uh06wa9pzn053xh6tnc4euyt5zqmvszu0mfuxgweq6emr05ns3skjp2"
+            .parse()
+            .unwrap(),
+        account.active_keyset_id.clone(),
+    );
+    address_service
+        .clone()
+        .insert(&[fake_registration], &account.id)
+        .await
+        .unwrap();
+    run_and_test_blockchain_polling_worker(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+    )
+    .await;
+    test_queue_message(&notification_service, &sqs_queue, &account.id, 2, false).await;
+
+    // Re-org onto the competing 107038. The original 107038 is orphaned, and looked at again (it's
+    // still cached, so not fetched) to find out which payments it confirmed; since the new chain
+    // doesn't confirm the payment, the account is told it's pending again.
+    run_blockchain_polling_worker_with_tip(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        FORK_BLOCK_107038,
+    )
+    .await;
+    assert_block_hits(
+        &mock_server,
+        &chain_mock_data,
+        &[
+            (
+                "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+                1,
+            ),
+            (FORK_BLOCK_107038, 1),
+            (FORK_BLOCK_107039, 0),
+        ],
+    );
+    test_payment_notifications(&notification_service, &sqs_queue, &account.id, 2, 1).await;
+
+    // Re-org back onto the original 107038. It's stale, so it's treated as a new block, and the
+    // payment it contains is confirmed again.
+    run_blockchain_polling_worker_with_tip(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        FORK_BLOCK_107039,
+    )
+    .await;
+    assert_block_hits(
+        &mock_server,
+        &chain_mock_data,
+        &[
+            (
+                "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+                1,
+            ),
+            (FORK_BLOCK_107038, 1),
+            (FORK_BLOCK_107039, 1),
+        ],
+    );
+    test_payment_notifications(&notification_service, &sqs_queue, &account.id, 3, 1).await;
+
+    // Polling the same tip again finds nothing new, and orphans nothing.
+    run_blockchain_polling_worker_with_tip(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        FORK_BLOCK_107039,
+    )
+    .await;
+    assert_block_hits(
+        &mock_server,
+        &chain_mock_data,
+        &[
+            (
+                "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+                1,
+            ),
+            (FORK_BLOCK_107038, 1),
+            (FORK_BLOCK_107039, 1),
+        ],
+    );
+    test_payment_notifications(&notification_service, &sqs_queue, &account.id, 3, 1).await;
+}
+
+#[tokio::test]
+async fn test_payment_confirmation_depths() {
+    let (mock_server, account, worker, services, mut chain_mock_data) =
+        setup_full_accounts_and_server().await;
+
+    let notification_service = services.notification_service;
+    let sqs_queue = services.sqs;
+    let chain_indexer_service = services.chain_indexer_service;
+
+    // Three more blocks on top of 107038, so that 107036 can get to 6 confirmations.
+    let fork_mock_data = setup_raw_block_mocks(
+        &mock_server,
+        vec![
+            BlockHeader {
+                block_hash: FORK_BLOCK_107039,
+                prev_hash: "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+            },
+            BlockHeader {
+                block_hash: FORK_BLOCK_107040,
+                prev_hash: FORK_BLOCK_107039,
+            },
+            BlockHeader {
+                block_hash: FORK_BLOCK_107041,
+                prev_hash: FORK_BLOCK_107040,
+            },
+        ],
+    );
+    chain_mock_data.blocks.extend(fork_mock_data.blocks);
+
+    // Ask to be notified at every depth.
+    let notifications_preferences = notification_service
+        .fetch_notifications_preferences(FetchNotificationsPreferencesInput {
+            account_id: &account.id,
+        })
+        .await
+        .unwrap();
+    notification_service
+        .update_notifications_preferences(UpdateNotificationsPreferencesInput {
+            account_id: &account.id,
+            notifications_preferences: &NotificationsPreferences {
+                payment_confirmation_depths: Some(HashSet::from([
+                    PaymentConfirmationDepth::One,
+                    PaymentConfirmationDepth::Three,
+                    PaymentConfirmationDepth::Six,
+                ])),
+                ..notifications_preferences
+            },
+            key_proof: None,
+        })
+        .await
+        .unwrap();
+
+    // Initializing service with block 107036, which confirms the payment.
+    run_and_test_blockchain_polling_worker(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        "00000049405168aecc9bdc996f2d35ae8f7855685dfd4c6513f68679428cdbfe",
+    )
+    .await;
+    test_queue_message(&notification_service, &sqs_queue, &account.id, 1, true).await;
+
+    // Store 107037 and 107038 without notifying, as if the job had crashed after storing them.
+    for block_hash in [
+        "000000d3d12016125e10320dc1e2b3a719266c48313c8529d812cd58b190d0d4",
+        "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+    ] {
+        let block: BdkBlock =
+            deserialize(&std::fs::read(format!("src/tests/raw_blocks/{block_hash}.bin")).unwrap())
+                .unwrap();
+        chain_indexer_service.add_block(&block).await.unwrap();
+    }
+
+    // The next run finds no new blocks, but the cursor is still at 107036, so it catches up, and
+    // the payment gets to 3 confirmations. Only the first notification warned about the inactive
+    // keyset.
+    run_blockchain_polling_worker_with_tip(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+    )
+    .await;
+    test_queue_message(&notification_service, &sqs_queue, &account.id, 2, false).await;
+
+    // Three more blocks take the payment to 6 confirmations.
+    run_blockchain_polling_worker_with_tip(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        FORK_BLOCK_107041,
+    )
+    .await;
+    test_queue_message(&notification_service, &sqs_queue, &account.id, 3, false).await;
+
+    // Nothing more to notify.
+    run_blockchain_polling_worker_with_tip(
+        &mock_server,
+        &worker,
+        &mut chain_mock_data,
+        FORK_BLOCK_107041,
+    )
+    .await;
+    test_queue_message(&notification_service, &sqs_queue, &account.id, 3, false).await;
+
+    // Every block was fetched once: the ones stored behind the worker's back when it got to them,
+    // and 107036 was still cached when it got to depths 3 and 6.
+    assert_block_hits(
+        &mock_server,
+        &chain_mock_data,
+        &[
+            (
+                "00000049405168aecc9bdc996f2d35ae8f7855685dfd4c6513f68679428cdbfe",
+                1,
+            ),
+            (
+                "000000d3d12016125e10320dc1e2b3a719266c48313c8529d812cd58b190d0d4",
+                1,
+            ),
+            (
+                "0000012b9852f41934927b43ebac7354b10627f17ebc85e1de6bae593312591a",
+                1,
+            ),
+            (FORK_BLOCK_107039, 1),
+            (FORK_BLOCK_107040, 1),
+            (FORK_BLOCK_107041, 1),
+        ],
+    );
+}
+
 async fn setup_full_accounts_and_server(
 ) -> (MockServer, FullAccount, TestWorker, Services, ChainMockData) {
     let mock_server = MockServer::start();
@@ -200,6 +465,7 @@ async fn setup_full_accounts_and_server(
                 account_security: HashSet::default(),
                 money_movement: HashSet::from([NotificationChannel::Push]),
                 product_marketing: HashSet::new(),
+                ..Default::default()
             },
             false,
             false,
@@ -224,6 +490,7 @@ async fn setup_full_accounts_and_server(
                 account_security: HashSet::default(),
                 money_movement: HashSet::from([NotificationChannel::Push]),
                 product_marketing: HashSet::new(),
+                ..Default::default()
             },
             false,
             false,
@@ -408,6 +675,82 @@ async fn run_and_test_blockchain_polling_worker(
     }
 }
 
+// Serve `tip_hash` as the tip, without the chain bookkeeping `run_and_test_blockchain_polling_worker`
+// does, which assumes the chain only ever grows.
+async fn run_blockchain_polling_worker_with_tip(
+    mock_server: &MockServer,
+    worker: &TestWorker,
+    chain_mock_data: &mut ChainMockData,
+    tip_hash: &'static str,
+) {
+    if let Some(tip_hash_mock_id) = chain_mock_data.tip_hash_mock_id {
+        Mock::new(tip_hash_mock_id, mock_server).delete();
+    }
+    let tip_hash_mock = mock_server.mock(|when, then| {
+        when.method(GET).path("/blocks/tip/hash");
+        then.status(200)
+            .header("content-type", "text/plain")
+            .body(tip_hash);
+    });
+    chain_mock_data.tip_hash_mock_id = Some(tip_hash_mock.id());
+    chain_mock_data.tip_hash = tip_hash;
+    chain_mock_data.expected_tip_hash_hits = 0;
+
+    worker.blockchain_polling().await;
+
+    tip_hash_mock.assert_hits(1);
+}
+
+fn assert_block_hits(
+    mock_server: &MockServer,
+    chain_mock_data: &ChainMockData,
+    expected_hits: &[(&'static str, usize)],
+) {
+    for (block_hash, hits) in expected_hits {
+        let raw_block_mock_id = chain_mock_data.blocks[block_hash].raw_block_mock_id;
+        Mock::new(raw_block_mock_id, mock_server).assert_hits(*hits);
+    }
+}
+
+async fn test_payment_notifications(
+    notification_service: &NotificationService,
+    sqs_queue: &SqsQueue,
+    account_id: &AccountId,
+    confirmed_count: usize,
+    pending_count: usize,
+) {
+    let notifications = notification_service
+        .fetch_customer_for_account(FetchForAccountInput {
+            account_id: account_id.clone(),
+        })
+        .await
+        .unwrap();
+    let count = |payload_type| {
+        notifications
+            .iter()
+            .filter(|n| n.payload_type == payload_type)
+            .count()
+    };
+    assert_eq!(
+        count(NotificationPayloadType::ConfirmedPaymentNotification),
+        confirmed_count,
+        "{:?}",
+        notifications
+    );
+    assert_eq!(
+        count(NotificationPayloadType::PendingPaymentNotification),
+        pending_count,
+        "{:?}",
+        notifications
+    );
+
+    let messages = sqs_queue.fetch_messages("fake_url").await.unwrap();
+    sqs_queue
+        .delete_messages("fake_url", messages)
+        .await
+        .unwrap();
+}
+
 async fn test_queue_message(
     notification_service: &NotificationService,
     sqs_queue: &SqsQueue,
@@ -601,6 +601,7 @@ async fn setup_full_accounts_and_server(
                 account_security: HashSet::default(),
                 money_movement: HashSet::from([NotificationChannel::Push]),
                 product_marketing: HashSet::new(),
+                ..Default::default()
             },
             false,
             false,
@@ -625,6 +626,7 @@ async fn setup_full_accounts_and_server(
                 account_security: HashSet::default(),
                 money_movement: HashSet::from([NotificationChannel::Push]),
                 product_marketing: HashSet::new(),
+                ..Default::default()
             },
             false,
             false,
@@ -18,6 +18,7 @@ mod export_tools_integration_tests;
 mod lib;
 mod mempool_polling_integration_tests;
 mod mobile_pay_tests;
+mod noise_channel_integration_tests;
 mod notification_integration_tests;
 mod onboarding_integration_tests;
 mod privileged_actions_integration_tests;
@@ -0,0 +1,182 @@
+use axum::body::Body;
+use crypto::noise::session::{NoiseSession, SessionPolicy};
+use crypto::noise::{NoiseContext, NoiseRole, PrivateKey};
+use http::{header::CONTENT_TYPE, Method, Request, StatusCode};
+use http_server::middlewares::noise::{self, SESSION_ID_HEADER};
+use recovery::routes::inheritance::UploadInheritancePackagesRequest;
+use types::account::bitcoin::Network;
+use types::account::identifiers::AccountId;
+
+use super::lib::{create_default_account_with_predefined_wallet, create_full_account};
+use super::requests::axum::TestClient;
+use crate::test_utils::AuthenticatedRequest;
+use crate::tests::gen_services;
+
+async fn establish_session(client: &TestClient) -> (String, NoiseSession) {
+    let service = http_server::config::extract::<noise::Config>("test".into())
+        .unwrap()
+        .to_service()
+        .unwrap();
+    let context = NoiseContext::new(
+        NoiseRole::Initiator,
+        PrivateKey::InMemory {
+            secret_bytes: vec![0x33; 32].into(),
+        },
+        Some(service.public_key().to_vec()),
+        None,
+    )
+    .unwrap();
+
+    let (status_code, headers, reply) = client
+        .call_raw(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/noise/handshake")
+                .body(Body::from(context.initiate_handshake().unwrap()))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(context.advance_handshake(reply).unwrap().is_none());
+
+    let session = NoiseSession::from_context(&context, SessionPolicy::default()).unwrap();
+    let session_id = headers
+        .get(SESSION_ID_HEADER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+    assert_eq!(session_id, hex::encode(session.id().as_bytes()));
+
+    (session_id, session)
+}
+
+fn package_upload_request(
+    account_id: &AccountId,
+    session_id: &str,
+    body: Vec<u8>,
+) -> Request<Body> {
+    Request::builder()
+        .method(Method::POST)
+        .uri(format!(
+            "/api/accounts/{account_id}/recovery/inheritance/packages"
+        ))
+        .authenticated(account_id, None, None)
+        .header(CONTENT_TYPE, "application/json")
+        .header(SESSION_ID_HEADER, session_id)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_noise_encrypted_package_upload() {
+    let (mut context, bootstrap) = gen_services().await;
+    let client = TestClient::new(bootstrap.router).await;
+    let (account, _) =
+        create_default_account_with_predefined_wallet(&mut context, &client, &bootstrap.services)
+            .await;
+    let (session_id, mut session) = establish_session(&client).await;
+
+    let request =
+        serde_json::to_vec(&UploadInheritancePackagesRequest { packages: vec![] }).unwrap();
+    let (status_code, _, body) = client
+        .call_raw(package_upload_request(
+            &account.id,
+            &session_id,
+            session.encrypt(&request).unwrap(),
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let response: serde_json::Value = serde_json::from_slice(&session.decrypt(&body).unwrap())
+        .expect("Response should decrypt to JSON");
+    assert_eq!(response, serde_json::json!({}));
+}
+
+#[tokio::test]
+async fn test_noise_session_dropped_after_bad_ciphertext() {
+    let (mut context, bootstrap) = gen_services().await;
+    let client = TestClient::new(bootstrap.router).await;
+    let (account, _) =
+        create_default_account_with_predefined_wallet(&mut context, &client, &bootstrap.services)
+            .await;
+    let (session_id, mut session) = establish_session(&client).await;
+
+    // Unknown sessions are rejected.
+    let (status_code, _, _) = client
+        .call_raw(package_upload_request(
+            &account.id,
+            &"00".repeat(16),
+            session.encrypt(b"{}").unwrap(),
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::UNAUTHORIZED);
+
+    // The server expects the first message on the session, which was spent above.
+    let (status_code, _, _) = client
+        .call_raw(package_upload_request(
+            &account.id,
+            &session_id,
+            session.encrypt(b"{}").unwrap(),
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _, _) = client
+        .call_raw(package_upload_request(
+            &account.id,
+            &session_id,
+            session.encrypt(b"{}").unwrap(),
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_noise_session_bound_to_first_account() {
+    let (mut context, bootstrap) = gen_services().await;
+    let client = TestClient::new(bootstrap.router).await;
+    let (account, _) =
+        create_default_account_with_predefined_wallet(&mut context, &client, &bootstrap.services)
+            .await;
+    let other_account = create_full_account(
+        &mut context,
+        &bootstrap.services,
+        Network::BitcoinSignet,
+        None,
+    )
+    .await;
+    let (session_id, mut session) = establish_session(&client).await;
+
+    let request =
+        serde_json::to_vec(&UploadInheritancePackagesRequest { packages: vec![] }).unwrap();
+    let (status_code, _, body) = client
+        .call_raw(package_upload_request(
+            &account.id,
+            &session_id,
+            session.encrypt(&request).unwrap(),
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+    session.decrypt(&body).unwrap();
+
+    // Another account can't use the session, and doesn't get it dropped either.
+    let (status_code, _, _) = client
+        .call_raw(package_upload_request(
+            &other_account.id,
+            &session_id,
+            b"not a Noise message".to_vec(),
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+
+    let (status_code, _, body) = client
+        .call_raw(package_upload_request(
+            &account.id,
+            &session_id,
+            session.encrypt(&request).unwrap(),
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+    session.decrypt(&body).unwrap();
+}
@@ -9,7 +9,9 @@ use types::account::bitcoin::Network;
 use types::account::entities::TouchpointPlatform;
 use types::account::identifiers::AccountId;
 use types::consent::{Consent, NotificationConsentAction};
-use types::notification::{NotificationCategory, NotificationChannel, NotificationsPreferences};
+use types::notification::{
+    NotificationCategory, NotificationChannel, NotificationsPreferences, PaymentConfirmationDepth,
+};
 
 use crate::tests;
 use crate::tests::gen_services;
@@ -48,6 +50,7 @@ async fn send_test_notification_test(vector: SendTestNotificationTestVector) {
                 account_security: HashSet::from([NotificationChannel::Push]),
                 money_movement: HashSet::default(),
                 product_marketing: HashSet::default(),
+                ..Default::default()
             },
             false,
             false,
@@ -181,6 +184,7 @@ async fn test_notifications_preferences() {
                     NotificationChannel::Sms,
                     NotificationChannel::Email,
                 ]),
+                ..Default::default()
             },
             false,
             false,
@@ -200,6 +204,7 @@ async fn test_notifications_preferences() {
                 NotificationChannel::Sms,
                 NotificationChannel::Email,
             ]),
+            ..Default::default()
         }
     );
     assert_eq!(
@@ -242,6 +247,7 @@ async fn test_notifications_preferences() {
                     NotificationChannel::Sms,
                 ]),
                 product_marketing: HashSet::default(),
+                ..Default::default()
             },
             false,
             false,
@@ -258,6 +264,7 @@ async fn test_notifications_preferences() {
             ]),
             money_movement: HashSet::from([NotificationChannel::Push, NotificationChannel::Sms]),
             product_marketing: HashSet::default(),
+            ..Default::default()
         }
     );
     assert_eq!(
@@ -293,6 +300,7 @@ async fn test_notifications_preferences() {
                     NotificationChannel::Push,
                     NotificationChannel::Sms,
                 ]),
+                ..Default::default()
             },
             false,
             false,
@@ -301,3 +309,66 @@ async fn test_notifications_preferences() {
         .await;
     assert_eq!(set_response.status_code, StatusCode::FORBIDDEN);
 }
+
+#[tokio::test]
+async fn test_notifications_preferences_keep_payment_confirmation_depths() {
+    let (mut context, bootstrap) = gen_services().await;
+    let client = TestClient::new(bootstrap.router).await;
+    let account = create_full_account(
+        &mut context,
+        &bootstrap.services,
+        Network::BitcoinSignet,
+        None,
+    )
+    .await;
+    let keys = context
+        .get_authentication_keys_for_account_id(&account.id)
+        .unwrap();
+    let depths = HashSet::from([PaymentConfirmationDepth::One, PaymentConfirmationDepth::Six]);
+
+    let set_response = client
+        .set_notifications_preferences(
+            &account.id.to_string(),
+            &NotificationsPreferences {
+                payment_confirmation_depths: Some(depths.clone()),
+                ..Default::default()
+            },
+            false,
+            false,
+            &keys,
+        )
+        .await;
+    assert_eq!(set_response.status_code, StatusCode::OK);
+
+    // A client that doesn't know about depths leaves them alone.
+    let set_response = client
+        .set_notifications_preferences(
+            &account.id.to_string(),
+            &NotificationsPreferences {
+                money_movement: HashSet::from([NotificationChannel::Push]),
+                payment_confirmation_depths: None,
+                ..Default::default()
+            },
+            false,
+            false,
+            &keys,
+        )
+        .await;
+    assert_eq!(set_response.status_code, StatusCode::OK);
+    assert_eq!(
+        set_response.body.unwrap().payment_confirmation_depths,
+        Some(depths.clone())
+    );
+
+    let get_response = client
+        .get_notifications_preferences(&account.id.to_string())
+        .await;
+    assert_eq!(
+        get_response.body.unwrap(),
+        NotificationsPreferences {
+            money_movement: HashSet::from([NotificationChannel::Push]),
+            payment_confirmation_depths: Some(depths),
+            ..Default::default()
+        }
+    );
+}
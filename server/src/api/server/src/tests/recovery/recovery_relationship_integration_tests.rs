@@ -447,6 +447,68 @@ tests! {
     },
 }
 
+#[tokio::test]
+async fn test_accept_recovery_relationship_invitation_limits_bad_codes() {
+    let (mut context, bootstrap) = gen_services().await;
+    let client = TestClient::new(bootstrap.router).await;
+
+    let customer_account = create_full_account(
+        &mut context,
+        &bootstrap.services,
+        Network::BitcoinSignet,
+        None,
+    )
+    .await;
+    let tc_account =
+        Account::Lite(create_lite_account(&mut context, &bootstrap.services, None, true).await);
+
+    let create_body = try_create_recovery_relationship(
+        &context,
+        &client,
+        &customer_account.id,
+        &CognitoAuthentication::Wallet {
+            is_app_signed: true,
+            is_hardware_signed: true,
+        },
+        StatusCode::OK,
+        1,
+        0,
+    )
+    .await
+    .unwrap();
+
+    for _ in 0..5 {
+        try_accept_recovery_relationship_invitation(
+            &context,
+            &client,
+            &customer_account.id,
+            tc_account.get_id(),
+            &TrustedContactRole::SocialRecoveryContact,
+            &CognitoAuthentication::Recovery,
+            &create_body.invitation,
+            CodeOverride::Mismatch,
+            StatusCode::BAD_REQUEST,
+            0,
+        )
+        .await;
+    }
+
+    // The invitation is used up, even with the right code.
+    try_accept_recovery_relationship_invitation(
+        &context,
+        &client,
+        &customer_account.id,
+        tc_account.get_id(),
+        &TrustedContactRole::SocialRecoveryContact,
+        &CognitoAuthentication::Recovery,
+        &create_body.invitation,
+        CodeOverride::None,
+        StatusCode::FORBIDDEN,
+        0,
+    )
+    .await;
+}
+
 #[derive(Debug)]
 struct EndorseRecoveryRelationshipTestVector {
     accept_recovery_relationship: bool,
@@ -191,6 +191,20 @@ impl TestClient {
         }
     }
 
+    // For requests and responses whose bodies aren't JSON, such as Noise encrypted ones.
+    pub(crate) async fn call_raw(
+        &self,
+        request: http::Request<Body>,
+    ) -> (http::StatusCode, HeaderMap, Vec<u8>) {
+        let response = self.router.lock().await.call(request).await.unwrap();
+
+        let status_code = response.status();
+        let headers = response.headers().to_owned();
+        let raw_body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status_code, headers, raw_body.to_vec())
+    }
+
     pub(crate) async fn create_account(
         &self,
         context: &mut TestContext,
@@ -35,17 +35,68 @@ pub enum NotificationCategory {
     ProductMarketing,
 }
 
+/// How deep in the chain an incoming payment is when the account is notified of it. An account can
+/// be notified at several depths, as the payment settles.
+#[derive(
+    Deserialize,
+    Serialize,
+    StrumDisplay,
+    Clone,
+    Debug,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    ToSchema,
+    EnumIter,
+)]
+pub enum PaymentConfirmationDepth {
+    #[default]
+    One,
+    Three,
+    Six,
+}
+
+impl PaymentConfirmationDepth {
+    pub fn confirmations(&self) -> u64 {
+        match self {
+            PaymentConfirmationDepth::One => 1,
+            PaymentConfirmationDepth::Three => 3,
+            PaymentConfirmationDepth::Six => 6,
+        }
+    }
+}
+
+fn default_payment_confirmation_depths() -> HashSet<PaymentConfirmationDepth> {
+    HashSet::from([PaymentConfirmationDepth::default()])
+}
+
 #[derive(Clone, Debug)]
 pub struct NotificationsPreferencesDiff {
     pub subscribes: Vec<(NotificationCategory, NotificationChannel)>,
     pub unsubscribes: Vec<(NotificationCategory, NotificationChannel)>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default, ToSchema)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
 pub struct NotificationsPreferences {
     pub account_security: HashSet<NotificationChannel>,
     pub money_movement: HashSet<NotificationChannel>,
     pub product_marketing: HashSet<NotificationChannel>,
+    /// Left unchanged when absent from an update, for clients that predate it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_confirmation_depths: Option<HashSet<PaymentConfirmationDepth>>,
+}
+
+impl Default for NotificationsPreferences {
+    fn default() -> Self {
+        Self {
+            account_security: HashSet::new(),
+            money_movement: HashSet::new(),
+            product_marketing: HashSet::new(),
+            payment_confirmation_depths: Some(default_payment_confirmation_depths()),
+        }
+    }
 }
 
 impl NotificationsPreferences {
@@ -91,6 +142,8 @@ pub struct NotificationsPreferencesState {
     pub account_security: HashSet<NotificationChannel>,
     pub money_movement: HashSet<NotificationChannel>,
     pub product_marketing: HashSet<NotificationChannel>,
+    #[serde(default = "default_payment_confirmation_depths")]
+    pub payment_confirmation_depths: HashSet<PaymentConfirmationDepth>,
     #[serde(default = "OffsetDateTime::now_utc")]
     pub email_updated_at: OffsetDateTime,
 }
@@ -101,6 +154,7 @@ impl Default for NotificationsPreferencesState {
             account_security: HashSet::new(),
             money_movement: HashSet::new(),
             product_marketing: HashSet::new(),
+            payment_confirmation_depths: default_payment_confirmation_depths(),
             email_updated_at: OffsetDateTime::now_utc(),
         }
     }
@@ -161,6 +215,10 @@ impl NotificationsPreferencesState {
             account_security: notifications_preferences.account_security.clone(),
             money_movement: notifications_preferences.money_movement.clone(),
             product_marketing: notifications_preferences.product_marketing.clone(),
+            payment_confirmation_depths: notifications_preferences
+                .payment_confirmation_depths
+                .clone()
+                .unwrap_or_else(|| self.payment_confirmation_depths.clone()),
             email_updated_at: if self.get_email_notification_categories()
                 != notifications_preferences.get_email_notification_categories()
             {
@@ -210,6 +268,7 @@ impl From<NotificationsPreferencesState> for NotificationsPreferences {
             account_security: state.account_security,
             money_movement: state.money_movement,
             product_marketing: state.product_marketing,
+            payment_confirmation_depths: Some(state.payment_confirmation_depths),
         }
     }
 }
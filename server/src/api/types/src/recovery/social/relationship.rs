@@ -94,6 +94,10 @@ pub struct RecoveryRelationshipInvitation {
     // The enrollment fields are thrown away once the customer has
     // endorsed the Trusted Contact
     pub protected_customer_enrollment_pake_pubkey: String,
+    // Wrong codes presented for this invitation. The code carries part of the PAKE password, so
+    // this bounds online guessing regardless of what the app's attempt limiter does.
+    #[serde(default)]
+    pub failed_code_attempts: u32,
 }
 
 impl RecoveryRelationshipInvitation {
@@ -106,6 +110,7 @@ impl RecoveryRelationshipInvitation {
             code: code.to_owned(),
             code_bit_length,
             expires_at: expires_at.to_owned(),
+            failed_code_attempts: 0,
         }
     }
 
@@ -166,6 +171,7 @@ impl RecoveryRelationship {
             expires_at: expires_at.to_owned(),
             protected_customer_enrollment_pake_pubkey: protected_customer_enrollment_pake_pubkey
                 .to_owned(),
+            failed_code_attempts: 0,
         })
     }
 
@@ -187,6 +193,7 @@ impl RecoveryRelationship {
                 protected_customer_enrollment_pake_pubkey: invitation
                     .protected_customer_enrollment_pake_pubkey
                     .to_owned(),
+                failed_code_attempts: invitation.failed_code_attempts,
             }),
             Self::Unendorsed(connection) => Self::Unendorsed(RecoveryRelationshipUnendorsed {
                 common_fields: common_fields.to_owned(),
@@ -1,6 +1,9 @@
-use bdk_utils::bdk::bitcoin::Address;
+use bdk_utils::bdk::bitcoin::{Address, Block, BlockHash};
+use chain_indexer::{entities::ChainUpdate, ChainIndexerError};
 use itertools::Itertools;
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
+use strum::IntoEnumIterator;
+use types::notification::PaymentConfirmationDepth;
 
 use bdk_utils::bdk::bitcoin::address::NetworkUnchecked;
 use tracing::{event, instrument, Level};
@@ -29,24 +32,158 @@ pub async fn handler(state: &WorkerState, sleep_duration_seconds: u64) -> Result
 pub async fn run_once(state: &WorkerState) -> Result<(), WorkerError> {
     event!(Level::INFO, "Starting blockchain polling job");
 
-    let blocks = state.chain_indexer_service.get_new_blocks().await?;
+    let ChainUpdate { blocks, reorg } = state.chain_indexer_service.get_new_blocks().await?;
     if blocks.is_empty() {
         event!(Level::INFO, "No new blocks detected");
-        return Ok(());
+    } else {
+        event!(Level::INFO, "{} blocks found", blocks.len());
+    }
+
+    // Where notifications got up to. The first time round, start from the new blocks.
+    let (cursor_hash, cursor_height) = match state.chain_indexer_service.get_cursor().await? {
+        Some(cursor) => (cursor.tip_hash, cursor.tip_height),
+        None => match blocks.first() {
+            Some(block) => (
+                block.header.prev_blockhash,
+                block
+                    .bip34_block_height()
+                    .map_err(ChainIndexerError::from)?
+                    - 1,
+            ),
+            None => return Ok(()),
+        },
+    };
+
+    if let Some(reorg) = &reorg {
+        state
+            .chain_indexer_service
+            .remove_orphaned_blocks(reorg)
+            .await?;
+        event!(
+            Level::INFO,
+            "{} blocks orphaned by a re-org of depth {}",
+            reorg.orphaned.len(),
+            reorg.depth
+        );
     }
-    event!(Level::INFO, "{} blocks found", blocks.len());
-    // We update state here to avoid sending duplicate notifications if the job crashes,
-    // however, this could result in missed notifications in that case. We plan on updating
-    // this job with a cursor so that it can resume where it left off.
+    // Blocks are stored before their notifications are sent, and the cursor only moves past a
+    // block once they have been, so if the job crashes in between they're sent on the next run.
     for block in &blocks {
         state.chain_indexer_service.add_block(block).await?;
     }
     event!(Level::INFO, "{} blocks added", blocks.len());
 
-    let addresses: Vec<Address<NetworkUnchecked>> = blocks
+    if let Some(reorg) = reorg {
+        // Payments that were confirmed in an orphaned block, and haven't been confirmed again in
+        // the new chain, are back to pending: tell their owners so. Payments that have been
+        // confirmed again are notified below along with everything else.
+        let addresses = addresses_in_blocks(state, &blocks);
+        let unconfirmed_addresses: Vec<_> = addresses_in_blocks(state, &reorg.orphaned_blocks)
+            .into_iter()
+            .filter(|address| !addresses.contains(address))
+            .collect();
+        event!(
+            Level::INFO,
+            "{} addresses had payments unconfirmed by the re-org",
+            unconfirmed_addresses.len()
+        );
+        notify_customers_with_addresses(
+            state,
+            unconfirmed_addresses,
+            PaymentNotificationType::Pending,
+            None,
+        )
+        .await?;
+    }
+
+    notify_confirmations(state, cursor_hash, cursor_height).await?;
+    event!(Level::INFO, "Ending blockchain polling job");
+    Ok(())
+}
+
+// Walk the cursor up to the tip one block at a time. Each new tip takes the blocks below it one
+// confirmation deeper, so send the notifications for every depth they've reached.
+async fn notify_confirmations(
+    state: &WorkerState,
+    cursor_hash: BlockHash,
+    cursor_height: u64,
+) -> Result<(), WorkerError> {
+    // If a re-org orphaned the cursor's block, notifications start again from the fork point.
+    let (mut fork_hash, mut fork_height) = (cursor_hash, cursor_height);
+    while let Some(block) = state
+        .chain_indexer_service
+        .get_stored_block(fork_hash)
+        .await?
+        .filter(|block| block.stale)
+    {
+        fork_hash = block.prev_hash;
+        fork_height = block.height - 1;
+    }
+
+    let max_confirmations = PaymentConfirmationDepth::iter()
+        .map(|depth| depth.confirmations())
+        .max()
+        .unwrap_or(1);
+    let chain: BTreeMap<_, _> = state
+        .chain_indexer_service
+        .get_best_chain_above(fork_height.saturating_sub(max_confirmations))
+        .await?
         .into_iter()
-        .flat_map(|block| block.txdata)
-        .flat_map(|transaction| transaction.output)
+        .map(|block| (block.height, block))
+        .collect();
+    let Some(&tip_height) = chain.keys().last() else {
+        return Ok(());
+    };
+
+    for height in fork_height + 1..=tip_height {
+        let Some(tip) = chain.get(&height) else {
+            continue;
+        };
+
+        for depth in PaymentConfirmationDepth::iter() {
+            let Some(confirmed_height) = (height + 1).checked_sub(depth.confirmations()) else {
+                continue;
+            };
+            // Blocks up to the fork point were already taken this deep by the orphaned chain.
+            if confirmed_height <= fork_height && height <= cursor_height {
+                continue;
+            }
+            let Some(confirmed_block) = chain.get(&confirmed_height) else {
+                continue;
+            };
+
+            let block = state
+                .chain_indexer_service
+                .get_block(&confirmed_block.block_hash)
+                .await?;
+            let addresses = addresses_in_blocks(state, std::slice::from_ref(&block));
+            event!(
+                Level::INFO,
+                "{} addresses found in block {} at {} confirmations",
+                addresses.len(),
+                confirmed_block.block_hash,
+                depth.confirmations()
+            );
+            notify_customers_with_addresses(
+                state,
+                addresses,
+                PaymentNotificationType::Confirmed(depth),
+                None,
+            )
+            .await?;
+        }
+
+        state.chain_indexer_service.set_cursor(tip).await?;
+    }
+
+    Ok(())
+}
+
+fn addresses_in_blocks(state: &WorkerState, blocks: &[Block]) -> Vec<Address<NetworkUnchecked>> {
+    blocks
+        .iter()
+        .flat_map(|block| &block.txdata)
+        .flat_map(|transaction| &transaction.output)
         .unique()
         .filter_map(|output| {
             if output.script_pubkey.is_op_return() {
@@ -76,10 +213,5 @@ pub async fn run_once(state: &WorkerState) -> Result<(), WorkerError> {
             }
         })
         .unique()
-        .collect();
-    event!(Level::INFO, "{} addresses found in blocks", addresses.len());
-    notify_customers_with_addresses(state, addresses, PaymentNotificationType::Confirmed, None)
-        .await?;
-    event!(Level::INFO, "Ending blockchain polling job");
-    Ok(())
+        .collect()
 }
@@ -13,6 +13,7 @@ use notification::{
     NotificationPayloadBuilder, NotificationPayloadType,
 };
 use types::account::identifiers::{AccountId, KeysetId};
+use types::notification::PaymentConfirmationDepth;
 
 use bdk_utils::bdk::bitcoin::address::NetworkUnchecked;
 use tracing::{event, Level};
@@ -22,14 +23,16 @@ use crate::{error::WorkerError, jobs::WorkerState};
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum PaymentNotificationType {
     Pending,
-    Confirmed,
+    Confirmed(PaymentConfirmationDepth),
 }
 
 impl Display for PaymentNotificationType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             PaymentNotificationType::Pending => write!(f, "pending"),
-            PaymentNotificationType::Confirmed => write!(f, "confirmed"),
+            PaymentNotificationType::Confirmed(depth) => {
+                write!(f, "confirmed ({} confirmations)", depth.confirmations())
+            }
         }
     }
 }
@@ -38,7 +41,7 @@ impl From<PaymentNotificationType> for NotificationPayloadType {
     fn from(value: PaymentNotificationType) -> Self {
         match value {
             PaymentNotificationType::Pending => NotificationPayloadType::PendingPaymentNotification,
-            PaymentNotificationType::Confirmed => {
+            PaymentNotificationType::Confirmed(_) => {
                 NotificationPayloadType::ConfirmedPaymentNotification
             }
         }
@@ -96,19 +99,54 @@ fn filter_accounts_by_flag(
         .collect()
 }
 
+// An account to notify of a confirmed payment.
+struct ConfirmedPaymentAccount {
+    active_keyset_id: KeysetId,
+    // Whether the payment's depth is the lowest, and the highest, the account is notified at.
+    is_first_depth: bool,
+    is_settled: bool,
+}
+
+// How a payment notification is worded for one account.
+#[derive(Clone, Copy, Default)]
+struct PaymentNotificationDetails {
+    // Only warned about once per payment, with the account's first notification for it.
+    is_addressed_to_inactive_keyset: bool,
+    is_settled: bool,
+}
+
+// For confirmed payments, this also leaves out accounts that don't want to be notified at the
+// payment's confirmation depth.
 async fn fetch_active_keysets(
     state: &WorkerState,
     accounts: &[AccountIdAndKeysetId],
     notification_type: PaymentNotificationType,
-) -> Result<HashMap<AccountId, KeysetId>, WorkerError> {
-    if matches!(notification_type, PaymentNotificationType::Confirmed) {
+) -> Result<HashMap<AccountId, ConfirmedPaymentAccount>, WorkerError> {
+    if let PaymentNotificationType::Confirmed(depth) = notification_type {
         let account_ids: Vec<_> = accounts.iter().map(|v| v.account_id.clone()).collect();
         Ok(state
             .account_service
             .fetch_full_accounts_by_account_ids(account_ids)
             .await?
             .into_iter()
-            .map(|(account_id, account)| (account_id, account.active_keyset_id))
+            .filter_map(|(account_id, account)| {
+                let depths = &account
+                    .common_fields
+                    .notifications_preferences_state
+                    .payment_confirmation_depths;
+                if !depths.contains(&depth) {
+                    return None;
+                }
+                let confirmations = depths.iter().map(|d| d.confirmations());
+                Some((
+                    account_id,
+                    ConfirmedPaymentAccount {
+                        active_keyset_id: account.active_keyset_id,
+                        is_first_depth: confirmations.clone().min() == Some(depth.confirmations()),
+                        is_settled: confirmations.max() == Some(depth.confirmations()),
+                    },
+                ))
+            })
             .collect())
     } else {
         Ok(HashMap::new())
@@ -117,24 +155,23 @@ async fn fetch_active_keysets(
 
 fn pair_accounts_with_keyset_info(
     accounts: Vec<AccountIdAndKeysetId>,
-    active_keysets: HashMap<AccountId, KeysetId>,
+    confirmed_accounts: HashMap<AccountId, ConfirmedPaymentAccount>,
     notification_type: PaymentNotificationType,
-) -> Vec<(AccountIdAndKeysetId, bool)> {
+) -> Vec<(AccountIdAndKeysetId, PaymentNotificationDetails)> {
     accounts
         .into_iter()
         .filter_map(|v| {
-            let account_id = &v.account_id;
-            let is_addressed_to_inactive_keyset =
-                if matches!(notification_type, PaymentNotificationType::Confirmed) {
-                    if let Some(active_keyset_id) = active_keysets.get(account_id) {
-                        v.spending_keyset_id != *active_keyset_id
-                    } else {
-                        return None;
-                    }
-                } else {
-                    false
-                };
-            Some((v, is_addressed_to_inactive_keyset))
+            let details = if matches!(notification_type, PaymentNotificationType::Confirmed(_)) {
+                let account = confirmed_accounts.get(&v.account_id)?;
+                PaymentNotificationDetails {
+                    is_addressed_to_inactive_keyset: account.is_first_depth
+                        && v.spending_keyset_id != account.active_keyset_id,
+                    is_settled: account.is_settled,
+                }
+            } else {
+                PaymentNotificationDetails::default()
+            };
+            Some((v, details))
         })
         .collect()
 }
@@ -165,19 +202,16 @@ pub async fn notify_customers_with_addresses(
     }
 
     // Fetch active keysets for accounts
-    let active_keyset_id_by_account =
+    let confirmed_accounts =
         fetch_active_keysets(state, &accounts_to_notify, notification_type).await?;
 
     // Pair accounts with keyset information
-    let accounts_to_notify_with_keyset_info = pair_accounts_with_keyset_info(
-        accounts_to_notify,
-        active_keyset_id_by_account,
-        notification_type,
-    );
+    let accounts_to_notify_with_keyset_info =
+        pair_accounts_with_keyset_info(accounts_to_notify, confirmed_accounts, notification_type);
 
     let num_notifications_to_inactive_keysets = accounts_to_notify_with_keyset_info
         .iter()
-        .filter(|(_, is_addressed_to_inactive_keyset)| *is_addressed_to_inactive_keyset)
+        .filter(|(_, details)| details.is_addressed_to_inactive_keyset)
         .count();
     event!(
         Level::INFO,
@@ -190,12 +224,12 @@ pub async fn notify_customers_with_addresses(
     // Send notifications
     let mut futures = accounts_to_notify_with_keyset_info
         .iter()
-        .map(|(v, is_addressed_to_inactive_keyset)| {
+        .map(|(v, details)| {
             send_new_tx_notifications(
                 &v.account_id,
                 &state.notification_service,
                 notification_type,
-                *is_addressed_to_inactive_keyset,
+                *details,
             )
         })
         .collect::<FuturesUnordered<_>>();
@@ -213,7 +247,7 @@ async fn send_new_tx_notifications(
     account_id: &AccountId,
     service: &NotificationService,
     notification_type: PaymentNotificationType,
-    is_addressed_to_inactive_keyset: bool,
+    details: PaymentNotificationDetails,
 ) -> Result<(), WorkerError> {
     event!(Level::INFO, "Sending notification for account {account_id}");
     let mut builder = NotificationPayloadBuilder::default();
@@ -221,15 +255,16 @@ async fn send_new_tx_notifications(
         PaymentNotificationType::Pending => {
             builder.pending_payment_payload(Some(PendingPaymentPayload {
                 account_id: account_id.clone(),
-                is_addressed_to_inactive_keyset,
+                is_addressed_to_inactive_keyset: details.is_addressed_to_inactive_keyset,
             }))
         }
-        PaymentNotificationType::Confirmed => {
-            builder.confirmed_payment_payload(Some(ConfirmedPaymentPayload {
+        PaymentNotificationType::Confirmed(confirmation_depth) => builder
+            .confirmed_payment_payload(Some(ConfirmedPaymentPayload {
                 account_id: account_id.clone(),
-                is_addressed_to_inactive_keyset,
-            }))
-        }
+                is_addressed_to_inactive_keyset: details.is_addressed_to_inactive_keyset,
+                confirmation_depth,
+                is_settled: details.is_settled,
+            })),
     }
     .build()?;
     service
@@ -25,6 +25,13 @@ pub struct CustomerKeyShare {
     /// The bitcoin network type to be used with the customer's root key share
     #[serde(default)]
     pub network: Option<Network>,
+    /// Base64-encoded ciphertext of refreshed share details that are awaiting the App's equality
+    /// check. Promoted to `share_details_ciphertext` once the share refresh completes.
+    #[serde(default)]
+    pub pending_share_details_ciphertext: Option<String>,
+    /// Base64-encoded nonce used to encrypt/decrypt the pending share details
+    #[serde(default)]
+    pub pending_share_details_nonce: Option<String>,
 }
 
 impl CustomerKeyShare {
@@ -43,6 +50,8 @@ impl CustomerKeyShare {
             dek_id,
             aggregate_public_key,
             network: Some(network),
+            pending_share_details_ciphertext: None,
+            pending_share_details_nonce: None,
         }
     }
 }
@@ -6,6 +6,7 @@ use serde_dynamo::{from_item, to_item};
 use tracing::instrument;
 use wsm_common::bitcoin::Network;
 use wsm_common::messages::DomainFactoredXpub;
+use wsm_common::spend_policy::SignedSpendPolicy;
 
 /// Struct representing the customer's root key. We use the data in this struct to derive subsequent
 /// child BIP32 xprvs. Customers and `CustomerKey` have a bijective relationship.
@@ -31,6 +32,10 @@ pub struct CustomerKey {
     #[serde(default)]
     /// Signature over the server public key using the WSM integrity key
     pub integrity_signature: Option<String>,
+    /// The spend policy `key_ciphertext` is bound to. Must be replaced together with
+    /// `key_ciphertext` and `key_nonce`, since the key can only be unwrapped with this policy.
+    #[serde(default)]
+    pub spend_policy: Option<SignedSpendPolicy>,
 }
 
 impl CustomerKey {
@@ -53,6 +58,7 @@ impl CustomerKey {
             xpubs,
             network: Some(network),
             integrity_signature: Some(integrity_signature),
+            spend_policy: None,
         }
     }
 }
@@ -6,7 +6,7 @@ use aws_types::SdkConfig;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use log::{log, Level};
 use reqwest::Response;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json;
 use thiserror::Error;
 use tracing::{event, instrument};
@@ -15,20 +15,31 @@ use wsm_common::enclave_log::LogBuffer;
 use wsm_common::messages::api::AttestationDocResponse;
 use wsm_common::messages::enclave::{
     DerivedKey, EnclaveContinueDistributedKeygenRequest, EnclaveContinueDistributedKeygenResponse,
+    EnclaveContinueShareRefreshRequest, EnclaveContinueShareRefreshResponse,
     EnclaveCreateKeyRequest, EnclaveDeriveKeyRequest, EnclaveInitiateDistributedKeygenRequest,
-    EnclaveInitiateDistributedKeygenResponse, LoadIntegrityKeyRequest,
+    EnclaveInitiateDistributedKeygenResponse, EnclaveInitiateShareRefreshRequest,
+    EnclaveInitiateShareRefreshResponse, EnclaveSetSpendPolicyRequest,
+    EnclaveSetSpendPolicyResponse, LoadIntegrityKeyRequest,
 };
 use wsm_common::messages::{
-    api::SignedPsbt,
-    enclave::{CreatedKey, EnclaveSignRequest, KmsRequest, LoadSecretRequest},
+    api::{SignedMessage, SignedPsbt},
+    enclave::{
+        CreatedKey, EnclaveSignMessageRequest, EnclaveSignRequest, KmsRequest, LoadSecretRequest,
+    },
     SecretRequest,
 };
+use wsm_common::psbt_validation::PsbtValidationError;
 
 use crate::dependencies::enclave_client::EnclaveClientError::CredentialProviderError;
 use crate::{DekStore, Settings};
 
 const PROD_WRAPPED_INTEGRITY_KEY_B64: &str = include_str!("../../../keys/prod_integrity_key.b64");
 
+#[derive(Deserialize)]
+struct EnclavePsbtValidationErrorResponse {
+    psbt_validation_error: PsbtValidationError,
+}
+
 #[derive(Error, Debug)]
 pub enum EnclaveClientError {
     #[error("Could not load kms credentials: {0}")]
@@ -136,6 +147,36 @@ impl EnclaveClient {
         Ok(result.json().await?)
     }
 
+    #[instrument(skip(self))]
+    pub async fn initiate_share_refresh(
+        &self,
+        req: EnclaveInitiateShareRefreshRequest,
+    ) -> anyhow::Result<EnclaveInitiateShareRefreshResponse> {
+        let result = self
+            .post_request_with_dek(SecretRequest::new(
+                "initiate-share-refresh",
+                req.dek_id.clone(),
+                req,
+            ))
+            .await?;
+        Ok(result.json().await?)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn continue_share_refresh(
+        &self,
+        req: EnclaveContinueShareRefreshRequest,
+    ) -> anyhow::Result<EnclaveContinueShareRefreshResponse> {
+        let result = self
+            .post_request_with_dek(SecretRequest::new(
+                "continue-share-refresh",
+                req.dek_id.clone(),
+                req,
+            ))
+            .await?;
+        Ok(result.json().await?)
+    }
+
     #[instrument(skip(self))]
     pub async fn derive_key(&self, req: EnclaveDeriveKeyRequest) -> anyhow::Result<DerivedKey> {
         let result = self
@@ -152,6 +193,32 @@ impl EnclaveClient {
         Ok(result.json().await?)
     }
 
+    #[instrument(skip(self))]
+    pub async fn sign_message(
+        &self,
+        req: EnclaveSignMessageRequest,
+    ) -> anyhow::Result<SignedMessage> {
+        let result = self
+            .post_request_with_dek(SecretRequest::new("sign-message", req.dek_id.clone(), req))
+            .await?;
+        Ok(result.json().await?)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn set_spend_policy(
+        &self,
+        req: EnclaveSetSpendPolicyRequest,
+    ) -> anyhow::Result<EnclaveSetSpendPolicyResponse> {
+        let result = self
+            .post_request_with_dek(SecretRequest::new(
+                "set-spend-policy",
+                req.dek_id.clone(),
+                req,
+            ))
+            .await?;
+        Ok(result.json().await?)
+    }
+
     #[instrument(skip(self))]
     pub async fn attestation_doc(&self) -> anyhow::Result<AttestationDocResponse> {
         let result = self
@@ -210,7 +277,16 @@ impl EnclaveClient {
                 return Ok(res);
             } else {
                 match res.text().await {
-                    Ok(v) => bail!("Error from the enclave: {}", v),
+                    Ok(v) => {
+                        // PSBT validation failures are the caller's fault; pass them through typed
+                        // so the API can say what was wrong with the PSBT.
+                        if let Ok(response) =
+                            serde_json::from_str::<EnclavePsbtValidationErrorResponse>(&v)
+                        {
+                            return Err(response.psbt_validation_error.into());
+                        }
+                        bail!("Error from the enclave: {}", v)
+                    }
                     Err(e) => bail!("Error from the enclave: {}", e),
                 }
             }
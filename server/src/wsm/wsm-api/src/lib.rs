@@ -26,15 +26,20 @@ use wsm_common::bitcoin::bip32::DerivationPath;
 use wsm_common::derivation::WSMSupportedDomain;
 use wsm_common::messages::api::{
     AttestationDocResponse, ContinueDistributedKeygenRequest, ContinueDistributedKeygenResponse,
-    CreateRootKeyRequest, CreatedSigningKey, GenerateIntegrityKeyResponse, GetIntegritySigRequest,
+    ContinueShareRefreshRequest, ContinueShareRefreshResponse, CreateRootKeyRequest,
+    CreatedSigningKey, GenerateIntegrityKeyResponse, GetIntegritySigRequest,
     GetIntegritySigResponse, InitiateDistributedKeygenRequest, InitiateDistributedKeygenResponse,
-    SignPsbtRequest, SignedPsbt,
+    InitiateShareRefreshRequest, InitiateShareRefreshResponse, SetSpendPolicyRequest,
+    SetSpendPolicyResponse, SignMessageRequest, SignPsbtRequest, SignedMessage, SignedPsbt,
 };
 use wsm_common::messages::enclave::{
-    EnclaveContinueDistributedKeygenRequest, EnclaveCreateKeyRequest, EnclaveDeriveKeyRequest,
-    EnclaveInitiateDistributedKeygenRequest, EnclaveSignRequest,
+    EnclaveContinueDistributedKeygenRequest, EnclaveContinueShareRefreshRequest,
+    EnclaveCreateKeyRequest, EnclaveDeriveKeyRequest, EnclaveFrostKeySpendRequest,
+    EnclaveInitiateDistributedKeygenRequest, EnclaveInitiateShareRefreshRequest,
+    EnclaveSetSpendPolicyRequest, EnclaveSignMessageRequest, EnclaveSignRequest,
 };
 use wsm_common::messages::DomainFactoredXpub;
+use wsm_common::psbt_validation::PsbtValidationError;
 
 use crate::dependencies::customer_key_store::{CustomerKey, CustomerKeyStore};
 use crate::dependencies::ddb;
@@ -62,6 +67,8 @@ impl From<RouteState> for Router {
             .route("/health-check", get(health_check))
             .route("/create-key", post(create_key))
             .route("/sign-psbt", post(sign_psbt))
+            .route("/sign-message", post(sign_message))
+            .route("/set-spend-policy", post(set_spend_policy))
             .route("/integrity-sig", get(integrity_sig))
             .route("/generate-integrity-key", get(generate_integrity_key))
             .route("/attestation-doc", get(attestation_doc))
@@ -73,6 +80,8 @@ impl From<RouteState> for Router {
                 "/continue-distributed-keygen",
                 post(continue_distributed_keygen),
             )
+            .route("/initiate-share-refresh", post(initiate_share_refresh))
+            .route("/continue-share-refresh", post(continue_share_refresh))
             .with_state(state)
     }
 }
@@ -87,18 +96,26 @@ enum ApiError {
     NotFound(String),
     #[error("Server Error: {0}")]
     ServerError(String),
+    #[error("Invalid PSBT: {0}")]
+    InvalidPsbt(PsbtValidationError),
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
-        let (status, message) = match self {
-            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
-            ApiError::ServerError(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
+        let body = match &self {
+            ApiError::InvalidPsbt(psbt_validation_error) => Json(json!({
+                "error": psbt_validation_error.to_string(),
+                "psbt_validation_error": psbt_validation_error,
+            })),
+            ApiError::NotFound(message) | ApiError::ServerError(message) => Json(json!({
+                "error": message,
+            })),
+        };
+        let status = match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::InvalidPsbt(_) => StatusCode::BAD_REQUEST,
         };
-
-        let body = Json(json!({
-            "error": message,
-        }));
 
         (status, body).into_response()
     }
@@ -160,6 +177,7 @@ async fn create_key(
                 key_nonce: new_key.wrapped_xprv_nonce.clone(),
                 derivation_path: DerivationPath::from(spend_domain),
                 network: Some(request.network),
+                spend_policy: None,
             };
 
             let spend_key = enclave_client
@@ -286,9 +304,108 @@ async fn continue_distributed_keygen(
     }
 }
 
-#[instrument(skip(customer_key_store, enclave_client))]
+#[instrument(err, skip(customer_key_share_store, enclave_client))]
+async fn initiate_share_refresh(
+    State(customer_key_share_store): State<CustomerKeyShareStore>,
+    State(enclave_client): State<Arc<EnclaveClient>>,
+    Json(request): Json<InitiateShareRefreshRequest>,
+) -> Result<Json<InitiateShareRefreshResponse>, ApiError> {
+    let root_key_id = &request.root_key_id;
+    match customer_key_share_store
+        .get_customer_key_share(root_key_id)
+        .await
+        .map_err(|e| {
+            ApiError::ServerError(format!("Could not read customer key shares DDB table: {e}"))
+        })? {
+        Some(mut cks) => {
+            let enclave_request = EnclaveInitiateShareRefreshRequest {
+                root_key_id: root_key_id.clone(),
+                dek_id: cks.dek_id.clone(),
+                network: request.network,
+                wrapped_share_details: cks.share_details_ciphertext.clone(),
+                wrapped_share_details_nonce: cks.share_details_nonce.clone(),
+                sealed_request: request.sealed_request,
+            };
+            let enclave_response = enclave_client
+                .initiate_share_refresh(enclave_request)
+                .await
+                .map_err(|e| {
+                    ApiError::ServerError(format!("Error initiating share refresh: {e}"))
+                })?;
+
+            // The refreshed share isn't usable until the App confirms it derived the same key
+            // commitments, so we keep signing with the current one until then.
+            cks.pending_share_details_ciphertext = Some(enclave_response.wrapped_share_details);
+            cks.pending_share_details_nonce = Some(enclave_response.wrapped_share_details_nonce);
+            customer_key_share_store
+                .put_customer_key_share(&cks)
+                .await
+                .map_err(|e| ApiError::ServerError(e.to_string()))?;
+
+            Ok(Json(InitiateShareRefreshResponse {
+                root_key_id: root_key_id.clone(),
+                sealed_response: enclave_response.sealed_response,
+            }))
+        }
+        None => Err(ApiError::NotFound(format!(
+            "Customer key share {root_key_id} not found"
+        ))),
+    }
+}
+
+#[instrument(err, skip(customer_key_share_store, enclave_client))]
+async fn continue_share_refresh(
+    State(customer_key_share_store): State<CustomerKeyShareStore>,
+    State(enclave_client): State<Arc<EnclaveClient>>,
+    Json(request): Json<ContinueShareRefreshRequest>,
+) -> Result<Json<ContinueShareRefreshResponse>, ApiError> {
+    let root_key_id = &request.root_key_id;
+    let mut cks = customer_key_share_store
+        .get_customer_key_share(root_key_id)
+        .await
+        .map_err(|e| {
+            ApiError::ServerError(format!("Could not read customer key shares DDB table: {e}"))
+        })?
+        .ok_or_else(|| ApiError::NotFound(format!("Customer key share {root_key_id} not found")))?;
+
+    let (Some(pending_ciphertext), Some(pending_nonce)) = (
+        cks.pending_share_details_ciphertext.take(),
+        cks.pending_share_details_nonce.take(),
+    ) else {
+        return Err(ApiError::NotFound(format!(
+            "No share refresh in progress for customer key share {root_key_id}"
+        )));
+    };
+
+    let enclave_request = EnclaveContinueShareRefreshRequest {
+        root_key_id: root_key_id.clone(),
+        dek_id: cks.dek_id.clone(),
+        network: request.network,
+        wrapped_share_details: pending_ciphertext.clone(),
+        wrapped_share_details_nonce: pending_nonce.clone(),
+        sealed_request: request.sealed_request,
+    };
+    enclave_client
+        .continue_share_refresh(enclave_request)
+        .await
+        .map_err(|e| ApiError::ServerError(format!("Error continuing share refresh: {e}")))?;
+
+    cks.share_details_ciphertext = pending_ciphertext;
+    cks.share_details_nonce = pending_nonce;
+    customer_key_share_store
+        .put_customer_key_share(&cks)
+        .await
+        .map_err(|e| ApiError::ServerError(e.to_string()))?;
+
+    Ok(Json(ContinueShareRefreshResponse {
+        root_key_id: root_key_id.clone(),
+    }))
+}
+
+#[instrument(skip(customer_key_store, customer_key_share_store, enclave_client))]
 async fn sign_psbt(
     State(customer_key_store): State<CustomerKeyStore>,
+    State(customer_key_share_store): State<CustomerKeyShareStore>,
     State(enclave_client): State<Arc<EnclaveClient>>,
     Json(request): Json<SignPsbtRequest>,
 ) -> Result<Json<SignedPsbt>, ApiError> {
@@ -297,6 +414,27 @@ async fn sign_psbt(
     let change_descriptor = &request.change_descriptor;
     let psbt = &request.psbt;
 
+    let frost_key_spend = if request.frost_commitments.is_empty() {
+        None
+    } else {
+        let cks = customer_key_share_store
+            .get_customer_key_share(root_key_id)
+            .await
+            .map_err(|e| {
+                ApiError::ServerError(format!("Could not read customer key shares DDB table: {e}"))
+            })?
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("Customer key share {root_key_id} not found"))
+            })?;
+        Some(EnclaveFrostKeySpendRequest {
+            dek_id: cks.dek_id,
+            network: cks.network,
+            wrapped_share_details: cks.share_details_ciphertext,
+            wrapped_share_details_nonce: cks.share_details_nonce,
+            app_commitments: request.frost_commitments.clone(),
+        })
+    };
+
     match customer_key_store
         .get_customer_key(root_key_id)
         .await
@@ -313,14 +451,19 @@ async fn sign_psbt(
                 change_descriptor: change_descriptor.to_string(),
                 psbt: psbt.to_string(),
                 network: ck.network,
+                frost_key_spend,
+                spend_policy: ck.spend_policy,
             };
-            let signed_psbt = enclave_client
-                .sign_psbt(req)
-                .await
-                .map_err(|e| ApiError::ServerError(format!("Error Signing PSBT: {e}")))?;
+            let signed_psbt = enclave_client.sign_psbt(req).await.map_err(|e| {
+                match e.downcast::<PsbtValidationError>() {
+                    Ok(psbt_validation_error) => ApiError::InvalidPsbt(psbt_validation_error),
+                    Err(e) => ApiError::ServerError(format!("Error Signing PSBT: {e}")),
+                }
+            })?;
             Ok(Json(SignedPsbt {
                 psbt: signed_psbt.psbt,
                 root_key_id: root_key_id.clone(),
+                frost_partial_signatures: signed_psbt.frost_partial_signatures,
             }))
         }
         None => Err(ApiError::NotFound(format!(
@@ -329,6 +472,99 @@ async fn sign_psbt(
     }
 }
 
+#[instrument(skip(customer_key_store, enclave_client))]
+async fn sign_message(
+    State(customer_key_store): State<CustomerKeyStore>,
+    State(enclave_client): State<Arc<EnclaveClient>>,
+    Json(request): Json<SignMessageRequest>,
+) -> Result<Json<SignedMessage>, ApiError> {
+    let root_key_id = &request.root_key_id;
+
+    match customer_key_store
+        .get_customer_key(root_key_id)
+        .await
+        .map_err(|e| {
+            ApiError::ServerError(format!("Could not read customer keys DDB table: {e}"))
+        })? {
+        Some(ck) => {
+            let req = EnclaveSignMessageRequest {
+                root_key_id: root_key_id.to_string(),
+                wrapped_xprv: ck.key_ciphertext,
+                dek_id: ck.dek_id,
+                key_nonce: ck.key_nonce,
+                descriptor: request.descriptor,
+                change_descriptor: request.change_descriptor,
+                message: request.message,
+                psbt: request.psbt,
+                format: request.format,
+                network: ck.network,
+                spend_policy: ck.spend_policy,
+            };
+            let signed_message = enclave_client.sign_message(req).await.map_err(|e| {
+                match e.downcast::<PsbtValidationError>() {
+                    Ok(psbt_validation_error) => ApiError::InvalidPsbt(psbt_validation_error),
+                    Err(e) => ApiError::ServerError(format!("Error Signing Message: {e}")),
+                }
+            })?;
+            Ok(Json(signed_message))
+        }
+        None => Err(ApiError::NotFound(format!(
+            "Customer signing key for KeySet {root_key_id} not found"
+        ))),
+    }
+}
+
+#[instrument(skip(customer_key_store, enclave_client))]
+async fn set_spend_policy(
+    State(customer_key_store): State<CustomerKeyStore>,
+    State(enclave_client): State<Arc<EnclaveClient>>,
+    Json(request): Json<SetSpendPolicyRequest>,
+) -> Result<Json<SetSpendPolicyResponse>, ApiError> {
+    let root_key_id = &request.root_key_id;
+    let mut ck = customer_key_store
+        .get_customer_key(root_key_id)
+        .await
+        .map_err(|e| ApiError::ServerError(format!("Could not read customer keys DDB table: {e}")))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "Customer signing key for KeySet {root_key_id} not found"
+            ))
+        })?;
+
+    let enclave_request = EnclaveSetSpendPolicyRequest {
+        root_key_id: root_key_id.clone(),
+        wrapped_xprv: ck.key_ciphertext.clone(),
+        dek_id: ck.dek_id.clone(),
+        key_nonce: ck.key_nonce.clone(),
+        descriptor: request.descriptor,
+        change_descriptor: request.change_descriptor,
+        network: ck.network,
+        current_spend_policy: ck.spend_policy.take(),
+        spend_policy: request.spend_policy.clone(),
+    };
+    let enclave_response = enclave_client
+        .set_spend_policy(enclave_request)
+        .await
+        .map_err(|e| match e.downcast::<PsbtValidationError>() {
+            Ok(psbt_validation_error) => ApiError::InvalidPsbt(psbt_validation_error),
+            Err(e) => ApiError::ServerError(format!("Error setting spend policy: {e}")),
+        })?;
+
+    // Overwrite the previous wrapped key, so that it can't be used to sign under the old policy.
+    ck.key_ciphertext = enclave_response.wrapped_xprv;
+    ck.key_nonce = enclave_response.wrapped_xprv_nonce;
+    ck.spend_policy = Some(request.spend_policy.clone());
+    customer_key_store
+        .put_customer_key(&ck)
+        .await
+        .map_err(|e| ApiError::ServerError(e.to_string()))?;
+
+    Ok(Json(SetSpendPolicyResponse {
+        root_key_id: root_key_id.clone(),
+        version: request.spend_policy.policy.version,
+    }))
+}
+
 // Shallow health check
 async fn health_check(State(enclave_client): State<Arc<EnclaveClient>>) -> Result<String, String> {
     enclave_client
@@ -0,0 +1,256 @@
+//! Construction of the virtual BIP-322 transactions used to sign messages with wallet keys.
+//!
+//! See https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use bitcoin::absolute::LockTime;
+use bitcoin::blockdata::opcodes::all::OP_RETURN;
+use bitcoin::blockdata::opcodes::OP_0;
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::script::{Builder, PushBytes};
+use bitcoin::{OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const MESSAGE_TAG: &[u8] = b"BIP0322-signed-message";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Bip322SignatureFormat {
+    /// Only the witness of the `to_sign` input.
+    Simple,
+    /// The whole `to_sign` transaction.
+    Full,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Bip322Error {
+    #[error("Signature is not valid base64")]
+    InvalidEncoding,
+    #[error("Signature is neither a witness nor a transaction")]
+    InvalidSignature,
+    #[error("Transaction is not the to_sign transaction for this message and address")]
+    NotToSign,
+    #[error("to_sign input is not finalized")]
+    NotFinalized,
+}
+
+/// The BIP340-style tagged hash of the message committed to by `to_spend`.
+pub fn message_hash(message: &[u8]) -> sha256::Hash {
+    let tag = sha256::Hash::hash(MESSAGE_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message);
+    sha256::Hash::from_engine(engine)
+}
+
+/// The transaction whose only output, paid to `script_pubkey`, is spent by `to_sign`.
+pub fn to_spend(script_pubkey: &Script, message: &[u8]) -> Transaction {
+    let message_hash = message_hash(message).to_byte_array();
+    let script_sig = Builder::new()
+        .push_opcode(OP_0)
+        .push_slice(<&PushBytes>::try_from(&message_hash[..]).expect("32 bytes fit in a push"))
+        .into_script();
+
+    Transaction {
+        version: 0,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0xFFFFFFFF,
+            },
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: script_pubkey.to_owned(),
+        }],
+    }
+}
+
+/// The unsigned transaction whose input witness is the message signature.
+pub fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: to_spend.txid(),
+                vout: 0,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/// `to_sign` as a PSBT, ready for the wallet's signers once its input has been updated with the
+/// descriptor for `script_pubkey`.
+pub fn to_sign_psbt(script_pubkey: &Script, message: &[u8]) -> PartiallySignedTransaction {
+    let to_spend = to_spend(script_pubkey, message);
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(to_sign(&to_spend))
+        .expect("to_sign is constructed without signatures");
+    psbt.inputs[0].witness_utxo = Some(to_spend.output[0].clone());
+    psbt
+}
+
+/// Whether `psbt` is exactly the `to_sign` PSBT for `message`, spending its own witness UTXO.
+/// Signers MUST check this before signing, so that a message signature can't be used to spend
+/// real coins.
+pub fn is_to_sign_psbt(psbt: &PartiallySignedTransaction, message: &[u8]) -> bool {
+    let [input] = psbt.inputs.as_slice() else {
+        return false;
+    };
+    match &input.witness_utxo {
+        Some(witness_utxo) => {
+            witness_utxo.value == 0
+                && psbt.unsigned_tx == to_sign(&to_spend(&witness_utxo.script_pubkey, message))
+        }
+        None => false,
+    }
+}
+
+/// Encodes the signature of a finalized `to_sign` transaction.
+pub fn encode_signature(
+    to_sign: &Transaction,
+    format: Bip322SignatureFormat,
+) -> Result<String, Bip322Error> {
+    let witness = &to_sign.input.first().ok_or(Bip322Error::NotToSign)?.witness;
+    if witness.is_empty() {
+        return Err(Bip322Error::NotFinalized);
+    }
+
+    Ok(match format {
+        Bip322SignatureFormat::Simple => BASE64.encode(serialize(witness)),
+        Bip322SignatureFormat::Full => BASE64.encode(serialize(to_sign)),
+    })
+}
+
+/// Decodes a simple or full signature into the signed `to_sign` transaction for `script_pubkey`
+/// and `message`, along with the output it spends.
+pub fn decode_signature(
+    signature: &str,
+    script_pubkey: &Script,
+    message: &[u8],
+) -> Result<(Transaction, TxOut), Bip322Error> {
+    let bytes = BASE64
+        .decode(signature)
+        .map_err(|_| Bip322Error::InvalidEncoding)?;
+    let to_spend = to_spend(script_pubkey, message);
+    let mut expected_to_sign = to_sign(&to_spend);
+
+    let signed_to_sign = if let Ok(witness) = deserialize::<Witness>(&bytes) {
+        expected_to_sign.input[0].witness = witness;
+        expected_to_sign
+    } else if let Ok(full) = deserialize::<Transaction>(&bytes) {
+        let mut unsigned = full.clone();
+        unsigned.input.iter_mut().for_each(|input| {
+            input.witness = Witness::new();
+        });
+        if unsigned != expected_to_sign {
+            return Err(Bip322Error::NotToSign);
+        }
+        full
+    } else {
+        return Err(Bip322Error::InvalidSignature);
+    };
+
+    Ok((signed_to_sign, to_spend.output[0].clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{Address, Network};
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_message_hash_vectors() {
+        // Test vectors from BIP-322.
+        assert_eq!(
+            message_hash(b""),
+            sha256::Hash::from_str(
+                "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            message_hash(b"Hello World"),
+            sha256::Hash::from_str(
+                "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_transaction_vectors() {
+        let address = Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l")
+            .unwrap()
+            .require_network(Network::Bitcoin)
+            .unwrap();
+        let script_pubkey = address.script_pubkey();
+
+        let to_spend_empty = to_spend(&script_pubkey, b"");
+        assert_eq!(
+            to_spend_empty.txid(),
+            Txid::from_str("c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7")
+                .unwrap()
+        );
+        assert_eq!(
+            to_sign(&to_spend_empty).txid(),
+            Txid::from_str("1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6")
+                .unwrap()
+        );
+
+        let to_spend_hello = to_spend(&script_pubkey, b"Hello World");
+        assert_eq!(
+            to_spend_hello.txid(),
+            Txid::from_str("b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b")
+                .unwrap()
+        );
+        assert_eq!(
+            to_sign(&to_spend_hello).txid(),
+            Txid::from_str("88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_signature_round_trip() {
+        let script_pubkey = ScriptBuf::from_hex(&format!("0014{}", "00".repeat(20))).unwrap();
+        let message = b"Hello World";
+
+        let psbt = to_sign_psbt(&script_pubkey, message);
+        assert!(is_to_sign_psbt(&psbt, message));
+        assert!(!is_to_sign_psbt(&psbt, b"Goodbye World"));
+
+        let mut signed = psbt.unsigned_tx.clone();
+        assert_eq!(
+            encode_signature(&signed, Bip322SignatureFormat::Simple),
+            Err(Bip322Error::NotFinalized)
+        );
+        signed.input[0].witness = Witness::from_slice(&[vec![1u8; 72], vec![2u8; 33]]);
+
+        for format in [Bip322SignatureFormat::Simple, Bip322SignatureFormat::Full] {
+            let signature = encode_signature(&signed, format).unwrap();
+            let (decoded, spent) = decode_signature(&signature, &script_pubkey, message).unwrap();
+            assert_eq!(decoded, signed);
+            assert_eq!(spent.script_pubkey, script_pubkey);
+
+            assert!(decode_signature(&signature, &script_pubkey, b"Goodbye World").is_err());
+        }
+    }
+}
@@ -1,5 +1,8 @@
+pub mod bip322;
 pub mod derivation;
 pub mod enclave_log;
 pub mod messages;
+pub mod psbt_validation;
+pub mod spend_policy;
 
 pub extern crate bitcoin;
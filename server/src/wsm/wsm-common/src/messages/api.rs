@@ -2,7 +2,10 @@ use bitcoin::Network;
 use crypto::keys::PublicKey;
 use serde::{Deserialize, Serialize};
 
+use crate::bip322::Bip322SignatureFormat;
 use crate::derivation::WSMSupportedDomain;
+use crate::messages::{FrostInputCommitment, FrostInputSignature};
+use crate::spend_policy::SignedSpendPolicy;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct InitiateDistributedKeygenRequest {
@@ -30,6 +33,31 @@ pub struct ContinueDistributedKeygenResponse {
     pub root_key_id: String,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+pub struct InitiateShareRefreshRequest {
+    pub root_key_id: String,
+    pub network: Network,
+    pub sealed_request: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct InitiateShareRefreshResponse {
+    pub root_key_id: String,
+    pub sealed_response: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ContinueShareRefreshRequest {
+    pub root_key_id: String,
+    pub network: Network,
+    pub sealed_request: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ContinueShareRefreshResponse {
+    pub root_key_id: String,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct CreateRootKeyRequest {
     pub root_key_id: String,
@@ -62,12 +90,53 @@ pub struct SignPsbtRequest {
     pub descriptor: String,
     pub change_descriptor: String,
     pub psbt: String,
+    /// Only needed when spending taproot inputs through the key path of a FROST distributed key.
+    #[serde(default)]
+    pub frost_commitments: Vec<FrostInputCommitment>,
+}
+
+/// Sets the limits the enclave enforces on every PSBT signed with the key from now on. The policy
+/// must be signed by one of the wallet's other keys, and have a higher version than the current one.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SetSpendPolicyRequest {
+    pub root_key_id: String,
+    pub descriptor: String,
+    pub change_descriptor: String,
+    pub spend_policy: SignedSpendPolicy,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetSpendPolicyResponse {
+    pub root_key_id: String,
+    pub version: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SignedPsbt {
     pub psbt: String,
     pub root_key_id: String,
+    #[serde(default)]
+    pub frost_partial_signatures: Vec<FrostInputSignature>,
+}
+
+/// Proves control of an address of the wallet by signing `message` per BIP-322. `psbt` is the
+/// `to_sign` PSBT for the address, already signed by one of the wallet's other keys.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SignMessageRequest {
+    pub root_key_id: String,
+    pub descriptor: String,
+    pub change_descriptor: String,
+    pub message: String,
+    pub psbt: String,
+    pub format: Bip322SignatureFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignedMessage {
+    pub root_key_id: String,
+    pub psbt: String,
+    /// The encoded BIP-322 signature, if the Server's signature completed the `to_sign` PSBT.
+    pub signature: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -3,6 +3,10 @@ use bitcoin::Network;
 use crypto::keys::PublicKey;
 use serde::{Deserialize, Serialize};
 
+use crate::bip322::Bip322SignatureFormat;
+use crate::messages::FrostInputCommitment;
+use crate::spend_policy::SignedSpendPolicy;
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct KmsRequest {
     pub region: String,
@@ -51,6 +55,63 @@ pub struct EnclaveSignRequest {
     pub change_descriptor: String,
     pub psbt: String,
     pub network: Option<Network>,
+    #[serde(default)]
+    pub frost_key_spend: Option<EnclaveFrostKeySpendRequest>,
+    /// The spend policy the key was last wrapped with, if any. The key can't be unwrapped without
+    /// it, so the policy is always enforced once set.
+    #[serde(default)]
+    pub spend_policy: Option<SignedSpendPolicy>,
+}
+
+/// Asks the enclave to add the Server's signature to the BIP-322 `to_sign` PSBT for `message`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveSignMessageRequest {
+    pub root_key_id: String,
+    pub wrapped_xprv: String,
+    pub dek_id: String,
+    pub key_nonce: String,
+    pub descriptor: String,
+    pub change_descriptor: String,
+    pub message: String,
+    pub psbt: String,
+    pub format: Bip322SignatureFormat,
+    pub network: Option<Network>,
+    /// The spend policy the key was last wrapped with, if any.
+    #[serde(default)]
+    pub spend_policy: Option<SignedSpendPolicy>,
+}
+
+/// Replaces the spend policy of a key, returning the key re-wrapped with the new policy's
+/// commitment in its AAD. `current_spend_policy` must be the policy the key is wrapped with now.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveSetSpendPolicyRequest {
+    pub root_key_id: String,
+    pub wrapped_xprv: String,
+    pub dek_id: String,
+    pub key_nonce: String,
+    pub descriptor: String,
+    pub change_descriptor: String,
+    pub network: Option<Network>,
+    #[serde(default)]
+    pub current_spend_policy: Option<SignedSpendPolicy>,
+    pub spend_policy: SignedSpendPolicy,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveSetSpendPolicyResponse {
+    pub wrapped_xprv: String,
+    pub wrapped_xprv_nonce: String,
+}
+
+/// The Server's share of the FROST key used as the taproot internal key, along with the App's
+/// nonce commitments for every input being spent through the key path.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveFrostKeySpendRequest {
+    pub dek_id: String,
+    pub network: Option<Network>,
+    pub wrapped_share_details: String,
+    pub wrapped_share_details_nonce: String,
+    pub app_commitments: Vec<FrostInputCommitment>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -99,6 +160,36 @@ pub struct EnclaveContinueDistributedKeygenRequest {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EnclaveContinueDistributedKeygenResponse {}
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveInitiateShareRefreshRequest {
+    pub root_key_id: String,
+    pub dek_id: String,
+    pub network: Network,
+    pub wrapped_share_details: String,
+    pub wrapped_share_details_nonce: String,
+    pub sealed_request: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveInitiateShareRefreshResponse {
+    pub wrapped_share_details: String,
+    pub wrapped_share_details_nonce: String,
+    pub sealed_response: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveContinueShareRefreshRequest {
+    pub root_key_id: String,
+    pub dek_id: String,
+    pub network: Network,
+    pub wrapped_share_details: String,
+    pub wrapped_share_details_nonce: String,
+    pub sealed_request: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnclaveContinueShareRefreshResponse {}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EnclaveDeriveKeyRequest {
     pub key_id: String,
@@ -107,6 +198,9 @@ pub struct EnclaveDeriveKeyRequest {
     pub key_nonce: String,
     pub derivation_path: DerivationPath,
     pub network: Option<Network>,
+    /// The spend policy the key was last wrapped with, if any.
+    #[serde(default)]
+    pub spend_policy: Option<SignedSpendPolicy>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -4,6 +4,7 @@ pub mod enclave;
 use std::fmt::{Debug, Formatter};
 
 use crate::derivation::WSMSupportedDomain;
+use crypto::frost::signing::{PartialSignature, SigningCommitment};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
@@ -63,3 +64,19 @@ pub struct SignedBlob {
     pub signature: String,
     pub root_key_id: String,
 }
+
+/// The App's FROST nonce commitment for a taproot key-path spend of one PSBT input.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FrostInputCommitment {
+    pub input_index: usize,
+    pub commitment: SigningCommitment,
+}
+
+/// The Server's FROST nonce commitment and partial signature for one PSBT input. The App combines
+/// these with its own to produce the key-path signature.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FrostInputSignature {
+    pub input_index: usize,
+    pub commitment: SigningCommitment,
+    pub partial_signature: PartialSignature,
+}
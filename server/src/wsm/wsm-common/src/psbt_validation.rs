@@ -0,0 +1,55 @@
+use bitcoin::Network;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The most inputs the enclave will sign for in a single PSBT.
+pub const MAX_PSBT_INPUTS: usize = 250;
+
+/// Reasons the enclave refuses to sign a PSBT. These are returned from the enclave through the API
+/// to the caller as-is, so that callers can tell bad input apart from a failure on our side.
+#[derive(Error, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PsbtValidationError {
+    #[error("Could not parse PSBT: {message}")]
+    Unparseable { message: String },
+    #[error("Could not parse wallet descriptor: {message}")]
+    InvalidDescriptor { message: String },
+    #[error("Wallet descriptor keys are not valid for network {network}")]
+    NetworkMismatch { network: Network },
+    #[error("PSBT has {count} inputs, more than the maximum of {max}")]
+    TooManyInputs { count: usize, max: usize },
+    #[error("Input {input_index} is missing its witness UTXO")]
+    MissingWitnessUtxo { input_index: usize },
+    #[error("Input {input_index} is missing its non-witness UTXO")]
+    MissingNonWitnessUtxo { input_index: usize },
+    #[error("Input {input_index} non-witness UTXO does not match the output it spends")]
+    NonWitnessUtxoMismatch { input_index: usize },
+    #[error("Input {input_index} does not spend an output of the provided descriptors")]
+    ForeignInput { input_index: usize },
+    #[error("Input {input_index} has {count} signatures, but must have exactly one")]
+    UnexpectedSignatureCount { input_index: usize, count: usize },
+    #[error("Input {input_index} is signed by a key that does not belong to the wallet")]
+    UnrecognizedSigner { input_index: usize },
+    #[error("Spend policy signature is invalid")]
+    InvalidSpendPolicySignature,
+    #[error("Spend policy is not signed by a key of the wallet")]
+    UnrecognizedSpendPolicySigner,
+    #[error("Spend policy is for key {policy_root_key_id}, not {root_key_id}")]
+    SpendPolicyKeyMismatch {
+        root_key_id: String,
+        policy_root_key_id: String,
+    },
+    #[error("Spend policy version {version} does not replace current version {current_version}")]
+    StaleSpendPolicy { version: u64, current_version: u64 },
+    #[error("Outflow of {outflow_sats} sats exceeds the maximum of {max_sats} sats")]
+    MaxOutflowExceeded { outflow_sats: u64, max_sats: u64 },
+    #[error("Output {output_index} claims to be change but is not paid to the wallet")]
+    ChangeOutputNotInWallet { output_index: usize },
+    #[error(
+        "Fee rate of {fee_rate_sat_per_vb} sat/vB exceeds the maximum of {max_sat_per_vb} sat/vB"
+    )]
+    FeeRateTooHigh {
+        fee_rate_sat_per_vb: u64,
+        max_sat_per_vb: u64,
+    },
+}
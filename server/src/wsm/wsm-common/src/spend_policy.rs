@@ -0,0 +1,55 @@
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{ecdsa::Signature, Message, PublicKey};
+use serde::{Deserialize, Serialize};
+
+const SPEND_POLICY_CONTEXT: &[u8] = b"WsmSpendPolicyV1";
+
+/// Limits the enclave enforces on a PSBT before adding the Server's signature, independent of any
+/// checks made by the API host.
+///
+/// Once set, the policy is bound to the key it was set for: its commitment is part of the AAD of
+/// the wrapped key, so the host can't sign with that key without presenting exactly this policy.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpendPolicy {
+    /// The WSM key this policy applies to.
+    pub root_key_id: String,
+    /// Must increase every time the policy for a key is replaced, so older policies can't be
+    /// replayed in place of newer ones.
+    pub version: u64,
+    /// The most that may leave the wallet, in sats, not counting the fee.
+    #[serde(default)]
+    pub max_outflow_sats: Option<u64>,
+    /// Reject PSBTs with outputs that claim to be change, but aren't paid to the wallet.
+    #[serde(default)]
+    pub require_change_in_wallet: bool,
+    /// The highest fee rate the enclave will cosign, in sats per virtual byte.
+    #[serde(default)]
+    pub max_fee_rate_sat_per_vb: Option<u64>,
+}
+
+impl SpendPolicy {
+    /// The hash of the policy that both its signature and the wrapped key's AAD commit to.
+    pub fn commitment(&self) -> sha256::Hash {
+        let mut hash_input = Vec::new();
+        hash_input.extend_from_slice(SPEND_POLICY_CONTEXT);
+        hash_input.extend_from_slice(
+            &serde_json::to_vec(self).expect("Spend policy should always serialize"),
+        );
+
+        sha256::Hash::hash(&hash_input)
+    }
+
+    /// The message a `SignedSpendPolicy` signature commits to.
+    pub fn signing_message(&self) -> Message {
+        Message::from(self.commitment())
+    }
+}
+
+/// A `SpendPolicy` signed by one of the wallet's account keys other than the Server's, so that the
+/// API host can't loosen it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignedSpendPolicy {
+    pub policy: SpendPolicy,
+    pub signer: PublicKey,
+    pub signature: Signature,
+}
@@ -1,3 +1,4 @@
+use bdk::bitcoin::hashes::sha256;
 use bdk::bitcoin::Network;
 use serde::Serialize;
 use std::error::Error;
@@ -7,6 +8,9 @@ use std::fmt::{Debug, Display, Formatter};
 pub struct Aad {
     root_key_id: String,
     network: Option<Network>, // NOTE: remove Option once keys are migrated
+    /// Commitment to the key's spend policy, so the key can't be unwrapped without it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spend_policy: Option<sha256::Hash>,
 }
 
 #[derive(Debug)]
@@ -35,12 +39,18 @@ impl Aad {
         Aad {
             root_key_id,
             network,
+            spend_policy: None,
         }
     }
 
+    pub fn with_spend_policy(mut self, spend_policy: Option<sha256::Hash>) -> Aad {
+        self.spend_policy = spend_policy;
+        self
+    }
+
     pub fn serialize(&self) -> Result<Vec<u8>, AadError> {
-        match self.network {
-            None => Ok(self.root_key_id.as_bytes().to_vec()),
+        match (self.network, self.spend_policy) {
+            (None, None) => Ok(self.root_key_id.as_bytes().to_vec()),
             _ => {
                 let mut output = vec![];
                 ciborium::ser::into_writer(self, &mut output)?;
@@ -52,6 +62,7 @@ impl Aad {
 
 #[cfg(test)]
 mod tests {
+    use bdk::bitcoin::hashes::{sha256, Hash};
     use bdk::bitcoin::Network;
 
     use super::Aad;
@@ -83,5 +94,19 @@ mod tests {
                 0x73, 0x69, 0x67, 0x6E, 0x65, 0x74 // "signet"
             ]
         );
+
+        // A spend policy commitment is only ever added, so keys without one keep the AAD above.
+        let with_policy = Aad::new("root_key_id".to_string(), Some(Network::Signet))
+            .with_spend_policy(Some(sha256::Hash::hash(b"policy")))
+            .serialize()
+            .unwrap();
+        assert_eq!(with_policy[0], 0xA3); // map(3)
+        assert_ne!(
+            with_policy,
+            Aad::new("root_key_id".to_string(), Some(Network::Signet))
+                .with_spend_policy(Some(sha256::Hash::hash(b"other policy")))
+                .serialize()
+                .unwrap()
+        );
     }
 }
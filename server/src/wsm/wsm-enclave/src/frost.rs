@@ -1,7 +1,14 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Context};
+use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bdk::bitcoin::TxOut;
 use crypto::frost::{
     dkg::{aggregate_shares, equality_check, generate_share_packages, KeygenError, SharePackage},
-    KeyCommitments, Participant, ShareDetails,
+    signing, KeyCommitments, Participant, ShareDetails,
 };
+use wsm_common::messages::{FrostInputCommitment, FrostInputSignature};
 
 struct ShareGenerator {
     app_share_package: SharePackage,
@@ -43,3 +50,61 @@ impl PendingEqualityChecker {
         equality_check(peer_key_commitments, self.share_details)
     }
 }
+
+/// Produces the Server's partial signature for every input the App sent a commitment for. Each of
+/// those inputs must be a taproot key-path spend with the FROST aggregate key as its internal key.
+pub fn sign_key_spend_inputs(
+    share_details: &ShareDetails,
+    psbt: &PartiallySignedTransaction,
+    app_commitments: &[FrostInputCommitment],
+) -> anyhow::Result<Vec<FrostInputSignature>> {
+    let (internal_key, _) = share_details
+        .key_commitments
+        .aggregate_public_key
+        .x_only_public_key();
+    let prevouts = psbt
+        .inputs
+        .iter()
+        .map(|input| input.witness_utxo.clone().context("Missing witness UTXO"))
+        .collect::<anyhow::Result<Vec<TxOut>>>()?;
+    let mut sighash_cache = SighashCache::new(&psbt.unsigned_tx);
+
+    let mut input_indices = HashSet::new();
+    if !app_commitments
+        .iter()
+        .all(|app_commitment| input_indices.insert(app_commitment.input_index))
+    {
+        bail!("More than one commitment for an input");
+    }
+
+    app_commitments
+        .iter()
+        .map(|app_commitment| {
+            let input = psbt
+                .inputs
+                .get(app_commitment.input_index)
+                .context("Commitment for unknown input")?;
+            if input.tap_internal_key != Some(internal_key) {
+                bail!("Input is not a key-path spend of the distributed key");
+            }
+
+            let sighash = sighash_cache.taproot_key_spend_signature_hash(
+                app_commitment.input_index,
+                &Prevouts::All(&prevouts),
+                TapSighashType::Default,
+            )?;
+            let result = signing::server::sign(
+                share_details,
+                sighash,
+                input.tap_merkle_root,
+                app_commitment.commitment.clone(),
+            )?;
+
+            Ok(FrostInputSignature {
+                input_index: app_commitment.input_index,
+                commitment: result.commitment,
+                partial_signature: result.partial_signature,
+            })
+        })
+        .collect()
+}
@@ -1,6 +1,8 @@
 use crate::psbt_verification::verify_inputs_only_have_one_signature;
 use crate::psbt_verification::verify_inputs_pubkey_belongs_to_wallet;
+use crate::psbt_verification::verify_key_spend_input_belongs_to_wallet;
 use crate::psbt_verification::WalletDescriptors;
+use crate::psbt_verification::{parse_psbt, verify_psbt_inputs};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
@@ -21,18 +23,20 @@ use axum::{extract::State, routing::post, Json, Router};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use bdk::bitcoin::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint};
 use bdk::bitcoin::hashes::sha256;
-use bdk::bitcoin::psbt::PartiallySignedTransaction;
 use bdk::bitcoin::secp256k1::{ecdsa::Signature, All, Message, Secp256k1, SecretKey};
 use bdk::bitcoin::Network;
 use bdk::database::MemoryDatabase;
 use bdk::descriptor::Segwitv0;
 use bdk::keys::{DerivableKey, DescriptorKey, DescriptorSecretKey, ExtendedKey};
+use bdk::miniscript::{ScriptContext, Tap};
 
 use bdk::signer::{SignerContext, SignerOrdering, SignerWrapper, TransactionSigner};
 use bdk::{KeychainKind, SignOptions, Wallet};
 
 use crypto::frost::dkg::server::continue_dkg;
 use crypto::frost::dkg::{server::initiate_dkg, SharePackage};
+use crypto::frost::refresh::server::{continue_refresh, initiate_refresh};
+use crypto::frost::refresh::RefreshPackage;
 
 use crypto::frost::KeyCommitments;
 use crypto::frost::ShareDetails;
@@ -44,20 +48,31 @@ use serde::Serialize;
 use tokio::net::TcpListener;
 use tokio::sync::RwLock;
 
+use wsm_common::bip322;
 use wsm_common::bitcoin::Network::Signet;
 use wsm_common::derivation::WSMSupportedDomain;
 use wsm_common::messages::api::AttestationDocResponse;
+use wsm_common::messages::api::SignedMessage;
 use wsm_common::messages::api::SignedPsbt;
 use wsm_common::messages::enclave::EnclaveContinueDistributedKeygenRequest;
 use wsm_common::messages::enclave::EnclaveContinueDistributedKeygenResponse;
+use wsm_common::messages::enclave::EnclaveContinueShareRefreshRequest;
+use wsm_common::messages::enclave::EnclaveContinueShareRefreshResponse;
 use wsm_common::messages::enclave::EnclaveInitiateDistributedKeygenRequest;
 use wsm_common::messages::enclave::EnclaveInitiateDistributedKeygenResponse;
+use wsm_common::messages::enclave::EnclaveInitiateShareRefreshRequest;
+use wsm_common::messages::enclave::EnclaveInitiateShareRefreshResponse;
+use wsm_common::messages::enclave::EnclaveSetSpendPolicyRequest;
+use wsm_common::messages::enclave::EnclaveSetSpendPolicyResponse;
+use wsm_common::messages::enclave::EnclaveSignMessageRequest;
 use wsm_common::messages::enclave::{
     CreateResponse, CreatedKey, DeriveResponse, DerivedKey, EnclaveCreateKeyRequest,
     EnclaveDeriveKeyRequest, EnclaveSignRequest, KmsRequest, LoadIntegrityKeyRequest,
     LoadSecretRequest, LoadedSecret,
 };
 use wsm_common::messages::TEST_KEY_IDS;
+use wsm_common::psbt_validation::PsbtValidationError;
+use wsm_common::spend_policy::SignedSpendPolicy;
 use wsm_common::{
     enclave_log::{LogBuffer, MAX_LOG_EVENT_SIZE_BYTES},
     try_with_log_and_error, wsm_log,
@@ -66,12 +81,16 @@ use wsm_common::{
 use crate::aad::Aad;
 use crate::kms_tool::{KmsTool, KmsToolError};
 use crate::settings::Settings;
+use crate::spend_policy::{
+    verify_spend_policy, verify_spend_policy_replaces, verify_spend_policy_signer,
+};
 
 mod aad;
 mod frost;
 mod kms_tool;
 mod psbt_verification;
 mod settings;
+mod spend_policy;
 
 const GLOBAL_CONTEXT: &[u8] = b"WsmIntegrityV1";
 const INTEGRITY_KEY_ID: &str = "integrity";
@@ -111,6 +130,7 @@ pub enum WsmError {
         log_buffer: LogBuffer,
     },
     IntegrityKeyNotLoaded(String, LogBuffer),
+    InvalidPsbt(PsbtValidationError, LogBuffer),
 }
 
 #[derive(Serialize)]
@@ -186,6 +206,12 @@ pub enum ErrorReponse {
         #[serde(skip_serializing)]
         log_buffer: LogBuffer,
     },
+    PsbtValidationErrorResponse {
+        message: String,
+        psbt_validation_error: PsbtValidationError,
+        #[serde(skip_serializing)]
+        log_buffer: LogBuffer,
+    },
 }
 
 impl IntoResponse for WsmError {
@@ -219,6 +245,13 @@ impl IntoResponse for WsmError {
                 message,
                 log_buffer,
             },
+            WsmError::InvalidPsbt(psbt_validation_error, log_buffer) => {
+                ErrorReponse::PsbtValidationErrorResponse {
+                    message: psbt_validation_error.to_string(),
+                    psbt_validation_error,
+                    log_buffer,
+                }
+            }
         };
 
         match &resp {
@@ -241,10 +274,27 @@ impl IntoResponse for WsmError {
                 Json(resp),
             )
                 .into_response(),
+            ErrorReponse::PsbtValidationErrorResponse {
+                message: _,
+                psbt_validation_error: _,
+                log_buffer,
+            } => (
+                StatusCode::BAD_REQUEST,
+                log_buffer.to_owned().to_header(),
+                Json(resp),
+            )
+                .into_response(),
         }
     }
 }
 
+impl WsmError {
+    fn invalid_psbt(error: PsbtValidationError, log_buffer: &mut LogBuffer) -> Self {
+        wsm_log!(log_buffer, &format!("Invalid PSBT: {}", error));
+        WsmError::InvalidPsbt(error, log_buffer.clone())
+    }
+}
+
 impl From<KmsToolError> for WsmError {
     fn from(err: KmsToolError) -> Self {
         WsmError::ServerError {
@@ -412,69 +462,276 @@ async fn sign_psbt(
 ) -> Result<Json<SignedPsbt>, WsmError> {
     let mut log_buffer = LogBuffer::new();
 
+    // Fails unless `spend_policy` is the policy the key was wrapped with, so the host can neither
+    // drop the key's policy nor swap in another one.
     let xprv = decode_wrapped_xprv(
-        keystore,
+        keystore.clone(),
         &request.wrapped_xprv,
         &request.key_nonce,
         &request.dek_id,
         &request.root_key_id,
         request.network,
+        request.spend_policy.as_ref(),
         &mut log_buffer,
     )
     .await?;
     let secp = Secp256k1::new();
 
     let network = request.network.unwrap_or(Signet);
-    let derivation_path = DerivationPath::from(WSMSupportedDomain::Spend(network.into()));
-
-    let derived_xprv = try_with_log_and_error!(
-        log_buffer,
-        WsmError::ServerError,
-        xprv.derive_priv(&secp, &derivation_path)
+    let extended_descriptors = WalletDescriptors::new(
+        &request.descriptor,
+        &request.change_descriptor,
+        &secp,
+        network,
+    )
+    .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    let mut wallet = server_signing_wallet(
+        &xprv,
+        &request.descriptor,
+        &request.change_descriptor,
+        &extended_descriptors,
+        network,
+        &secp,
+        &mut log_buffer,
     )?;
-    // Will need to change the descriptor key type param for taproot
-    let external_descriptor_xpriv: DescriptorKey<Segwitv0> = try_with_log_and_error!(
-        log_buffer,
-        WsmError::ServerError,
-        derived_xprv.into_descriptor_key(
-            Some((xprv.fingerprint(&secp), derivation_path.clone())),
+    let (derived_xprv, _) = derive_spend_xprv(&xprv, network, &secp, &mut log_buffer)?;
+
+    let mut psbt =
+        parse_psbt(&request.psbt).map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    verify_psbt_inputs(&extended_descriptors, &psbt, &secp)
+        .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    if let Some(signed_policy) = &request.spend_policy {
+        verify_spend_policy(
+            signed_policy,
+            &request.root_key_id,
+            &extended_descriptors,
+            &derived_xprv.private_key.public_key(&secp),
+            &psbt,
+            &secp,
+        )
+        .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    }
+
+    let key_spend_inputs = request
+        .frost_key_spend
+        .as_ref()
+        .map(|frost_key_spend| {
+            frost_key_spend
+                .app_commitments
+                .iter()
+                .map(|commitment| commitment.input_index)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let (key_spend_psbt_inputs, script_spend_psbt_inputs): (Vec<_>, Vec<_>) = psbt
+        .inputs
+        .iter()
+        .enumerate()
+        .partition(|(index, _)| key_spend_inputs.contains(index));
+
+    // Check inputs have only one signature. Key-path inputs are signed jointly and carry none yet.
+    verify_inputs_only_have_one_signature(script_spend_psbt_inputs.iter().copied())
+        .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+
+    // Check inputs already presigned by a key that belongs to the wallet.
+    verify_inputs_pubkey_belongs_to_wallet(
+        &extended_descriptors,
+        script_spend_psbt_inputs.iter().copied(),
+        &secp,
+    )
+    .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    for (input_index, input) in key_spend_psbt_inputs {
+        verify_key_spend_input_belongs_to_wallet(&extended_descriptors, input, &secp).map_err(
+            |_| {
+                WsmError::invalid_psbt(
+                    PsbtValidationError::ForeignInput { input_index },
+                    &mut log_buffer,
+                )
+            },
+        )?;
+    }
+
+    let frost_partial_signatures = match &request.frost_key_spend {
+        Some(frost_key_spend) => {
+            let share_details = decode_wrapped_share_details(
+                keystore,
+                &frost_key_spend.wrapped_share_details,
+                &frost_key_spend.wrapped_share_details_nonce,
+                &frost_key_spend.dek_id,
+                &request.root_key_id,
+                frost_key_spend.network,
+                &mut log_buffer,
+            )
+            .await?;
             try_with_log_and_error!(
                 log_buffer,
                 WsmError::ServerError,
-                DerivationPath::from_str("m/0")
-            )?,
-        )
-    )?;
-    let internal_descriptor_xpriv: DescriptorKey<Segwitv0> = try_with_log_and_error!(
+                frost::sign_key_spend_inputs(
+                    &share_details,
+                    &psbt,
+                    &frost_key_spend.app_commitments
+                )
+            )?
+        }
+        None => vec![],
+    };
+
+    let _finalized = try_with_log_and_error!(
         log_buffer,
         WsmError::ServerError,
-        derived_xprv.into_descriptor_key(
-            Some((xprv.fingerprint(&secp), derivation_path)),
-            try_with_log_and_error!(
-                log_buffer,
-                WsmError::ServerError,
-                DerivationPath::from_str("m/1")
-            )?,
-        )
+        wallet.sign(&mut psbt, SignOptions::default())
     )?;
-    // We'll need to change the context for taproot
-    let signer_context = SignerContext::Segwitv0;
-    let external_signer = try_with_log_and_error!(
+
+    Ok(Json(SignedPsbt {
+        psbt: psbt.to_string(),
+        root_key_id: request.root_key_id.clone(),
+        frost_partial_signatures,
+    }))
+}
+
+async fn sign_message(
+    State(keystore): State<KeyStore>,
+    Json(request): Json<EnclaveSignMessageRequest>,
+) -> Result<Json<SignedMessage>, WsmError> {
+    let mut log_buffer = LogBuffer::new();
+
+    let xprv = decode_wrapped_xprv(
+        keystore,
+        &request.wrapped_xprv,
+        &request.key_nonce,
+        &request.dek_id,
+        &request.root_key_id,
+        request.network,
+        request.spend_policy.as_ref(),
+        &mut log_buffer,
+    )
+    .await?;
+    let secp = Secp256k1::new();
+
+    let network = request.network.unwrap_or(Signet);
+    let extended_descriptors = WalletDescriptors::new(
+        &request.descriptor,
+        &request.change_descriptor,
+        &secp,
+        network,
+    )
+    .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    let wallet = server_signing_wallet(
+        &xprv,
+        &request.descriptor,
+        &request.change_descriptor,
+        &extended_descriptors,
+        network,
+        &secp,
+        &mut log_buffer,
+    )?;
+
+    let mut psbt =
+        parse_psbt(&request.psbt).map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    // Only ever sign the virtual to_sign transaction for this message, which can't move coins.
+    if !bip322::is_to_sign_psbt(&psbt, request.message.as_bytes()) {
+        let message = "PSBT is not the BIP-322 to_sign PSBT for the message".to_string();
+        wsm_log!(log_buffer, &message);
+        return Err(WsmError::BadRequest(message, log_buffer));
+    }
+    verify_psbt_inputs(&extended_descriptors, &psbt, &secp)
+        .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    verify_inputs_only_have_one_signature(psbt.inputs.iter().enumerate())
+        .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    verify_inputs_pubkey_belongs_to_wallet(
+        &extended_descriptors,
+        psbt.inputs.iter().enumerate(),
+        &secp,
+    )
+    .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+
+    let finalized = try_with_log_and_error!(
         log_buffer,
         WsmError::ServerError,
-        descriptor_key_to_signer(external_descriptor_xpriv, signer_context)
+        wallet.sign(
+            &mut psbt,
+            SignOptions {
+                // to_spend is never broadcast, so there is no previous transaction to provide.
+                trust_witness_utxo: true,
+                ..Default::default()
+            },
+        )
     )?;
-    let internal_signer = try_with_log_and_error!(
+    let signature = if finalized {
+        Some(try_with_log_and_error!(
+            log_buffer,
+            WsmError::ServerError,
+            bip322::encode_signature(&psbt.clone().extract_tx(), request.format)
+        )?)
+    } else {
+        None
+    };
+
+    Ok(Json(SignedMessage {
+        root_key_id: request.root_key_id,
+        psbt: psbt.to_string(),
+        signature,
+    }))
+}
+
+/// Derives the Server's account-level spend key, along with its derivation path.
+fn derive_spend_xprv(
+    xprv: &ExtendedPrivKey,
+    network: Network,
+    secp: &Secp256k1<All>,
+    log_buffer: &mut LogBuffer,
+) -> Result<(ExtendedPrivKey, DerivationPath), WsmError> {
+    let derivation_path = DerivationPath::from(WSMSupportedDomain::Spend(network.into()));
+    let derived_xprv = try_with_log_and_error!(
         log_buffer,
         WsmError::ServerError,
-        descriptor_key_to_signer(internal_descriptor_xpriv, signer_context)
+        xprv.derive_priv(secp, &derivation_path)
     )?;
+    Ok((derived_xprv, derivation_path))
+}
+
+/// A wallet for the given descriptors that signs with the Server's spend key.
+fn server_signing_wallet(
+    xprv: &ExtendedPrivKey,
+    descriptor: &str,
+    change_descriptor: &str,
+    wallet_descriptors: &WalletDescriptors,
+    network: Network,
+    secp: &Secp256k1<All>,
+    log_buffer: &mut LogBuffer,
+) -> Result<Wallet<MemoryDatabase>, WsmError> {
+    let (derived_xprv, derivation_path) = derive_spend_xprv(xprv, network, secp, log_buffer)?;
+    let (external_signer, internal_signer) = if wallet_descriptors.is_taproot() {
+        // The Server's key only ever appears in script leaves; the internal key is either the
+        // FROST aggregate key or unspendable.
+        try_with_log_and_error!(
+            log_buffer,
+            WsmError::ServerError,
+            descriptor_signers::<Tap>(
+                &derived_xprv,
+                (xprv.fingerprint(secp), derivation_path),
+                SignerContext::Tap {
+                    is_internal_key: false,
+                },
+            )
+        )?
+    } else {
+        try_with_log_and_error!(
+            log_buffer,
+            WsmError::ServerError,
+            descriptor_signers::<Segwitv0>(
+                &derived_xprv,
+                (xprv.fingerprint(secp), derivation_path),
+                SignerContext::Segwitv0,
+            )
+        )?
+    };
     let mut wallet = try_with_log_and_error!(
         log_buffer,
         WsmError::ServerError,
         Wallet::new(
-            request.descriptor.as_str(),
-            Some(request.change_descriptor.as_str()),
+            descriptor,
+            Some(change_descriptor),
             network,
             MemoryDatabase::default(),
         )
@@ -491,47 +748,28 @@ async fn sign_psbt(
         internal_signer,
     );
 
-    let mut psbt =
-        PartiallySignedTransaction::from_str(request.psbt.as_str()).expect("Could not parse PSBT");
-
-    // Check inputs have only one signature.
-    try_with_log_and_error!(
-        log_buffer,
-        WsmError::ServerError,
-        verify_inputs_only_have_one_signature(&psbt.inputs)
-    )?;
-
-    // Check inputs already presigned by a key that belongs to the wallet.
-    let extended_descriptors = try_with_log_and_error!(
-        log_buffer,
-        WsmError::ServerError,
-        WalletDescriptors::new(
-            &request.descriptor,
-            &request.change_descriptor,
-            &secp,
-            network,
-        )
-    )?;
-    try_with_log_and_error!(
-        log_buffer,
-        WsmError::ServerError,
-        verify_inputs_pubkey_belongs_to_wallet(&extended_descriptors, &psbt.inputs, &secp)
-    )?;
-
-    let _finalized = try_with_log_and_error!(
-        log_buffer,
-        WsmError::ServerError,
-        wallet.sign(&mut psbt, SignOptions::default())
-    )?;
+    Ok(wallet)
+}
 
-    Ok(Json(SignedPsbt {
-        psbt: psbt.to_string(),
-        root_key_id: request.root_key_id.clone(),
-    }))
+/// Builds the external and change keychain signers for the spend xprv in the given script context.
+fn descriptor_signers<Ctx: ScriptContext>(
+    derived_xprv: &ExtendedPrivKey,
+    origin: (Fingerprint, DerivationPath),
+    signer_context: SignerContext,
+) -> anyhow::Result<(Arc<dyn TransactionSigner>, Arc<dyn TransactionSigner>)> {
+    let external_descriptor_xpriv: DescriptorKey<Ctx> =
+        derived_xprv.into_descriptor_key(Some(origin.clone()), DerivationPath::from_str("m/0")?)?;
+    let internal_descriptor_xpriv: DescriptorKey<Ctx> =
+        derived_xprv.into_descriptor_key(Some(origin), DerivationPath::from_str("m/1")?)?;
+
+    Ok((
+        descriptor_key_to_signer(external_descriptor_xpriv, signer_context)?,
+        descriptor_key_to_signer(internal_descriptor_xpriv, signer_context)?,
+    ))
 }
 
-fn descriptor_key_to_signer(
-    descriptor_xpriv: DescriptorKey<Segwitv0>,
+fn descriptor_key_to_signer<Ctx: ScriptContext>(
+    descriptor_xpriv: DescriptorKey<Ctx>,
     signer_context: SignerContext,
 ) -> anyhow::Result<Arc<dyn TransactionSigner>> {
     let signer: Arc<dyn TransactionSigner> = match descriptor_xpriv {
@@ -573,6 +811,7 @@ async fn create_key(
         &datakey,
         &xprv,
         Some(request.network),
+        None,
         &mut log_buffer,
     )?;
 
@@ -713,6 +952,201 @@ async fn continue_distributed_keygen(
     Ok(Json(EnclaveContinueDistributedKeygenResponse {}))
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+struct InitiateShareRefreshSealedRequest {
+    pub app_refresh_package: RefreshPackage,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct InitiateShareRefreshSealedResponse {
+    pub server_refresh_package: RefreshPackage,
+    pub server_key_commitments: KeyCommitments,
+}
+
+/// Re-randomizes the server's share of a distributed key. The refreshed share details are only
+/// returned wrapped; the caller must hold on to them until `continue_share_refresh` succeeds, and
+/// keep using the existing share until then.
+async fn initiate_share_refresh(
+    State(route_state): State<RouteState>,
+    Json(request): Json<EnclaveInitiateShareRefreshRequest>,
+) -> Result<Json<EnclaveInitiateShareRefreshResponse>, WsmError> {
+    let keystore = route_state.keystore.clone();
+    let mut log_buffer = LogBuffer::new();
+
+    let share_details = decode_wrapped_share_details(
+        keystore.clone(),
+        &request.wrapped_share_details,
+        &request.wrapped_share_details_nonce,
+        &request.dek_id,
+        &request.root_key_id,
+        Some(request.network),
+        &mut log_buffer,
+    )
+    .await?;
+
+    let sealed_request_bytes = BASE64
+        .decode(request.sealed_request.as_bytes())
+        .map_err(|e| WsmError::ServerError {
+            message: format!("Failed to decode sealed request: {}", e),
+            log_buffer: log_buffer.clone(),
+        })?;
+
+    let sealed_request =
+        serde_json::from_slice::<InitiateShareRefreshSealedRequest>(&sealed_request_bytes)
+            .map_err(|e| WsmError::ServerError {
+                message: format!("Failed to deserialize sealed request: {}", e),
+                log_buffer: log_buffer.clone(),
+            })?;
+
+    let initiate_result = initiate_refresh(&share_details, &sealed_request.app_refresh_package)
+        .map_err(|e| WsmError::ServerError {
+            message: format!("Failed to initiate share refresh: {}", e),
+            log_buffer: log_buffer.clone(),
+        })?;
+
+    let sealed_response = InitiateShareRefreshSealedResponse {
+        server_refresh_package: initiate_result.refresh_package,
+        server_key_commitments: initiate_result.share_details.key_commitments.clone(),
+    };
+
+    let sealed_response_bytes =
+        serde_json::to_vec(&sealed_response).map_err(|e| WsmError::ServerError {
+            message: format!("Failed to serialize sealed response: {}", e),
+            log_buffer: log_buffer.clone(),
+        })?;
+
+    let datakey = get_dek(&request.dek_id, keystore, &mut log_buffer).await?;
+    let (wrapped_share_details, wrapped_share_details_nonce) = encrypt_share_details(
+        &request.root_key_id,
+        &datakey,
+        &initiate_result.share_details,
+        Some(request.network),
+        &mut log_buffer,
+    )?;
+
+    Ok(Json(EnclaveInitiateShareRefreshResponse {
+        sealed_response: BASE64.encode(sealed_response_bytes),
+        wrapped_share_details,
+        wrapped_share_details_nonce,
+    }))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct ContinueShareRefreshSealedRequest {
+    pub app_key_commitments: KeyCommitments,
+}
+
+async fn continue_share_refresh(
+    State(route_state): State<RouteState>,
+    Json(request): Json<EnclaveContinueShareRefreshRequest>,
+) -> Result<Json<EnclaveContinueShareRefreshResponse>, WsmError> {
+    let keystore = route_state.keystore.clone();
+    let mut log_buffer = LogBuffer::new();
+
+    // These are the refreshed share details returned by `initiate_share_refresh`.
+    let share_details = decode_wrapped_share_details(
+        keystore.clone(),
+        &request.wrapped_share_details,
+        &request.wrapped_share_details_nonce,
+        &request.dek_id,
+        &request.root_key_id,
+        Some(request.network),
+        &mut log_buffer,
+    )
+    .await?;
+
+    let sealed_request_bytes = BASE64
+        .decode(request.sealed_request.as_bytes())
+        .map_err(|e| WsmError::ServerError {
+            message: format!("Failed to decode sealed request: {}", e),
+            log_buffer: log_buffer.clone(),
+        })?;
+
+    let sealed_request =
+        serde_json::from_slice::<ContinueShareRefreshSealedRequest>(&sealed_request_bytes)
+            .map_err(|e| WsmError::ServerError {
+                message: format!("Failed to deserialize sealed request: {}", e),
+                log_buffer: log_buffer.clone(),
+            })?;
+
+    continue_refresh(share_details, &sealed_request.app_key_commitments).map_err(|e| {
+        WsmError::ServerError {
+            message: format!("Failed to continue share refresh: {}", e),
+            log_buffer: log_buffer.clone(),
+        }
+    })?;
+
+    Ok(Json(EnclaveContinueShareRefreshResponse {}))
+}
+
+async fn set_spend_policy(
+    State(keystore): State<KeyStore>,
+    Json(request): Json<EnclaveSetSpendPolicyRequest>,
+) -> Result<Json<EnclaveSetSpendPolicyResponse>, WsmError> {
+    let mut log_buffer = LogBuffer::new();
+
+    let xprv = decode_wrapped_xprv(
+        keystore.clone(),
+        &request.wrapped_xprv,
+        &request.key_nonce,
+        &request.dek_id,
+        &request.root_key_id,
+        request.network,
+        request.current_spend_policy.as_ref(),
+        &mut log_buffer,
+    )
+    .await?;
+    let secp = Secp256k1::new();
+
+    let network = request.network.unwrap_or(Signet);
+    let extended_descriptors = WalletDescriptors::new(
+        &request.descriptor,
+        &request.change_descriptor,
+        &secp,
+        network,
+    )
+    .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    let (derived_xprv, _) = derive_spend_xprv(&xprv, network, &secp, &mut log_buffer)?;
+    let server_account_key = derived_xprv.private_key.public_key(&secp);
+    // The policy signer is checked against the descriptors' keys, so they must be this key's wallet.
+    if !extended_descriptors
+        .account_keys()
+        .contains(&server_account_key)
+    {
+        let message = "Descriptors do not belong to the wallet of this key".to_string();
+        wsm_log!(log_buffer, &message);
+        return Err(WsmError::BadRequest(message, log_buffer));
+    }
+
+    verify_spend_policy_signer(
+        &request.spend_policy,
+        &request.root_key_id,
+        &extended_descriptors,
+        &server_account_key,
+        &secp,
+    )
+    .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+    verify_spend_policy_replaces(request.current_spend_policy.as_ref(), &request.spend_policy)
+        .map_err(|e| WsmError::invalid_psbt(e, &mut log_buffer))?;
+
+    // The enclave is stateless, so it can't stop the host from keeping the previous wrapped key and
+    // policy around. The API must overwrite them.
+    let datakey = get_dek(&request.dek_id, keystore, &mut log_buffer).await?;
+    let (wrapped_xprv, wrapped_xprv_nonce) = encrypt_root_key(
+        &request.root_key_id,
+        &datakey,
+        &xprv,
+        request.network,
+        Some(&request.spend_policy),
+        &mut log_buffer,
+    )?;
+
+    Ok(Json(EnclaveSetSpendPolicyResponse {
+        wrapped_xprv,
+        wrapped_xprv_nonce,
+    }))
+}
+
 async fn derive_key(
     State(route_state): State<RouteState>,
     Json(request): Json<EnclaveDeriveKeyRequest>,
@@ -731,6 +1165,7 @@ async fn derive_key(
         &request.dek_id,
         &request.key_id,
         request.network,
+        request.spend_policy.as_ref(),
         &mut log_buffer,
     )
     .await?;
@@ -787,10 +1222,12 @@ fn encrypt_root_key(
     datakey: &Aes256Gcm,
     xprv: &ExtendedPrivKey,
     network: Option<Network>,
+    spend_policy: Option<&SignedSpendPolicy>,
     log_buffer: &mut LogBuffer,
 ) -> Result<(String, String), WsmError> {
     let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
-    let aad = Aad::new(root_key_id.to_string(), network);
+    let aad = Aad::new(root_key_id.to_string(), network)
+        .with_spend_policy(spend_policy.map(|p| p.policy.commitment()));
     let payload = Payload {
         aad: &try_with_log_and_error!(log_buffer, WsmError::ServerError, aad.serialize())?,
         msg: &xprv.encode(),
@@ -903,6 +1340,7 @@ async fn decode_wrapped_xprv(
     dek_id: &String,
     root_key_id: &String,
     network: Option<Network>,
+    spend_policy: Option<&SignedSpendPolicy>,
     log_buffer: &mut LogBuffer,
 ) -> Result<ExtendedPrivKey, WsmError> {
     let decoded_wrapped_xprv = BASE64
@@ -919,7 +1357,8 @@ async fn decode_wrapped_xprv(
         })?;
 
     let cipher = get_dek(dek_id, keystore, log_buffer).await?;
-    let aad = Aad::new(root_key_id.to_string(), network);
+    let aad = Aad::new(root_key_id.to_string(), network)
+        .with_spend_policy(spend_policy.map(|p| p.policy.commitment()));
     let plaintext_prv = cipher
         .decrypt(
             Nonce::from_slice(decoded_nonce.as_slice()),
@@ -931,7 +1370,13 @@ async fn decode_wrapped_xprv(
                 msg: decoded_wrapped_xprv.as_ref(),
             },
         )
-        .expect("Could not decrypt xprv");
+        .map_err(|_| {
+            let message =
+                "Could not decrypt xprv; the spend policy must be the one the key was wrapped with"
+                    .to_string();
+            wsm_log!(log_buffer, &message);
+            WsmError::BadRequest(message, log_buffer.clone())
+        })?;
 
     let xprv = ExtendedPrivKey::decode(plaintext_prv.as_slice()).expect("Could not decrypt xprv");
 
@@ -1034,8 +1479,10 @@ impl From<RouteState> for Router {
             .route("/load-secret", post(load_secret))
             .route("/load-integrity-key", post(load_integrity_key))
             .route("/sign-psbt", post(sign_psbt))
+            .route("/sign-message", post(sign_message))
             .route("/create-key", post(create_key))
             .route("/derive-key", post(derive_key))
+            .route("/set-spend-policy", post(set_spend_policy))
             .route("/attestation-doc-from-enclave", get(attestation_doc))
             .route(
                 "/initiate-distributed-keygen",
@@ -1045,6 +1492,8 @@ impl From<RouteState> for Router {
                 "/continue-distributed-keygen",
                 post(continue_distributed_keygen),
             )
+            .route("/initiate-share-refresh", post(initiate_share_refresh))
+            .route("/continue-share-refresh", post(continue_share_refresh))
             .with_state(state)
     }
 }
@@ -1273,6 +1722,7 @@ mod tests {
                                 CoinType::Testnet,
                             )),
                             network: Some(Signet),
+                            spend_policy: None,
                         })
                         .unwrap(),
                     ))
@@ -1,30 +1,89 @@
 use anyhow::{bail, Context};
 
-use bdk::bitcoin::psbt::Input;
-use bdk::bitcoin::secp256k1::{All, Secp256k1};
-
-use bdk::bitcoin::{Network, PublicKey};
-use bdk::descriptor::IntoWalletDescriptor;
-use bdk::keys::DescriptorPublicKey;
-use bdk::miniscript::descriptor::Descriptor;
-
-pub(crate) fn verify_inputs_only_have_one_signature(inputs: &[Input]) -> anyhow::Result<()> {
-    for input in inputs.iter() {
-        if input.partial_sigs.len() != 1 {
-            bail!("Input does not only have one signature")
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use bdk::bitcoin::bip32::{DerivationPath, Fingerprint, KeySource};
+use bdk::bitcoin::psbt::{Input, Output, PartiallySignedTransaction};
+use bdk::bitcoin::secp256k1::{self, All, Secp256k1, XOnlyPublicKey};
+
+use bdk::bitcoin::taproot::{TapLeafHash, TapNodeHash};
+use bdk::bitcoin::{Network, PublicKey, ScriptBuf, TxOut, VarInt};
+use bdk::descriptor::{DescriptorError, IntoWalletDescriptor};
+use bdk::keys::{DescriptorPublicKey, KeyError};
+use bdk::miniscript::descriptor::{Descriptor, WshInner};
+use wsm_common::psbt_validation::{PsbtValidationError, MAX_PSBT_INPUTS};
+
+pub(crate) fn parse_psbt(psbt: &str) -> Result<PartiallySignedTransaction, PsbtValidationError> {
+    PartiallySignedTransaction::from_str(psbt).map_err(|e| PsbtValidationError::Unparseable {
+        message: e.to_string(),
+    })
+}
+
+/// Checks that the PSBT is small enough to sign and that every input spends a known output of the
+/// wallet. This says nothing about who signed the inputs; see
+/// `verify_inputs_pubkey_belongs_to_wallet` for that.
+pub(crate) fn verify_psbt_inputs(
+    wallet_descriptor: &WalletDescriptors,
+    psbt: &PartiallySignedTransaction,
+    secp: &Secp256k1<All>,
+) -> Result<(), PsbtValidationError> {
+    if psbt.inputs.len() > MAX_PSBT_INPUTS {
+        return Err(PsbtValidationError::TooManyInputs {
+            count: psbt.inputs.len(),
+            max: MAX_PSBT_INPUTS,
+        });
+    }
+
+    for (input_index, input) in psbt.inputs.iter().enumerate() {
+        let witness_utxo = input
+            .witness_utxo
+            .as_ref()
+            .ok_or(PsbtValidationError::MissingWitnessUtxo { input_index })?;
+
+        let index = input_derivation_index(input)
+            .ok_or(PsbtValidationError::ForeignInput { input_index })?;
+        let is_wallet_output = wallet_descriptor
+            .to_definite_dpub(index, secp)
+            .map(|derived_descriptor| {
+                derived_descriptor
+                    .script_pubkeys()
+                    .contains(&witness_utxo.script_pubkey)
+            })
+            .unwrap_or(false);
+        if !is_wallet_output {
+            return Err(PsbtValidationError::ForeignInput { input_index });
+        }
+    }
+
+    Ok(())
+}
+
+/// Counts both segwit v0 signatures and taproot script-path signatures. Inputs spent through the
+/// taproot key path are signed jointly with FROST and must not be passed in here.
+/// Takes each input along with its index in the PSBT.
+pub(crate) fn verify_inputs_only_have_one_signature<'a>(
+    inputs: impl IntoIterator<Item = (usize, &'a Input)>,
+) -> Result<(), PsbtValidationError> {
+    for (input_index, input) in inputs {
+        let count = input.partial_sigs.len() + input.tap_script_sigs.len();
+        if count != 1 {
+            return Err(PsbtValidationError::UnexpectedSignatureCount { input_index, count });
         }
     }
 
     Ok(())
 }
 
-pub(crate) fn verify_inputs_pubkey_belongs_to_wallet(
+/// Takes each input along with its index in the PSBT.
+pub(crate) fn verify_inputs_pubkey_belongs_to_wallet<'a>(
     wallet_descriptor: &WalletDescriptors,
-    inputs: &[Input],
+    inputs: impl IntoIterator<Item = (usize, &'a Input)>,
     secp: &Secp256k1<All>,
-) -> anyhow::Result<()> {
-    for input in inputs.iter() {
-        verify_input_belongs_to_wallet(wallet_descriptor, input, secp)?;
+) -> Result<(), PsbtValidationError> {
+    for (input_index, input) in inputs {
+        verify_input_belongs_to_wallet(wallet_descriptor, input, secp)
+            .map_err(|_| PsbtValidationError::UnrecognizedSigner { input_index })?;
     }
 
     Ok(())
@@ -40,19 +99,129 @@ fn verify_input_belongs_to_wallet(
             .bip32_derivation
             .get(&partial_sig_public_key.inner)
             .context("Invalid PSBT")?;
-        let path_vec: Vec<u32> = derivation_path.1.into_iter().map(|&el| el.into()).collect();
-        let last_index = path_vec.last().context("Invalid derivation path")?;
+        let last_index = last_derivation_index(&derivation_path.1)?;
 
-        let derived_descriptor = wallet_descriptor.to_definite_dpub(*last_index, secp)?;
+        let derived_descriptor = wallet_descriptor.to_definite_dpub(last_index, secp)?;
 
         if !derived_descriptor.contains(partial_sig_public_key)? {
             return Err(anyhow::anyhow!("Unrecognized public key"));
         }
     }
 
+    for (tap_script_sig_public_key, _) in input.tap_script_sigs.keys() {
+        let (_, key_source) = input
+            .tap_key_origins
+            .get(tap_script_sig_public_key)
+            .context("Invalid PSBT")?;
+        let last_index = last_derivation_index(&key_source.1)?;
+
+        let derived_descriptor = wallet_descriptor.to_definite_dpub(last_index, secp)?;
+
+        if !derived_descriptor.contains_x_only(tap_script_sig_public_key)? {
+            return Err(anyhow::anyhow!("Unrecognized public key"));
+        }
+    }
+
     Ok(())
 }
 
+/// Checks that a taproot input about to be spent through the key path has the wallet's internal
+/// key and script tree at the derivation index given by its key origins.
+pub(crate) fn verify_key_spend_input_belongs_to_wallet(
+    wallet_descriptor: &WalletDescriptors,
+    input: &Input,
+    secp: &Secp256k1<All>,
+) -> anyhow::Result<()> {
+    let internal_key = input.tap_internal_key.context("Invalid PSBT")?;
+    // The internal key may not be derived (as is the case for a FROST aggregate key), in which
+    // case the index only affects the script tree. Origins for keys outside the wallet say nothing
+    // about where it is.
+    let fingerprints = wallet_descriptor.fingerprints();
+    let index = match input
+        .tap_key_origins
+        .values()
+        .find(|(_, (fingerprint, _))| fingerprints.contains(fingerprint))
+    {
+        Some((_, key_source)) => last_derivation_index(&key_source.1)?,
+        None if input.tap_key_origins.is_empty() => 0,
+        None => bail!("No key origin belongs to the wallet"),
+    };
+
+    let derived_descriptor = wallet_descriptor.to_definite_dpub(index, secp)?;
+    if !derived_descriptor.has_key_spend(&internal_key, input.tap_merkle_root)? {
+        return Err(anyhow::anyhow!("Unrecognized internal key"));
+    }
+
+    Ok(())
+}
+
+/// Whether an output is paid to the wallet, or `None` if the PSBT carries no key origins for it,
+/// i.e. it doesn't claim to be change.
+pub(crate) fn is_wallet_output(
+    wallet_descriptor: &WalletDescriptors,
+    output: &Output,
+    txout: &TxOut,
+    secp: &Secp256k1<All>,
+) -> Option<bool> {
+    let index = derivation_index(
+        &output.bip32_derivation,
+        &output.tap_key_origins,
+        output.tap_internal_key,
+    )?;
+
+    Some(
+        wallet_descriptor
+            .to_definite_dpub(index, secp)
+            .map(|derived_descriptor| {
+                derived_descriptor
+                    .script_pubkeys()
+                    .contains(&txout.script_pubkey)
+            })
+            .unwrap_or(false),
+    )
+}
+
+fn input_derivation_index(input: &Input) -> Option<u32> {
+    derivation_index(
+        &input.bip32_derivation,
+        &input.tap_key_origins,
+        input.tap_internal_key,
+    )
+}
+
+/// The address index of an input or output, taken from whichever key origin the PSBT provides.
+/// Taproot outputs with an underived internal key may have no origins at all, in which case they
+/// can only be at index 0.
+fn derivation_index(
+    bip32_derivation: &BTreeMap<secp256k1::PublicKey, KeySource>,
+    tap_key_origins: &BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, KeySource)>,
+    tap_internal_key: Option<XOnlyPublicKey>,
+) -> Option<u32> {
+    let derivation_path = bip32_derivation
+        .values()
+        .map(|(_, derivation_path)| derivation_path)
+        .chain(
+            tap_key_origins
+                .values()
+                .map(|(_, (_, derivation_path))| derivation_path),
+        )
+        .next();
+
+    match derivation_path {
+        Some(derivation_path) => last_derivation_index(derivation_path).ok(),
+        None if tap_internal_key.is_some() => Some(0),
+        None => None,
+    }
+}
+
+fn last_derivation_index(derivation_path: &DerivationPath) -> anyhow::Result<u32> {
+    derivation_path
+        .into_iter()
+        .last()
+        .map(|&child_number| child_number.into())
+        .context("Invalid derivation path")
+}
+
 pub(crate) struct WalletDescriptors {
     external: Descriptor<DescriptorPublicKey>,
     change: Descriptor<DescriptorPublicKey>,
@@ -64,13 +233,21 @@ impl WalletDescriptors {
         change_descriptor: &String,
         secp: &Secp256k1<All>,
         network: Network,
-    ) -> anyhow::Result<Self> {
+    ) -> Result<Self, PsbtValidationError> {
+        let to_validation_error = |e: DescriptorError| match e {
+            DescriptorError::Key(KeyError::InvalidNetwork) => {
+                PsbtValidationError::NetworkMismatch { network }
+            }
+            e => PsbtValidationError::InvalidDescriptor {
+                message: e.to_string(),
+            },
+        };
         let wallet_extended_descriptor = descriptor
             .into_wallet_descriptor(secp, network)
-            .or_else(|_| bail!("Unable to derive wallet descriptor"))?;
+            .map_err(to_validation_error)?;
         let wallet_extended_change_descriptor = change_descriptor
             .into_wallet_descriptor(secp, network)
-            .or_else(|_| bail!("Unable to derive wallet change descriptor"))?;
+            .map_err(to_validation_error)?;
 
         Ok(Self {
             external: wallet_extended_descriptor.0,
@@ -78,7 +255,43 @@ impl WalletDescriptors {
         })
     }
 
-    fn to_definite_dpub(
+    pub fn is_taproot(&self) -> bool {
+        matches!(self.external, Descriptor::Tr(_))
+    }
+
+    /// The account-level public keys of every extended key in the descriptors.
+    pub fn account_keys(&self) -> Vec<secp256k1::PublicKey> {
+        let mut account_keys = vec![];
+        for descriptor in [&self.external, &self.change] {
+            descriptor.for_each_key(|descriptor_public_key| {
+                if let DescriptorPublicKey::XPub(xpub) = descriptor_public_key {
+                    account_keys.push(xpub.xkey.public_key);
+                }
+                true
+            });
+        }
+        account_keys
+    }
+
+    /// The master fingerprints of every key in the descriptors.
+    pub fn fingerprints(&self) -> Vec<Fingerprint> {
+        let mut fingerprints = vec![];
+        for descriptor in [&self.external, &self.change] {
+            descriptor.for_each_key(|descriptor_public_key| {
+                fingerprints.push(descriptor_public_key.master_fingerprint());
+                true
+            });
+        }
+        fingerprints
+    }
+
+    /// A lower bound on the weight of the witness needed to spend any output of the wallet, so that
+    /// fee rates estimated with it are never lower than the real fee rate once signed.
+    pub fn min_input_satisfaction_weight(&self) -> usize {
+        min_weight_to_satisfy(&self.external).min(min_weight_to_satisfy(&self.change))
+    }
+
+    pub(crate) fn to_definite_dpub(
         &self,
         index: u32,
         secp: &Secp256k1<All>,
@@ -107,7 +320,40 @@ impl WalletDescriptors {
     }
 }
 
-struct DefiniteWalletDescriptor {
+fn min_weight_to_satisfy(descriptor: &Descriptor<DescriptorPublicKey>) -> usize {
+    // The number of witness elements.
+    const WITNESS_COUNT: usize = 1;
+    // A BIP-340 signature with the default sighash, and its length.
+    const MIN_SCHNORR_SIGNATURE: usize = 1 + 64;
+    // The shortest DER-encoded ECDSA signature with its sighash byte, and its length.
+    const MIN_ECDSA_SIGNATURE: usize = 1 + 9;
+    // The internal key, before any merkle path.
+    const CONTROL_BLOCK_BASE_SIZE: usize = 33;
+
+    let with_length = |size: usize| VarInt(size as u64).len() + size;
+    match descriptor {
+        // Leaf signatures aren't counted, but the leaf script and control block always are.
+        Descriptor::Tr(tr) => {
+            tr.iter_scripts()
+                .map(|(depth, ms)| {
+                    with_length(ms.script_size())
+                        + with_length(CONTROL_BLOCK_BASE_SIZE + 32 * depth as usize)
+                })
+                .fold(MIN_SCHNORR_SIGNATURE, usize::min)
+                + WITNESS_COUNT
+        }
+        Descriptor::Wsh(wsh) => match wsh.as_inner() {
+            // The empty element is the dummy consumed by OP_CHECKMULTISIG.
+            WshInner::SortedMulti(smv) => {
+                WITNESS_COUNT + with_length(smv.script_size()) + 1 + smv.k * MIN_ECDSA_SIGNATURE
+            }
+            WshInner::Ms(ms) => WITNESS_COUNT + with_length(ms.script_size()),
+        },
+        _ => 0,
+    }
+}
+
+pub(crate) struct DefiniteWalletDescriptor {
     external: Descriptor<PublicKey>,
     change: Descriptor<PublicKey>,
 }
@@ -117,25 +363,57 @@ impl DefiniteWalletDescriptor {
         Self { external, change }
     }
 
-    fn contains(self, public_key: &PublicKey) -> anyhow::Result<bool> {
-        let descriptor_public_keys = match self.external {
-            Descriptor::Wsh(public_key) => match public_key.into_inner() {
-                bdk::miniscript::descriptor::WshInner::SortedMulti(vec) => vec.pks,
-                _ => bail!("We do not use Wsh Miniscript"),
-            },
-            _ => bail!("Attempted to parse descriptor with unsupported script type."),
-        };
+    pub(crate) fn script_pubkeys(&self) -> [ScriptBuf; 2] {
+        [self.external.script_pubkey(), self.change.script_pubkey()]
+    }
 
-        let change_descriptor_public_keys = match self.change {
-            Descriptor::Wsh(public_key) => match public_key.into_inner() {
-                bdk::miniscript::descriptor::WshInner::SortedMulti(vec) => vec.pks,
-                _ => bail!("We do not use Wsh Miniscript"),
-            },
-            _ => bail!("Attempted to parse descriptor with unsupported script type."),
-        };
+    fn contains(&self, public_key: &PublicKey) -> anyhow::Result<bool> {
+        Ok(descriptor_public_keys(&self.external)?.contains(public_key)
+            || descriptor_public_keys(&self.change)?.contains(public_key))
+    }
+
+    fn contains_x_only(&self, public_key: &XOnlyPublicKey) -> anyhow::Result<bool> {
+        Ok(descriptor_public_keys(&self.external)?
+            .iter()
+            .chain(descriptor_public_keys(&self.change)?.iter())
+            .any(|descriptor_public_key| {
+                descriptor_public_key.inner.x_only_public_key().0 == *public_key
+            }))
+    }
+
+    fn has_key_spend(
+        &self,
+        internal_key: &XOnlyPublicKey,
+        merkle_root: Option<TapNodeHash>,
+    ) -> anyhow::Result<bool> {
+        for descriptor in [&self.external, &self.change] {
+            let Descriptor::Tr(tr) = descriptor else {
+                bail!("Attempted key-path spend with a non-taproot descriptor.");
+            };
+            let spend_info = tr.spend_info();
+            if spend_info.internal_key() == *internal_key && spend_info.merkle_root() == merkle_root
+            {
+                return Ok(true);
+            }
+        }
 
-        Ok(descriptor_public_keys.contains(public_key)
-            || change_descriptor_public_keys.contains(public_key))
+        Ok(false)
+    }
+}
+
+/// Script-path keys of the descriptors we support: `wsh(sortedmulti(...))`, or `tr(...)` whose
+/// leaves hold the multisig keys.
+fn descriptor_public_keys(descriptor: &Descriptor<PublicKey>) -> anyhow::Result<Vec<PublicKey>> {
+    match descriptor {
+        Descriptor::Wsh(wsh) => match wsh.as_inner() {
+            bdk::miniscript::descriptor::WshInner::SortedMulti(vec) => Ok(vec.pks.clone()),
+            _ => bail!("We do not use Wsh Miniscript"),
+        },
+        Descriptor::Tr(tr) => Ok(tr
+            .iter_scripts()
+            .flat_map(|(_, miniscript)| miniscript.iter_pk())
+            .collect()),
+        _ => bail!("Attempted to parse descriptor with unsupported script type."),
     }
 }
 
@@ -143,12 +421,15 @@ impl DefiniteWalletDescriptor {
 mod tests {
     use bdk::{
         bitcoin::{
+            absolute::LockTime,
             bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint},
             ecdsa,
-            key::Secp256k1,
+            key::{KeyPair, Secp256k1},
             psbt::Input,
+            psbt::PartiallySignedTransaction,
             secp256k1::{All, Message},
-            Network, PublicKey as BdkPublicKey,
+            taproot::{self, LeafVersion, TapLeafHash},
+            Network, PublicKey as BdkPublicKey, ScriptBuf, Transaction, TxIn, TxOut,
         },
         keys::DescriptorPublicKey,
         miniscript::{
@@ -157,9 +438,13 @@ mod tests {
         },
     };
     use std::collections::BTreeMap;
+    use std::str::FromStr;
+    use wsm_common::messages::TEST_DPUB_SPEND;
+    use wsm_common::psbt_validation::{PsbtValidationError, MAX_PSBT_INPUTS};
 
     use super::{
-        verify_input_belongs_to_wallet, verify_inputs_only_have_one_signature, WalletDescriptors,
+        parse_psbt, verify_input_belongs_to_wallet, verify_inputs_only_have_one_signature,
+        verify_key_spend_input_belongs_to_wallet, verify_psbt_inputs, WalletDescriptors,
     };
 
     #[test]
@@ -194,15 +479,24 @@ mod tests {
             partial_sigs: BTreeMap::from([(bdk_pk2, sig2)]),
             ..Default::default()
         };
-        assert!(
-            verify_inputs_only_have_one_signature(&vec![input_1.clone(), input_2.clone()]).is_ok()
-        );
+        assert!(verify_inputs_only_have_one_signature(
+            [input_1.clone(), input_2.clone()].iter().enumerate()
+        )
+        .is_ok());
 
         // At least one input has no signatures
-        assert!(verify_inputs_only_have_one_signature(&vec![input_1, Input::default()]).is_err());
+        assert_eq!(
+            verify_inputs_only_have_one_signature([input_1, Input::default()].iter().enumerate()),
+            Err(PsbtValidationError::UnexpectedSignatureCount {
+                input_index: 1,
+                count: 0
+            })
+        );
 
         // No signatures
-        assert!(verify_inputs_only_have_one_signature(&vec![Input::default()]).is_err());
+        assert!(
+            verify_inputs_only_have_one_signature([Input::default()].iter().enumerate()).is_err()
+        );
 
         // More than one signature
         let input_with_more_than_one_partial_sig = Input {
@@ -210,11 +504,11 @@ mod tests {
             ..Default::default()
         };
         assert_eq!(
-            verify_inputs_only_have_one_signature(&vec![input_with_more_than_one_partial_sig])
-                .err()
-                .unwrap()
-                .to_string(),
-            "Input does not only have one signature"
+            verify_inputs_only_have_one_signature([(0, &input_with_more_than_one_partial_sig)]),
+            Err(PsbtValidationError::UnexpectedSignatureCount {
+                input_index: 0,
+                count: 2
+            })
         )
     }
 
@@ -525,4 +819,362 @@ mod tests {
             "Invalid derivation path"
         )
     }
+
+    fn generate_taproot_descriptors(
+        secp: &Secp256k1<All>,
+        derivation_path: &DerivationPath,
+        internal_key: &str,
+        n: usize,
+    ) -> (Vec<ExtendedPrivKey>, WalletDescriptors) {
+        let external_xprvs: Vec<ExtendedPrivKey> = (0..n)
+            .map(|i| {
+                derive_child_xprv(
+                    &generate_xprv(&[i as u8; 32]),
+                    derivation_path,
+                    ChildNumber::Normal { index: 0 },
+                    secp,
+                )
+            })
+            .collect();
+        let change_xprvs: Vec<ExtendedPrivKey> = (0..n)
+            .map(|i| {
+                derive_child_xprv(
+                    &generate_xprv(&[i as u8; 32]),
+                    derivation_path,
+                    ChildNumber::Normal { index: 1 },
+                    secp,
+                )
+            })
+            .collect();
+
+        let tr_descriptor = |xprvs: &[ExtendedPrivKey], child_number: ChildNumber| {
+            let keys = xprvs
+                .iter()
+                .map(|xprv| derive_xpub(xprv, derivation_path, child_number, secp).to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            Descriptor::<DescriptorPublicKey>::from_str(&format!(
+                "tr({internal_key},multi_a(2,{keys}))"
+            ))
+            .unwrap()
+        };
+
+        let descriptors = WalletDescriptors {
+            external: tr_descriptor(&external_xprvs, ChildNumber::Normal { index: 0 }),
+            change: tr_descriptor(&change_xprvs, ChildNumber::Normal { index: 1 }),
+        };
+
+        (external_xprvs, descriptors)
+    }
+
+    #[test]
+    fn test_verify_taproot_input_belongs_to_wallet() {
+        let secp = Secp256k1::new();
+
+        // Setup "Wallet", with a stand-in for the FROST aggregate key as the internal key.
+        let bk_derivation_path: DerivationPath = vec![
+            ChildNumber::from_hardened_idx(86).unwrap(),
+            ChildNumber::from_hardened_idx(1).unwrap(),
+            ChildNumber::from_hardened_idx(0).unwrap(),
+        ]
+        .into();
+        let (_, internal_pk) = secp.generate_keypair(&mut rand::thread_rng());
+        let (internal_key, _) = internal_pk.x_only_public_key();
+        let (external_xprvs, wallet_descriptor) =
+            generate_taproot_descriptors(&secp, &bk_derivation_path, &internal_key.to_string(), 3);
+        assert!(wallet_descriptor.is_taproot());
+
+        let leaf_hash = TapLeafHash::from_script(&ScriptBuf::new(), LeafVersion::TapScript);
+        let index = ChildNumber::Normal { index: 1 };
+
+        // Script-path input signed with a key from a leaf – OK
+        for xprv in &external_xprvs {
+            let derived_xprv = xprv.derive_priv(&secp, &[index]).unwrap();
+            let keypair = KeyPair::from_secret_key(&secp, &derived_xprv.private_key);
+            let (x_only_pk, _) = keypair.x_only_public_key();
+            let sig = taproot::Signature {
+                sig: secp
+                    .sign_schnorr_no_aux_rand(&Message::from_slice(&[0; 32]).unwrap(), &keypair),
+                hash_ty: bdk::bitcoin::sighash::TapSighashType::Default,
+            };
+            let input = Input {
+                tap_script_sigs: BTreeMap::from([((x_only_pk, leaf_hash), sig)]),
+                tap_key_origins: BTreeMap::from([(
+                    x_only_pk,
+                    (
+                        vec![leaf_hash],
+                        (Fingerprint::default(), DerivationPath::from(vec![index])),
+                    ),
+                )]),
+                ..Default::default()
+            };
+            verify_input_belongs_to_wallet(&wallet_descriptor, &input, &secp).unwrap();
+            verify_inputs_only_have_one_signature([(0, &input)]).unwrap();
+        }
+
+        // Script-path input signed with a key not in the leaves – nOK
+        let (sk_1, pk_1) = secp.generate_keypair(&mut rand::thread_rng());
+        let (x_only_pk_1, _) = pk_1.x_only_public_key();
+        let input = Input {
+            tap_script_sigs: BTreeMap::from([(
+                (x_only_pk_1, leaf_hash),
+                taproot::Signature {
+                    sig: secp.sign_schnorr_no_aux_rand(
+                        &Message::from_slice(&[0; 32]).unwrap(),
+                        &KeyPair::from_secret_key(&secp, &sk_1),
+                    ),
+                    hash_ty: bdk::bitcoin::sighash::TapSighashType::Default,
+                },
+            )]),
+            tap_key_origins: BTreeMap::from([(
+                x_only_pk_1,
+                (
+                    vec![leaf_hash],
+                    (Fingerprint::default(), DerivationPath::from(vec![index])),
+                ),
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(
+            verify_input_belongs_to_wallet(&wallet_descriptor, &input, &secp)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Unrecognized public key"
+        );
+
+        // Key-path input with the wallet's internal key and script tree – OK
+        let merkle_root = match wallet_descriptor
+            .to_definite_dpub(1, &secp)
+            .unwrap()
+            .external
+        {
+            Descriptor::Tr(tr) => tr.spend_info().merkle_root(),
+            _ => unreachable!(),
+        };
+        let wallet_fingerprint = external_xprvs[0].fingerprint(&secp);
+        let key_spend_input = Input {
+            tap_internal_key: Some(internal_key),
+            tap_merkle_root: merkle_root,
+            tap_key_origins: BTreeMap::from([(
+                internal_key,
+                (
+                    vec![],
+                    (wallet_fingerprint, DerivationPath::from(vec![index])),
+                ),
+            )]),
+            ..Default::default()
+        };
+        verify_key_spend_input_belongs_to_wallet(&wallet_descriptor, &key_spend_input, &secp)
+            .unwrap();
+
+        // Key-path input with an extra origin from outside the wallet at another index – OK, the
+        // wallet's own origin is used whichever comes first
+        let mut decoy_origin_input = key_spend_input.clone();
+        for (_, x_only_pk) in [
+            secp.generate_keypair(&mut rand::thread_rng()),
+            secp.generate_keypair(&mut rand::thread_rng()),
+        ] {
+            decoy_origin_input.tap_key_origins.insert(
+                x_only_pk.x_only_public_key().0,
+                (
+                    vec![],
+                    (
+                        Fingerprint::default(),
+                        DerivationPath::from(vec![ChildNumber::Normal { index: 7 }]),
+                    ),
+                ),
+            );
+        }
+        verify_key_spend_input_belongs_to_wallet(&wallet_descriptor, &decoy_origin_input, &secp)
+            .unwrap();
+
+        // Key-path input whose only origin is from outside the wallet – nOK
+        let foreign_origin_input = Input {
+            tap_key_origins: BTreeMap::from([(
+                internal_key,
+                (
+                    vec![],
+                    (Fingerprint::default(), DerivationPath::from(vec![index])),
+                ),
+            )]),
+            ..key_spend_input.clone()
+        };
+        assert!(verify_key_spend_input_belongs_to_wallet(
+            &wallet_descriptor,
+            &foreign_origin_input,
+            &secp
+        )
+        .is_err());
+
+        // Key-path input committing to a different script tree – nOK
+        let wrong_tree_input = Input {
+            tap_merkle_root: None,
+            ..key_spend_input.clone()
+        };
+        assert!(verify_key_spend_input_belongs_to_wallet(
+            &wallet_descriptor,
+            &wrong_tree_input,
+            &secp
+        )
+        .is_err());
+
+        // Key-path input with someone else's internal key – nOK
+        let wrong_key_input = Input {
+            tap_internal_key: Some(x_only_pk_1),
+            ..key_spend_input
+        };
+        assert!(verify_key_spend_input_belongs_to_wallet(
+            &wallet_descriptor,
+            &wrong_key_input,
+            &secp
+        )
+        .is_err());
+    }
+
+    fn psbt_with_inputs(inputs: Vec<Input>) -> PartiallySignedTransaction {
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default(); inputs.len()],
+            output: vec![],
+        })
+        .unwrap();
+        psbt.inputs = inputs;
+        psbt
+    }
+
+    #[test]
+    fn test_parse_psbt() {
+        assert!(matches!(
+            parse_psbt("not a psbt"),
+            Err(PsbtValidationError::Unparseable { .. })
+        ));
+
+        let psbt = psbt_with_inputs(vec![Input::default()]);
+        assert_eq!(parse_psbt(&psbt.to_string()).unwrap(), psbt);
+    }
+
+    #[test]
+    fn test_wallet_descriptors_network_mismatch() {
+        let secp = Secp256k1::new();
+        let descriptor = format!("wsh(sortedmulti(1,{}))", *TEST_DPUB_SPEND);
+
+        assert!(WalletDescriptors::new(&descriptor, &descriptor, &secp, Network::Testnet).is_ok());
+        assert_eq!(
+            WalletDescriptors::new(&descriptor, &descriptor, &secp, Network::Bitcoin).err(),
+            Some(PsbtValidationError::NetworkMismatch {
+                network: Network::Bitcoin
+            })
+        );
+        assert!(matches!(
+            WalletDescriptors::new(
+                &"wsh(garbage)".to_string(),
+                &descriptor,
+                &secp,
+                Network::Testnet
+            ),
+            Err(PsbtValidationError::InvalidDescriptor { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_psbt_inputs() {
+        let secp = Secp256k1::new();
+
+        // Setup "Wallet"
+        let bk_derivation_path: DerivationPath = vec![
+            ChildNumber::from_hardened_idx(84).unwrap(),
+            ChildNumber::from_hardened_idx(1).unwrap(),
+            ChildNumber::from_hardened_idx(0).unwrap(),
+        ]
+        .into();
+        let (_, _, wallet_descriptor) = generate_descriptors(&secp, &bk_derivation_path, 3);
+
+        let index = ChildNumber::Normal { index: 1 };
+        let [external_script_pubkey, change_script_pubkey] = wallet_descriptor
+            .to_definite_dpub(1, &secp)
+            .unwrap()
+            .script_pubkeys();
+        let (_, pk) = secp.generate_keypair(&mut rand::thread_rng());
+        let wallet_input = |script_pubkey: ScriptBuf| Input {
+            witness_utxo: Some(TxOut {
+                value: 10_000,
+                script_pubkey,
+            }),
+            bip32_derivation: BTreeMap::from([(
+                pk,
+                (Fingerprint::default(), DerivationPath::from(vec![index])),
+            )]),
+            ..Default::default()
+        };
+
+        // Inputs spending external and change outputs – OK
+        verify_psbt_inputs(
+            &wallet_descriptor,
+            &psbt_with_inputs(vec![
+                wallet_input(external_script_pubkey.clone()),
+                wallet_input(change_script_pubkey),
+            ]),
+            &secp,
+        )
+        .unwrap();
+
+        // Missing witness UTXO – nOK
+        let missing_utxo_input = Input {
+            witness_utxo: None,
+            ..wallet_input(external_script_pubkey.clone())
+        };
+        assert_eq!(
+            verify_psbt_inputs(
+                &wallet_descriptor,
+                &psbt_with_inputs(vec![
+                    wallet_input(external_script_pubkey.clone()),
+                    missing_utxo_input
+                ]),
+                &secp,
+            ),
+            Err(PsbtValidationError::MissingWitnessUtxo { input_index: 1 })
+        );
+
+        // Output that isn't the wallet's at the claimed index – nOK
+        assert_eq!(
+            verify_psbt_inputs(
+                &wallet_descriptor,
+                &psbt_with_inputs(vec![wallet_input(ScriptBuf::new())]),
+                &secp,
+            ),
+            Err(PsbtValidationError::ForeignInput { input_index: 0 })
+        );
+
+        // No derivation information at all – nOK
+        let no_origin_input = Input {
+            bip32_derivation: BTreeMap::new(),
+            ..wallet_input(external_script_pubkey.clone())
+        };
+        assert_eq!(
+            verify_psbt_inputs(
+                &wallet_descriptor,
+                &psbt_with_inputs(vec![no_origin_input]),
+                &secp,
+            ),
+            Err(PsbtValidationError::ForeignInput { input_index: 0 })
+        );
+
+        // Too many inputs – nOK
+        assert_eq!(
+            verify_psbt_inputs(
+                &wallet_descriptor,
+                &psbt_with_inputs(vec![
+                    wallet_input(external_script_pubkey);
+                    MAX_PSBT_INPUTS + 1
+                ]),
+                &secp,
+            ),
+            Err(PsbtValidationError::TooManyInputs {
+                count: MAX_PSBT_INPUTS + 1,
+                max: MAX_PSBT_INPUTS
+            })
+        );
+    }
 }
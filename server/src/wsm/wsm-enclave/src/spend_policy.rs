@@ -0,0 +1,557 @@
+use bdk::bitcoin::psbt::{Input, PartiallySignedTransaction};
+use bdk::bitcoin::secp256k1::{All, PublicKey, Secp256k1};
+use bdk::bitcoin::TxIn;
+use wsm_common::psbt_validation::PsbtValidationError;
+use wsm_common::spend_policy::SignedSpendPolicy;
+
+use crate::psbt_verification::{is_wallet_output, WalletDescriptors};
+
+/// Checks that a spend policy was set for `root_key_id` and signed by one of the wallet's keys other
+/// than the Server's.
+pub(crate) fn verify_spend_policy_signer(
+    signed_policy: &SignedSpendPolicy,
+    root_key_id: &str,
+    wallet_descriptor: &WalletDescriptors,
+    server_account_key: &PublicKey,
+    secp: &Secp256k1<All>,
+) -> Result<(), PsbtValidationError> {
+    if signed_policy.policy.root_key_id != root_key_id {
+        return Err(PsbtValidationError::SpendPolicyKeyMismatch {
+            root_key_id: root_key_id.to_string(),
+            policy_root_key_id: signed_policy.policy.root_key_id.clone(),
+        });
+    }
+    // The Server's own key must not be able to vouch for a policy; otherwise anyone who can ask the
+    // enclave to sign could write their own.
+    if signed_policy.signer == *server_account_key
+        || !wallet_descriptor
+            .account_keys()
+            .contains(&signed_policy.signer)
+    {
+        return Err(PsbtValidationError::UnrecognizedSpendPolicySigner);
+    }
+    secp.verify_ecdsa(
+        &signed_policy.policy.signing_message(),
+        &signed_policy.signature,
+        &signed_policy.signer,
+    )
+    .map_err(|_| PsbtValidationError::InvalidSpendPolicySignature)
+}
+
+/// Checks that `new_policy` may replace the policy a key currently has.
+pub(crate) fn verify_spend_policy_replaces(
+    current_policy: Option<&SignedSpendPolicy>,
+    new_policy: &SignedSpendPolicy,
+) -> Result<(), PsbtValidationError> {
+    match current_policy {
+        Some(current) if new_policy.policy.version <= current.policy.version => {
+            Err(PsbtValidationError::StaleSpendPolicy {
+                version: new_policy.policy.version,
+                current_version: current.policy.version,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks the PSBT against a spend policy signed by one of the wallet's other keys. Assumes the
+/// inputs have already been checked with `verify_psbt_inputs`.
+pub(crate) fn verify_spend_policy(
+    signed_policy: &SignedSpendPolicy,
+    root_key_id: &str,
+    wallet_descriptor: &WalletDescriptors,
+    server_account_key: &PublicKey,
+    psbt: &PartiallySignedTransaction,
+    secp: &Secp256k1<All>,
+) -> Result<(), PsbtValidationError> {
+    verify_spend_policy_signer(
+        signed_policy,
+        root_key_id,
+        wallet_descriptor,
+        server_account_key,
+        secp,
+    )?;
+
+    let policy = &signed_policy.policy;
+
+    let mut outflow_sats: u64 = 0;
+    for (output_index, (output, txout)) in psbt
+        .outputs
+        .iter()
+        .zip(psbt.unsigned_tx.output.iter())
+        .enumerate()
+    {
+        match is_wallet_output(wallet_descriptor, output, txout, secp) {
+            Some(true) => {}
+            Some(false) if policy.require_change_in_wallet => {
+                return Err(PsbtValidationError::ChangeOutputNotInWallet { output_index });
+            }
+            Some(false) | None => outflow_sats = outflow_sats.saturating_add(txout.value),
+        }
+    }
+    if let Some(max_sats) = policy.max_outflow_sats {
+        if outflow_sats > max_sats {
+            return Err(PsbtValidationError::MaxOutflowExceeded {
+                outflow_sats,
+                max_sats,
+            });
+        }
+    }
+
+    if let Some(max_sat_per_vb) = policy.max_fee_rate_sat_per_vb {
+        let fee_rate_sat_per_vb = estimate_fee_rate(wallet_descriptor, psbt)?;
+        if fee_rate_sat_per_vb > max_sat_per_vb {
+            return Err(PsbtValidationError::FeeRateTooHigh {
+                fee_rate_sat_per_vb,
+                max_sat_per_vb,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Estimates the fee rate once fully signed, rounding up. Every input is assumed to need the
+/// smallest possible witness, so the estimate is never lower than the real fee rate.
+fn estimate_fee_rate(
+    wallet_descriptor: &WalletDescriptors,
+    psbt: &PartiallySignedTransaction,
+) -> Result<u64, PsbtValidationError> {
+    let mut input_sats: u64 = 0;
+    for (input_index, (input, txin)) in psbt
+        .inputs
+        .iter()
+        .zip(psbt.unsigned_tx.input.iter())
+        .enumerate()
+    {
+        input_sats =
+            input_sats.saturating_add(input_value(wallet_descriptor, input, txin, input_index)?);
+    }
+    let output_sats = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .fold(0u64, |total, txout| total.saturating_add(txout.value));
+    let fee_sats = input_sats.saturating_sub(output_sats);
+
+    let satisfaction_weight = wallet_descriptor.min_input_satisfaction_weight();
+    let weight =
+        psbt.unsigned_tx.weight().to_wu() + (satisfaction_weight * psbt.inputs.len()) as u64;
+    let vsize = weight.div_ceil(4);
+
+    Ok(fee_sats.div_ceil(vsize))
+}
+
+/// The value of the output an input spends. Segwit v0 signatures only commit to the value of the
+/// input they sign, so a host could understate the value of the other inputs in `witness_utxo` to
+/// hide the fee. Their value is taken from the full previous transaction instead, which must hash
+/// to the outpoint being spent. Taproot signatures commit to the value of every input.
+fn input_value(
+    wallet_descriptor: &WalletDescriptors,
+    input: &Input,
+    txin: &TxIn,
+    input_index: usize,
+) -> Result<u64, PsbtValidationError> {
+    if wallet_descriptor.is_taproot() {
+        return input
+            .witness_utxo
+            .as_ref()
+            .map(|witness_utxo| witness_utxo.value)
+            .ok_or(PsbtValidationError::MissingWitnessUtxo { input_index });
+    }
+
+    let previous_tx = input
+        .non_witness_utxo
+        .as_ref()
+        .ok_or(PsbtValidationError::MissingNonWitnessUtxo { input_index })?;
+    if previous_tx.txid() != txin.previous_output.txid {
+        return Err(PsbtValidationError::NonWitnessUtxoMismatch { input_index });
+    }
+    previous_tx
+        .output
+        .get(txin.previous_output.vout as usize)
+        .map(|txout| txout.value)
+        .ok_or(PsbtValidationError::NonWitnessUtxoMismatch { input_index })
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::bitcoin::{
+        absolute::LockTime,
+        bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint},
+        psbt::{Input, Output, PartiallySignedTransaction},
+        secp256k1::{All, PublicKey, Secp256k1, SecretKey},
+        Network, OutPoint, ScriptBuf, Transaction, TxIn, TxOut,
+    };
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+    use wsm_common::psbt_validation::PsbtValidationError;
+    use wsm_common::spend_policy::{SignedSpendPolicy, SpendPolicy};
+
+    use super::{verify_spend_policy, verify_spend_policy_replaces};
+    use crate::psbt_verification::WalletDescriptors;
+
+    const ROOT_KEY_ID: &str = "root_key_id";
+
+    struct TestWallet {
+        descriptors: WalletDescriptors,
+        app_account_key: SecretKey,
+        server_account_key: PublicKey,
+        script_pubkeys: [ScriptBuf; 2],
+    }
+
+    fn account_xprv(seed: u8, secp: &Secp256k1<All>) -> (ExtendedPrivKey, String) {
+        let master = ExtendedPrivKey::new_master(Network::Testnet, &[seed; 32]).unwrap();
+        let path = DerivationPath::from_str("m/84'/1'/0'").unwrap();
+        let account = master.derive_priv(secp, &path).unwrap();
+        let xpub = ExtendedPubKey::from_priv(secp, &account);
+        let origin = format!("[{}/84'/1'/0']", master.fingerprint(secp));
+        (account, format!("{origin}{xpub}"))
+    }
+
+    fn test_wallet(secp: &Secp256k1<All>) -> TestWallet {
+        let (app, app_dpub) = account_xprv(0, secp);
+        let (_, hw_dpub) = account_xprv(1, secp);
+        let (server, server_dpub) = account_xprv(2, secp);
+
+        let descriptor = |keychain: u32| {
+            format!("wsh(sortedmulti(2,{app_dpub}/{keychain}/*,{hw_dpub}/{keychain}/*,{server_dpub}/{keychain}/*))")
+        };
+        let descriptors =
+            WalletDescriptors::new(&descriptor(0), &descriptor(1), secp, Network::Testnet).unwrap();
+        let script_pubkeys = descriptors
+            .to_definite_dpub(0, secp)
+            .unwrap()
+            .script_pubkeys();
+
+        TestWallet {
+            descriptors,
+            app_account_key: app.private_key,
+            server_account_key: server.private_key.public_key(secp),
+            script_pubkeys,
+        }
+    }
+
+    fn sign_policy(
+        policy: SpendPolicy,
+        secret_key: &SecretKey,
+        secp: &Secp256k1<All>,
+    ) -> SignedSpendPolicy {
+        SignedSpendPolicy {
+            signature: secp.sign_ecdsa(&policy.signing_message(), secret_key),
+            signer: secret_key.public_key(secp),
+            policy,
+        }
+    }
+
+    /// Spends a 100,000 sat wallet output to `outputs`, where each output is optionally marked as
+    /// change at index 0.
+    fn psbt(outputs: Vec<(TxOut, bool)>) -> PartiallySignedTransaction {
+        let previous_output = TxOut {
+            value: 100_000,
+            script_pubkey: ScriptBuf::new(),
+        };
+        let previous_tx = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![previous_output.clone()],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(previous_tx.txid(), 0),
+                ..Default::default()
+            }],
+            output: outputs.iter().map(|(txout, _)| txout.clone()).collect(),
+        })
+        .unwrap();
+        psbt.inputs = vec![Input {
+            witness_utxo: Some(previous_output),
+            non_witness_utxo: Some(previous_tx),
+            ..Default::default()
+        }];
+        let (_, change_pk) = Secp256k1::new().generate_keypair(&mut rand::thread_rng());
+        psbt.outputs = outputs
+            .iter()
+            .map(|(_, is_change)| Output {
+                bip32_derivation: if *is_change {
+                    BTreeMap::from([(
+                        change_pk,
+                        (
+                            Fingerprint::default(),
+                            DerivationPath::from(vec![ChildNumber::Normal { index: 0 }]),
+                        ),
+                    )])
+                } else {
+                    BTreeMap::new()
+                },
+                ..Default::default()
+            })
+            .collect();
+        psbt
+    }
+
+    #[test]
+    fn test_policy_signer_must_be_a_non_server_wallet_key() {
+        let secp = Secp256k1::new();
+        let wallet = test_wallet(&secp);
+        let psbt = psbt(vec![]);
+        let policy = SpendPolicy {
+            root_key_id: ROOT_KEY_ID.to_string(),
+            version: 1,
+            max_outflow_sats: None,
+            require_change_in_wallet: false,
+            max_fee_rate_sat_per_vb: None,
+        };
+
+        let signed = sign_policy(policy.clone(), &wallet.app_account_key, &secp);
+        verify_spend_policy(
+            &signed,
+            ROOT_KEY_ID,
+            &wallet.descriptors,
+            &wallet.server_account_key,
+            &psbt,
+            &secp,
+        )
+        .unwrap();
+
+        // Tampered policy
+        let tampered = SignedSpendPolicy {
+            policy: SpendPolicy {
+                max_outflow_sats: Some(1),
+                ..policy.clone()
+            },
+            ..signed.clone()
+        };
+        assert_eq!(
+            verify_spend_policy(
+                &tampered,
+                ROOT_KEY_ID,
+                &wallet.descriptors,
+                &wallet.server_account_key,
+                &psbt,
+                &secp,
+            ),
+            Err(PsbtValidationError::InvalidSpendPolicySignature)
+        );
+
+        // Policy set for another key
+        assert_eq!(
+            verify_spend_policy(
+                &signed,
+                "another_root_key_id",
+                &wallet.descriptors,
+                &wallet.server_account_key,
+                &psbt,
+                &secp,
+            ),
+            Err(PsbtValidationError::SpendPolicyKeyMismatch {
+                root_key_id: "another_root_key_id".to_string(),
+                policy_root_key_id: ROOT_KEY_ID.to_string(),
+            })
+        );
+
+        // Key outside the wallet
+        let (outsider, _) = secp.generate_keypair(&mut rand::thread_rng());
+        assert_eq!(
+            verify_spend_policy(
+                &sign_policy(policy.clone(), &outsider, &secp),
+                ROOT_KEY_ID,
+                &wallet.descriptors,
+                &wallet.server_account_key,
+                &psbt,
+                &secp,
+            ),
+            Err(PsbtValidationError::UnrecognizedSpendPolicySigner)
+        );
+
+        // The Server's own key
+        let (server, _) = account_xprv(2, &secp);
+        assert_eq!(
+            verify_spend_policy(
+                &sign_policy(policy, &server.private_key, &secp),
+                ROOT_KEY_ID,
+                &wallet.descriptors,
+                &wallet.server_account_key,
+                &psbt,
+                &secp,
+            ),
+            Err(PsbtValidationError::UnrecognizedSpendPolicySigner)
+        );
+    }
+
+    #[test]
+    fn test_policy_version_must_increase() {
+        let secp = Secp256k1::new();
+        let wallet = test_wallet(&secp);
+        let policy = |version| {
+            sign_policy(
+                SpendPolicy {
+                    root_key_id: ROOT_KEY_ID.to_string(),
+                    version,
+                    max_outflow_sats: None,
+                    require_change_in_wallet: false,
+                    max_fee_rate_sat_per_vb: None,
+                },
+                &wallet.app_account_key,
+                &secp,
+            )
+        };
+
+        // First policy for the key – OK
+        verify_spend_policy_replaces(None, &policy(1)).unwrap();
+        // Newer policy – OK
+        verify_spend_policy_replaces(Some(&policy(1)), &policy(2)).unwrap();
+        // Same or older policy – nOK
+        assert_eq!(
+            verify_spend_policy_replaces(Some(&policy(2)), &policy(2)),
+            Err(PsbtValidationError::StaleSpendPolicy {
+                version: 2,
+                current_version: 2
+            })
+        );
+        assert_eq!(
+            verify_spend_policy_replaces(Some(&policy(2)), &policy(1)),
+            Err(PsbtValidationError::StaleSpendPolicy {
+                version: 1,
+                current_version: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_max_outflow_and_change() {
+        let secp = Secp256k1::new();
+        let wallet = test_wallet(&secp);
+        let policy = sign_policy(
+            SpendPolicy {
+                root_key_id: ROOT_KEY_ID.to_string(),
+                version: 1,
+                max_outflow_sats: Some(50_000),
+                require_change_in_wallet: true,
+                max_fee_rate_sat_per_vb: None,
+            },
+            &wallet.app_account_key,
+            &secp,
+        );
+        let external = |value| TxOut {
+            value,
+            script_pubkey: ScriptBuf::new(),
+        };
+        let change = |value| TxOut {
+            value,
+            script_pubkey: wallet.script_pubkeys[1].clone(),
+        };
+        let verify = |psbt| {
+            verify_spend_policy(
+                &policy,
+                ROOT_KEY_ID,
+                &wallet.descriptors,
+                &wallet.server_account_key,
+                &psbt,
+                &secp,
+            )
+        };
+
+        // Payment under the limit with change back to the wallet – OK
+        verify(psbt(vec![
+            (external(40_000), false),
+            (change(59_000), true),
+        ]))
+        .unwrap();
+
+        // Payment over the limit – nOK
+        assert_eq!(
+            verify(psbt(vec![
+                (external(60_000), false),
+                (change(39_000), true)
+            ])),
+            Err(PsbtValidationError::MaxOutflowExceeded {
+                outflow_sats: 60_000,
+                max_sats: 50_000
+            })
+        );
+
+        // Payments that add up to more than the limit – nOK
+        assert!(verify(psbt(vec![
+            (external(30_000), false),
+            (external(30_000), false)
+        ]))
+        .is_err());
+
+        // "Change" paid outside the wallet – nOK
+        assert_eq!(
+            verify(psbt(vec![
+                (external(40_000), false),
+                (external(59_000), true)
+            ])),
+            Err(PsbtValidationError::ChangeOutputNotInWallet { output_index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_max_fee_rate() {
+        let secp = Secp256k1::new();
+        let wallet = test_wallet(&secp);
+        let policy = sign_policy(
+            SpendPolicy {
+                root_key_id: ROOT_KEY_ID.to_string(),
+                version: 1,
+                max_outflow_sats: None,
+                require_change_in_wallet: false,
+                max_fee_rate_sat_per_vb: Some(100),
+            },
+            &wallet.app_account_key,
+            &secp,
+        );
+        let verify = |psbt| {
+            verify_spend_policy(
+                &policy,
+                ROOT_KEY_ID,
+                &wallet.descriptors,
+                &wallet.server_account_key,
+                &psbt,
+                &secp,
+            )
+        };
+        let change = |value| TxOut {
+            value,
+            script_pubkey: wallet.script_pubkeys[1].clone(),
+        };
+
+        // A 1,000 sat fee on a ~130 vB transaction – OK
+        verify(psbt(vec![(change(99_000), true)])).unwrap();
+
+        // Spending almost everything to fees – nOK
+        assert!(matches!(
+            verify(psbt(vec![(change(1_000), true)])),
+            Err(PsbtValidationError::FeeRateTooHigh {
+                max_sat_per_vb: 100,
+                ..
+            })
+        ));
+
+        // Input value only claimed by the witness UTXO – nOK
+        let mut unverified = psbt(vec![(change(99_000), true)]);
+        unverified.inputs[0].non_witness_utxo = None;
+        assert_eq!(
+            verify(unverified),
+            Err(PsbtValidationError::MissingNonWitnessUtxo { input_index: 0 })
+        );
+
+        // Previous transaction that isn't the one being spent – nOK
+        let mut mismatched = psbt(vec![(change(99_000), true)]);
+        mismatched.inputs[0]
+            .non_witness_utxo
+            .as_mut()
+            .unwrap()
+            .output[0]
+            .value = 200_000;
+        assert_eq!(
+            verify(mismatched),
+            Err(PsbtValidationError::NonWitnessUtxoMismatch { input_index: 0 })
+        );
+    }
+}
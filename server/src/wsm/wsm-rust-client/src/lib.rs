@@ -13,13 +13,19 @@ use url::Url;
 use wsm_common::bitcoin::Network;
 use wsm_common::messages::api::{
     AttestationDocResponse, ContinueDistributedKeygenRequest, ContinueDistributedKeygenResponse,
-    CreateRootKeyRequest, GetIntegritySigRequest, GetIntegritySigResponse,
-    InitiateDistributedKeygenRequest, InitiateDistributedKeygenResponse,
+    ContinueShareRefreshRequest, ContinueShareRefreshResponse, CreateRootKeyRequest,
+    GetIntegritySigRequest, GetIntegritySigResponse, InitiateDistributedKeygenRequest,
+    InitiateDistributedKeygenResponse, InitiateShareRefreshRequest, InitiateShareRefreshResponse,
+    SetSpendPolicyRequest, SignMessageRequest,
 };
 
+pub use wsm_common::bip322::Bip322SignatureFormat;
+pub use wsm_common::messages::api::{SetSpendPolicyResponse, SignedMessage};
 pub use wsm_common::messages::{
     TEST_DPUB_SPEND, TEST_XPUB_CONFIG, TEST_XPUB_SPEND, TEST_XPUB_SPEND_ORIGIN,
 };
+pub use wsm_common::psbt_validation::PsbtValidationError;
+pub use wsm_common::spend_policy::{SignedSpendPolicy, SpendPolicy};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -33,6 +39,8 @@ pub enum Error {
     Wsm(String),
     #[error("Not Implemented: {0}")]
     NotImplemented(String),
+    #[error("Invalid PSBT: {0}")]
+    InvalidPsbt(PsbtValidationError),
 }
 
 #[derive(Deserialize, Serialize)]
@@ -81,6 +89,18 @@ pub trait SigningService {
         network: Network,
         sealed_request: &str,
     ) -> Result<ContinueDistributedKeygenResponse, Error>;
+    async fn initiate_share_refresh(
+        &self,
+        root_key_id: &str,
+        network: Network,
+        sealed_request: &str,
+    ) -> Result<InitiateShareRefreshResponse, Error>;
+    async fn continue_share_refresh(
+        &self,
+        root_key_id: &str,
+        network: Network,
+        sealed_request: &str,
+    ) -> Result<ContinueShareRefreshResponse, Error>;
     async fn sign_psbt(
         &self,
         root_key_id: &str,
@@ -88,6 +108,22 @@ pub trait SigningService {
         change_descriptor: &str,
         psbt: &str,
     ) -> Result<SignedPsbt, Error>;
+    async fn sign_message(
+        &self,
+        root_key_id: &str,
+        descriptor: &str,
+        change_descriptor: &str,
+        message: &str,
+        psbt: &str,
+        format: Bip322SignatureFormat,
+    ) -> Result<SignedMessage, Error>;
+    async fn set_spend_policy(
+        &self,
+        root_key_id: &str,
+        descriptor: &str,
+        change_descriptor: &str,
+        spend_policy: &SignedSpendPolicy,
+    ) -> Result<SetSpendPolicyResponse, Error>;
     async fn get_key_integrity_sig(
         &self,
         root_key_id: &str,
@@ -105,6 +141,8 @@ pub struct WsmClient {
 struct WsmError {
     #[serde(rename = "error")]
     message: String,
+    #[serde(default)]
+    psbt_validation_error: Option<PsbtValidationError>,
 }
 
 impl Debug for WsmClient {
@@ -139,6 +177,10 @@ impl WsmClient {
             Ok(res.json().await?)
         } else {
             match res.json::<WsmError>().await {
+                Ok(WsmError {
+                    psbt_validation_error: Some(psbt_validation_error),
+                    ..
+                }) => Err(Error::InvalidPsbt(psbt_validation_error)),
                 Ok(wsm_error) => Err(Error::Wsm(wsm_error.message)),
                 Err(err) => Err(Error::Wsm(err.to_string())),
             }
@@ -219,6 +261,48 @@ impl SigningService for WsmClient {
         self.handle_wsm_response(res).await
     }
 
+    #[instrument]
+    async fn initiate_share_refresh(
+        &self,
+        root_key_id: &str,
+        network: Network,
+        sealed_request: &str,
+    ) -> Result<InitiateShareRefreshResponse, Error> {
+        let res = self
+            .client
+            .post(self.endpoint.join("initiate-share-refresh")?)
+            .json(&InitiateShareRefreshRequest {
+                root_key_id: root_key_id.to_string(),
+                network,
+                sealed_request: sealed_request.to_string(),
+            })
+            .send()
+            .await?;
+
+        self.handle_wsm_response(res).await
+    }
+
+    #[instrument]
+    async fn continue_share_refresh(
+        &self,
+        root_key_id: &str,
+        network: Network,
+        sealed_request: &str,
+    ) -> Result<ContinueShareRefreshResponse, Error> {
+        let res = self
+            .client
+            .post(self.endpoint.join("continue-share-refresh")?)
+            .json(&ContinueShareRefreshRequest {
+                root_key_id: root_key_id.to_string(),
+                network,
+                sealed_request: sealed_request.to_string(),
+            })
+            .send()
+            .await?;
+
+        self.handle_wsm_response(res).await
+    }
+
     #[instrument(skip(descriptor, root_key_id, change_descriptor, psbt))]
     async fn sign_psbt(
         &self,
@@ -242,6 +326,56 @@ impl SigningService for WsmClient {
         self.handle_wsm_response(res).await
     }
 
+    #[instrument(skip(descriptor, root_key_id, change_descriptor, psbt))]
+    async fn sign_message(
+        &self,
+        root_key_id: &str,
+        descriptor: &str,
+        change_descriptor: &str,
+        message: &str,
+        psbt: &str,
+        format: Bip322SignatureFormat,
+    ) -> Result<SignedMessage, Error> {
+        let res = self
+            .client
+            .post(self.endpoint.join("sign-message")?)
+            .json(&SignMessageRequest {
+                root_key_id: root_key_id.to_string(),
+                descriptor: descriptor.to_string(),
+                change_descriptor: change_descriptor.to_string(),
+                message: message.to_string(),
+                psbt: psbt.to_string(),
+                format,
+            })
+            .send()
+            .await?;
+
+        self.handle_wsm_response(res).await
+    }
+
+    #[instrument(skip(descriptor, change_descriptor, spend_policy))]
+    async fn set_spend_policy(
+        &self,
+        root_key_id: &str,
+        descriptor: &str,
+        change_descriptor: &str,
+        spend_policy: &SignedSpendPolicy,
+    ) -> Result<SetSpendPolicyResponse, Error> {
+        let res = self
+            .client
+            .post(self.endpoint.join("set-spend-policy")?)
+            .json(&SetSpendPolicyRequest {
+                root_key_id: root_key_id.to_string(),
+                descriptor: descriptor.to_string(),
+                change_descriptor: change_descriptor.to_string(),
+                spend_policy: spend_policy.clone(),
+            })
+            .send()
+            .await?;
+
+        self.handle_wsm_response(res).await
+    }
+
     #[instrument]
     async fn get_key_integrity_sig(
         &self,
@@ -118,6 +118,10 @@ data "aws_secretsmanager_secret" "fromagerie_twilio_credentials" {
   name = "fromagerie/twilio/credentials"
 }
 
+data "aws_secretsmanager_secret" "fromagerie_noise_keys" {
+  name = "fromagerie/noise/keys"
+}
+
 data "aws_secretsmanager_secret" "fromagerie_launchdarkly_sdk_key" {
   name = "fromagerie/launchdarkly/sdk_key"
 }
@@ -209,6 +213,8 @@ module "ecs_api" {
     TWILIO_KEY_SID                 = "${data.aws_secretsmanager_secret.fromagerie_twilio_credentials.arn}:TWILIO_KEY_SID::",
     TWILIO_KEY_SECRET              = "${data.aws_secretsmanager_secret.fromagerie_twilio_credentials.arn}:TWILIO_KEY_SECRET::",
     ZENDESK_AUTHORIZATION          = data.aws_secretsmanager_secret.fromagerie_zendesk_credentials.arn,
+    NOISE_STATIC_PRIVATE_KEY       = "${data.aws_secretsmanager_secret.fromagerie_noise_keys.arn}:NOISE_STATIC_PRIVATE_KEY::",
+    NOISE_TICKET_KEY               = "${data.aws_secretsmanager_secret.fromagerie_noise_keys.arn}:NOISE_TICKET_KEY::",
   })
   image_name       = var.image_name
   image_tag        = var.image_tag
@@ -234,6 +240,7 @@ module "api_migration" {
     COGNITO_CLIENT_ID       = var.cognito_user_pool_client_id
     MIGRATION_TABLE         = local.tables.migration_record_table_name
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
   })
   environment = var.environment
   secrets = merge(local.common_secrets, {
@@ -296,6 +303,7 @@ module "api_user_balance_histogram" {
     COGNITO_USER_POOL       = var.cognito_user_pool_id
     COGNITO_CLIENT_ID       = var.cognito_user_pool_client_id
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
   })
   environment = var.environment
   secrets = merge(local.common_secrets, {
@@ -363,6 +371,7 @@ module "ecs_job_push" {
     SERVER_TWILIO           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ITERABLE         = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
   })
   secrets          = merge(local.common_secrets, {})
   image_name       = var.image_name
@@ -391,6 +400,7 @@ module "ecs_job_email" {
     SERVER_COGNITO          = "test"        //TODO: Pick apart bootstrap dependence on Cognito,
     SERVER_TWILIO           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
   })
   secrets = merge(local.common_secrets, {
     ITERABLE_API_KEY = data.aws_secretsmanager_secret.fromagerie_iterable_credentials.arn
@@ -421,6 +431,7 @@ module "ecs_job_sms" {
     SERVER_COGNITO          = "test"        //TODO: Pick apart bootstrap dependence on Cognito,
     SERVER_ITERABLE         = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
   })
   secrets = merge(local.common_secrets, {
     TWILIO_ACCOUNT_SID = "${data.aws_secretsmanager_secret.fromagerie_twilio_credentials.arn}:TWILIO_ACCOUNT_SID::",
@@ -458,6 +469,7 @@ module "ecs_job_scheduled_notification_task" {
     SERVER_TWILIO           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ITERABLE         = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
   })
   secrets          = merge(local.common_secrets, {})
   cpu_architecture = "ARM64"
@@ -486,6 +498,7 @@ module "ecs_job_blockchain_polling_task_signet" {
     SERVER_TWILIO           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ITERABLE         = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
     CHAIN_INDEXER_BASE_URL  = "https://bitkey.mempool.space/signet/api"
     CHAIN_INDEXER_NETWORK   = "signet"
   })
@@ -516,6 +529,7 @@ module "ecs_job_blockchain_polling_task_mainnet" {
     SERVER_TWILIO           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ITERABLE         = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
     CHAIN_INDEXER_BASE_URL  = "https://bitkey.mempool.space/api"
     CHAIN_INDEXER_NETWORK   = "bitcoin"
   })
@@ -546,6 +560,7 @@ module "ecs_job_mempool_polling_task_signet" {
     SERVER_TWILIO            = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ITERABLE          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE             = "{mode=test}" // Only the API serves Noise channels
     MEMPOOL_INDEXER_BASE_URL = "https://bitkey.mempool.space/signet/api"
     MEMPOOL_INDEXER_NETWORK  = "signet"
   })
@@ -576,6 +591,7 @@ module "ecs_job_mempool_polling_task_mainnet" {
     SERVER_TWILIO            = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ITERABLE          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE             = "{mode=test}" // Only the API serves Noise channels
     MEMPOOL_INDEXER_BASE_URL = "https://bitkey.mempool.space/api"
     MEMPOOL_INDEXER_NETWORK  = "bitcoin"
   })
@@ -608,6 +624,7 @@ module "ecs_job_metrics" {
     SERVER_TWILIO           = "{mode=test}" //TODO: Pick apart bootstrap dependence on Twilio,
     SERVER_ITERABLE         = "{mode=test}" //TODO: Pick apart bootstrap dependence on Iterable,
     SERVER_ZENDESK          = "{mode=test}" //TODO: Pick apart bootstrap dependence on Zendesk,
+    SERVER_NOISE            = "{mode=test}" // Only the API serves Noise channels
   })
   secrets          = merge(local.common_secrets, {})
   cpu_architecture = "ARM64"